@@ -0,0 +1,33 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use digimorse::libs::channel_codec::channel_encoding::ChannelSymbol;
+use digimorse::libs::transmitter::modulate::GfskModulator;
+use digimorse::libs::transmitter::transmitter::{AudioFrequencyHz, maximum_number_of_symbols};
+
+const SAMPLE_RATE: AudioFrequencyHz = 48000;
+const AUDIO_FREQUENCY: AudioFrequencyHz = 600;
+
+fn block() -> Vec<ChannelSymbol> {
+    (0..maximum_number_of_symbols()).map(|i| (i % 16) as ChannelSymbol).collect()
+}
+
+fn gfsk_modulator_benchmark(c: &mut Criterion) {
+    let block = block();
+    let mut waveform = vec![0.0; (block.len() + 1) * 7680 + 2 * 960];
+
+    c.bench_function("build modulator", |b| b.iter(|| GfskModulator::new(black_box(SAMPLE_RATE))));
+
+    let mut modulator = GfskModulator::new(SAMPLE_RATE);
+    c.bench_function("modulate single block", |b| b.iter(|| {
+        modulator.modulate(AUDIO_FREQUENCY, black_box(&block), &mut waveform, true, true)
+    }));
+
+    // Successive blocks of a transmission, continuing from the previous one.
+    let mut modulator = GfskModulator::new(SAMPLE_RATE);
+    c.bench_function("modulate continuing block", |b| b.iter(|| {
+        modulator.modulate(AUDIO_FREQUENCY, black_box(&block), &mut waveform, false, false)
+    }));
+}
+
+criterion_group!(benches, gfsk_modulator_benchmark);
+criterion_main!(benches);
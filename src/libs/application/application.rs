@@ -21,6 +21,8 @@ use crate::libs::channel_codec::channel_encoder::ChannelEncoder;
 use crate::libs::channel_codec::channel_encoding::ChannelEncoding;
 use crate::libs::conversion::conversion::text_to_keying;
 use crate::libs::gui::gui_facades::GUIOutput;
+use crate::libs::playback::receive_filter::{ReceiveFilter, ReceiveFiltering};
+use crate::libs::transmitter::tune::{Tuner, TuneSignal};
 use crate::libs::source_codec::source_encoder::SourceEncoderTrait;
 use crate::libs::source_codec::source_encoding::SourceEncoding;
 
@@ -53,6 +55,7 @@ fn add_sidetone_channel_to_keying_event(keying_event: KeyingEvent) -> KeyingEven
 // thread pool, etc..
 pub struct Application {
     terminate_flag: Arc<AtomicBool>,
+    transmit_halt_flag: Arc<AtomicBool>,
     scheduled_thread_pool: Arc<ScheduledThreadPool>,
     pa: PortAudio,
     mode: Option<ApplicationMode>,
@@ -77,6 +80,9 @@ pub struct Application {
     transmitter: Option<Arc<Mutex<dyn BusInput<ChannelEncoding>>>>,
     transmitter_channel_encoding_rx: Option<Arc<Mutex<BusReader<ChannelEncoding>>>>,
     playback: Option<Arc<Mutex<dyn BusOutput<KeyingEventToneChannel>>>>,
+    receive_filter: ReceiveFilter,
+    receive_filtering: Option<Arc<Mutex<dyn ReceiveFiltering>>>,
+    tuner: Option<Arc<Mutex<dyn Tuner>>>,
 }
 
 impl Application {
@@ -88,6 +94,7 @@ impl Application {
 
         Self {
             terminate_flag,
+            transmit_halt_flag: Arc::new(AtomicBool::new(false)),
             scheduled_thread_pool,
             pa,
             mode: None,
@@ -112,6 +119,9 @@ impl Application {
             transmitter: None,
             transmitter_channel_encoding_rx: None,
             playback: None,
+            receive_filter: ReceiveFilter::default(),
+            receive_filtering: None,
+            tuner: None,
         }
     }
 
@@ -146,6 +156,11 @@ impl Application {
         self.terminate_flag.clone()
     }
 
+    // Obtain a clone of the transmit halt flag, which the Transmitter clears once it has halted.
+    pub fn transmit_halt_flag(&mut self) -> Arc<AtomicBool> {
+        self.transmit_halt_flag.clone()
+    }
+
     // Obtain a clone of the global scheduled thread pool.
     pub fn scheduled_thread_pool(&mut self) -> Arc<ScheduledThreadPool> {
         self.scheduled_thread_pool.clone()
@@ -597,6 +612,26 @@ impl Application {
         self.playback.is_some()
     }
 
+    // The ReceiveFilter set by the GUI is applied to this (the Playback), starting with the current
+    // one.
+    pub fn set_receive_filtering(&mut self, receive_filtering: Arc<Mutex<dyn ReceiveFiltering>>) {
+        receive_filtering.lock().unwrap().set_receive_filter(self.receive_filter.clone());
+        self.receive_filtering = Some(receive_filtering);
+    }
+
+    pub fn clear_receive_filtering(&mut self) {
+        self.receive_filtering = None;
+    }
+
+    // Tuning is started and stopped by the GUI on this (the Transmitter).
+    pub fn set_tuner(&mut self, tuner: Arc<Mutex<dyn Tuner>>) {
+        self.tuner = Some(tuner);
+    }
+
+    pub fn clear_tuner(&mut self) {
+        self.tuner = None;
+    }
+
 
     // PortAudio functions...
     pub fn open_output_audio_device(&self, out_dev_str: &str) -> Result<OutputStreamSettings<f32>, Box<dyn Error>> {
@@ -638,6 +673,43 @@ impl GUIOutput for Application {
     fn get_keyer_speed(&self) -> KeyerSpeed {
         self.keyer_speed
     }
+
+    fn set_receive_filter(&mut self, receive_filter: ReceiveFilter) {
+        info!("Setting receive filter to {:?}", receive_filter);
+        if let Some(receive_filtering) = &self.receive_filtering {
+            receive_filtering.lock().unwrap().set_receive_filter(receive_filter.clone());
+        }
+        self.receive_filter = receive_filter;
+    }
+
+    fn get_receive_filter(&self) -> ReceiveFilter {
+        self.receive_filter.clone()
+    }
+
+    fn set_tuning(&mut self, tune_signal: Option<TuneSignal>) {
+        match &self.tuner {
+            None => warn!("Cannot tune without a transmitter"),
+            Some(tuner) => {
+                let mut locked_tuner = tuner.lock().unwrap();
+                match tune_signal {
+                    Some(tune_signal) => locked_tuner.start_tuning(tune_signal),
+                    None => locked_tuner.stop_tuning(),
+                }
+            }
+        }
+    }
+
+    fn is_tuning(&self) -> bool {
+        self.tuner.as_ref().map_or(false, |tuner| tuner.lock().unwrap().is_tuning())
+    }
+
+    fn halt_transmission(&mut self) {
+        warn!("Halting transmission");
+        if let Some(tuner) = &self.tuner {
+            tuner.lock().unwrap().stop_tuning();
+        }
+        self.transmit_halt_flag.store(true, Ordering::SeqCst);
+    }
 }
 
 impl Drop for Application {
@@ -20,8 +20,10 @@ mod application_spec {
     use crate::libs::channel_codec::channel_encoding::{CHANNEL_ENCODER_BLOCK_SIZE, ChannelEncoding};
     use crate::libs::gui::gui_facades::GUIOutput;
     use crate::libs::keyer_io::keyer_io::{Keyer, KeyerMode, KeyerPolarity, KeyerSpeed, KeyingEvent};
+    use crate::libs::playback::receive_filter::{ReceiveFilter, ReceiveFiltering};
     use crate::libs::source_codec::source_encoding::{Frame, SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, SourceEncoding};
     use crate::libs::source_codec::test_encoding_builder::encoded;
+    use crate::libs::transmitter::tune::{Tuner, TuneSignal};
     use crate::libs::util::test_util;
 
     #[ctor::ctor]
@@ -613,6 +615,94 @@ mod application_spec {
         assert_eq!(test_keyer.lock().unwrap().get_speed(), Ok(15 as KeyerSpeed));
     }
 
+    #[derive(Default)]
+    struct StubReceiveFiltering {
+        receive_filter: Option<ReceiveFilter>,
+    }
+
+    impl ReceiveFiltering for StubReceiveFiltering {
+        fn set_receive_filter(&mut self, receive_filter: ReceiveFilter) {
+            self.receive_filter = Some(receive_filter);
+        }
+    }
+
+    #[rstest]
+    #[serial]
+    pub fn application_has_receive_filter_disabled_initially(fixture: ApplicationFixture) {
+        assert_eq!(fixture.application.get_receive_filter(), ReceiveFilter::Disabled);
+    }
+
+    #[rstest]
+    #[serial]
+    pub fn receive_filtering_has_receive_filter_given_on_set(mut fixture: ApplicationFixture) {
+        fixture.application.set_receive_filter(ReceiveFilter::bandpass(1000, 1500));
+        let receive_filtering = Arc::new(Mutex::new(StubReceiveFiltering::default()));
+        fixture.application.set_receive_filtering(receive_filtering.clone());
+        assert_eq!(receive_filtering.lock().unwrap().receive_filter, Some(ReceiveFilter::bandpass(1000, 1500)));
+    }
+
+    #[rstest]
+    #[serial]
+    pub fn receive_filtering_has_receive_filter_set_by_application(mut fixture: ApplicationFixture) {
+        let receive_filtering = Arc::new(Mutex::new(StubReceiveFiltering::default()));
+        fixture.application.set_receive_filtering(receive_filtering.clone());
+        fixture.application.set_receive_filter(ReceiveFilter::Station { callsign_hash: 0x1234, audio_offset: 700 });
+        assert_eq!(receive_filtering.lock().unwrap().receive_filter, Some(ReceiveFilter::Station { callsign_hash: 0x1234, audio_offset: 700 }));
+        assert_eq!(fixture.application.get_receive_filter(), ReceiveFilter::Station { callsign_hash: 0x1234, audio_offset: 700 });
+    }
+
+    #[derive(Default)]
+    struct StubTuner {
+        tune_signal: Option<TuneSignal>,
+    }
+
+    impl Tuner for StubTuner {
+        fn start_tuning(&mut self, tune_signal: TuneSignal) {
+            self.tune_signal = Some(tune_signal);
+        }
+
+        fn stop_tuning(&mut self) {
+            self.tune_signal = None;
+        }
+
+        fn is_tuning(&self) -> bool {
+            self.tune_signal.is_some()
+        }
+    }
+
+    #[rstest]
+    #[serial]
+    pub fn application_is_not_tuning_without_tuner(mut fixture: ApplicationFixture) {
+        fixture.application.set_tuning(Some(TuneSignal::Carrier));
+        assert_eq!(fixture.application.is_tuning(), false);
+    }
+
+    #[rstest]
+    #[serial]
+    pub fn tuner_is_started_and_stopped_by_application(mut fixture: ApplicationFixture) {
+        let tuner = Arc::new(Mutex::new(StubTuner::default()));
+        fixture.application.set_tuner(tuner.clone());
+        fixture.application.set_tuning(Some(TuneSignal::TwoTone));
+        assert_eq!(tuner.lock().unwrap().tune_signal, Some(TuneSignal::TwoTone));
+        assert_eq!(fixture.application.is_tuning(), true);
+        fixture.application.set_tuning(None);
+        assert_eq!(tuner.lock().unwrap().tune_signal, None);
+        assert_eq!(fixture.application.is_tuning(), false);
+    }
+
+    #[rstest]
+    #[serial]
+    pub fn halt_sets_transmit_halt_flag_and_stops_tuning(mut fixture: ApplicationFixture) {
+        let tuner = Arc::new(Mutex::new(StubTuner::default()));
+        fixture.application.set_tuner(tuner.clone());
+        let transmit_halt = fixture.application.transmit_halt_flag();
+        assert_eq!(transmit_halt.load(Ordering::SeqCst), false);
+        fixture.application.set_tuning(Some(TuneSignal::Carrier));
+        fixture.application.halt_transmission();
+        assert_eq!(transmit_halt.load(Ordering::SeqCst), true);
+        assert_eq!(fixture.application.is_tuning(), false);
+    }
+
     // Mode/Component set/clear validation tests
 
     #[rstest]
@@ -1173,7 +1263,7 @@ mod application_spec {
             Frame::Padding
         ];
         let block = encoded(SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, 20, keying_frames);
-        let source_encoding = SourceEncoding { block, is_end: true };
+        let source_encoding = SourceEncoding { block, is_end: true, is_metadata_only: false };
         source_encoding
     }
 }
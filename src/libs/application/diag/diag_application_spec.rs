@@ -30,7 +30,7 @@ mod diag_application_spec {
     use crate::libs::playback::playback::Playback;
     use crate::libs::serial_io::serial_io::DefaultSerialIO;
     use crate::libs::source_codec::source_decoder::SourceDecoder;
-    use crate::libs::source_codec::source_encoding::{SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, SourceEncoding};
+    use crate::libs::source_codec::source_encoding::{CallsignHash, SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, SourceEncoding};
     use crate::libs::test::test_hardware;
     use crate::libs::util::test_util;
 
@@ -272,7 +272,7 @@ mod diag_application_spec {
 
         // Precondition: set_input_rx has been called.
         fn process(&mut self) {
-            const REPLAY_CALLSIGN_HASH: u16 = 0x1234u16;
+            const REPLAY_CALLSIGN_HASH: CallsignHash = 0x1234;
             let source_decoder = SourceDecoder::new(SOURCE_ENCODER_BLOCK_SIZE_IN_BITS);
 
             loop {
@@ -82,7 +82,6 @@ impl Display for AmplitudeRamping {
 // with differing audio_frequency as decoded streams are played into the keying_events channel by
 // the receiver playback system.
 pub struct ToneGenerator {
-    enabled_in_filter_bandpass: bool,
     sample_rate: u32,
     dt: f32, // Reciprocal of the sample rate
     thread_handle: Option<JoinHandle<()>>,
@@ -98,6 +97,7 @@ pub struct CallbackData {
     amplitude: f32, // used for ramping up/down output waveform for key click suppression
     audio_frequency: u16,
     enabled: bool,
+    in_filter_bandpass: bool, // a channel filtered out is silent, but keeps its ramping and phase
     delta_phase: f32, // added to the phase after recording each sample
     phase: f32,       // sin(phase) is the sample value
 }
@@ -133,6 +133,7 @@ impl ToneGenerator {
             amplitude: 0.0,
             audio_frequency: sidetone_audio_frequency,
             enabled: true, // cannot be disabled
+            in_filter_bandpass: true, // cannot be filtered out
             delta_phase: 0.0,
             phase: 0.0,
         };
@@ -141,7 +142,6 @@ impl ToneGenerator {
         let move_clone_sidetone_callback_data = arc_lock_sidetone_callback_data.clone();
         Self {
             input_rx: input_rx_holder,
-            enabled_in_filter_bandpass: true,
             sample_rate: 0, // will be initialised when the callback is initialised
             dt: 0.0,        // will be initialised when the callback is initialised
             thread_handle: Some(thread::spawn(move || {
@@ -259,7 +259,11 @@ impl ToneGenerator {
                     }
 
                     locked_callback_data.phase += locked_callback_data.delta_phase;
-                    let sine_val = f32::sin(locked_callback_data.phase) * locked_callback_data.amplitude;
+                    let sine_val = if locked_callback_data.in_filter_bandpass {
+                        f32::sin(locked_callback_data.phase) * locked_callback_data.amplitude
+                    } else {
+                        0.0
+                    };
 
                     drop(locked_callback_data);
 
@@ -322,8 +326,17 @@ impl ToneGenerator {
         debug!("Setting tone#{} frequency to {}, sample_rate {}", tone_index, locked_callback_data.audio_frequency, self.sample_rate);
     }
 
-    pub fn set_in_filter_bandpass(&mut self, in_bandpass: bool) -> () {
-        self.enabled_in_filter_bandpass = in_bandpass;
+    // Playback sets whether each received station's channel passes the receive filter.
+    pub fn set_in_filter_bandpass(&mut self, tone_index: usize, in_bandpass: bool) {
+        // Tone index 0 is for the sidetone; it cannot be filtered out.
+        if tone_index == 0 {
+            return;
+        }
+        let callback_datas = self.callback_data.read().unwrap();
+        if tone_index >= callback_datas.len() {
+            return;
+        }
+        callback_datas[tone_index].lock().unwrap().in_filter_bandpass = in_bandpass;
     }
 
     // Allocate the first disabled channel, or extend if there isn't one.
@@ -333,6 +346,7 @@ impl ToneGenerator {
             amplitude: 0.0,
             audio_frequency: freq,
             enabled: true, // well if you're allocating it, it's enabled!
+            in_filter_bandpass: true,
             delta_phase: 0.0,
             phase: 0.0,
         };
@@ -376,6 +390,13 @@ impl ToneGenerator {
         }
     }
 
+    // Used by tests to check set_in_filter_bandpass.
+    #[cfg(test)]
+    pub fn test_get_in_filter_bandpass_states(&mut self) -> Vec<bool> {
+        let callback_datas = self.callback_data.read().unwrap();
+        callback_datas.iter().map(|callback_data| callback_data.lock().unwrap().in_filter_bandpass).collect()
+    }
+
     // Used by tests to check allocate/deallocate functions.
     #[cfg(test)]
     pub fn test_get_enabled_states(&mut self) -> Vec<bool> {
@@ -134,4 +134,29 @@ mod tone_generator_channel_alloc_spec {
         fixture.tone_generator.allocate_channel(1000);
         assert_eq!(fixture.tone_generator.test_get_enabled_states(), vec![true, true, true]);
     }
-}
\ No newline at end of file
+
+    #[rstest]
+    #[serial]
+    pub fn channels_are_filtered_out_but_not_the_sidetone(mut fixture: ToneGeneratorFixture) {
+        fixture.tone_generator.allocate_channel(800);
+        fixture.tone_generator.allocate_channel(900);
+        assert_eq!(fixture.tone_generator.test_get_in_filter_bandpass_states(), vec![true, true, true]);
+        fixture.tone_generator.set_in_filter_bandpass(0, false); // does not
+        fixture.tone_generator.set_in_filter_bandpass(2, false);
+        fixture.tone_generator.set_in_filter_bandpass(3, false); // does nothing
+        assert_eq!(fixture.tone_generator.test_get_in_filter_bandpass_states(), vec![true, true, false]);
+        fixture.tone_generator.set_in_filter_bandpass(2, true);
+        assert_eq!(fixture.tone_generator.test_get_in_filter_bandpass_states(), vec![true, true, true]);
+    }
+
+    #[rstest]
+    #[serial]
+    pub fn reallocated_channel_is_in_filter_bandpass(mut fixture: ToneGeneratorFixture) {
+        fixture.tone_generator.allocate_channel(800);
+        fixture.tone_generator.allocate_channel(900);
+        fixture.tone_generator.set_in_filter_bandpass(1, false);
+        fixture.tone_generator.deallocate_channel(1);
+        fixture.tone_generator.allocate_channel(1000);
+        assert_eq!(fixture.tone_generator.test_get_in_filter_bandpass_states(), vec![true, true, true]);
+    }
+}
@@ -1,10 +1,14 @@
 use std::fmt::Display;
 use std::sync::Arc;
-use log::debug;
 use crate::libs::patterns::observer::{ConcreteObserverList, Observable, Observer, ObserverList};
 
+// Downsampled audio is collected into a circular buffer. Once the first 160ms has been received,
+// the last 160ms is emitted to observers every 40ms: successive slices overlap by 120ms.
+// At 12000Hz, 160ms is 1920 samples, and 40ms is 480.
 pub const OBSERVABLE_BUFFER_SLICE_SIZE: usize = 1920;
+pub const OBSERVABLE_BUFFER_HOP_SIZE: usize = OBSERVABLE_BUFFER_SLICE_SIZE / 4;
 
+// Twice the slice, so that the slice being emitted is never overwritten.
 const OBSERVABLE_BUFFER_SIZE: usize = OBSERVABLE_BUFFER_SLICE_SIZE * 2;
 
 // T is going to be some primitive type: f32, u32 etc.
@@ -21,8 +25,12 @@ impl<T: Clone + Copy + Default + Display + Send + Sync> Observable for Observabl
 pub struct ObservableBuffer<T> where T: Clone + Copy + Default + Display + Send + Sync {
     buffer: Vec<T>,
     observers: ConcreteObserverList<ObservableBufferSlice<T>>,
+    // The index of the start of the next slice to be emitted.
     from: usize,
+    // The index at which the next sample will be stored.
     to: usize,
+    // The number of samples stored from the start of the next slice.
+    stored: usize,
 }
 
 impl<T: Clone + Copy + Default + Display + Send + Sync> ObservableBuffer<T> {
@@ -37,6 +45,7 @@ impl<T: Clone + Copy + Default + Display + Send + Sync> ObservableBuffer<T> {
             observers: obs,
             from: 0,
             to: 0,
+            stored: 0,
         }
     }
 
@@ -44,19 +53,24 @@ impl<T: Clone + Copy + Default + Display + Send + Sync> ObservableBuffer<T> {
         self.observers.register_observer(observer);
     }
 
+    // Notifies the observers on the caller's thread; the Receiver does not call this in its audio
+    // callback.
     pub fn add_sample(&mut self, sample: T) {
-        // TODO cyclic buffer wraparound
         self.buffer[self.to] = sample;
-        self.to += 1;
-        let stored_amount = self.to - self.from;
-        debug!("sample {}; stored amount {}", sample, stored_amount);
-        if stored_amount == OBSERVABLE_BUFFER_SLICE_SIZE {
-            debug!("notifying observers");
-            let slice = &self.buffer[self.from..self.to];
-            let observable = ObservableBufferSlice { slice: slice.to_vec() };
-            // TODO notification must be done on a separate thread - this is the callback.
+        self.to = (self.to + 1) % OBSERVABLE_BUFFER_SIZE;
+        self.stored += 1;
+        if self.stored == OBSERVABLE_BUFFER_SLICE_SIZE {
+            let mut slice = Vec::with_capacity(OBSERVABLE_BUFFER_SLICE_SIZE);
+            if self.from < self.to {
+                slice.extend_from_slice(&self.buffer[self.from..self.to]);
+            } else {
+                slice.extend_from_slice(&self.buffer[self.from..]);
+                slice.extend_from_slice(&self.buffer[..self.to]);
+            }
+            let observable = ObservableBufferSlice { slice };
             self.observers.notify_observers(&observable);
-            self.from += OBSERVABLE_BUFFER_SLICE_SIZE;
+            self.from = (self.from + OBSERVABLE_BUFFER_HOP_SIZE) % OBSERVABLE_BUFFER_SIZE;
+            self.stored -= OBSERVABLE_BUFFER_HOP_SIZE;
         }
     }
 
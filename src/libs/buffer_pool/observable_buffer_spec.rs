@@ -6,7 +6,7 @@ mod observable_buffer_spec {
 
     use hamcrest2::prelude::*;
     use rstest::*;
-    use crate::libs::buffer_pool::observable_buffer::{OBSERVABLE_BUFFER_SLICE_SIZE, ObservableBuffer, ObservableBufferSlice};
+    use crate::libs::buffer_pool::observable_buffer::{OBSERVABLE_BUFFER_HOP_SIZE, OBSERVABLE_BUFFER_SLICE_SIZE, ObservableBuffer, ObservableBufferSlice};
     use crate::libs::patterns::observer::Observer;
 
     #[ctor::ctor]
@@ -54,6 +54,12 @@ mod observable_buffer_spec {
                 self.add_sample();
             }
         }
+
+        fn add_hop_of_samples(&mut self) {
+            for _ in 0..OBSERVABLE_BUFFER_HOP_SIZE {
+                self.add_sample();
+            }
+        }
     }
 
     #[fixture]
@@ -87,7 +93,8 @@ mod observable_buffer_spec {
     #[serial]
     pub fn emit_first_slice(mut fixture: ObservableBufferFixture) {
         fixture.add_slice_of_samples();
-        assert_that!(fixture.observable_buffer.range(), equal_to((OBSERVABLE_BUFFER_SLICE_SIZE, OBSERVABLE_BUFFER_SLICE_SIZE)));
+        // The next slice starts a hop later.
+        assert_that!(fixture.observable_buffer.range(), equal_to((OBSERVABLE_BUFFER_HOP_SIZE, OBSERVABLE_BUFFER_SLICE_SIZE)));
         let observations = fixture.observer.observations();
         assert_that!(observations.len(), equal_to(1));
         let observation = observations.get(0).unwrap();
@@ -96,23 +103,44 @@ mod observable_buffer_spec {
         }
     }
 
-    // WRONG - 1920 samples is 160ms; need to emit first slice after this, but subsequent after
-    // 40ms (ie 480 samples) - these overlap the first (they take the last 1440 samples of the first
-    // slice, and the just-received 480..
+    // 1920 samples is 160ms; the first slice is emitted after this, but subsequent slices after
+    // 40ms (ie 480 samples) - these overlap the previous (they take its last 1440 samples, and the
+    // just-received 480).
 
     #[rstest]
     #[serial]
-    pub fn emit_second_slice(mut fixture: ObservableBufferFixture) {
+    pub fn emit_second_slice_after_a_hop(mut fixture: ObservableBufferFixture) {
         fixture.add_slice_of_samples();
-        assert_that!(fixture.observable_buffer.range(), equal_to((OBSERVABLE_BUFFER_SLICE_SIZE, OBSERVABLE_BUFFER_SLICE_SIZE)));
-        fixture.add_slice_of_samples();
-        assert_that!(fixture.observable_buffer.range(), equal_to((OBSERVABLE_BUFFER_SLICE_SIZE * 2, OBSERVABLE_BUFFER_SLICE_SIZE * 2)));
+        for _ in 0..OBSERVABLE_BUFFER_HOP_SIZE - 1 {
+            fixture.add_sample();
+        }
+        assert_that!(fixture.observer.observations().len(), equal_to(1));
+        fixture.add_sample();
+        assert_that!(fixture.observable_buffer.range(), equal_to((OBSERVABLE_BUFFER_HOP_SIZE * 2, OBSERVABLE_BUFFER_SLICE_SIZE + OBSERVABLE_BUFFER_HOP_SIZE)));
         let observations = fixture.observer.observations();
         assert_that!(observations.len(), equal_to(2));
         let observation = observations.get(1).unwrap();
         for i in 0..OBSERVABLE_BUFFER_SLICE_SIZE {
-            assert_that!(observation.slice[i], equal_to(i as u32 + OBSERVABLE_BUFFER_SLICE_SIZE as u32));
+            assert_that!(observation.slice[i], equal_to((i + OBSERVABLE_BUFFER_HOP_SIZE) as u32));
         }
     }
 
+    #[rstest]
+    #[serial]
+    pub fn slices_wrap_around_the_buffer(mut fixture: ObservableBufferFixture) {
+        fixture.add_slice_of_samples();
+        // Enough to wrap around the buffer several times.
+        let hops = 20;
+        for _ in 0..hops {
+            fixture.add_hop_of_samples();
+        }
+        let observations = fixture.observer.observations();
+        assert_that!(observations.len(), equal_to(hops + 1));
+        for (n, observation) in observations.iter().enumerate() {
+            assert_that!(observation.slice.len(), equal_to(OBSERVABLE_BUFFER_SLICE_SIZE));
+            for i in 0..OBSERVABLE_BUFFER_SLICE_SIZE {
+                assert_that!(observation.slice[i], equal_to((n * OBSERVABLE_BUFFER_HOP_SIZE + i) as u32));
+            }
+        }
+    }
 }
@@ -0,0 +1,44 @@
+/*
+ * A CatController controls the rig (Computer Aided Transceiver control). The Transmitter keys the
+ * rig's PTT through it before the first symbol of a transmission, and releases it when the last
+ * symbol has been output. A rig keyed by a serial port's RTS or DTR line uses the
+ * SerialPttCatController; one without CAT control (e.g. keyed by VOX) uses the NullCatController.
+ * Controllers that can read the rig's dial frequency report it, for the DialFrequencyPoller.
+ */
+
+use log::debug;
+use serde_derive::{Deserialize, Serialize};
+use crate::libs::transmitter::transmitter::RadioFrequencyHz;
+
+#[derive(Serialize, Deserialize, Debug, PartialOrd, PartialEq, Copy, Clone)]
+pub enum CatType {
+    Null, Rigctld, SerialPtt
+}
+
+pub trait CatController : Send {
+    // Key the rig's PTT to transmit (true), or release it to receive (false).
+    fn set_ptt(&mut self, transmit: bool) -> Result<(), String>;
+    // The rig's dial frequency, or None if this controller cannot read it.
+    fn get_dial_frequency(&mut self) -> Result<Option<RadioFrequencyHz>, String>;
+}
+
+#[derive(Default)]
+pub struct NullCatController {
+}
+
+impl NullCatController {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl CatController for NullCatController {
+    fn set_ptt(&mut self, transmit: bool) -> Result<(), String> {
+        debug!("No CAT control to set PTT {}", transmit);
+        Ok(())
+    }
+
+    fn get_dial_frequency(&mut self) -> Result<Option<RadioFrequencyHz>, String> {
+        Ok(None)
+    }
+}
@@ -0,0 +1,123 @@
+/*
+ * The rig's dial frequency is held centrally in the DialFrequency, so that the radio frequency of
+ * each decoded station (its audio offset above the dial, as the rig is in USB) can be reported,
+ * e.g. "14.070.750" rather than "750 Hz". The DialFrequencyPoller reads it through the
+ * CatController, and tells the GUI when it changes. It is unknown if the controller cannot read
+ * it, or reading it fails.
+ */
+
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::SyncSender;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use log::{debug, info, warn};
+use crate::libs::cat::cat::CatController;
+use crate::libs::gui::gui_facades::GUIInputMessage;
+use crate::libs::transmitter::transmitter::{AudioFrequencyHz, RadioFrequencyHz};
+
+pub const DIAL_FREQUENCY_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// How often the poller checks for termination while waiting to poll.
+const TERMINATE_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+// Formats a radio frequency in MHz, with its kHz and Hz digits in groups, e.g. "14.070.750".
+pub fn format_radio_frequency(frequency: RadioFrequencyHz) -> String {
+    format!("{}.{:03}.{:03}", frequency / 1_000_000, (frequency / 1_000) % 1_000, frequency % 1_000)
+}
+
+#[derive(Default)]
+pub struct DialFrequency {
+    frequency: Mutex<Option<RadioFrequencyHz>>,
+}
+
+impl DialFrequency {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self) -> Option<RadioFrequencyHz> {
+        *self.frequency.lock().unwrap()
+    }
+
+    // Returns whether the frequency has changed.
+    pub fn set(&self, frequency: Option<RadioFrequencyHz>) -> bool {
+        let mut locked_frequency = self.frequency.lock().unwrap();
+        let changed = *locked_frequency != frequency;
+        *locked_frequency = frequency;
+        changed
+    }
+
+    // The radio frequency of a station at the given audio offset, if the dial frequency is known.
+    pub fn station_frequency(&self, audio_offset: AudioFrequencyHz) -> Option<RadioFrequencyHz> {
+        self.get().map(|dial| dial + audio_offset as RadioFrequencyHz)
+    }
+}
+
+pub struct DialFrequencyPoller {
+    terminate: Arc<AtomicBool>,
+    gui_input: Arc<Mutex<Option<Arc<SyncSender<GUIInputMessage>>>>>,
+    dial_frequency: Arc<DialFrequency>,
+    thread_handle: Option<JoinHandle<()>>,
+}
+
+impl DialFrequencyPoller {
+    pub fn new(cat_controller: Arc<Mutex<dyn CatController>>, dial_frequency: Arc<DialFrequency>,
+               poll_interval: Duration, terminate: Arc<AtomicBool>) -> Self {
+        let gui_input: Arc<Mutex<Option<Arc<SyncSender<GUIInputMessage>>>>> = Arc::new(Mutex::new(None));
+        let move_clone_gui_input = gui_input.clone();
+        let move_clone_dial_frequency = dial_frequency.clone();
+        let move_clone_terminate = terminate.clone();
+        Self {
+            terminate,
+            gui_input,
+            dial_frequency,
+            thread_handle: Some(thread::spawn(move || {
+                info!("Dial frequency poller thread started");
+                while !move_clone_terminate.load(Ordering::SeqCst) {
+                    let poll_start = Instant::now();
+                    let frequency = match cat_controller.lock().unwrap().get_dial_frequency() {
+                        Ok(frequency) => frequency,
+                        Err(e) => {
+                            debug!("Could not read dial frequency: {}", e);
+                            None
+                        }
+                    };
+                    if move_clone_dial_frequency.set(frequency) {
+                        match frequency {
+                            Some(frequency) => info!("Dial frequency is {}", format_radio_frequency(frequency)),
+                            None => warn!("Dial frequency is unknown"),
+                        }
+                        if let Some(gui_input) = move_clone_gui_input.lock().unwrap().as_ref() {
+                            let _ = gui_input.try_send(GUIInputMessage::SetDialFrequency(frequency));
+                        }
+                    }
+                    while poll_start.elapsed() < poll_interval && !move_clone_terminate.load(Ordering::SeqCst) {
+                        thread::sleep(TERMINATE_CHECK_INTERVAL.min(poll_interval));
+                    }
+                }
+                info!("Dial frequency poller thread stopped");
+            })),
+        }
+    }
+
+    // The GUI is told the current dial frequency, then of each change.
+    pub fn set_gui_input(&mut self, gui_input: Arc<SyncSender<GUIInputMessage>>) {
+        let mut locked_gui_input = self.gui_input.lock().unwrap();
+        let _ = gui_input.try_send(GUIInputMessage::SetDialFrequency(self.dial_frequency.get()));
+        *locked_gui_input = Some(gui_input);
+    }
+}
+
+impl Drop for DialFrequencyPoller {
+    fn drop(&mut self) {
+        debug!("DialFrequencyPoller signalling termination to thread on drop");
+        self.terminate.store(true, Ordering::SeqCst);
+        self.thread_handle.take().map(JoinHandle::join);
+    }
+}
+
+#[cfg(test)]
+#[path = "./dial_frequency_spec.rs"]
+mod dial_frequency_spec;
@@ -0,0 +1,71 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod dial_frequency_spec {
+    use std::env;
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::AtomicBool;
+    use std::sync::mpsc::sync_channel;
+    use std::time::Duration;
+    use hamcrest2::prelude::*;
+    use rstest::*;
+    use crate::libs::cat::dial_frequency::{DialFrequency, DialFrequencyPoller, format_radio_frequency};
+    use crate::libs::cat::mock_cat::MockCatController;
+    use crate::libs::gui::gui_facades::GUIInputMessage;
+    use crate::libs::transmitter::transmitter::RadioFrequencyHz;
+
+    #[ctor::ctor]
+    fn before_each() {
+        env::set_var("RUST_LOG", "debug");
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[ctor::dtor]
+    fn after_each() {}
+
+    #[rstest]
+    #[case(14070750, "14.070.750")]
+    #[case(7074000, "7.074.000")]
+    #[case(144174005, "144.174.005")]
+    #[case(475500, "0.475.500")]
+    fn radio_frequency_is_formatted(#[case] frequency: RadioFrequencyHz, #[case] expected: &str) {
+        assert_that!(format_radio_frequency(frequency), equal_to(expected.to_string()));
+    }
+
+    #[test]
+    fn station_frequency_is_dial_plus_audio_offset() {
+        let dial_frequency = DialFrequency::new();
+        assert_that!(dial_frequency.station_frequency(750), none());
+        assert_that!(dial_frequency.set(Some(14070000)), equal_to(true));
+        assert_that!(dial_frequency.set(Some(14070000)), equal_to(false));
+        assert_that!(dial_frequency.station_frequency(750), has(14070750));
+    }
+
+    #[test]
+    fn dial_frequency_is_polled_and_changes_sent_to_gui() {
+        let cat_controller = Arc::new(Mutex::new(MockCatController::new()));
+        cat_controller.lock().unwrap().set_dial_frequency(Some(14070000));
+        let dial_frequency = Arc::new(DialFrequency::new());
+        let terminate = Arc::new(AtomicBool::new(false));
+        let (gui_input_tx, gui_input_rx) = sync_channel::<GUIInputMessage>(16);
+        let mut poller = DialFrequencyPoller::new(cat_controller.clone(), dial_frequency.clone(), Duration::from_millis(20), terminate);
+        poller.set_gui_input(Arc::new(gui_input_tx));
+
+        let timeout = Duration::from_secs(2);
+        // The GUI is told the frequency when given to the poller (it may not have been polled yet).
+        let mut message = gui_input_rx.recv_timeout(timeout).unwrap();
+        if message == GUIInputMessage::SetDialFrequency(None) {
+            message = gui_input_rx.recv_timeout(timeout).unwrap();
+        }
+        assert_that!(message, equal_to(GUIInputMessage::SetDialFrequency(Some(14070000))));
+        assert_that!(dial_frequency.get(), has(14070000));
+
+        cat_controller.lock().unwrap().set_dial_frequency(Some(7074000));
+        assert_that!(gui_input_rx.recv_timeout(timeout).unwrap(), equal_to(GUIInputMessage::SetDialFrequency(Some(7074000))));
+        assert_that!(dial_frequency.station_frequency(1200), has(7075200));
+
+        cat_controller.lock().unwrap().set_dial_frequency(None);
+        assert_that!(gui_input_rx.recv_timeout(timeout).unwrap(), equal_to(GUIInputMessage::SetDialFrequency(None)));
+        assert_that!(dial_frequency.get(), none());
+    }
+}
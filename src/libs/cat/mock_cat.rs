@@ -0,0 +1,39 @@
+use crate::libs::cat::cat::CatController;
+use crate::libs::transmitter::transmitter::RadioFrequencyHz;
+
+// A CatController for tests, recording the PTT changes made through it, and reporting whatever
+// dial frequency it is given.
+#[derive(Default)]
+pub struct MockCatController {
+    ptt_history: Vec<bool>,
+    dial_frequency: Option<RadioFrequencyHz>,
+}
+
+impl MockCatController {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn ptt(&self) -> bool {
+        self.ptt_history.last().copied().unwrap_or(false)
+    }
+
+    pub fn ptt_history(&self) -> Vec<bool> {
+        self.ptt_history.clone()
+    }
+
+    pub fn set_dial_frequency(&mut self, dial_frequency: Option<RadioFrequencyHz>) {
+        self.dial_frequency = dial_frequency;
+    }
+}
+
+impl CatController for MockCatController {
+    fn set_ptt(&mut self, transmit: bool) -> Result<(), String> {
+        self.ptt_history.push(transmit);
+        Ok(())
+    }
+
+    fn get_dial_frequency(&mut self) -> Result<Option<RadioFrequencyHz>, String> {
+        Ok(self.dial_frequency)
+    }
+}
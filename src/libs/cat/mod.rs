@@ -0,0 +1,6 @@
+pub mod cat;
+pub mod dial_frequency;
+#[cfg(test)]
+pub mod mock_cat;
+pub mod rigctld_cat;
+pub mod serial_ptt_cat;
@@ -0,0 +1,102 @@
+/*
+ * Controls the rig via Hamlib's rigctld, which listens on a TCP port (4532 by default) for commands
+ * of one line each, using its default (not extended) protocol. A command that sets something is
+ * answered with "RPRT 0", or with a negative Hamlib error code; one that gets something is
+ * answered with its value, or with an error code.
+ * The controller is created disconnected; the connection is made by the first command, or by
+ * connect, to check at startup that rigctld is there. If it cannot be made, or is lost, the command
+ * fails, and the next command tries again.
+ */
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use log::{debug, info, warn};
+use crate::libs::cat::cat::CatController;
+use crate::libs::transmitter::transmitter::RadioFrequencyHz;
+
+pub const DEFAULT_RIGCTLD_ADDRESS: &str = "localhost:4532";
+
+const RIGCTLD_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub struct RigctldCatController {
+    address: String,
+    stream: Option<BufReader<TcpStream>>,
+}
+
+impl RigctldCatController {
+    pub fn new(address: String) -> Self {
+        Self { address, stream: None }
+    }
+
+    pub fn connect(&mut self) -> Result<(), String> {
+        info!("Connecting to rigctld at {}", self.address);
+        let socket_address = self.address.to_socket_addrs()
+            .map_err(|e| format!("Cannot resolve rigctld address {}: {}", self.address, e))?
+            .next()
+            .ok_or(format!("No rigctld address for {}", self.address))?;
+        let stream = TcpStream::connect_timeout(&socket_address, RIGCTLD_TIMEOUT)
+            .map_err(|e| format!("Cannot connect to rigctld at {}: {}", self.address, e))?;
+        stream.set_read_timeout(Some(RIGCTLD_TIMEOUT)).map_err(|e| e.to_string())?;
+        stream.set_write_timeout(Some(RIGCTLD_TIMEOUT)).map_err(|e| e.to_string())?;
+        self.stream = Some(BufReader::new(stream));
+        Ok(())
+    }
+
+    // Send a command, returning the line of its response, or an error if rigctld reports one or
+    // the connection fails (in which case it is dropped, to be reconnected by the next command).
+    fn command(&mut self, command: &str) -> Result<String, String> {
+        if self.stream.is_none() {
+            self.connect()?;
+        }
+        let stream = self.stream.as_mut().unwrap();
+        debug!("rigctld command '{}'", command);
+        let mut response = String::new();
+        let result = stream.get_mut().write_all(format!("{}\n", command).as_bytes())
+            .and_then(|_| stream.read_line(&mut response));
+        match result {
+            Ok(0) | Err(_) => {
+                warn!("Lost connection to rigctld at {}", self.address);
+                self.stream = None;
+                return Err(format!("No response from rigctld to '{}'", command));
+            }
+            Ok(_) => {}
+        }
+        let response = response.trim().to_string();
+        debug!("rigctld response '{}'", response);
+        match response.strip_prefix("RPRT ") {
+            Some("0") => Ok(response),
+            Some(code) => Err(format!("rigctld error {} in response to '{}'", code, command)),
+            None => Ok(response),
+        }
+    }
+
+    // Send a command that sets something, expecting only a report of its success.
+    fn set_command(&mut self, command: &str) -> Result<(), String> {
+        let response = self.command(command)?;
+        if response == "RPRT 0" {
+            Ok(())
+        } else {
+            Err(format!("Unexpected response '{}' from rigctld to '{}'", response, command))
+        }
+    }
+}
+
+impl CatController for RigctldCatController {
+    fn set_ptt(&mut self, transmit: bool) -> Result<(), String> {
+        self.set_command(if transmit { "T 1" } else { "T 0" })
+    }
+
+    fn get_dial_frequency(&mut self) -> Result<Option<RadioFrequencyHz>, String> {
+        let response = self.command("f")?;
+        // Some rigs report the frequency with a fractional part, e.g. "14074000.000000".
+        match response.parse::<f64>() {
+            Ok(frequency) if frequency >= 0.0 => Ok(Some(frequency.round() as RadioFrequencyHz)),
+            _ => Err(format!("Unexpected frequency '{}' from rigctld", response)),
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./rigctld_cat_spec.rs"]
+mod rigctld_cat_spec;
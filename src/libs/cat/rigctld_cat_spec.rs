@@ -0,0 +1,138 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod rigctld_cat_spec {
+    use std::env;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use hamcrest2::prelude::*;
+    use crate::libs::cat::cat::CatController;
+    use crate::libs::cat::rigctld_cat::RigctldCatController;
+
+    #[ctor::ctor]
+    fn before_each() {
+        env::set_var("RUST_LOG", "debug");
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[ctor::dtor]
+    fn after_each() {}
+
+    struct FakeRigctld {
+        address: String,
+        commands: Arc<Mutex<Vec<String>>>,
+    }
+
+    // A local rigctld, recording the commands it receives, and answering each with the response
+    // given by respond; an empty response closes the connection instead.
+    fn fake_rigctld(respond: impl FnMut(&str) -> String + Send + 'static) -> FakeRigctld {
+        fake_rigctld_at("127.0.0.1:0", respond)
+    }
+
+    fn fake_rigctld_at(address: &str, mut respond: impl FnMut(&str) -> String + Send + 'static) -> FakeRigctld {
+        let listener = TcpListener::bind(address).unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let commands = Arc::new(Mutex::new(vec![]));
+        let thread_commands = commands.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut writer = stream.unwrap();
+                let mut reader = BufReader::new(writer.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap_or(0) > 0 {
+                    let command = line.trim().to_string();
+                    let response = respond(&command);
+                    thread_commands.lock().unwrap().push(command);
+                    if response.is_empty() {
+                        break;
+                    }
+                    writer.write_all(format!("{}\n", response).as_bytes()).unwrap();
+                    line.clear();
+                }
+            }
+        });
+        FakeRigctld { address, commands }
+    }
+
+    #[test]
+    fn ptt_is_keyed_and_released() {
+        let rigctld = fake_rigctld(|_| "RPRT 0".to_string());
+        let mut controller = RigctldCatController::new(rigctld.address.clone());
+        assert_that!(controller.set_ptt(true), equal_to(Ok(())));
+        assert_that!(controller.set_ptt(false), equal_to(Ok(())));
+        assert_that!(rigctld.commands.lock().unwrap().clone(), equal_to(vec!["T 1".to_string(), "T 0".to_string()]));
+    }
+
+    #[test]
+    fn rigctld_error_is_an_error() {
+        let rigctld = fake_rigctld(|_| "RPRT -9".to_string());
+        let mut controller = RigctldCatController::new(rigctld.address);
+        let result = controller.set_ptt(true);
+        assert_that!(result.unwrap_err(), matches_regex("rigctld error -9"));
+    }
+
+    #[test]
+    fn unexpected_response_is_an_error() {
+        let rigctld = fake_rigctld(|_| "14074000".to_string());
+        let mut controller = RigctldCatController::new(rigctld.address);
+        assert_that!(controller.set_ptt(true).is_err(), equal_to(true));
+    }
+
+    #[test]
+    fn dial_frequency_is_read() {
+        let rigctld = fake_rigctld(|command| if command == "f" { "14074000".to_string() } else { "RPRT -11".to_string() });
+        let mut controller = RigctldCatController::new(rigctld.address);
+        assert_that!(controller.get_dial_frequency(), equal_to(Ok(Some(14074000))));
+    }
+
+    #[test]
+    fn fractional_dial_frequency_is_read() {
+        let rigctld = fake_rigctld(|_| "7074000.000000".to_string());
+        let mut controller = RigctldCatController::new(rigctld.address);
+        assert_that!(controller.get_dial_frequency(), equal_to(Ok(Some(7074000))));
+    }
+
+    #[test]
+    fn unreadable_dial_frequency_is_an_error() {
+        let rigctld = fake_rigctld(|_| "VFOA".to_string());
+        let mut controller = RigctldCatController::new(rigctld.address);
+        assert_that!(controller.get_dial_frequency().is_err(), equal_to(true));
+    }
+
+    #[test]
+    fn unreachable_rigctld_is_an_error() {
+        let address = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+        let mut controller = RigctldCatController::new(address);
+        assert_that!(controller.connect().is_err(), equal_to(true));
+        assert_that!(controller.set_ptt(true).is_err(), equal_to(true));
+    }
+
+    #[test]
+    fn rigctld_started_later_is_connected_to_by_the_next_command() {
+        let address = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+        let mut controller = RigctldCatController::new(address.clone());
+        assert_that!(controller.set_ptt(true).is_err(), equal_to(true));
+        let rigctld = fake_rigctld_at(&address, |_| "RPRT 0".to_string());
+        assert_that!(controller.set_ptt(true), equal_to(Ok(())));
+        assert_that!(rigctld.commands.lock().unwrap().clone(), equal_to(vec!["T 1".to_string()]));
+    }
+
+    #[test]
+    fn lost_connection_is_reconnected() {
+        let mut closed = false;
+        let rigctld = fake_rigctld(move |_| {
+            if closed {
+                "RPRT 0".to_string()
+            } else {
+                closed = true;
+                String::new()
+            }
+        });
+        let mut controller = RigctldCatController::new(rigctld.address.clone());
+        assert_that!(controller.set_ptt(true).is_err(), equal_to(true));
+        assert_that!(controller.set_ptt(true), equal_to(Ok(())));
+        assert_that!(rigctld.commands.lock().unwrap().clone(), equal_to(vec!["T 1".to_string(), "T 1".to_string()]));
+    }
+}
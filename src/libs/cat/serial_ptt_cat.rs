@@ -0,0 +1,65 @@
+/*
+ * Keys the rig's PTT with a modem control line (RTS or DTR) of a serial port, as with a simple
+ * USB-serial adapter interface, rather than with CAT commands.
+ * Relays and linear amplifiers take time to switch, so the line is asserted for the lead delay
+ * before the Transmitter starts its output, and held for the tail delay after its output ends.
+ */
+
+use std::thread;
+use std::time::Duration;
+use log::debug;
+use serde_derive::{Deserialize, Serialize};
+use crate::libs::cat::cat::CatController;
+use crate::libs::serial_io::serial_io::SerialIO;
+use crate::libs::transmitter::transmitter::RadioFrequencyHz;
+
+#[derive(Serialize, Deserialize, Debug, PartialOrd, PartialEq, Copy, Clone)]
+pub enum PttLine {
+    Rts, Dtr
+}
+
+pub struct SerialPttCatController {
+    serial_io: Box<dyn SerialIO>,
+    line: PttLine,
+    lead_delay: Duration,
+    tail_delay: Duration,
+}
+
+impl SerialPttCatController {
+    // The line is released initially, as opening the port may have asserted it.
+    pub fn new(serial_io: Box<dyn SerialIO>, line: PttLine, lead_delay: Duration, tail_delay: Duration) -> Result<Self, String> {
+        let mut controller = Self { serial_io, line, lead_delay, tail_delay };
+        controller.set_line(false)?;
+        Ok(controller)
+    }
+
+    fn set_line(&mut self, level: bool) -> Result<(), String> {
+        debug!("Setting PTT line {:?} to {}", self.line, level);
+        let result = match self.line {
+            PttLine::Rts => self.serial_io.set_request_to_send(level),
+            PttLine::Dtr => self.serial_io.set_data_terminal_ready(level),
+        };
+        result.map_err(|e| format!("Cannot set PTT line {:?} to {}: {}", self.line, level, e))
+    }
+}
+
+impl CatController for SerialPttCatController {
+    fn set_ptt(&mut self, transmit: bool) -> Result<(), String> {
+        if transmit {
+            self.set_line(true)?;
+            thread::sleep(self.lead_delay);
+            Ok(())
+        } else {
+            thread::sleep(self.tail_delay);
+            self.set_line(false)
+        }
+    }
+
+    fn get_dial_frequency(&mut self) -> Result<Option<RadioFrequencyHz>, String> {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+#[path = "./serial_ptt_cat_spec.rs"]
+mod serial_ptt_cat_spec;
@@ -0,0 +1,109 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod serial_ptt_cat_spec {
+    use std::env;
+    use std::io;
+    use std::io::{Error, ErrorKind};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+    use hamcrest2::prelude::*;
+    use crate::libs::cat::cat::CatController;
+    use crate::libs::cat::serial_ptt_cat::{PttLine, SerialPttCatController};
+    use crate::libs::serial_io::serial_io::SerialIO;
+
+    #[ctor::ctor]
+    fn before_each() {
+        env::set_var("RUST_LOG", "debug");
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[ctor::dtor]
+    fn after_each() {}
+
+    type LineChanges = Arc<Mutex<Vec<(PttLine, bool, Instant)>>>;
+
+    // Records when each modem control line is set, and to what.
+    struct MockSerialIO {
+        line_changes: LineChanges,
+        fail: bool,
+    }
+
+    impl MockSerialIO {
+        fn set_line(&mut self, line: PttLine, level: bool) -> io::Result<()> {
+            if self.fail {
+                return Err(Error::new(ErrorKind::BrokenPipe, "Device removed"));
+            }
+            self.line_changes.lock().unwrap().push((line, level, Instant::now()));
+            Ok(())
+        }
+    }
+
+    impl SerialIO for MockSerialIO {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            Ok(0)
+        }
+
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn set_request_to_send(&mut self, level: bool) -> io::Result<()> {
+            self.set_line(PttLine::Rts, level)
+        }
+
+        fn set_data_terminal_ready(&mut self, level: bool) -> io::Result<()> {
+            self.set_line(PttLine::Dtr, level)
+        }
+    }
+
+    fn mock_serial_io() -> (Box<dyn SerialIO>, LineChanges) {
+        let line_changes = Arc::new(Mutex::new(vec![]));
+        (Box::new(MockSerialIO { line_changes: line_changes.clone(), fail: false }), line_changes)
+    }
+
+    fn levels(line_changes: &LineChanges) -> Vec<(PttLine, bool)> {
+        line_changes.lock().unwrap().iter().map(|(line, level, _)| (*line, *level)).collect()
+    }
+
+    #[test]
+    fn line_is_released_on_creation() {
+        let (serial_io, line_changes) = mock_serial_io();
+        let _controller = SerialPttCatController::new(serial_io, PttLine::Dtr, Duration::ZERO, Duration::ZERO).unwrap();
+        assert_that!(levels(&line_changes), equal_to(vec![(PttLine::Dtr, false)]));
+    }
+
+    #[test]
+    fn ptt_is_keyed_with_lead_and_tail_delays() {
+        let lead_delay = Duration::from_millis(100);
+        let tail_delay = Duration::from_millis(150);
+        let (serial_io, line_changes) = mock_serial_io();
+        let mut controller = SerialPttCatController::new(serial_io, PttLine::Rts, lead_delay, tail_delay).unwrap();
+
+        // Keying returns, and so the Transmitter's output starts, only after the lead delay.
+        assert_that!(controller.set_ptt(true), equal_to(Ok(())));
+        let output_start = Instant::now();
+        // The Transmitter's output ends; the line is held for the tail delay.
+        let output_end = Instant::now();
+        assert_that!(controller.set_ptt(false), equal_to(Ok(())));
+
+        assert_that!(levels(&line_changes), equal_to(vec![(PttLine::Rts, false), (PttLine::Rts, true), (PttLine::Rts, false)]));
+        let locked_line_changes = line_changes.lock().unwrap();
+        let keyed = locked_line_changes[1].2;
+        let released = locked_line_changes[2].2;
+        assert_that!(output_start.duration_since(keyed) >= lead_delay, equal_to(true));
+        assert_that!(released.duration_since(output_end) >= tail_delay, equal_to(true));
+    }
+
+    #[test]
+    fn line_failure_is_an_error() {
+        let line_changes = Arc::new(Mutex::new(vec![]));
+        let serial_io = Box::new(MockSerialIO { line_changes, fail: true });
+        let result = SerialPttCatController::new(serial_io, PttLine::Rts, Duration::ZERO, Duration::ZERO);
+        assert_that!(result.err().unwrap(), matches_regex("Cannot set PTT line Rts to false"));
+    }
+}
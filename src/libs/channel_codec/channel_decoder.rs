@@ -0,0 +1,160 @@
+/*
+ * The Channel Decoder is the inverse of the Channel Encoder. It receives the symbols demodulated
+ * from a block (after its Costas Array), undoes the Gray mapping, repacks the 4-bit symbols into
+ * the 256-bit codeword, deinterleaves it, and LDPC-decodes it. The resulting packed message is
+ * unpacked into the source encoded data and its CRC, which is checked.
+ * Alternatively, the received magnitudes of every tone of each symbol may be soft-decision decoded.
+ * Each block is reported as a ChannelDecoding, giving the SourceEncoding or the reason for failure,
+ * and the number of LDPC decoder iterations.
+ */
+
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use log::{debug, info};
+use metered::time_source::{Instant, StdInstant};
+
+use crate::libs::channel_codec::channel_encoding::{CHANNEL_ENCODER_BLOCK_SIZE, ChannelEncoding, ChannelSymbol, ToneMagnitudes};
+use crate::libs::channel_codec::costas::COSTAS_ARRAY_SYMBOLS;
+use crate::libs::channel_codec::crc::{CRC, crc14};
+use crate::libs::channel_codec::gray::from_gray_code;
+use crate::libs::channel_codec::interleave::{CODEWORD_INTERLEAVER, Interleaver};
+use crate::libs::channel_codec::ldpc::{CodeWord, decode_codeword_with_iterations, decode_llrs_with_iterations, unpack_message};
+use crate::libs::channel_codec::soft_decision::tone_magnitudes_to_llrs;
+use crate::libs::source_codec::source_encoding::SourceEncoding;
+use crate::libs::transform_bus::transform_bus::TransformBus;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChannelDecodeError {
+    // The block does not contain CHANNEL_ENCODER_BLOCK_SIZE symbols.
+    WrongNumberOfSymbols(usize),
+    // A symbol is not one of the 16 tones.
+    InvalidSymbol(ChannelSymbol),
+    // The LDPC decoder could not find a codeword.
+    LDPCDecodeFailure,
+    // The LDPC decoder found a codeword, but its CRC does not match its data.
+    CRCMismatch { received: CRC, computed: CRC },
+    // The block's audio could not be demodulated into symbols.
+    DemodulationFailure(String),
+}
+
+impl Display for ChannelDecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ChannelDecodeError::WrongNumberOfSymbols(symbols) => {
+                write!(f, "Cannot decode a block of {} symbols", symbols)
+            }
+            ChannelDecodeError::InvalidSymbol(symbol) => {
+                write!(f, "Cannot decode a block containing symbol {}", symbol)
+            }
+            ChannelDecodeError::LDPCDecodeFailure => {
+                write!(f, "LDPC decoding failed")
+            }
+            ChannelDecodeError::CRCMismatch { received, computed } => {
+                write!(f, "CRC mismatch: received 0x{:04X?}, computed 0x{:04X?}", received, computed)
+            }
+            ChannelDecodeError::DemodulationFailure(reason) => {
+                write!(f, "Demodulation failed: {}", reason)
+            }
+        }
+    }
+}
+
+impl Error for ChannelDecodeError {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChannelDecoding {
+    pub source_encoding: Result<SourceEncoding, ChannelDecodeError>,
+    // How many iterations the LDPC decoder took, whether it succeeded or not.
+    pub iterations: usize,
+}
+
+impl Display for ChannelDecoding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.source_encoding {
+            Ok(source_encoding) => write!(f, "Decoded in {} iterations: {}", self.iterations, source_encoding),
+            Err(e) => write!(f, "Failed after {} iterations: {}", self.iterations, e),
+        }
+    }
+}
+
+// The block of the ChannelEncoding starts with the Costas Array, which is skipped.
+pub fn channel_encoding_to_channel_decoding(channel_encoding: ChannelEncoding) -> ChannelDecoding {
+    let symbols = channel_encoding.block.get(COSTAS_ARRAY_SYMBOLS..).unwrap_or(&[]);
+    channel_symbols_to_channel_decoding(symbols)
+}
+
+// The symbols are those following the Costas Array.
+pub fn channel_symbols_to_channel_decoding(symbols: &[ChannelSymbol]) -> ChannelDecoding {
+    channel_symbols_to_channel_decoding_with(symbols, &CODEWORD_INTERLEAVER)
+}
+
+pub fn channel_symbols_to_channel_decoding_with(symbols: &[ChannelSymbol], interleaver: &dyn Interleaver) -> ChannelDecoding {
+    let decode_duration = StdInstant::now();
+    let code_word = match symbols_to_codeword(symbols) {
+        Ok(code_word) => code_word,
+        Err(e) => {
+            return ChannelDecoding { source_encoding: Err(e), iterations: 0 };
+        }
+    };
+    let (maybe_packed_message, iterations) = decode_codeword_with_iterations(&interleaver.deinterleave(&code_word));
+    let out = packed_message_to_channel_decoding(maybe_packed_message, iterations);
+    info!("Channel decoding done in {}ms; {}", decode_duration.elapsed_time(), out);
+    out
+}
+
+// Soft-decision decoding of the received magnitudes of each tone, for each of the symbols following
+// the Costas Array (see soft_decision.rs).
+pub fn channel_tone_magnitudes_to_channel_decoding(symbol_tone_magnitudes: &[ToneMagnitudes]) -> ChannelDecoding {
+    channel_tone_magnitudes_to_channel_decoding_with(symbol_tone_magnitudes, &CODEWORD_INTERLEAVER)
+}
+
+pub fn channel_tone_magnitudes_to_channel_decoding_with(symbol_tone_magnitudes: &[ToneMagnitudes], interleaver: &dyn Interleaver) -> ChannelDecoding {
+    let decode_duration = StdInstant::now();
+    if symbol_tone_magnitudes.len() != CHANNEL_ENCODER_BLOCK_SIZE {
+        return ChannelDecoding { source_encoding: Err(ChannelDecodeError::WrongNumberOfSymbols(symbol_tone_magnitudes.len())), iterations: 0 };
+    }
+    let llrs = interleaver.deinterleave_llrs(&tone_magnitudes_to_llrs(symbol_tone_magnitudes));
+    let (maybe_packed_message, iterations) = decode_llrs_with_iterations(&llrs);
+    let out = packed_message_to_channel_decoding(maybe_packed_message, iterations);
+    info!("Soft channel decoding done in {}ms; {}", decode_duration.elapsed_time(), out);
+    out
+}
+
+fn packed_message_to_channel_decoding(maybe_packed_message: Option<CodeWord>, iterations: usize) -> ChannelDecoding {
+    let source_encoding = match maybe_packed_message {
+        None => Err(ChannelDecodeError::LDPCDecodeFailure),
+        Some(packed_message) => {
+            let (block, is_end, is_metadata_only, received) = unpack_message(&packed_message);
+            let computed = crc14(block.as_slice());
+            if received == computed {
+                Ok(SourceEncoding { block, is_end, is_metadata_only })
+            } else {
+                Err(ChannelDecodeError::CRCMismatch { received, computed })
+            }
+        }
+    };
+    ChannelDecoding { source_encoding, iterations }
+}
+
+// Convert each symbol from its Gray code, and pack pairs of them into the bytes of the codeword.
+fn symbols_to_codeword(symbols: &[ChannelSymbol]) -> Result<CodeWord, ChannelDecodeError> {
+    if symbols.len() != CHANNEL_ENCODER_BLOCK_SIZE {
+        return Err(ChannelDecodeError::WrongNumberOfSymbols(symbols.len()));
+    }
+    if let Some(symbol) = symbols.iter().find(|symbol| **symbol > 0x0f) {
+        return Err(ChannelDecodeError::InvalidSymbol(*symbol));
+    }
+    let code_word: CodeWord = symbols.chunks(2)
+        .map(|pair| (from_gray_code(pair[0]) << 4) | from_gray_code(pair[1]))
+        .collect();
+    debug!("Code word {:02X?}", code_word);
+    Ok(code_word)
+}
+
+pub type ChannelDecoder = TransformBus<ChannelEncoding, ChannelDecoding>;
+
+#[cfg(test)]
+#[path = "./channel_decoder_spec.rs"]
+mod channel_decoder_spec;
@@ -0,0 +1,195 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod channel_decoder_spec {
+    use std::env;
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+    use bus::{Bus, BusReader};
+    use hamcrest2::prelude::*;
+
+    use log::{debug, info};
+    use rstest::*;
+    use crate::libs::application::application::{BusInput, BusOutput};
+    use crate::libs::channel_codec::channel_decoder::{channel_encoding_to_channel_decoding, channel_symbols_to_channel_decoding, channel_symbols_to_channel_decoding_with, ChannelDecodeError, ChannelDecoder, ChannelDecoding};
+    use crate::libs::channel_codec::channel_encoder::{source_encoding_to_channel_encoding, source_encoding_to_channel_encoding_with};
+    use crate::libs::channel_codec::channel_encoding::ChannelEncoding;
+    use crate::libs::channel_codec::costas::{COSTAS_ARRAY, COSTAS_ARRAY_SYMBOLS};
+    use crate::libs::channel_codec::crc::crc14;
+    use crate::libs::channel_codec::gray::to_gray_code;
+    use crate::libs::channel_codec::interleave::NoInterleaver;
+    use crate::libs::channel_codec::ldpc::{encode_packed_message, pack_message};
+    use crate::libs::channel_codec::sample_channel_encoding::sample_channel_encoding;
+    use crate::libs::source_codec::source_decoder::SourceDecoder;
+    use crate::libs::source_codec::source_encoding::{Frame, SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, SourceEncoding};
+    use crate::libs::source_codec::test_encoding_builder::encoded;
+
+    use crate::libs::util::test_util;
+
+    #[ctor::ctor]
+    fn before_each() {
+        env::set_var("RUST_LOG", "debug");
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[ctor::dtor]
+    fn after_each() {}
+
+    pub struct ChannelDecoderFixture {
+        terminate: Arc<AtomicBool>,
+        channel_encoding_tx: Bus<ChannelEncoding>,
+        channel_decoder_rx: BusReader<ChannelDecoding>,
+        _channel_decoder: ChannelDecoder,
+    }
+
+    #[fixture]
+    fn fixture() -> ChannelDecoderFixture {
+        let terminate = Arc::new(AtomicBool::new(false));
+        let mut channel_encoding_tx = Bus::new(16);
+        let channel_encoding_rx = channel_encoding_tx.add_rx();
+        let mut channel_decoder_tx = Bus::new(16);
+        let channel_decoder_rx = channel_decoder_tx.add_rx();
+        let mut channel_decoder = ChannelDecoder::new(channel_encoding_to_channel_decoding, terminate.clone());
+        channel_decoder.set_input_rx(Arc::new(Mutex::new(channel_encoding_rx)));
+        channel_decoder.set_output_tx(Arc::new(Mutex::new(channel_decoder_tx)));
+
+        info!("Fixture setup sleeping");
+        test_util::wait_5_ms();
+        // give things time to start
+        info!("Fixture setup out of sleep");
+
+        ChannelDecoderFixture {
+            terminate,
+            channel_encoding_tx,
+            channel_decoder_rx,
+            _channel_decoder: channel_decoder,
+        }
+    }
+
+    impl Drop for ChannelDecoderFixture {
+        fn drop(&mut self) {
+            debug!("ChannelDecoderFixture setting terminate flag...");
+            self.terminate.store(true, Ordering::SeqCst);
+            test_util::wait_5_ms();
+            debug!("ChannelDecoderFixture ...set terminate flag");
+        }
+    }
+
+    fn sample_frames() -> Vec<Frame> {
+        vec![
+            Frame::CallsignMetadata { callsign: "M0CUV".to_owned() },
+            Frame::WPMPolarity { wpm: 20, polarity: true },
+            Frame::KeyingPerfectDah,
+            Frame::KeyingPerfectDit,
+            Frame::KeyingDeltaDah { delta: 5 },
+            Frame::KeyingPerfectWordgap,
+            Frame::KeyingEnd,
+        ]
+    }
+
+    fn sample_source_encoding() -> SourceEncoding {
+        let block = encoded(SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, 20, &sample_frames());
+        SourceEncoding { block, is_end: false, is_metadata_only: false }
+    }
+
+    #[rstest]
+    pub fn transform_encodings_with_channel_decoder_active_object(mut fixture: ChannelDecoderFixture) {
+        fixture.channel_encoding_tx.broadcast(source_encoding_to_channel_encoding(sample_source_encoding()));
+        info!("channel encoding sent; waiting for channel decoding");
+
+        let result = fixture.channel_decoder_rx.recv_timeout(Duration::from_millis(250));
+        assert_that!(result.map(|decoding| decoding.source_encoding), has(Ok(sample_source_encoding())));
+    }
+
+    #[test]
+    pub fn decode_sample_channel_encoding() {
+        let decoding = channel_encoding_to_channel_decoding(sample_channel_encoding());
+        let source_encoding = decoding.source_encoding.unwrap();
+        assert_that!(source_encoding.block.len(), equal_to(SOURCE_ENCODER_BLOCK_SIZE_IN_BITS / 8));
+        assert_that!(source_encoding.is_end, equal_to(true));
+        assert_that!(decoding.iterations, equal_to(0));
+    }
+
+    #[test]
+    pub fn keying_survives_the_channel_encoder_and_decoder() {
+        let channel_encoding = source_encoding_to_channel_encoding(sample_source_encoding());
+        let decoding = channel_encoding_to_channel_decoding(channel_encoding);
+
+        let source_decoder = SourceDecoder::new(SOURCE_ENCODER_BLOCK_SIZE_IN_BITS);
+        let frames = source_decoder.source_decode(decoding.source_encoding.unwrap().block).unwrap();
+        let mut expected_frames = sample_frames();
+        expected_frames.push(Frame::Padding);
+        assert_that!(frames, equal_to(expected_frames));
+    }
+
+    #[test]
+    pub fn flags_survive_the_channel_encoder_and_decoder() {
+        for (is_end, is_metadata_only) in [(false, false), (true, false), (false, true), (true, true)] {
+            let mut source_encoding = sample_source_encoding();
+            source_encoding.is_end = is_end;
+            source_encoding.is_metadata_only = is_metadata_only;
+            let decoding = channel_encoding_to_channel_decoding(source_encoding_to_channel_encoding(source_encoding.clone()));
+            assert_that!(decoding.source_encoding, equal_to(Ok(source_encoding)));
+        }
+    }
+
+    #[test]
+    pub fn damaged_symbols_are_corrected() {
+        let mut channel_encoding = source_encoding_to_channel_encoding(sample_source_encoding());
+        for symbol in [10, 30, 50] {
+            let index = COSTAS_ARRAY_SYMBOLS + symbol;
+            channel_encoding.block[index] ^= 0x01;
+        }
+        let decoding = channel_encoding_to_channel_decoding(channel_encoding);
+        assert_that!(decoding.source_encoding, equal_to(Ok(sample_source_encoding())));
+        assert_that!(decoding.iterations, greater_than(0));
+    }
+
+    #[test]
+    pub fn decode_without_interleaving() {
+        let channel_encoding = source_encoding_to_channel_encoding_with(sample_source_encoding(), COSTAS_ARRAY, &NoInterleaver {});
+        let decoding = channel_symbols_to_channel_decoding_with(&channel_encoding.block[COSTAS_ARRAY_SYMBOLS..], &NoInterleaver {});
+        assert_that!(decoding.source_encoding, equal_to(Ok(sample_source_encoding())));
+    }
+
+    #[test]
+    pub fn badly_damaged_block_cannot_be_decoded() {
+        let mut channel_encoding = source_encoding_to_channel_encoding(sample_source_encoding());
+        for symbol in 0..32 {
+            channel_encoding.block[COSTAS_ARRAY_SYMBOLS + symbol * 2] ^= 0x0f;
+        }
+        let decoding = channel_encoding_to_channel_decoding(channel_encoding);
+        assert_that!(decoding.source_encoding, equal_to(Err(ChannelDecodeError::LDPCDecodeFailure)));
+        assert_that!(decoding.iterations, greater_than(0));
+    }
+
+    #[test]
+    pub fn crc_mismatch_is_detected() {
+        let source_encoding = sample_source_encoding();
+        let computed = crc14(source_encoding.block.as_slice());
+        let received = computed ^ 0x0001;
+        // A valid codeword, but with the wrong CRC.
+        let code_word = encode_packed_message(&pack_message(&source_encoding.block, false, false, received));
+        let symbols: Vec<u8> = code_word.iter().flat_map(|byte| vec![to_gray_code(byte >> 4), to_gray_code(byte & 0x0f)]).collect();
+        let decoding = channel_symbols_to_channel_decoding_with(&symbols, &NoInterleaver {});
+        assert_that!(decoding.source_encoding, equal_to(Err(ChannelDecodeError::CRCMismatch { received, computed })));
+    }
+
+    #[test]
+    pub fn wrong_number_of_symbols_cannot_be_decoded() {
+        let decoding = channel_symbols_to_channel_decoding(&[0; 63]);
+        assert_that!(decoding.source_encoding, equal_to(Err(ChannelDecodeError::WrongNumberOfSymbols(63))));
+        assert_that!(decoding.iterations, equal_to(0));
+        let decoding = channel_encoding_to_channel_decoding(ChannelEncoding { block: vec![0; 3], is_end: false });
+        assert_that!(decoding.source_encoding, equal_to(Err(ChannelDecodeError::WrongNumberOfSymbols(0))));
+    }
+
+    #[test]
+    pub fn invalid_symbols_cannot_be_decoded() {
+        let mut symbols = vec![0; 64];
+        symbols[17] = 16;
+        let decoding = channel_symbols_to_channel_decoding(&symbols);
+        assert_that!(decoding.source_encoding, equal_to(Err(ChannelDecodeError::InvalidSymbol(16))));
+    }
+}
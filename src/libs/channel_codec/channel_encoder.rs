@@ -4,10 +4,10 @@
  * is not critical to error control; the LDPC performance dominates. See discussions on the WSJT-X
  * mailing list.
  * The Channel Encoder receives SourceEncodings on its input bus, applies the CRC, then the LDPC.
- * Then the resulting data is split into 4-bit fields (each can hold a number from 0-15 hence the
- * 16 tones used), these are then mapped to a set of 4-bit Gray codes. A ramping-up symbol is
- * emitted followed by a 7x7 Costas Array, then the encoded 4-bit symbols, followed by a final
- * ramping-down symbol.
+ * The codeword is interleaved (see interleave.rs). Then the resulting data is split into 4-bit
+ * fields (each can hold a number from 0-15 hence the 16 tones used), these are then mapped to a
+ * set of 4-bit Gray codes. A ramping-up symbol is emitted followed by a Costas Array (see
+ * costas.rs), then the encoded 4-bit symbols, followed by a final ramping-down symbol.
  * Ramping symbols have duration 20ms; 4-bit tone symbols have duration 160ms.
  * The transmitter will then output a Gaussian Frequency Shift Keyed tone for each (either by
  * generating tones starting at the currently configured transmit audio offset, or by directly
@@ -31,6 +31,8 @@
 use log::{debug, info};
 use metered::time_source::{Instant, StdInstant};
 use crate::libs::channel_codec::channel_encoding::{ChannelEncoding, ChannelSymbol};
+use crate::libs::channel_codec::costas::COSTAS_ARRAY;
+use crate::libs::channel_codec::interleave::{CODEWORD_INTERLEAVER, Interleaver};
 use crate::libs::channel_codec::crc::crc14;
 use crate::libs::channel_codec::ldpc::{encode_packed_message, pack_message};
 use crate::libs::source_codec::source_encoding::SourceEncoding;
@@ -39,9 +41,13 @@ use pretty_hex::*;
 use crate::libs::channel_codec::gray::to_gray_code;
 
 pub fn source_encoding_to_channel_encoding(source_encoding: SourceEncoding) -> ChannelEncoding {
+    source_encoding_to_channel_encoding_with(source_encoding, COSTAS_ARRAY, &CODEWORD_INTERLEAVER)
+}
+
+pub fn source_encoding_to_channel_encoding_with(source_encoding: SourceEncoding, costas_array: &[ChannelSymbol], interleaver: &dyn Interleaver) -> ChannelEncoding {
     let encode_duration = StdInstant::now();
     let crc = crc14(&source_encoding.block.as_slice());
-    let packed_message = pack_message(&source_encoding.block, false, false, crc);
+    let packed_message = pack_message(&source_encoding.block, source_encoding.is_end, source_encoding.is_metadata_only, crc);
     let code_word = encode_packed_message(&packed_message);
 
     let hexdump = pretty_hex(&code_word.as_slice());
@@ -53,14 +59,14 @@ pub fn source_encoding_to_channel_encoding(source_encoding: SourceEncoding) -> C
     // Now convert the code_word into a Vec<ChannelSymbol>
     let mut channel_symbols: Vec<ChannelSymbol> = Vec::new();
 
-    // TODO interleave nybbles of the codeword? Does fading more adversely affect different parts of
-    // the code word - e.g. if the LDPC data is damaged, does that make recovery harder than if
-    // the source data is damaged?
+    // Prefix the Costas Array, so the receiver can synchronise with the block.
+    channel_symbols.extend_from_slice(costas_array);
 
-    // TODO Costas Array - possibly just use the same 7x7 array as FT8, using tones 0-6?
+    // Spread the bits of the codeword so that consecutive symbols don't carry adjacent bits.
+    let interleaved_code_word = interleaver.interleave(&code_word);
 
     // Convert each nybble of the codeword into its Gray code.
-    for byte in code_word {
+    for byte in interleaved_code_word {
         channel_symbols.push(to_gray_code(byte >> 4) as ChannelSymbol );
         channel_symbols.push(to_gray_code(byte & 0x0f) as ChannelSymbol )
     }
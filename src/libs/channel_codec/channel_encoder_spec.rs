@@ -12,8 +12,10 @@ mod channel_encoder_spec {
     use log::{debug, info};
     use rstest::*;
     use crate::libs::application::application::{BusInput, BusOutput};
-    use crate::libs::channel_codec::channel_encoder::{ChannelEncoder, source_encoding_to_channel_encoding};
+    use crate::libs::channel_codec::channel_encoder::{ChannelEncoder, source_encoding_to_channel_encoding, source_encoding_to_channel_encoding_with};
     use crate::libs::channel_codec::channel_encoding::{ChannelEncoding, CHANNEL_ENCODER_BLOCK_SIZE};
+    use crate::libs::channel_codec::costas::{COSTAS_ARRAY_16X16, COSTAS_ARRAY_SYMBOLS};
+    use crate::libs::channel_codec::interleave::CODEWORD_INTERLEAVER;
     use crate::libs::channel_codec::sample_channel_encoding::sample_channel_encoding;
     use crate::libs::source_codec::source_encoding::{Frame, SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, SourceEncoding};
     use crate::libs::source_codec::test_encoding_builder::encoded;
@@ -91,11 +93,18 @@ mod channel_encoder_spec {
             debug!("Channel encoding {:?}", line);
         }
         let expected_channel_encoding = sample_channel_encoding();
-        info!("There are {} elements in the channel encoding", expected_channel_encoding.block.len()); // 64
+        info!("There are {} elements in the channel encoding", expected_channel_encoding.block.len()); // 71
         assert_that!(channel_encoding, equal_to(expected_channel_encoding));
-        assert_that!(channel_encoding_len, equal_to(CHANNEL_ENCODER_BLOCK_SIZE));
+        assert_that!(channel_encoding_len, equal_to(COSTAS_ARRAY_SYMBOLS + CHANNEL_ENCODER_BLOCK_SIZE));
     }
 
+    #[test]
+    pub fn channel_encoding_is_prefixed_with_the_chosen_costas_array() {
+        let channel_encoding = source_encoding_to_channel_encoding_with(generate_sample_source_encoding(), COSTAS_ARRAY_16X16, &CODEWORD_INTERLEAVER);
+        let expected_channel_encoding = sample_channel_encoding();
+        assert_that!(&channel_encoding.block[0..16], equal_to(COSTAS_ARRAY_16X16));
+        assert_that!(&channel_encoding.block[16..], equal_to(&expected_channel_encoding.block[COSTAS_ARRAY_SYMBOLS..]));
+    }
 
     fn generate_sample_source_encoding() -> SourceEncoding {
         let keying_frames = &[
@@ -107,7 +116,7 @@ mod channel_encoder_spec {
             Frame::Padding
         ];
         let block = encoded(SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, 20, keying_frames);
-        let source_encoding = SourceEncoding { block, is_end: true };
+        let source_encoding = SourceEncoding { block, is_end: true, is_metadata_only: false };
         source_encoding
     }
 }
@@ -3,6 +3,13 @@ use std::fmt;
 
 pub type ChannelSymbol = u8;
 
+// Each ChannelSymbol is one of 16 tones.
+pub const NUMBER_OF_TONES: usize = 16;
+
+// The received magnitude of each tone during a symbol period.
+pub type ToneMagnitudes = [f32; NUMBER_OF_TONES];
+
+// The number of symbols of the Gray-coded codeword that follow the Costas array.
 pub const CHANNEL_ENCODER_BLOCK_SIZE: usize = 64;
 
 #[derive(Clone, PartialEq, Debug)]
@@ -0,0 +1,59 @@
+use crate::libs::channel_codec::channel_encoding::ChannelSymbol;
+
+/*
+ * Each ChannelEncoding is prefixed with a Costas array, so that the receiver can find the start of
+ * a block in time, and its audio offset in frequency. A Costas array is a permutation of tones in
+ * which every displacement vector between pairs of symbols is distinct: shifted copies of it
+ * overlap the original in at most one symbol, whatever the shift in time or frequency. Its
+ * correlation with the received spectra therefore has one sharp peak.
+ *
+ * Two arrays are available. The 7x7 array is that of FT8, using tones 0-6 of the 16. The 16x16
+ * array uses all 16 tones, and is generated by the Welch construction using the prime 17 and its
+ * primitive root 3, giving tone 3^(i+1) mod 17 - 1 for symbol i. It is longer, so takes more time
+ * to send, but should be more robust in detection.
+ */
+pub const COSTAS_ARRAY_7X7: &[ChannelSymbol] = &[3, 1, 4, 0, 6, 5, 2];
+pub const COSTAS_ARRAY_16X16: &[ChannelSymbol] = &[2, 8, 9, 12, 4, 14, 10, 15, 13, 7, 6, 3, 11, 1, 5, 0];
+
+// The array prefixed to every ChannelEncoding, chosen at build time with the costas16 feature.
+// Block sizes, buffer sizes and the detector's window are all derived from it, and a transmitter
+// and receiver must use the same array, so it is fixed for the program rather than configured at
+// run time.
+#[cfg(not(feature = "costas16"))]
+pub const COSTAS_ARRAY: &[ChannelSymbol] = COSTAS_ARRAY_7X7;
+#[cfg(feature = "costas16")]
+pub const COSTAS_ARRAY: &[ChannelSymbol] = COSTAS_ARRAY_16X16;
+pub const COSTAS_ARRAY_SYMBOLS: usize = COSTAS_ARRAY.len();
+
+// Does the sequence of tones have the Costas property: is each a distinct tone, and is each
+// displacement (in time and tone) between pairs of symbols distinct?
+pub fn is_costas_array(symbols: &[ChannelSymbol]) -> bool {
+    let mut displacements = vec![];
+    for (i, first) in symbols.iter().enumerate() {
+        for (j, second) in symbols.iter().enumerate().skip(i + 1) {
+            if first == second {
+                return false;
+            }
+            let displacement = (j - i, *second as i16 - *first as i16);
+            if displacements.contains(&displacement) {
+                return false;
+            }
+            displacements.push(displacement);
+        }
+    }
+    true
+}
+
+// How many of the array's tones match the symbols, starting at the given symbol index and with
+// each tone shifted up by the given number of tones? Symbols beyond the end don't match.
+pub fn costas_array_matches(array: &[ChannelSymbol], symbols: &[ChannelSymbol], start: usize, tone_shift: i16) -> usize {
+    array.iter().enumerate()
+        .filter(|(i, tone)| {
+            symbols.get(start + i).map_or(false, |symbol| *symbol as i16 == **tone as i16 + tone_shift)
+        })
+        .count()
+}
+
+#[cfg(test)]
+#[path = "./costas_spec.rs"]
+mod costas_spec;
@@ -0,0 +1,85 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod costas_spec {
+    use std::env;
+    use hamcrest2::prelude::*;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+    use rstest::*;
+    use crate::libs::channel_codec::channel_encoder::source_encoding_to_channel_encoding_with;
+    use crate::libs::channel_codec::channel_encoding::ChannelSymbol;
+    use crate::libs::channel_codec::costas::{COSTAS_ARRAY_16X16, COSTAS_ARRAY_7X7, costas_array_matches, is_costas_array};
+    use crate::libs::channel_codec::interleave::CODEWORD_INTERLEAVER;
+    use crate::libs::source_codec::source_encoding::{SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, SourceEncoding};
+
+    #[ctor::ctor]
+    fn before_each() {
+        env::set_var("RUST_LOG", "debug");
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[ctor::dtor]
+    fn after_each() {}
+
+    #[rstest(array, tones,
+        case(COSTAS_ARRAY_7X7, 7),
+        case(COSTAS_ARRAY_16X16, 16),
+    )]
+    fn arrays_are_costas_arrays(array: &[ChannelSymbol], tones: usize) {
+        assert_that!(array.len(), equal_to(tones));
+        assert_that!(array.iter().all(|tone| (*tone as usize) < tones), equal_to(true));
+        assert_that!(is_costas_array(array), equal_to(true));
+    }
+
+    #[test]
+    fn non_costas_arrays_are_detected() {
+        assert_that!(is_costas_array(&[0, 1, 2, 3]), equal_to(false)); // repeated displacements
+        assert_that!(is_costas_array(&[3, 1, 3]), equal_to(false)); // repeated tone
+    }
+
+    #[rstest(array,
+        case(COSTAS_ARRAY_7X7),
+        case(COSTAS_ARRAY_16X16),
+    )]
+    fn shifted_arrays_overlap_in_at_most_one_symbol(array: &[ChannelSymbol]) {
+        let length = array.len();
+        // Place the array in the middle of a sequence of tones that can't match.
+        let mut symbols = vec![0xff; length];
+        symbols.extend_from_slice(array);
+        symbols.extend(vec![0xff; length]);
+        for start in 1..(2 * length) {
+            for tone_shift in -15..=15 {
+                if start == length && tone_shift == 0 {
+                    assert_that!(costas_array_matches(array, &symbols, start, tone_shift), equal_to(length));
+                } else {
+                    assert_that!(costas_array_matches(array, &symbols, start, tone_shift), less_than_or_equal_to(1));
+                }
+            }
+        }
+    }
+
+    // The array cannot be confused with the data that follows it: in channel encodings of random
+    // blocks, it only matches completely at the start, with no tone shift; elsewhere in the
+    // encoding, at any tone shift, no more than half its symbols match.
+    #[rstest(array,
+        case(COSTAS_ARRAY_7X7),
+        case(COSTAS_ARRAY_16X16),
+    )]
+    fn array_is_not_confused_with_data(array: &[ChannelSymbol]) {
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        for _ in 0..100 {
+            let block: Vec<u8> = (0..SOURCE_ENCODER_BLOCK_SIZE_IN_BITS / 8).map(|_| rng.gen()).collect();
+            let channel_encoding = source_encoding_to_channel_encoding_with(SourceEncoding { block, is_end: false, is_metadata_only: false }, array, &CODEWORD_INTERLEAVER);
+            let symbols = &channel_encoding.block;
+            assert_that!(costas_array_matches(array, symbols, 0, 0), equal_to(array.len()));
+            for start in 0..symbols.len() {
+                for tone_shift in -15..=15 {
+                    if start != 0 || tone_shift != 0 {
+                        assert_that!(costas_array_matches(array, symbols, start, tone_shift), less_than_or_equal_to((array.len() + 1) / 2));
+                    }
+                }
+            }
+        }
+    }
+}
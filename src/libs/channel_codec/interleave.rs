@@ -0,0 +1,111 @@
+use crate::libs::channel_codec::ldpc::{CodeWord, LLR};
+
+/*
+ * Fading, static crashes and QRM tend to damage a few consecutive symbols, rather than symbols
+ * spread evenly through a block. Each symbol carries 4 adjacent bits of the LDPC codeword, so a
+ * burst of damaged symbols corrupts a contiguous region of the codeword, which the LDPC decoder is
+ * less able to correct than the same number of errors scattered throughout it.
+ * An Interleaver reorders the bits of the codeword after LDPC encoding, and before it is split
+ * into symbols and Gray mapped, so that the bits of consecutive symbols come from widely separated
+ * parts of the codeword. The receiver deinterleaves before LDPC decoding, which scatters a burst
+ * of errors throughout the codeword. Soft-decision decoding deinterleaves the log-likelihood
+ * ratios of each bit in the same way.
+ */
+pub trait Interleaver {
+    fn interleave(&self, code_word: &CodeWord) -> CodeWord;
+    fn deinterleave(&self, code_word: &CodeWord) -> CodeWord;
+    fn deinterleave_llrs(&self, llrs: &[LLR]) -> Vec<LLR>;
+}
+
+// Leaves the codeword as it is.
+pub struct NoInterleaver {}
+
+impl Interleaver for NoInterleaver {
+    fn interleave(&self, code_word: &CodeWord) -> CodeWord {
+        code_word.clone()
+    }
+
+    fn deinterleave(&self, code_word: &CodeWord) -> CodeWord {
+        code_word.clone()
+    }
+
+    fn deinterleave_llrs(&self, llrs: &[LLR]) -> Vec<LLR> {
+        llrs.to_vec()
+    }
+}
+
+// A burst of up to six symbols (roughly a second) is better corrected with this interleaver than
+// without. Longer bursts are not; few blocks survive those either way.
+pub const CODEWORD_INTERLEAVER: BlockInterleaver = BlockInterleaver::new(16, 16);
+
+// The bits of the codeword are written into a matrix of the given rows and columns, row by row,
+// then read out column by column. Adjacent bits of the output are therefore separated by the
+// number of columns in the codeword.
+pub struct BlockInterleaver {
+    rows: usize,
+    columns: usize,
+}
+
+impl BlockInterleaver {
+    pub const fn new(rows: usize, columns: usize) -> Self {
+        Self { rows, columns }
+    }
+
+    fn permute(&self, code_word: &CodeWord, to_index: fn(&Self, usize) -> usize) -> CodeWord {
+        let bits = self.rows * self.columns;
+        if code_word.len() * 8 != bits {
+            panic!("Expecting {} bits of codeword to interleave, not {}", bits, code_word.len() * 8);
+        }
+        let mut out = vec![0u8; code_word.len()];
+        for from in 0..bits {
+            if get_bit(code_word, from) {
+                set_bit(&mut out, to_index(self, from));
+            }
+        }
+        out
+    }
+
+    // The bit at row r, column c is read out at index c * rows + r.
+    fn interleaved_index(&self, from: usize) -> usize {
+        (from % self.columns) * self.rows + from / self.columns
+    }
+
+    fn deinterleaved_index(&self, from: usize) -> usize {
+        (from % self.rows) * self.columns + from / self.rows
+    }
+}
+
+impl Interleaver for BlockInterleaver {
+    fn interleave(&self, code_word: &CodeWord) -> CodeWord {
+        self.permute(code_word, BlockInterleaver::interleaved_index)
+    }
+
+    fn deinterleave(&self, code_word: &CodeWord) -> CodeWord {
+        self.permute(code_word, BlockInterleaver::deinterleaved_index)
+    }
+
+    fn deinterleave_llrs(&self, llrs: &[LLR]) -> Vec<LLR> {
+        let bits = self.rows * self.columns;
+        if llrs.len() != bits {
+            panic!("Expecting {} LLRs of codeword to deinterleave, not {}", bits, llrs.len());
+        }
+        let mut out = vec![0 as LLR; bits];
+        for (from, llr) in llrs.iter().enumerate() {
+            out[self.deinterleaved_index(from)] = *llr;
+        }
+        out
+    }
+}
+
+// Bits are numbered from the most significant bit of the first byte.
+fn get_bit(bytes: &[u8], index: usize) -> bool {
+    bytes[index >> 3] & (0x80 >> (index & 0x07)) != 0
+}
+
+fn set_bit(bytes: &mut [u8], index: usize) {
+    bytes[index >> 3] |= 0x80 >> (index & 0x07);
+}
+
+#[cfg(test)]
+#[path = "./interleave_spec.rs"]
+mod interleave_spec;
@@ -0,0 +1,115 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod interleave_spec {
+    use std::env;
+    use hamcrest2::prelude::*;
+    use log::info;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+    use rstest::*;
+    use crate::libs::channel_codec::crc::crc14;
+    use crate::libs::channel_codec::interleave::{BlockInterleaver, CODEWORD_INTERLEAVER, Interleaver, NoInterleaver};
+    use crate::libs::channel_codec::ldpc::{CodeWord, decode_codeword, encode_packed_message, pack_message};
+
+    #[ctor::ctor]
+    fn before_each() {
+        env::set_var("RUST_LOG", "info");
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[ctor::dtor]
+    fn after_each() {}
+
+    fn single_bit_code_word(index: usize) -> CodeWord {
+        let mut code_word = vec![0u8; 32];
+        code_word[index >> 3] = 0x80 >> (index & 0x07);
+        code_word
+    }
+
+    fn set_bit_index(code_word: &CodeWord) -> usize {
+        let byte = code_word.iter().position(|b| *b != 0).unwrap();
+        byte * 8 + code_word[byte].leading_zeros() as usize
+    }
+
+    #[test]
+    fn no_interleaver_leaves_the_codeword_alone() {
+        let code_word: CodeWord = (0..32).collect();
+        assert_that!(NoInterleaver {}.interleave(&code_word), equal_to(code_word.clone()));
+        assert_that!(NoInterleaver {}.deinterleave(&code_word), equal_to(code_word.clone()));
+    }
+
+    #[test]
+    fn block_interleaver_round_trips() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..100 {
+            let code_word: CodeWord = (0..32).map(|_| rng.gen()).collect();
+            let interleaved = CODEWORD_INTERLEAVER.interleave(&code_word);
+            assert_that!(CODEWORD_INTERLEAVER.deinterleave(&interleaved), equal_to(code_word.clone()));
+        }
+    }
+
+    #[test]
+    fn block_interleaver_reads_columns_of_rows() {
+        let interleaver = BlockInterleaver::new(8, 32);
+        // Row 0 column 1 is read out second of column 1, after the 8 bits of column 0.
+        assert_that!(set_bit_index(&interleaver.interleave(&single_bit_code_word(1))), equal_to(8));
+        // Row 1 column 0 is read out second.
+        assert_that!(set_bit_index(&interleaver.interleave(&single_bit_code_word(32))), equal_to(1));
+        assert_that!(set_bit_index(&interleaver.interleave(&single_bit_code_word(255))), equal_to(255));
+    }
+
+    #[test]
+    fn bits_of_each_symbol_come_from_separate_parts_of_the_codeword() {
+        for symbol in 0..64 {
+            let mut sources: Vec<usize> = (0..4)
+                .map(|bit| set_bit_index(&CODEWORD_INTERLEAVER.deinterleave(&single_bit_code_word(symbol * 4 + bit))))
+                .collect();
+            sources.sort();
+            for pair in sources.windows(2) {
+                assert_that!(pair[1] - pair[0], greater_than_or_equal_to(16));
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn codeword_must_fill_the_block() {
+        CODEWORD_INTERLEAVER.interleave(&vec![0u8; 31]);
+    }
+
+    // Send random blocks, replacing a burst of consecutive symbols with random ones, and count how
+    // many are recovered by the LDPC decoder.
+    fn recovered_blocks(interleaver: &dyn Interleaver, burst_symbols: usize) -> usize {
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        let mut recovered = 0;
+        for _ in 0..100 {
+            let message: Vec<u8> = (0..14).map(|_| rng.gen()).collect();
+            let packed_message = pack_message(&message, false, false, crc14(&message));
+            let mut sent = interleaver.interleave(&encode_packed_message(&packed_message));
+            let start = rng.gen_range(0..=(64 - burst_symbols));
+            for symbol in start..(start + burst_symbols) {
+                let noise: u8 = rng.gen_range(0..16);
+                let byte = symbol / 2;
+                sent[byte] = if symbol % 2 == 0 { (sent[byte] & 0x0f) | (noise << 4) } else { (sent[byte] & 0xf0) | noise };
+            }
+            if let Some(decoded) = decode_codeword(&interleaver.deinterleave(&sent)) {
+                if decoded[0..16] == packed_message[..] {
+                    recovered += 1;
+                }
+            }
+        }
+        recovered
+    }
+
+    #[rstest]
+    #[case(4)]
+    #[case(5)]
+    #[case(6)]
+    fn interleaving_recovers_more_burst_damaged_blocks(#[case] burst_symbols: usize) {
+        let without_interleaving = recovered_blocks(&NoInterleaver {}, burst_symbols);
+        let with_interleaving = recovered_blocks(&CODEWORD_INTERLEAVER, burst_symbols);
+        info!("Bursts of {} symbols: recovered {}/100 without interleaving; {}/100 with", burst_symbols, without_interleaving, with_interleaving);
+        assert_that!(with_interleaving, greater_than(without_interleaving));
+    }
+}
@@ -118,10 +118,15 @@ impl BitVecAppender {
     }
 }
 
-// 112 bits of source encoded data; 2 spare unused bits ; 14 bits of CRC - gives 128 bits of message
+// 112 bits of source encoded data; 2 flag bits ; 14 bits of CRC - gives 128 bits of message
+// The flags are:
+// end_of_transmission: this is the last block of the transmission, so receivers need not wait for
+//   the station to time out before considering it finished.
+// metadata_only: the block carries only metadata frames, and no keying.
+// The CRC is of the source encoded data only; the flags are protected by the LDPC code.
 pub type PackedMessage = Vec<u8>;
 pub type SourceEncodingData = Vec<u8>;
-pub fn pack_message(source_encoding: &SourceEncodingData, unused_flag_1: bool, unused_flag_2: bool, crc: CRC) -> PackedMessage {
+pub fn pack_message(source_encoding: &SourceEncodingData, end_of_transmission: bool, metadata_only: bool, crc: CRC) -> PackedMessage {
     if source_encoding.len() != 14 {
         panic!("Expecting 14 bytes of source encoding data, not {}", source_encoding.len());
     }
@@ -130,8 +135,8 @@ pub fn pack_message(source_encoding: &SourceEncodingData, unused_flag_1: bool, u
     for i in 0..source_encoding.len() {
         appender.add_8_bits(source_encoding[i], 8);
     }
-    appender.add_bool(unused_flag_1);
-    appender.add_bool(unused_flag_2);
+    appender.add_bool(end_of_transmission);
+    appender.add_bool(metadata_only);
     appender.append_crc(crc);
     appender.build() as PackedMessage
 }
@@ -154,6 +159,11 @@ pub fn encode_packed_message(packed_message: &PackedMessage) -> CodeWord {
 
 // 256 bits (32 bytes) of data (a potential codeword) are decoded into 128 bits of packed message.
 pub fn decode_codeword(codeword: &CodeWord) -> Option<CodeWord> {
+    decode_codeword_with_iterations(codeword).0
+}
+
+// As decode_codeword, also returning the number of iterations the decoder took.
+pub fn decode_codeword_with_iterations(codeword: &CodeWord) -> (Option<CodeWord>, usize) {
     if codeword.len() != 32 {
         panic!("Expecting to decode 32 bytes of codeword");
     }
@@ -168,23 +178,56 @@ pub fn decode_codeword(codeword: &CodeWord) -> Option<CodeWord> {
     debug!("LDPC decoded in {}ms", ldpc_decode_duration.elapsed_time());
     if success {
         debug!("Decoding required {} iterations", iters);
-        Some(decoded)
+        (Some(decoded), iters)
     } else {
         debug!("Decoding unsuccessful after {} iterations", iters);
-        None
+        (None, iters)
+    }
+}
+
+// The log-likelihood ratio of a bit of the codeword: positive if the bit is more likely to be 0,
+// with larger magnitudes indicating greater confidence.
+pub type LLR = f32;
+
+const MAX_MIN_SUM_ITERATIONS: usize = 100;
+
+// 256 log-likelihood ratios, one per bit of a potential codeword, are decoded by min-sum belief
+// propagation into 128 bits of packed message; also returning the number of iterations the decoder
+// took.
+pub fn decode_llrs_with_iterations(llrs: &[LLR]) -> (Option<CodeWord>, usize) {
+    if llrs.len() != CODE.n() {
+        panic!("Expecting to decode {} LLRs of codeword", CODE.n());
+    }
+
+    let ldpc_decode_duration = StdInstant::now();
+
+    // Allocate some memory for the decoder's working areas and output
+    let mut working = vec![0 as LLR; CODE.decode_ms_working_len()];
+    let mut working_u8 = vec![0u8; CODE.decode_ms_working_u8_len()];
+    let mut decoded = vec![0u8; CODE.output_len()];
+
+    let (success, iters) = CODE.decode_ms(llrs, &mut decoded, &mut working, &mut working_u8, MAX_MIN_SUM_ITERATIONS);
+    debug!("LDPC soft decoded in {}ms", ldpc_decode_duration.elapsed_time());
+    if success {
+        debug!("Soft decoding required {} iterations", iters);
+        (Some(decoded), iters)
+    } else {
+        debug!("Soft decoding unsuccessful after {} iterations", iters);
+        (None, iters)
     }
 }
 
-// Unpack a packed message of 128 bits into message, flags and CRC
-// 112 bits of source encoded data; 2 spare unused bits ; 14 bits of CRC - gives 128 bits of message
+// Unpack a packed message of 128 bits into message, end_of_transmission and metadata_only flags,
+// and CRC.
+// 112 bits of source encoded data; 2 flag bits ; 14 bits of CRC - gives 128 bits of message
 pub fn unpack_message(packed_message: &PackedMessage) -> (SourceEncodingData, bool, bool, CRC) {
     let message = packed_message[0..14].to_vec();
     let crc_msb = packed_message[14] as u16;
     let crc_lsb = packed_message[15] as u16;
-    let unused_flags_and_crc: u16 = (crc_msb << 8) as u16 | crc_lsb as u16;
-    let unused_flag_1 = unused_flags_and_crc & 0x8000 == 0x8000;
-    let unused_flag_2 = unused_flags_and_crc & 0x4000 == 0x4000;
-    (message, unused_flag_1, unused_flag_2, (unused_flags_and_crc & 0x3fff) as CRC)
+    let flags_and_crc: u16 = (crc_msb << 8) as u16 | crc_lsb as u16;
+    let end_of_transmission = flags_and_crc & 0x8000 == 0x8000;
+    let metadata_only = flags_and_crc & 0x4000 == 0x4000;
+    (message, end_of_transmission, metadata_only, (flags_and_crc & 0x3fff) as CRC)
 }
 
 
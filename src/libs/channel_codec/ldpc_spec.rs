@@ -62,11 +62,11 @@ mod ldpc_spec {
         let message_at_start = packed_message_string.substring(0, 112);
         assert_that!(message_at_start, equal_to(message_string.as_str()));
 
-        let unused_flag_1 = packed_message_string.substring(112, 113);
-        assert_that!(unused_flag_1, equal_to("0"));
+        let end_of_transmission = packed_message_string.substring(112, 113);
+        assert_that!(end_of_transmission, equal_to("0"));
 
-        let unused_flag_2 = packed_message_string.substring(113, 114);
-        assert_that!(unused_flag_2, equal_to("1"));
+        let metadata_only = packed_message_string.substring(113, 114);
+        assert_that!(metadata_only, equal_to("1"));
 
         let crc_at_end = packed_message_string.split_off(128-14);
         assert_that!(crc_at_end, equal_to(crc_binary.strip_prefix("0b").unwrap()));
@@ -157,10 +157,10 @@ mod ldpc_spec {
 
         let packed_message = pack_message(&message, false, true, crc);
 
-        let (u_message, u_unused_flag_1, u_unused_flag_2, u_crc) = unpack_message(&packed_message);
+        let (u_message, u_end_of_transmission, u_metadata_only, u_crc) = unpack_message(&packed_message);
         assert_that!(u_message, equal_to(message));
-        assert_that!(u_unused_flag_1, equal_to(false));
-        assert_that!(u_unused_flag_2, equal_to(true));
+        assert_that!(u_end_of_transmission, equal_to(false));
+        assert_that!(u_metadata_only, equal_to(true));
         assert_that!(u_crc, equal_to(crc));
     }
 }
@@ -1,8 +1,12 @@
+pub mod channel_decoder;
 pub mod channel_encoder;
 pub mod channel_encoding;
+pub mod costas;
 pub mod crc;
 pub mod gray;
+pub mod interleave;
 pub mod ldpc;
+pub mod soft_decision;
 
 #[cfg(test)]
 pub mod sample_channel_encoding;
\ No newline at end of file
@@ -1,70 +1,74 @@
 use crate::libs::channel_codec::channel_encoding::ChannelEncoding;
+use crate::libs::channel_codec::costas::COSTAS_ARRAY;
 
+// The Costas array, followed by the interleaved codeword.
 pub fn sample_channel_encoding() -> ChannelEncoding {
-    ChannelEncoding { block: vec![
-        1,
+    let mut block = COSTAS_ARRAY.to_vec();
+    block.extend_from_slice(&[
+        2,
         1,
-        4,
         5,
+        5,
+        1,
         0,
-        3,
-        12,
-        8,
-        13,
-        14,
-        0,
-        14,
         9,
-        0,
-        0,
-        0,
-        0,
-        0,
-        0,
-        0,
-        0,
-        0,
-        0,
-        0,
-        0,
-        0,
-        0,
-        0,
-        3,
+        9,
+        1,
+        1,
+        2,
         8,
         15,
-        1,
+        0,
         2,
-        11,
         13,
-        11,
-        4,
-        10,
         3,
-        4,
-        10,
-        10,
-        9,
-        6,
-        7,
+        1,
+        2,
+        0,
+        0,
+        1,
         8,
-        10,
+        2,
+        5,
+        1,
+        11,
         9,
+        15,
         1,
-        13,
-        14,
-        5,
         2,
-        3,
+        15,
+        6,
         1,
-        5,
+        10,
         8,
+        12,
+        0,
+        0,
+        7,
+        12,
+        1,
+        15,
         5,
+        12,
         0,
         0,
+        3,
+        5,
+        0,
+        12,
+        3,
         10,
+        0,
+        11,
+        14,
         9,
-        2,
-        3,
-    ], is_end: true }
+        0,
+        12,
+        13,
+        5,
+        1,
+        14,
+        13,
+    ]);
+    ChannelEncoding { block, is_end: true }
 }
@@ -0,0 +1,57 @@
+/*
+ * Hard-decision decoding takes the strongest tone of each received symbol as the symbol sent, and
+ * discards how much stronger it was than the others. With weak signals, the strongest tone is
+ * often noise, and the LDPC decoder has no way of knowing which symbols are doubtful.
+ * Soft-decision decoding retains this information. For each symbol, the received magnitudes of all
+ * 16 tones are used to estimate how likely each of the symbol's four codeword bits is to be 0 or 1:
+ * each tone carries a known pattern of bits, via the Gray code, so a bit's log-likelihood ratio is
+ * estimated (the 'max-log' approximation, as in FT8) by the difference between the strongest tone
+ * whose bit is 0, and the strongest tone whose bit is 1. These are then decoded by min-sum belief
+ * propagation, rather than bit-flipping.
+ */
+
+use crate::libs::channel_codec::channel_encoding::{ChannelSymbol, NUMBER_OF_TONES, ToneMagnitudes};
+use crate::libs::channel_codec::gray::from_gray_code;
+use crate::libs::channel_codec::ldpc::LLR;
+
+// The codeword bits carried by each symbol, most significant first.
+const BITS_PER_SYMBOL: usize = 4;
+
+// Returns four LLRs per symbol, in the (interleaved) order the bits were sent.
+pub fn tone_magnitudes_to_llrs(symbol_tone_magnitudes: &[ToneMagnitudes]) -> Vec<LLR> {
+    let mut llrs = Vec::with_capacity(symbol_tone_magnitudes.len() * BITS_PER_SYMBOL);
+    for tone_magnitudes in symbol_tone_magnitudes {
+        for bit in (0..BITS_PER_SYMBOL).rev() {
+            let mut strongest_zero = 0.0 as LLR;
+            let mut strongest_one = 0.0 as LLR;
+            for (tone, magnitude) in tone_magnitudes.iter().enumerate() {
+                if from_gray_code(tone as u8) & (1 << bit) == 0 {
+                    strongest_zero = strongest_zero.max(*magnitude);
+                } else {
+                    strongest_one = strongest_one.max(*magnitude);
+                }
+            }
+            llrs.push(strongest_zero - strongest_one);
+        }
+    }
+    llrs
+}
+
+// The hard decision: the strongest tone of each symbol.
+pub fn tone_magnitudes_to_symbols(symbol_tone_magnitudes: &[ToneMagnitudes]) -> Vec<ChannelSymbol> {
+    symbol_tone_magnitudes.iter()
+        .map(|tone_magnitudes| {
+            let mut strongest = 0;
+            for tone in 1..NUMBER_OF_TONES {
+                if tone_magnitudes[tone] > tone_magnitudes[strongest] {
+                    strongest = tone;
+                }
+            }
+            strongest as ChannelSymbol
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[path = "./soft_decision_spec.rs"]
+mod soft_decision_spec;
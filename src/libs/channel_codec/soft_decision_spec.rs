@@ -0,0 +1,158 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod soft_decision_spec {
+    use std::env;
+    use hamcrest2::prelude::*;
+    use log::info;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+    use crate::libs::channel_codec::channel_decoder::{channel_symbols_to_channel_decoding, channel_tone_magnitudes_to_channel_decoding, ChannelDecodeError};
+    use crate::libs::channel_codec::channel_encoder::source_encoding_to_channel_encoding;
+    use crate::libs::channel_codec::channel_encoding::{CHANNEL_ENCODER_BLOCK_SIZE, ChannelSymbol, NUMBER_OF_TONES, ToneMagnitudes};
+    use crate::libs::channel_codec::costas::COSTAS_ARRAY_SYMBOLS;
+    use crate::libs::channel_codec::gray::to_gray_code;
+    use crate::libs::channel_codec::soft_decision::{tone_magnitudes_to_llrs, tone_magnitudes_to_symbols};
+    use crate::libs::source_codec::source_encoding::{SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, SourceEncoding};
+    use crate::libs::util::test_util::gaussian;
+
+    #[ctor::ctor]
+    fn before_each() {
+        env::set_var("RUST_LOG", "info");
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[ctor::dtor]
+    fn after_each() {}
+
+    fn clean_tone(symbol: ChannelSymbol) -> ToneMagnitudes {
+        let mut tone_magnitudes = [0.0; NUMBER_OF_TONES];
+        tone_magnitudes[symbol as usize] = 1.0;
+        tone_magnitudes
+    }
+
+    #[test]
+    fn clean_tone_gives_confident_llrs_of_its_bits() {
+        // Nybble 0b1010 is sent as its Gray code.
+        let llrs = tone_magnitudes_to_llrs(&[clean_tone(to_gray_code(0b1010))]);
+        assert_that!(llrs, equal_to(vec![-1.0, 1.0, -1.0, 1.0]));
+    }
+
+    #[test]
+    fn equally_strong_tones_give_no_confidence_in_the_bits_they_differ_in() {
+        // Adjacent tones differ in one bit, by the Gray code.
+        let mut tone_magnitudes = clean_tone(to_gray_code(0b0110));
+        tone_magnitudes[to_gray_code(0b0111) as usize] = 1.0;
+        let llrs = tone_magnitudes_to_llrs(&[tone_magnitudes]);
+        assert_that!(llrs, equal_to(vec![1.0, -1.0, -1.0, 0.0]));
+    }
+
+    #[test]
+    fn hard_decision_is_the_strongest_tone() {
+        let mut tone_magnitudes = clean_tone(7);
+        tone_magnitudes[3] = 0.5;
+        tone_magnitudes[12] = 1.5;
+        assert_that!(tone_magnitudes_to_symbols(&[clean_tone(7), tone_magnitudes]), equal_to(vec![7, 12]));
+    }
+
+    #[test]
+    fn clean_tones_are_soft_decoded() {
+        let source_encoding = random_source_encoding(&mut StdRng::seed_from_u64(1));
+        let magnitudes: Vec<ToneMagnitudes> = channel_symbols(&source_encoding).iter().map(|symbol| clean_tone(*symbol)).collect();
+        let decoding = channel_tone_magnitudes_to_channel_decoding(&magnitudes);
+        assert_that!(decoding.source_encoding, equal_to(Ok(source_encoding)));
+    }
+
+    #[test]
+    fn wrong_number_of_symbols_cannot_be_soft_decoded() {
+        let decoding = channel_tone_magnitudes_to_channel_decoding(&[clean_tone(0); 63]);
+        assert_that!(decoding.source_encoding, equal_to(Err(ChannelDecodeError::WrongNumberOfSymbols(63))));
+        assert_that!(decoding.iterations, equal_to(0));
+    }
+
+    fn random_source_encoding(rng: &mut StdRng) -> SourceEncoding {
+        let block: Vec<u8> = (0..SOURCE_ENCODER_BLOCK_SIZE_IN_BITS / 8).map(|_| rng.gen()).collect();
+        SourceEncoding { block, is_end: false, is_metadata_only: false }
+    }
+
+    // The symbols following the Costas Array.
+    fn channel_symbols(source_encoding: &SourceEncoding) -> Vec<ChannelSymbol> {
+        source_encoding_to_channel_encoding(source_encoding.clone()).block[COSTAS_ARRAY_SYMBOLS..].to_vec()
+    }
+
+    // The magnitudes a non-coherent 16-FSK receiver sees in each tone's FFT bin, for a symbol sent
+    // in additive white Gaussian noise. The noise in each bin is complex, with unit variance in each
+    // component, so a tone of amplitude A has Es/N0 = A^2 / 2. Eb is the energy per bit of the
+    // SourceEncoding, whose bit errors are counted: its 112 bits are sent in 64 symbols (the flags,
+    // CRC and parity making up the rest), so Es = 1.75 Eb.
+    fn awgn_tone_magnitudes(symbol: ChannelSymbol, eb_n0_db: f32, rng: &mut StdRng) -> ToneMagnitudes {
+        let eb_n0 = 10f32.powf(eb_n0_db / 10.0);
+        let bits_per_symbol = SOURCE_ENCODER_BLOCK_SIZE_IN_BITS as f32 / CHANNEL_ENCODER_BLOCK_SIZE as f32;
+        let amplitude = (2.0 * bits_per_symbol * eb_n0).sqrt();
+        let mut tone_magnitudes = [0.0; NUMBER_OF_TONES];
+        for (tone, magnitude) in tone_magnitudes.iter_mut().enumerate() {
+            let signal = if tone == symbol as usize { amplitude } else { 0.0 };
+            let in_phase = signal + gaussian(rng);
+            let quadrature = gaussian(rng);
+            *magnitude = (in_phase * in_phase + quadrature * quadrature).sqrt();
+        }
+        tone_magnitudes
+    }
+
+    fn bit_errors(sent: &[u8], received: &[u8]) -> usize {
+        sent.iter().zip(received).map(|(s, r)| (s ^ r).count_ones() as usize).sum()
+    }
+
+    struct ErrorRates {
+        blocks_decoded: usize,
+        bit_errors: usize,
+    }
+
+    // Send blocks through the channel at the given Eb/N0, and decode each by hard and soft decision.
+    // A block that cannot be decoded contributes half its bits as errors, as if they were guessed.
+    fn hard_and_soft_error_rates(eb_n0_db: f32, blocks: usize) -> (ErrorRates, ErrorRates) {
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        let mut hard = ErrorRates { blocks_decoded: 0, bit_errors: 0 };
+        let mut soft = ErrorRates { blocks_decoded: 0, bit_errors: 0 };
+        for _ in 0..blocks {
+            let source_encoding = random_source_encoding(&mut rng);
+            let magnitudes: Vec<ToneMagnitudes> = channel_symbols(&source_encoding).iter()
+                .map(|symbol| awgn_tone_magnitudes(*symbol, eb_n0_db, &mut rng))
+                .collect();
+
+            let hard_decoding = channel_symbols_to_channel_decoding(&tone_magnitudes_to_symbols(&magnitudes));
+            let soft_decoding = channel_tone_magnitudes_to_channel_decoding(&magnitudes);
+            for (decoding, rates) in [(hard_decoding, &mut hard), (soft_decoding, &mut soft)] {
+                match decoding.source_encoding {
+                    Ok(decoded) => {
+                        rates.blocks_decoded += 1;
+                        rates.bit_errors += bit_errors(&source_encoding.block, &decoded.block);
+                    }
+                    Err(_) => {
+                        rates.bit_errors += SOURCE_ENCODER_BLOCK_SIZE_IN_BITS / 2;
+                    }
+                }
+            }
+        }
+        (hard, soft)
+    }
+
+    #[test]
+    fn soft_decision_decodes_more_blocks_than_hard_decision_in_awgn() {
+        let blocks = 40;
+        let bits = (blocks * SOURCE_ENCODER_BLOCK_SIZE_IN_BITS) as f32;
+        let mut hard_blocks_decoded = 0;
+        let mut soft_blocks_decoded = 0;
+        info!("Eb/N0 dB | hard decoded | hard BER | soft decoded | soft BER");
+        for eb_n0_db in [3.0, 4.0, 5.0, 6.0, 7.0] {
+            let (hard, soft) = hard_and_soft_error_rates(eb_n0_db, blocks);
+            info!("{:7.1} | {:9}/{} | {:8.5} | {:9}/{} | {:8.5}", eb_n0_db,
+                  hard.blocks_decoded, blocks, hard.bit_errors as f32 / bits,
+                  soft.blocks_decoded, blocks, soft.bit_errors as f32 / bits);
+            assert_that!(soft.blocks_decoded, greater_than_or_equal_to(hard.blocks_decoded));
+            hard_blocks_decoded += hard.blocks_decoded;
+            soft_blocks_decoded += soft.blocks_decoded;
+        }
+        assert_that!(soft_blocks_decoded, greater_than(hard_blocks_decoded));
+    }
+}
@@ -0,0 +1,307 @@
+/*
+ * The ChannelSimulator adds the effects of an HF channel to a clean waveform (such as one written
+ * by the TransmitWav mode), at controlled levels, so that the weak-signal performance of the
+ * receiver can be measured. In the order they are applied:
+ * - a timing offset: silence before the waveform, or its start removed;
+ * - fading: a single Rayleigh-faded path, or the two independently faded paths of the Watterson
+ *   model, the second delayed. Each path's gain is a complex Gaussian process with a Gaussian
+ *   Doppler spectrum, whose spread is twice its standard deviation, as in CCIR 520;
+ * - linear frequency drift;
+ * - interfering carriers, and CW sent by another station;
+ * - additive white Gaussian noise, at a signal-to-noise ratio in a 2500Hz bandwidth.
+ * Fading and drift are applied to the analytic signal, obtained by a Hilbert transform of the whole
+ * waveform. All randomness comes from a seeded generator, so a simulation is reproducible.
+ */
+
+use std::f32::consts::PI;
+use num::Complex;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use realfft::RealFftPlanner;
+use crate::libs::conversion::conversion::text_to_keying;
+use crate::libs::keyer_io::keyer_io::KeyingEvent;
+use crate::libs::transmitter::transmitter::AudioFrequencyHz;
+
+// The bandwidth in which the signal-to-noise ratio is measured, as in WSJT-X.
+pub const SNR_BANDWIDTH_HZ: f32 = 2500.0;
+
+// The fading gains are generated at this many samples per Hz of Doppler spread, and interpolated.
+const FADING_SAMPLES_PER_SPREAD_HZ: f32 = 32.0;
+
+// The rise and fall time of interfering CW.
+const CW_RAMP_SECONDS: f32 = 0.005;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Fading {
+    Rayleigh { doppler_spread_hz: f32 },
+    Watterson { doppler_spread_hz: f32, delay_ms: f32 },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Interferer {
+    Carrier { frequency: AudioFrequencyHz, amplitude: f32 },
+    // The text is sent repeatedly, a word gap apart, for the duration of the waveform.
+    Cw { frequency: AudioFrequencyHz, amplitude: f32, wpm: u32, text: String },
+}
+
+pub struct ChannelSimulator {
+    sample_rate: AudioFrequencyHz,
+    rng: StdRng,
+    snr_db: Option<f32>,
+    fading: Option<Fading>,
+    drift_hz_per_second: f32,
+    timing_offset_seconds: f32,
+    interferers: Vec<Interferer>,
+}
+
+impl ChannelSimulator {
+    // A simulator that, until effects are set, passes the waveform through unchanged.
+    pub fn new(sample_rate: AudioFrequencyHz, seed: u64) -> Self {
+        Self {
+            sample_rate,
+            rng: StdRng::seed_from_u64(seed),
+            snr_db: None,
+            fading: None,
+            drift_hz_per_second: 0.0,
+            timing_offset_seconds: 0.0,
+            interferers: vec![],
+        }
+    }
+
+    // The signal-to-noise ratio, in dB, relative to the mean power of the transmission in the clean
+    // waveform (from its first to its last non-zero sample).
+    pub fn set_snr(&mut self, snr_db: f32) {
+        self.snr_db = Some(snr_db);
+    }
+
+    pub fn set_fading(&mut self, fading: Fading) {
+        self.fading = Some(fading);
+    }
+
+    pub fn set_drift(&mut self, hz_per_second: f32) {
+        self.drift_hz_per_second = hz_per_second;
+    }
+
+    // Positive offsets delay the waveform by adding silence before it; negative offsets remove its
+    // start.
+    pub fn set_timing_offset(&mut self, seconds: f32) {
+        self.timing_offset_seconds = seconds;
+    }
+
+    // CW interferers must have a non-zero speed.
+    pub fn add_interferer(&mut self, interferer: Interferer) -> Result<(), String> {
+        if let Interferer::Cw { wpm: 0, .. } = interferer {
+            return Err("A CW interferer's WPM must be greater than zero".to_string());
+        }
+        self.interferers.push(interferer);
+        Ok(())
+    }
+
+    // Apply the effects to the waveform. Successive simulations continue from the generator's
+    // state, so each adds different noise and fading.
+    pub fn simulate(&mut self, waveform: &[f32]) -> Vec<f32> {
+        let signal_power = transmission_power(waveform);
+        let offset_samples = (self.timing_offset_seconds * self.sample_rate as f32).round() as isize;
+        let mut output: Vec<f32> = if offset_samples >= 0 {
+            let mut delayed = vec![0.0; offset_samples as usize];
+            delayed.extend_from_slice(waveform);
+            delayed
+        } else {
+            waveform.iter().skip(offset_samples.unsigned_abs()).copied().collect()
+        };
+
+        if self.fading.is_some() || self.drift_hz_per_second != 0.0 {
+            let mut analytic = analytic_signal(&output);
+            if let Some(fading) = self.fading.clone() {
+                analytic = self.fade(&analytic, &fading);
+            }
+            if self.drift_hz_per_second != 0.0 {
+                self.drift(&mut analytic);
+            }
+            output = analytic.iter().map(|z| z.re).collect();
+        }
+
+        for interferer in &self.interferers {
+            self.interfere(&mut output, interferer);
+        }
+
+        if let Some(snr_db) = self.snr_db {
+            // Noise of this power in the SNR bandwidth; white, so spread over the whole band.
+            let noise_power = signal_power / 10.0_f32.powf(snr_db / 10.0);
+            let sigma = (noise_power * self.sample_rate as f32 / 2.0 / SNR_BANDWIDTH_HZ).sqrt();
+            for sample in output.iter_mut() {
+                *sample += sigma * gaussian(&mut self.rng);
+            }
+        }
+        output
+    }
+
+    fn fade(&mut self, analytic: &[Complex<f32>], fading: &Fading) -> Vec<Complex<f32>> {
+        match *fading {
+            Fading::Rayleigh { doppler_spread_hz } => {
+                let gains = self.fading_gains(analytic.len(), doppler_spread_hz);
+                analytic.iter().zip(gains).map(|(z, g)| z * g).collect()
+            }
+            Fading::Watterson { doppler_spread_hz, delay_ms } => {
+                let delay = (delay_ms / 1000.0 * self.sample_rate as f32).round() as usize;
+                let first_gains = self.fading_gains(analytic.len(), doppler_spread_hz);
+                let second_gains = self.fading_gains(analytic.len(), doppler_spread_hz);
+                // Two paths of equal mean power, together that of the unfaded signal.
+                let scale = 1.0 / 2.0_f32.sqrt();
+                (0..analytic.len())
+                    .map(|n| {
+                        let delayed = if n >= delay { analytic[n - delay] } else { Complex::new(0.0, 0.0) };
+                        (analytic[n] * first_gains[n] + delayed * second_gains[n]) * scale
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    // A complex Gaussian process of unit mean power, with a Gaussian Doppler spectrum of the given
+    // spread: white noise filtered by a Gaussian, generated at a low rate and interpolated.
+    fn fading_gains(&mut self, samples: usize, doppler_spread_hz: f32) -> Vec<Complex<f32>> {
+        if doppler_spread_hz <= 0.0 {
+            let gain = self.complex_gaussian();
+            return vec![gain; samples];
+        }
+        let rate = FADING_SAMPLES_PER_SPREAD_HZ * doppler_spread_hz;
+        // The spread is twice the standard deviation of the power spectrum. A Gaussian impulse
+        // response of standard deviation s has a power spectrum of standard deviation
+        // 1 / (2 * sqrt(2) * pi * s); this is s, in gain samples.
+        let sigma = rate / (2.0 * std::f32::consts::SQRT_2 * PI * doppler_spread_hz / 2.0);
+        let half_length = (4.0 * sigma).ceil() as usize;
+        let mut taps: Vec<f32> = (0..=2 * half_length)
+            .map(|i| {
+                let t = i as f32 - half_length as f32;
+                (-t * t / (2.0 * sigma * sigma)).exp()
+            })
+            .collect();
+        let energy: f32 = taps.iter().map(|tap| tap * tap).sum();
+        taps.iter_mut().for_each(|tap| *tap /= energy.sqrt());
+
+        let gain_samples = (samples as f32 / self.sample_rate as f32 * rate).ceil() as usize + 2;
+        let white: Vec<Complex<f32>> = (0..gain_samples + taps.len()).map(|_| self.complex_gaussian()).collect();
+        let gains: Vec<Complex<f32>> = (0..gain_samples)
+            .map(|k| taps.iter().zip(&white[k..]).map(|(tap, w)| w * tap).sum())
+            .collect();
+        (0..samples)
+            .map(|n| {
+                let position = n as f32 / self.sample_rate as f32 * rate;
+                let index = position as usize;
+                let fraction = position - index as f32;
+                gains[index] * (1.0 - fraction) + gains[index + 1] * fraction
+            })
+            .collect()
+    }
+
+    fn drift(&self, analytic: &mut [Complex<f32>]) {
+        for (n, z) in analytic.iter_mut().enumerate() {
+            // The phase of a frequency rising linearly from zero; computed in f64 as it grows large.
+            let t = n as f64 / self.sample_rate as f64;
+            let phase = std::f64::consts::PI * self.drift_hz_per_second as f64 * t * t;
+            *z *= Complex::new(phase.cos() as f32, phase.sin() as f32);
+        }
+    }
+
+    fn interfere(&self, output: &mut [f32], interferer: &Interferer) {
+        let (frequency, amplitude) = match interferer {
+            Interferer::Carrier { frequency, amplitude } => (*frequency, *amplitude),
+            Interferer::Cw { frequency, amplitude, .. } => (*frequency, *amplitude),
+        };
+        let envelope = match interferer {
+            Interferer::Carrier { .. } => vec![1.0; output.len()],
+            Interferer::Cw { wpm, text, .. } => cw_envelope(output.len(), self.sample_rate, *wpm, text),
+        };
+        let delta_phase = 2.0 * std::f64::consts::PI * frequency as f64 / self.sample_rate as f64;
+        for (n, (sample, gain)) in output.iter_mut().zip(envelope).enumerate() {
+            *sample += amplitude * gain * (delta_phase * n as f64).sin() as f32;
+        }
+    }
+
+    fn complex_gaussian(&mut self) -> Complex<f32> {
+        let scale = 1.0 / 2.0_f32.sqrt();
+        Complex::new(gaussian(&mut self.rng) * scale, gaussian(&mut self.rng) * scale)
+    }
+}
+
+// Box-Muller; a standard normal deviate.
+fn gaussian(rng: &mut StdRng) -> f32 {
+    let u1: f32 = rng.gen_range(f32::EPSILON..1.0);
+    let u2: f32 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
+
+// The mean power of the waveform from its first to its last non-zero sample.
+fn transmission_power(waveform: &[f32]) -> f32 {
+    let first = waveform.iter().position(|sample| *sample != 0.0);
+    let last = waveform.iter().rposition(|sample| *sample != 0.0);
+    match (first, last) {
+        (Some(first), Some(last)) => {
+            let transmission = &waveform[first..=last];
+            transmission.iter().map(|sample| sample * sample).sum::<f32>() / transmission.len() as f32
+        }
+        _ => 0.0,
+    }
+}
+
+// The analytic signal, whose real part is the waveform and imaginary part its Hilbert transform:
+// the spectrum of the waveform is rotated by -90 degrees, and transformed back.
+fn analytic_signal(waveform: &[f32]) -> Vec<Complex<f32>> {
+    if waveform.is_empty() {
+        return vec![];
+    }
+    let mut planner = RealFftPlanner::<f32>::new();
+    let r2c = planner.plan_fft_forward(waveform.len());
+    let c2r = planner.plan_fft_inverse(waveform.len());
+    let mut input = waveform.to_vec();
+    let mut spectrum = r2c.make_output_vec();
+    r2c.process(&mut input, &mut spectrum).unwrap();
+    for bin in spectrum.iter_mut() {
+        *bin = Complex::new(bin.im, -bin.re);
+    }
+    // The transform of DC, and of the Nyquist frequency (if present), is zero.
+    let last = spectrum.len() - 1;
+    spectrum[0] = Complex::new(0.0, 0.0);
+    if waveform.len() % 2 == 0 {
+        spectrum[last] = Complex::new(0.0, 0.0);
+    }
+    let mut hilbert = c2r.make_output_vec();
+    c2r.process(&mut spectrum, &mut hilbert).unwrap();
+    let scale = 1.0 / waveform.len() as f32;
+    waveform.iter().zip(hilbert).map(|(re, im)| Complex::new(*re, im * scale)).collect()
+}
+
+// The keying of the text, repeated a word gap apart, as a gain rising and falling between 0 and 1.
+fn cw_envelope(samples: usize, sample_rate: AudioFrequencyHz, wpm: u32, text: &str) -> Vec<f32> {
+    let samples_per_ms = sample_rate as f32 / 1000.0;
+    let word_gap_ms = 7.0 * 1200.0 / wpm as f32;
+    let mut keyed: Vec<bool> = Vec::with_capacity(samples);
+    let keying = text_to_keying(wpm, text);
+    while keyed.len() < samples {
+        let start = keyed.len();
+        for keying_event in &keying {
+            if let KeyingEvent::Timed(timed) = keying_event {
+                // An edge going up ends a mark.
+                keyed.extend(std::iter::repeat(timed.up).take((timed.duration as f32 * samples_per_ms) as usize));
+            }
+        }
+        keyed.extend(std::iter::repeat(false).take((word_gap_ms * samples_per_ms) as usize));
+        if keyed.len() == start {
+            break;
+        }
+    }
+    keyed.resize(samples, false);
+    let step = 1.0 / (CW_RAMP_SECONDS * sample_rate as f32);
+    let mut gain: f32 = 0.0;
+    keyed.iter()
+        .map(|key_down| {
+            gain = if *key_down { (gain + step).min(1.0) } else { (gain - step).max(0.0) };
+            gain
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[path = "./channel_sim_spec.rs"]
+mod channel_sim_spec;
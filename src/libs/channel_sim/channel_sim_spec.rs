@@ -0,0 +1,208 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod channel_sim_spec {
+    use std::env;
+    use std::error::Error;
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::AtomicBool;
+    use hamcrest2::prelude::*;
+    use log::info;
+    use rstest::*;
+    use crate::libs::channel_sim::channel_sim::{ChannelSimulator, Fading, Interferer};
+    use crate::libs::conversion::conversion::text_to_keying;
+    use crate::libs::playback::playback::FramePlayer;
+    use crate::libs::receiver::waveform_file_decoder::decode_waveform_file;
+    use crate::libs::source_codec::source_encoder::SourceEncoder;
+    use crate::libs::source_codec::source_encoding::{CallsignHash, Frame, SOURCE_ENCODER_BLOCK_SIZE_IN_BITS};
+    use crate::libs::transmitter::transmitter::AudioFrequencyHz;
+    use crate::libs::transmitter::waveform_file_encoder::{modulate_source_encodings, source_encode_keying};
+    use crate::libs::wav::wav::write_waveform_file;
+
+    const SAMPLE_RATE: AudioFrequencyHz = 12000;
+
+    #[ctor::ctor]
+    fn before_each() {
+        env::set_var("RUST_LOG", "info");
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[ctor::dtor]
+    fn after_each() {}
+
+    fn sine(frequency: f32, amplitude: f32, seconds: f32) -> Vec<f32> {
+        let samples = (seconds * SAMPLE_RATE as f32) as usize;
+        (0..samples)
+            .map(|n| amplitude * (2.0 * std::f64::consts::PI * frequency as f64 * n as f64 / SAMPLE_RATE as f64).sin() as f32)
+            .collect()
+    }
+
+    fn power(samples: &[f32]) -> f32 {
+        samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32
+    }
+
+    fn seconds(seconds: f32) -> usize {
+        (seconds * SAMPLE_RATE as f32) as usize
+    }
+
+    #[test]
+    fn waveform_is_unchanged_without_effects() {
+        let waveform = sine(1000.0, 0.5, 0.5);
+        let mut simulator = ChannelSimulator::new(SAMPLE_RATE, 1);
+        assert_that!(simulator.simulate(&waveform), equal_to(waveform.clone()));
+    }
+
+    #[test]
+    fn simulation_is_reproducible_from_its_seed() {
+        let waveform = sine(1000.0, 0.5, 2.0);
+        let simulate = |seed: u64| {
+            let mut simulator = ChannelSimulator::new(SAMPLE_RATE, seed);
+            simulator.set_snr(-10.0);
+            simulator.set_fading(Fading::Watterson { doppler_spread_hz: 1.0, delay_ms: 2.0 });
+            simulator.simulate(&waveform)
+        };
+        assert_that!(simulate(42), equal_to(simulate(42)));
+        assert_that!(simulate(42) != simulate(43), equal_to(true));
+    }
+
+    #[test]
+    fn noise_is_added_at_the_snr_in_2500hz() {
+        // Silence either side of the transmission doesn't change the signal power.
+        let mut waveform = vec![0.0; seconds(1.0)];
+        waveform.extend(sine(1000.0, 0.5, 4.0));
+        waveform.extend(vec![0.0; seconds(1.0)]);
+        let mut simulator = ChannelSimulator::new(SAMPLE_RATE, 2);
+        simulator.set_snr(-10.0);
+        let output = simulator.simulate(&waveform);
+
+        let noise: Vec<f32> = output.iter().zip(&waveform).map(|(out, clean)| out - clean).collect();
+        // At -10dB, 0.125 signal power needs 1.25 noise power in 2500Hz; spread over the 6000Hz band.
+        let expected = 1.25 * 6000.0 / 2500.0;
+        assert_that!(power(&noise), close_to(expected, expected * 0.02));
+    }
+
+    #[test]
+    fn positive_timing_offset_delays_the_waveform() {
+        let waveform = sine(1000.0, 0.5, 1.0);
+        let mut simulator = ChannelSimulator::new(SAMPLE_RATE, 3);
+        simulator.set_timing_offset(0.25);
+        let output = simulator.simulate(&waveform);
+        let silence = vec![0.0; seconds(0.25)];
+        assert_that!(output.len(), equal_to(seconds(1.25)));
+        assert_that!(&output[..seconds(0.25)], equal_to(&silence[..]));
+        assert_that!(&output[seconds(0.25)..], equal_to(&waveform[..]));
+    }
+
+    #[test]
+    fn negative_timing_offset_removes_the_start_of_the_waveform() {
+        let waveform = sine(1000.0, 0.5, 1.0);
+        let mut simulator = ChannelSimulator::new(SAMPLE_RATE, 4);
+        simulator.set_timing_offset(-0.25);
+        assert_that!(simulator.simulate(&waveform), equal_to(waveform[seconds(0.25)..].to_vec()));
+    }
+
+    #[test]
+    fn frequency_drifts_linearly() {
+        let waveform = sine(1000.0, 0.5, 2.0);
+        let mut simulator = ChannelSimulator::new(SAMPLE_RATE, 5);
+        simulator.set_drift(10.0);
+        let output = simulator.simulate(&waveform);
+        // Over the second second, the frequency rises from 1010 to 1020Hz.
+        let second = &output[seconds(1.0)..seconds(2.0)];
+        let rising_zero_crossings = second.windows(2).filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0).count();
+        assert_that!(rising_zero_crossings as f32, close_to(1015.0, 1.0));
+    }
+
+    #[rstest]
+    #[case(Fading::Rayleigh { doppler_spread_hz: 1.0 })]
+    #[case(Fading::Watterson { doppler_spread_hz: 1.0, delay_ms: 1.0 })]
+    fn fading_varies_the_signal_but_preserves_its_mean_power(#[case] fading: Fading) {
+        let waveform = sine(1000.0, 1.0, 60.0);
+        let mut simulator = ChannelSimulator::new(SAMPLE_RATE, 6);
+        simulator.set_fading(fading);
+        let output = simulator.simulate(&waveform);
+
+        let window_powers: Vec<f32> = output.chunks(seconds(0.1)).map(power).collect();
+        let mean = window_powers.iter().sum::<f32>() / window_powers.len() as f32;
+        let (min, max) = window_powers.iter().fold((f32::MAX, 0.0_f32), |(min, max), p| (min.min(*p), max.max(*p)));
+        info!("Faded power: mean {}, min {}, max {}", mean, min, max);
+        assert_that!(mean, close_to(0.5, 0.15));
+        assert_that!(min, less_than(0.05 * mean));
+        assert_that!(max, greater_than(2.0 * mean));
+    }
+
+    #[test]
+    fn interfering_carrier_is_added() {
+        let mut simulator = ChannelSimulator::new(SAMPLE_RATE, 7);
+        simulator.add_interferer(Interferer::Carrier { frequency: 1500, amplitude: 0.2 }).unwrap();
+        let output = simulator.simulate(&vec![0.0; seconds(1.0)]);
+        let error = output.iter().zip(sine(1500.0, 0.2, 1.0)).map(|(out, carrier)| (out - carrier).abs()).fold(0.0, f32::max);
+        assert_that!(error, less_than(0.00001));
+    }
+
+    #[test]
+    fn interfering_cw_is_keyed_repeatedly() {
+        let mut simulator = ChannelSimulator::new(SAMPLE_RATE, 8);
+        // At 20WPM, E is a 60ms dit, followed by a 420ms word gap.
+        simulator.add_interferer(Interferer::Cw { frequency: 700, amplitude: 0.5, wpm: 20, text: "E".to_string() }).unwrap();
+        let output = simulator.simulate(&vec![0.0; seconds(1.0)]);
+        assert_that!(power(&output[seconds(0.01)..seconds(0.06)]), close_to(0.125, 0.01));
+        assert_that!(power(&output[seconds(0.07)..seconds(0.48)]), equal_to(0.0));
+        assert_that!(power(&output[seconds(0.49)..seconds(0.54)]), close_to(0.125, 0.01));
+        assert_that!(power(&output[seconds(0.55)..seconds(0.96)]), equal_to(0.0));
+    }
+
+    #[test]
+    fn interfering_cw_must_have_a_speed() {
+        let mut simulator = ChannelSimulator::new(SAMPLE_RATE, 9);
+        let result = simulator.add_interferer(Interferer::Cw { frequency: 700, amplitude: 0.5, wpm: 0, text: "E".to_string() });
+        assert_that!(result, equal_to(Err("A CW interferer's WPM must be greater than zero".to_string())));
+        let output = simulator.simulate(&vec![0.0; seconds(0.1)]);
+        assert_that!(power(&output), equal_to(0.0));
+    }
+
+    #[derive(Default)]
+    struct BlockCounter {
+        decoded: usize,
+    }
+
+    impl FramePlayer for BlockCounter {
+        fn play(&mut self, decode: Result<Vec<Frame>, Box<dyn Error>>, _callsign_hash: CallsignHash, _audio_offset: u16) {
+            if decode.is_ok() {
+                self.decoded += 1;
+            }
+        }
+    }
+
+    // A report of the proportion of blocks decoded over the full transmit and receive chain, as the
+    // SNR falls; slow, so run manually, with --release.
+    #[test]
+    #[ignore]
+    fn decode_rate_vs_snr() {
+        let mut source_encoder = SourceEncoder::new(Arc::new(AtomicBool::new(false)), SOURCE_ENCODER_BLOCK_SIZE_IN_BITS);
+        source_encoder.set_keyer_speed(20);
+        source_encoder.set_callsign("M0CUV".to_string()).unwrap();
+        let source_encodings = source_encode_keying(&mut source_encoder, &text_to_keying(20, "CQ CQ DE M0CUV M0CUV K")).unwrap();
+        let sample_rate = 48000;
+        let mut waveform = vec![0.0; sample_rate as usize];
+        waveform.extend(modulate_source_encodings(&source_encodings, 1500, sample_rate));
+        waveform.extend(vec![0.0; sample_rate as usize]);
+
+        let filename = env::temp_dir().join("channel-sim-decode-rate.wav").to_str().unwrap().to_string();
+        let trials = 4;
+        for snr in (-24..=-6).step_by(3) {
+            let mut simulator = ChannelSimulator::new(sample_rate, 0x5eed);
+            simulator.set_snr(snr as f32);
+            simulator.set_fading(Fading::Watterson { doppler_spread_hz: 0.5, delay_ms: 1.0 });
+            simulator.set_drift(0.1);
+            let mut decoded = 0;
+            for _ in 0..trials {
+                write_waveform_file(simulator.simulate(&waveform), &filename).unwrap();
+                let counter = Arc::new(Mutex::new(BlockCounter::default()));
+                decode_waveform_file(&filename, counter.clone()).unwrap();
+                decoded += counter.lock().unwrap().decoded;
+            }
+            info!("SNR {:>3}dB: {:>5.1}% of blocks decoded", snr, 100.0 * decoded as f32 / (trials * source_encodings.len()) as f32);
+        }
+    }
+}
@@ -0,0 +1 @@
+pub mod channel_sim;
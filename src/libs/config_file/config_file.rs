@@ -1,6 +1,9 @@
 use log::{debug, warn};
 use std::path::{Path, PathBuf};
 
+use crate::libs::cat::cat::CatType;
+use crate::libs::cat::serial_ptt_cat::PttLine;
+use crate::libs::config_file::waterfall_settings::ColourMap;
 use crate::libs::keyer_io::keyer_io::KeyerType;
 
 use serde_derive::Deserialize;
@@ -13,7 +16,13 @@ struct Config {
     keyer: Keyer,
     audio_devices: AudioDevices,
     #[serde(default = "default_transceiver")]
-    transceiver: Transceiver
+    transceiver: Transceiver,
+    #[serde(default = "default_station")]
+    station: Station,
+    #[serde(default = "default_waterfall")]
+    waterfall: Waterfall,
+    #[serde(default = "default_cat")]
+    cat: Cat,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -30,6 +39,9 @@ pub struct Transceiver {
     transmit_offset_frequency: u16,
     #[serde(default = "default_transmit_amplitude")]
     transmit_amplitude: f32,
+    // A transmission (or tuning) is halted if it lasts longer than this.
+    #[serde(default = "default_max_transmit_seconds")]
+    max_transmit_seconds: u16,
 }
 
 fn default_transceiver() -> Transceiver {
@@ -44,6 +56,109 @@ fn default_transmit_amplitude() -> f32 {
     DEFAULT_CONFIG.transceiver.transmit_amplitude
 }
 
+fn default_max_transmit_seconds() -> u16 {
+    DEFAULT_CONFIG.transceiver.max_transmit_seconds
+}
+
+// The operator's details, sent as metadata at the start of each transmission. Empty strings and
+// no power mean that the item is not sent.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Station {
+    #[serde(default = "default_callsign")]
+    callsign: String,
+    #[serde(default = "default_locator")]
+    locator: String,
+    #[serde(default)]
+    power: Option<u8>,
+}
+
+fn default_station() -> Station {
+    DEFAULT_CONFIG.station
+}
+
+fn default_callsign() -> String {
+    DEFAULT_CONFIG.station.callsign
+}
+
+fn default_locator() -> String {
+    DEFAULT_CONFIG.station.locator
+}
+
+// How the received spectrum is rendered: the gain in dB brightens it, and the contrast scales the
+// range of magnitudes shown.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Waterfall {
+    #[serde(default = "default_colour_map")]
+    colour_map: ColourMap,
+    #[serde(default = "default_waterfall_gain")]
+    gain: f32,
+    #[serde(default = "default_waterfall_contrast")]
+    contrast: f32,
+}
+
+fn default_waterfall() -> Waterfall {
+    DEFAULT_CONFIG.waterfall
+}
+
+fn default_colour_map() -> ColourMap {
+    DEFAULT_CONFIG.waterfall.colour_map
+}
+
+fn default_waterfall_gain() -> f32 {
+    DEFAULT_CONFIG.waterfall.gain
+}
+
+fn default_waterfall_contrast() -> f32 {
+    DEFAULT_CONFIG.waterfall.contrast
+}
+
+// How the rig's PTT is keyed. An empty rigctld address means rigctld's default, on this computer.
+// The serial PTT delays are the time the line is keyed before the transmission starts, and held
+// after it ends.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Cat {
+    #[serde(default = "default_cat_type")]
+    cat_type: CatType,
+    #[serde(default = "default_rigctld_address")]
+    rigctld_address: String,
+    #[serde(default = "default_serial_ptt_port")]
+    serial_ptt_port: String,
+    #[serde(default = "default_serial_ptt_line")]
+    serial_ptt_line: PttLine,
+    #[serde(default = "default_serial_ptt_lead_delay_ms")]
+    serial_ptt_lead_delay_ms: u32,
+    #[serde(default = "default_serial_ptt_tail_delay_ms")]
+    serial_ptt_tail_delay_ms: u32,
+}
+
+fn default_cat() -> Cat {
+    DEFAULT_CONFIG.cat
+}
+
+fn default_cat_type() -> CatType {
+    DEFAULT_CONFIG.cat.cat_type
+}
+
+fn default_rigctld_address() -> String {
+    DEFAULT_CONFIG.cat.rigctld_address
+}
+
+fn default_serial_ptt_port() -> String {
+    DEFAULT_CONFIG.cat.serial_ptt_port
+}
+
+fn default_serial_ptt_line() -> PttLine {
+    DEFAULT_CONFIG.cat.serial_ptt_line
+}
+
+fn default_serial_ptt_lead_delay_ms() -> u32 {
+    DEFAULT_CONFIG.cat.serial_ptt_lead_delay_ms
+}
+
+fn default_serial_ptt_tail_delay_ms() -> u32 {
+    DEFAULT_CONFIG.cat.serial_ptt_tail_delay_ms
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 struct AudioDevices {
     audio_out_device: String,
@@ -65,7 +180,26 @@ const DEFAULT_CONFIG: Config = Config {
     },
     transceiver: Transceiver {
         transmit_offset_frequency: 1500,
-        transmit_amplitude: 0.5
+        transmit_amplitude: 0.5,
+        max_transmit_seconds: 180,
+    },
+    station: Station {
+        callsign: String::new(),
+        locator: String::new(),
+        power: None,
+    },
+    waterfall: Waterfall {
+        colour_map: ColourMap::Blue,
+        gain: 30.0,
+        contrast: 1.0,
+    },
+    cat: Cat {
+        cat_type: CatType::Null,
+        rigctld_address: String::new(),
+        serial_ptt_port: String::new(),
+        serial_ptt_line: PttLine::Rts,
+        serial_ptt_lead_delay_ms: 50,
+        serial_ptt_tail_delay_ms: 50,
     }
 };
 
@@ -191,6 +325,128 @@ impl ConfigurationStore {
     pub fn get_transmit_amplitude(&self) -> f32 {
         self.config.transceiver.transmit_amplitude
     }
+
+    pub fn set_max_transmit_seconds(&mut self, new_seconds: u16) -> Result<(), String> {
+        if new_seconds == 0 {
+            return Err("Cannot store a maximum transmit duration of zero seconds".to_owned());
+        }
+        self.config.transceiver.max_transmit_seconds = new_seconds;
+        self.save()
+    }
+
+    pub fn get_max_transmit_seconds(&self) -> u16 {
+        self.config.transceiver.max_transmit_seconds
+    }
+
+    pub fn set_callsign(&mut self, new_callsign: String) -> Result<(), String> {
+        self.config.station.callsign = new_callsign;
+        self.save()
+    }
+
+    pub fn get_callsign(&self) -> String {
+        self.config.station.callsign.to_owned()
+    }
+
+    pub fn set_locator(&mut self, new_locator: String) -> Result<(), String> {
+        self.config.station.locator = new_locator;
+        self.save()
+    }
+
+    pub fn get_locator(&self) -> String {
+        self.config.station.locator.to_owned()
+    }
+
+    // Power in dBm
+    pub fn set_power(&mut self, new_power: Option<u8>) -> Result<(), String> {
+        self.config.station.power = new_power;
+        self.save()
+    }
+
+    pub fn get_power(&self) -> Option<u8> {
+        self.config.station.power
+    }
+
+    pub fn set_waterfall_colour_map(&mut self, new_colour_map: ColourMap) -> Result<(), String> {
+        self.config.waterfall.colour_map = new_colour_map;
+        self.save()
+    }
+
+    pub fn get_waterfall_colour_map(&self) -> ColourMap {
+        self.config.waterfall.colour_map
+    }
+
+    // Gain in dB
+    pub fn set_waterfall_gain(&mut self, new_gain: f32) -> Result<(), String> {
+        self.config.waterfall.gain = new_gain;
+        self.save()
+    }
+
+    pub fn get_waterfall_gain(&self) -> f32 {
+        self.config.waterfall.gain
+    }
+
+    pub fn set_waterfall_contrast(&mut self, new_contrast: f32) -> Result<(), String> {
+        self.config.waterfall.contrast = new_contrast;
+        self.save()
+    }
+
+    pub fn get_waterfall_contrast(&self) -> f32 {
+        self.config.waterfall.contrast
+    }
+
+    pub fn set_cat_type(&mut self, new_cat_type: CatType) -> Result<(), String> {
+        self.config.cat.cat_type = new_cat_type;
+        self.save()
+    }
+
+    pub fn get_cat_type(&self) -> CatType {
+        self.config.cat.cat_type
+    }
+
+    pub fn set_rigctld_address(&mut self, new_address: String) -> Result<(), String> {
+        self.config.cat.rigctld_address = new_address;
+        self.save()
+    }
+
+    pub fn get_rigctld_address(&self) -> String {
+        self.config.cat.rigctld_address.clone()
+    }
+
+    pub fn set_serial_ptt_port(&mut self, new_port: String) -> Result<(), String> {
+        self.config.cat.serial_ptt_port = new_port;
+        self.save()
+    }
+
+    pub fn get_serial_ptt_port(&self) -> String {
+        self.config.cat.serial_ptt_port.clone()
+    }
+
+    pub fn set_serial_ptt_line(&mut self, new_line: PttLine) -> Result<(), String> {
+        self.config.cat.serial_ptt_line = new_line;
+        self.save()
+    }
+
+    pub fn get_serial_ptt_line(&self) -> PttLine {
+        self.config.cat.serial_ptt_line
+    }
+
+    pub fn set_serial_ptt_lead_delay_ms(&mut self, new_delay_ms: u32) -> Result<(), String> {
+        self.config.cat.serial_ptt_lead_delay_ms = new_delay_ms;
+        self.save()
+    }
+
+    pub fn get_serial_ptt_lead_delay_ms(&self) -> u32 {
+        self.config.cat.serial_ptt_lead_delay_ms
+    }
+
+    pub fn set_serial_ptt_tail_delay_ms(&mut self, new_delay_ms: u32) -> Result<(), String> {
+        self.config.cat.serial_ptt_tail_delay_ms = new_delay_ms;
+        self.save()
+    }
+
+    pub fn get_serial_ptt_tail_delay_ms(&self) -> u32 {
+        self.config.cat.serial_ptt_tail_delay_ms
+    }
 }
 
 
@@ -7,6 +7,9 @@ mod config_file_spec {
     use crate::libs::config_file::config_file::ConfigurationStore;
     use hamcrest2::prelude::*;
     use std::path::Path;
+    use crate::libs::cat::cat::CatType;
+    use crate::libs::cat::serial_ptt_cat::PttLine;
+    use crate::libs::config_file::waterfall_settings::ColourMap;
     use crate::libs::keyer_io::keyer_io::KeyerType;
 
     #[ctor::ctor]
@@ -53,6 +56,19 @@ mod config_file_spec {
         assert_that!(config.get_rig_in_device(), eq(""));
         assert_that!(config.get_transmit_offset_frequency(), eq(1500));
         assert_that!(config.get_transmit_amplitude(), eq(0.5));
+        assert_that!(config.get_max_transmit_seconds(), eq(180));
+        assert_that!(config.get_callsign(), eq(""));
+        assert_that!(config.get_locator(), eq(""));
+        assert_that!(config.get_power(), eq(None));
+        assert_that!(config.get_waterfall_colour_map(), eq(ColourMap::Blue));
+        assert_that!(config.get_waterfall_gain(), eq(30.0));
+        assert_that!(config.get_waterfall_contrast(), eq(1.0));
+        assert_that!(config.get_cat_type(), eq(CatType::Null));
+        assert_that!(config.get_rigctld_address(), eq(""));
+        assert_that!(config.get_serial_ptt_port(), eq(""));
+        assert_that!(config.get_serial_ptt_line(), eq(PttLine::Rts));
+        assert_that!(config.get_serial_ptt_lead_delay_ms(), eq(50));
+        assert_that!(config.get_serial_ptt_tail_delay_ms(), eq(50));
     }
 
     #[test]
@@ -71,6 +87,22 @@ mod config_file_spec {
 
         config.set_transmit_offset_frequency(500).unwrap();
         config.set_transmit_amplitude(0.3).unwrap();
+        config.set_max_transmit_seconds(60).unwrap();
+
+        config.set_callsign("M0CUV".to_string()).unwrap();
+        config.set_locator("JO01".to_string()).unwrap();
+        config.set_power(Some(37)).unwrap();
+
+        config.set_waterfall_colour_map(ColourMap::Heat).unwrap();
+        config.set_waterfall_gain(45.5).unwrap();
+        config.set_waterfall_contrast(1.5).unwrap();
+
+        config.set_cat_type(CatType::Rigctld).unwrap();
+        config.set_rigctld_address("shack-pi:4532".to_string()).unwrap();
+        config.set_serial_ptt_port("/dev/ttyUSB1".to_string()).unwrap();
+        config.set_serial_ptt_line(PttLine::Dtr).unwrap();
+        config.set_serial_ptt_lead_delay_ms(80).unwrap();
+        config.set_serial_ptt_tail_delay_ms(120).unwrap();
 
         assert_that!(config.get_keyer_type(), eq(KeyerType::Arduino));
         assert_that!(config.get_port(), eq("/dev/imaginary-usb-port"));
@@ -83,6 +115,22 @@ mod config_file_spec {
 
         assert_that!(config.get_transmit_offset_frequency(), eq(500));
         assert_that!(config.get_transmit_amplitude(), eq(0.3));
+        assert_that!(config.get_max_transmit_seconds(), eq(60));
+
+        assert_that!(config.get_callsign(), eq("M0CUV"));
+        assert_that!(config.get_locator(), eq("JO01"));
+        assert_that!(config.get_power(), eq(Some(37)));
+
+        assert_that!(config.get_waterfall_colour_map(), eq(ColourMap::Heat));
+        assert_that!(config.get_waterfall_gain(), eq(45.5));
+        assert_that!(config.get_waterfall_contrast(), eq(1.5));
+
+        assert_that!(config.get_cat_type(), eq(CatType::Rigctld));
+        assert_that!(config.get_rigctld_address(), eq("shack-pi:4532"));
+        assert_that!(config.get_serial_ptt_port(), eq("/dev/ttyUSB1"));
+        assert_that!(config.get_serial_ptt_line(), eq(PttLine::Dtr));
+        assert_that!(config.get_serial_ptt_lead_delay_ms(), eq(80));
+        assert_that!(config.get_serial_ptt_tail_delay_ms(), eq(120));
         let reread_config = ConfigurationStore::new(temp.clone()).unwrap();
 
         assert_that!(reread_config.get_keyer_type(), eq(KeyerType::Arduino));
@@ -96,5 +144,78 @@ mod config_file_spec {
 
         assert_that!(reread_config.get_transmit_offset_frequency(), eq(500));
         assert_that!(reread_config.get_transmit_amplitude(), eq(0.3));
+        assert_that!(reread_config.get_max_transmit_seconds(), eq(60));
+
+        assert_that!(reread_config.get_callsign(), eq("M0CUV"));
+        assert_that!(reread_config.get_locator(), eq("JO01"));
+        assert_that!(reread_config.get_power(), eq(Some(37)));
+
+        assert_that!(reread_config.get_waterfall_colour_map(), eq(ColourMap::Heat));
+        assert_that!(reread_config.get_waterfall_gain(), eq(45.5));
+        assert_that!(reread_config.get_waterfall_contrast(), eq(1.5));
+
+        assert_that!(reread_config.get_cat_type(), eq(CatType::Rigctld));
+        assert_that!(reread_config.get_rigctld_address(), eq("shack-pi:4532"));
+        assert_that!(reread_config.get_serial_ptt_port(), eq("/dev/ttyUSB1"));
+        assert_that!(reread_config.get_serial_ptt_line(), eq(PttLine::Dtr));
+        assert_that!(reread_config.get_serial_ptt_lead_delay_ms(), eq(80));
+        assert_that!(reread_config.get_serial_ptt_tail_delay_ms(), eq(120));
+    }
+
+    #[test]
+    fn max_transmit_duration_of_zero_is_rejected() {
+        let (temp, _temp_dir) = temp_config_dir();
+        let mut config = ConfigurationStore::new(temp.clone()).unwrap();
+        assert_that!(config.set_max_transmit_seconds(0).is_err(), eq(true));
+        assert_that!(config.get_max_transmit_seconds(), eq(180));
+    }
+
+    #[test]
+    fn config_without_station_section_has_default_station() {
+        let (temp, _temp_dir) = temp_config_dir();
+        let config = ConfigurationStore::new(temp.clone()).unwrap();
+        let config_file_path = config.get_config_file_path();
+        let toml = std::fs::read_to_string(config_file_path).unwrap();
+        let station_index = toml.find("[station]").unwrap();
+        std::fs::write(config_file_path, &toml[..station_index]).unwrap();
+
+        let reread_config = ConfigurationStore::new(temp.clone()).unwrap();
+
+        assert_that!(reread_config.get_callsign(), eq(""));
+        assert_that!(reread_config.get_locator(), eq(""));
+        assert_that!(reread_config.get_power(), eq(None));
+    }
+
+    #[test]
+    fn config_without_waterfall_section_has_default_waterfall() {
+        let (temp, _temp_dir) = temp_config_dir();
+        let config = ConfigurationStore::new(temp.clone()).unwrap();
+        let config_file_path = config.get_config_file_path();
+        let toml = std::fs::read_to_string(config_file_path).unwrap();
+        let waterfall_index = toml.find("[waterfall]").unwrap();
+        std::fs::write(config_file_path, &toml[..waterfall_index]).unwrap();
+
+        let reread_config = ConfigurationStore::new(temp.clone()).unwrap();
+
+        assert_that!(reread_config.get_waterfall_colour_map(), eq(ColourMap::Blue));
+        assert_that!(reread_config.get_waterfall_gain(), eq(30.0));
+        assert_that!(reread_config.get_waterfall_contrast(), eq(1.0));
+    }
+
+    #[test]
+    fn config_without_cat_section_has_no_cat_control() {
+        let (temp, _temp_dir) = temp_config_dir();
+        let config = ConfigurationStore::new(temp.clone()).unwrap();
+        let config_file_path = config.get_config_file_path();
+        let toml = std::fs::read_to_string(config_file_path).unwrap();
+        let cat_index = toml.find("[cat]").unwrap();
+        std::fs::write(config_file_path, &toml[..cat_index]).unwrap();
+
+        let reread_config = ConfigurationStore::new(temp.clone()).unwrap();
+
+        assert_that!(reread_config.get_cat_type(), eq(CatType::Null));
+        assert_that!(reread_config.get_rigctld_address(), eq(""));
+        assert_that!(reread_config.get_serial_ptt_line(), eq(PttLine::Rts));
+        assert_that!(reread_config.get_serial_ptt_lead_delay_ms(), eq(50));
     }
 }
@@ -0,0 +1,40 @@
+/*
+ * The waterfall's colour maps, which convert a level between 0 and 1 to a colour. The map is chosen
+ * in the configuration file, so it is kept here rather than with the GUI's waterfall.
+ */
+
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Copy, Clone)]
+pub enum ColourMap {
+    Greyscale,
+    Blue,
+    Heat,
+}
+
+impl ColourMap {
+    // The colours at evenly-spaced levels from 0 to 1.
+    fn stops(&self) -> &'static [[u8; 3]] {
+        match self {
+            ColourMap::Greyscale => &[[0, 0, 0], [255, 255, 255]],
+            ColourMap::Blue => &[[0, 0, 0], [0, 0, 160], [0, 200, 255], [255, 255, 0], [255, 0, 0]],
+            ColourMap::Heat => &[[0, 0, 0], [200, 0, 0], [255, 200, 0], [255, 255, 255]],
+        }
+    }
+
+    // The colour of a level, interpolated between the stops either side of it; levels outside 0 to
+    // 1 are clamped.
+    pub fn colour(&self, level: f32) -> [u8; 3] {
+        let stops = self.stops();
+        let position = level.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+        let below = (position as usize).min(stops.len() - 2);
+        let fraction = position - below as f32;
+        let mut colour = [0; 3];
+        for (component, value) in colour.iter_mut().enumerate() {
+            let from = stops[below][component] as f32;
+            let to = stops[below + 1][component] as f32;
+            *value = (from + (to - from) * fraction).round() as u8;
+        }
+        colour
+    }
+}
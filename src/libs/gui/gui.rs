@@ -11,10 +11,16 @@ use fltk::{
 use fltk::input::MultilineInput;
 use fltk::output::Output;
 use log::{debug, info};
+use crate::libs::cat::dial_frequency::format_radio_frequency;
 use crate::libs::config_file::config_file::ConfigurationStore;
 use crate::libs::gui::message::{KeyingText, Message};
 use crate::libs::gui::gui_facades::GUIOutput;
+use crate::libs::gui::station_overlays::{audio_offset_column, column_audio_offset, StationOverlays};
+use crate::libs::gui::waterfall::Waterfall;
 use crate::libs::keyer_io::keyer_io::{MAX_KEYER_SPEED, MIN_KEYER_SPEED};
+use crate::libs::playback::receive_filter::ReceiveFilter;
+use crate::libs::transmitter::tune::TuneSignal;
+use crate::libs::util::util::get_epoch_ms;
 use crate::libs::util::version::VERSION;
 
 use super::gui_facades::GUIInputMessage;
@@ -23,8 +29,11 @@ pub const WIDGET_PADDING: i32 = 10;
 
 const WIDGET_HEIGHT: i32 = 25;
 
-const WATERFALL_WIDTH: i32 = 1000;
-const WATERFALL_HEIGHT: i32 = 500;
+pub const WATERFALL_WIDTH: i32 = 1000;
+pub const WATERFALL_HEIGHT: i32 = 500;
+
+// The dial frequency is shown at the right of the status line, once CAT has reported it.
+const DIAL_FREQUENCY_WIDTH: i32 = 160;
 
 // Central controls column
 const CENTRAL_CONTROLS_WIDTH: i32 = 240;
@@ -41,19 +50,36 @@ const TX_INDICATOR_WIDTH: i32 = 40;
 
 const TEXT_ENTRY_HEIGHT: i32 = 120;
 
+const TUNE_BUTTON_WIDTH: i32 = (CENTRAL_CONTROLS_WIDTH - WIDGET_PADDING) / 2;
+
+const COSTAS_ARRAY_MARKER_HEIGHT: i32 = 6;
+const STATION_MARKER_HEIGHT: i32 = 14;
+const STATION_MARKER_FONT_SIZE: i32 = 10;
+// A click within this many columns of a station's marker selects it.
+const STATION_SELECT_TOLERANCE_COLUMNS: usize = 4;
+// A drag over fewer columns than this is a click.
+const FILTER_DRAG_MIN_COLUMNS: i32 = 3;
+
 pub struct Gui {
     config: Arc<Mutex<ConfigurationStore>>,
     gui_output: Arc<Mutex<dyn GUIOutput>>,
     sender: Sender<Message>,
     receiver: Receiver<Message>,
     waterfall_canvas: Widget,
+    waterfall: Rc<RefCell<Waterfall>>,
+    station_overlays: Rc<RefCell<StationOverlays>>,
+    receive_filter: Rc<RefCell<ReceiveFilter>>,
     status_output: Output,
+    dial_frequency_output: Output,
     code_speed_output: Output,
     code_speed_up_button: Button,
     code_speed_down_button: Button,
     code_speed_label: Widget, // PITA, Frame doesn't align properly
     indicators_canvas: Widget,
     text_entry: Rc<RefCell<MultilineInput>>,
+    tune_button: Button,
+    two_tone_button: Button,
+    halt_button: Button,
     window_width: i32,
     window_height: i32,
     rx_indicator: Arc<RefCell<bool>>,
@@ -83,6 +109,15 @@ impl Gui {
         let wait_indicator = Arc::new(RefCell::new(false));
         let tx_indicator = Arc::new(RefCell::new(false));
  
+        let waterfall = {
+            let config = config.lock().unwrap();
+            Rc::new(RefCell::new(Waterfall::new(WATERFALL_WIDTH as usize, WATERFALL_HEIGHT as usize,
+                                                config.get_waterfall_colour_map(), config.get_waterfall_gain(),
+                                                config.get_waterfall_contrast())))
+        };
+
+        let receive_filter = Rc::new(RefCell::new(gui_output.lock().unwrap().get_receive_filter()));
+
         let thread_terminate = terminate.clone();
 
         let (gui_input_tx, gui_input_rx) = sync_channel::<GUIInputMessage>(16);
@@ -93,9 +128,15 @@ impl Gui {
             sender,
             receiver,
             waterfall_canvas: Widget::new(WIDGET_PADDING, WIDGET_PADDING, WATERFALL_WIDTH, WATERFALL_HEIGHT, ""),
+            waterfall,
+            station_overlays: Rc::new(RefCell::new(StationOverlays::new())),
+            receive_filter,
             status_output: Output::default()
-                .with_size(WATERFALL_WIDTH, WIDGET_HEIGHT)
+                .with_size(WATERFALL_WIDTH - WIDGET_PADDING - DIAL_FREQUENCY_WIDTH, WIDGET_HEIGHT)
                 .with_pos(WIDGET_PADDING, WIDGET_PADDING + WATERFALL_HEIGHT + WIDGET_PADDING),
+            dial_frequency_output: Output::default()
+                .with_size(DIAL_FREQUENCY_WIDTH, WIDGET_HEIGHT)
+                .with_pos(WIDGET_PADDING + WATERFALL_WIDTH - DIAL_FREQUENCY_WIDTH, WIDGET_PADDING + WATERFALL_HEIGHT + WIDGET_PADDING),
             code_speed_output: Output::default()
                 .with_size(CODE_SPEED_WIDTH, CODE_SPEED_HEIGHT)
                 .with_pos(WIDGET_PADDING + WATERFALL_WIDTH + WIDGET_PADDING, WIDGET_PADDING),
@@ -116,6 +157,18 @@ impl Gui {
             text_entry: Rc::new(RefCell::new(MultilineInput::default()
                 .with_size(CENTRAL_CONTROLS_WIDTH, TEXT_ENTRY_HEIGHT)
                 .with_pos(WIDGET_PADDING + WATERFALL_WIDTH + WIDGET_PADDING, WIDGET_PADDING + CODE_SPEED_BUTTON_DIM * 2 + WIDGET_PADDING + INDICATORS_CANVAS_HEIGHT + WIDGET_PADDING))),
+            tune_button: Button::default()
+                .with_size(TUNE_BUTTON_WIDTH, WIDGET_HEIGHT)
+                .with_pos(WIDGET_PADDING + WATERFALL_WIDTH + WIDGET_PADDING, WIDGET_PADDING + CODE_SPEED_BUTTON_DIM * 2 + WIDGET_PADDING + INDICATORS_CANVAS_HEIGHT + WIDGET_PADDING + TEXT_ENTRY_HEIGHT + WIDGET_PADDING)
+                .with_label("Tune"),
+            two_tone_button: Button::default()
+                .with_size(TUNE_BUTTON_WIDTH, WIDGET_HEIGHT)
+                .with_pos(WIDGET_PADDING + WATERFALL_WIDTH + WIDGET_PADDING + TUNE_BUTTON_WIDTH + WIDGET_PADDING, WIDGET_PADDING + CODE_SPEED_BUTTON_DIM * 2 + WIDGET_PADDING + INDICATORS_CANVAS_HEIGHT + WIDGET_PADDING + TEXT_ENTRY_HEIGHT + WIDGET_PADDING)
+                .with_label("Two-Tone"),
+            halt_button: Button::default()
+                .with_size(CENTRAL_CONTROLS_WIDTH, WIDGET_HEIGHT)
+                .with_pos(WIDGET_PADDING + WATERFALL_WIDTH + WIDGET_PADDING, WIDGET_PADDING + CODE_SPEED_BUTTON_DIM * 2 + WIDGET_PADDING + INDICATORS_CANVAS_HEIGHT + WIDGET_PADDING + TEXT_ENTRY_HEIGHT + WIDGET_PADDING + WIDGET_HEIGHT + WIDGET_PADDING)
+                .with_label("Halt"),
             window_width: WIDGET_PADDING + WATERFALL_WIDTH + WIDGET_PADDING + CENTRAL_CONTROLS_WIDTH + WIDGET_PADDING,
             window_height: WIDGET_PADDING + WATERFALL_HEIGHT + WIDGET_PADDING + WIDGET_HEIGHT + WIDGET_PADDING,
             rx_indicator,
@@ -126,15 +179,81 @@ impl Gui {
         };
 
         gui.waterfall_canvas.set_trigger(CallbackTrigger::Release);
+        let canvas_waterfall = gui.waterfall.clone();
+        let canvas_station_overlays = gui.station_overlays.clone();
+        let canvas_receive_filter = gui.receive_filter.clone();
         gui.waterfall_canvas.draw(move |wid| {
             push_clip(wid.x(), wid.y(), wid.width(), wid.height());
-            draw_rect_fill(wid.x(), wid.y(), wid.width(), wid.height(), waterfall_canvas_background);
+            let waterfall = canvas_waterfall.borrow();
+            if draw_image(waterfall.rgb(), wid.x(), wid.y(), waterfall.width() as i32, waterfall.height() as i32, ColorDepth::Rgb8).is_err() {
+                draw_rect_fill(wid.x(), wid.y(), wid.width(), wid.height(), waterfall_canvas_background);
+            }
+
+            // Costas Arrays are ticks along the top of the waterfall; stations are lines down from
+            // it, labelled.
+            let station_overlays = canvas_station_overlays.borrow();
+            set_draw_color(Color::Yellow);
+            for column in station_overlays.costas_array_markers(wid.width() as usize) {
+                let x = wid.x() + column as i32;
+                draw_line(x, wid.y(), x, wid.y() + COSTAS_ARRAY_MARKER_HEIGHT);
+            }
+            set_draw_color(Color::White);
+            fltk::draw::set_font(Font::Helvetica, STATION_MARKER_FONT_SIZE);
+            for (column, label) in station_overlays.station_markers(wid.width() as usize) {
+                let x = wid.x() + column as i32;
+                draw_line(x, wid.y(), x, wid.y() + STATION_MARKER_HEIGHT);
+                draw_text(&label, x + 2, wid.y() + STATION_MARKER_HEIGHT);
+            }
+
+            // The receive filter's bandpass edges, or selected station, are lines down the waterfall.
+            let filter_columns = match *canvas_receive_filter.borrow() {
+                ReceiveFilter::Disabled => vec![],
+                ReceiveFilter::Bandpass { low, high } => vec![low, high],
+                ReceiveFilter::Station { audio_offset, .. } => vec![audio_offset],
+            };
+            set_draw_color(Color::Green);
+            for audio_offset in filter_columns {
+                let x = wid.x() + audio_offset_column(audio_offset, wid.width() as usize) as i32;
+                draw_line(x, wid.y(), x, wid.y() + wid.height());
+            }
 
             set_draw_color(Color::Black);
             draw_rect(wid.x(), wid.y(), wid.width(), wid.height());
             pop_clip();
         });
 
+        // Drag over the waterfall to filter the stations heard to a range of audio offsets; click on
+        // a station to hear only it, or elsewhere to hear all of them.
+        let handle_station_overlays = gui.station_overlays.clone();
+        let handle_sender = gui.sender.clone();
+        let mut drag_start_x = 0;
+        gui.waterfall_canvas.handle(move |wid, event| {
+            match event {
+                Event::Push => {
+                    drag_start_x = event_x() - wid.x();
+                    true
+                }
+                Event::Drag => true,
+                Event::Released => {
+                    let width = wid.width() as usize;
+                    let drag_end_x = event_x() - wid.x();
+                    let start_column = drag_start_x.clamp(0, wid.width()) as usize;
+                    let end_column = drag_end_x.clamp(0, wid.width()) as usize;
+                    let receive_filter = if (drag_end_x - drag_start_x).abs() >= FILTER_DRAG_MIN_COLUMNS {
+                        ReceiveFilter::bandpass(column_audio_offset(start_column, width), column_audio_offset(end_column, width))
+                    } else {
+                        match handle_station_overlays.borrow().station_near(end_column, width, STATION_SELECT_TOLERANCE_COLUMNS) {
+                            Some(station) => ReceiveFilter::Station { callsign_hash: station.callsign_hash, audio_offset: station.audio_offset },
+                            None => ReceiveFilter::Disabled,
+                        }
+                    };
+                    handle_sender.send(Message::SetReceiveFilter(receive_filter));
+                    true
+                }
+                _ => false,
+            }
+        });
+
         gui.code_speed_label.draw(move |wid| {
             push_clip(wid.x(), wid.y(), wid.width(), wid.height());
             draw_rect_fill(wid.x(), wid.y(), wid.width(), wid.height(), window_background);
@@ -147,6 +266,15 @@ impl Gui {
         gui.code_speed_up_button.emit(gui.sender.clone(), Message::IncreaseKeyingSpeedRequest);
         gui.code_speed_down_button.emit(gui.sender.clone(), Message::DecreaseKeyingSpeedRequest);
 
+        // Either button starts tuning, or stops it; the TX indicator shows when it is on.
+        gui.tune_button.set_tooltip("Transmit a carrier, to set the rig's ALC or ATU");
+        gui.tune_button.emit(gui.sender.clone(), Message::ToggleTuning(TuneSignal::Carrier));
+        gui.two_tone_button.set_tooltip("Transmit a two-tone test signal, to set the rig's ALC");
+        gui.two_tone_button.emit(gui.sender.clone(), Message::ToggleTuning(TuneSignal::TwoTone));
+        gui.halt_button.set_tooltip("Stop transmitting or tuning now, discarding any queued text");
+        gui.halt_button.set_label_color(Color::Red);
+        gui.halt_button.emit(gui.sender.clone(), Message::HaltTransmission);
+
         let canvas_rx = gui.rx_indicator.clone();
         let canvas_wait = gui.wait_indicator.clone();
         let canvas_tx = gui.tx_indicator.clone();
@@ -178,6 +306,10 @@ impl Gui {
         gui.status_output.set_text_color(Color::from_hex_str("#f2cc91").unwrap());
         gui.status_output.set_value("status message");
 
+        gui.dial_frequency_output.set_color(Color::Black);
+        gui.dial_frequency_output.set_text_color(Color::from_hex_str("#f2cc91").unwrap());
+        gui.dial_frequency_output.hide();
+
         gui.code_speed_output.set_color(window_background);
         gui.code_speed_output.set_text_color(Color::Black);
         gui.code_speed_output.set_value(gui.config.lock().unwrap().get_wpm().to_string().as_str());
@@ -235,6 +367,24 @@ impl Gui {
                         GUIInputMessage::SetTxIndicator(state) => {
                             thread_gui_sender.send(Message::SetTxIndicator(state));
                         }
+                        GUIInputMessage::AddWaterfallSpectrum(spectrum) => {
+                            thread_gui_sender.send(Message::AddWaterfallSpectrum(spectrum));
+                        }
+                        GUIInputMessage::AddStation(station_details) => {
+                            thread_gui_sender.send(Message::AddStation(station_details));
+                        }
+                        GUIInputMessage::ClearStation(audio_offset) => {
+                            thread_gui_sender.send(Message::ClearStation(audio_offset));
+                        }
+                        GUIInputMessage::AddCostasArray(audio_offset) => {
+                            thread_gui_sender.send(Message::AddCostasArray(audio_offset));
+                        }
+                        GUIInputMessage::ClearCostasArray(audio_offset) => {
+                            thread_gui_sender.send(Message::ClearCostasArray(audio_offset));
+                        }
+                        GUIInputMessage::SetDialFrequency(dial_frequency) => {
+                            thread_gui_sender.send(Message::SetDialFrequency(dial_frequency));
+                        }
                     }
                 }
              }
@@ -259,7 +409,10 @@ impl Gui {
                 // noop
             }
             Some(message) => {
-                info!("App message {:?}", message);
+                // Spectra arrive many times a second; don't log them.
+                if !matches!(message, Message::AddWaterfallSpectrum(_)) {
+                    info!("App message {:?}", message);
+                }
                 match message {
                     Message::KeyingText(keying_text) => {
                         info!("Sending the text [{}]", keying_text.text);
@@ -313,6 +466,59 @@ impl Gui {
                         self.indicators_canvas.redraw();
                     }
 
+                    Message::AddWaterfallSpectrum(spectrum) => {
+                        self.waterfall.borrow_mut().add_spectrum(&spectrum);
+                        self.station_overlays.borrow_mut().age_out(get_epoch_ms());
+                        self.waterfall_canvas.redraw();
+                    }
+
+                    Message::AddStation(station_details) => {
+                        self.station_overlays.borrow_mut().add_station(station_details, get_epoch_ms());
+                        self.waterfall_canvas.redraw();
+                    }
+
+                    Message::ClearStation(audio_offset) => {
+                        self.station_overlays.borrow_mut().clear_station(audio_offset);
+                        self.waterfall_canvas.redraw();
+                    }
+
+                    Message::AddCostasArray(audio_offset) => {
+                        self.station_overlays.borrow_mut().add_costas_array(audio_offset, get_epoch_ms());
+                        self.waterfall_canvas.redraw();
+                    }
+
+                    Message::ClearCostasArray(audio_offset) => {
+                        self.station_overlays.borrow_mut().clear_costas_array(audio_offset);
+                        self.waterfall_canvas.redraw();
+                    }
+
+                    Message::SetReceiveFilter(receive_filter) => {
+                        self.gui_output.lock().unwrap().set_receive_filter(receive_filter.clone());
+                        *self.receive_filter.borrow_mut() = receive_filter;
+                        self.waterfall_canvas.redraw();
+                    }
+
+                    Message::ToggleTuning(tune_signal) => {
+                        let mut gui_output = self.gui_output.lock().unwrap();
+                        if gui_output.is_tuning() {
+                            gui_output.set_tuning(None);
+                        } else {
+                            gui_output.set_tuning(Some(tune_signal));
+                        }
+                    }
+
+                    Message::HaltTransmission => {
+                        self.gui_output.lock().unwrap().halt_transmission();
+                    }
+
+                    Message::SetDialFrequency(dial_frequency) => {
+                        match dial_frequency {
+                            Some(dial_frequency) => self.dial_frequency_output.set_value(&format!("Dial {}", format_radio_frequency(dial_frequency))),
+                            None => self.dial_frequency_output.set_value("Dial unknown"),
+                        }
+                        self.dial_frequency_output.show();
+                    }
+
                 }
             }
         }
@@ -1,16 +1,37 @@
 use crate::libs::keyer_io::keyer_io::KeyerSpeed;
+use crate::libs::playback::receive_filter::ReceiveFilter;
+use crate::libs::source_codec::source_encoding::{Callsign, CallsignHash, Locator};
+use crate::libs::transmitter::tune::TuneSignal;
+use crate::libs::transmitter::transmitter::{AudioFrequencyHz, RadioFrequencyHz};
+
+// A station the Decoder has decoded, as far as it has identified itself. Its radio frequency is
+// known if the rig's dial frequency is.
+#[derive(Clone, PartialEq, Debug)]
+pub struct StationDetails {
+    pub audio_offset: AudioFrequencyHz,
+    pub radio_frequency: Option<RadioFrequencyHz>,
+    pub callsign_hash: CallsignHash,
+    pub callsign: Option<Callsign>,
+    pub locator: Option<Locator>,
+}
 
 // The rest of the system can effect changes in parts of the GUI by sending messages of this type
 // to the GUIInput channel (sender), obtained from the GUI.
-#[derive(Clone, PartialEq, Copy)]
+#[derive(Clone, PartialEq, Debug)]
 pub enum GUIInputMessage {
     SetRxIndicator(bool),
     SetWaitIndicator(bool),
     SetTxIndicator(bool),
+    // A spectrum, reduced to the width of the waterfall.
+    AddWaterfallSpectrum(Vec<f32>),
+    // Station and Costas Array markers on the waterfall, at their audio offsets.
+    AddStation(StationDetails),
+    ClearStation(AudioFrequencyHz),
+    AddCostasArray(AudioFrequencyHz),
+    ClearCostasArray(AudioFrequencyHz),
+    // The rig's dial frequency, shown on the status line; None if unknown.
+    SetDialFrequency(Option<RadioFrequencyHz>),
 
-    // TODO add downsampled FFT to waterfall
-    // TODO add/clear dx station details for callsign/hash/offset
-    // TODO add/clear receipt of costas array at offset
     // TODO clear decode frame
     // TODO add string to decode frame
 }
@@ -21,10 +42,13 @@ pub trait GUIOutput {
     fn warning_beep(&mut self);
     fn set_keyer_speed(&mut self, keyer_speed: KeyerSpeed);
     fn get_keyer_speed(&self) -> KeyerSpeed;
-    // TODO filter disable (play everything)
-    // TODO filter by range (left .. right) Hz
-    // TODO filter by callsign/hash/offset
+    fn set_receive_filter(&mut self, receive_filter: ReceiveFilter);
+    fn get_receive_filter(&self) -> ReceiveFilter;
     // TODO set transmit offset
-    // TODO enable/disable tuning output at current transmit offset
+    // Tuning output at the current transmit offset, until stopped (None) or timed out.
+    fn set_tuning(&mut self, tune_signal: Option<TuneSignal>);
+    fn is_tuning(&self) -> bool;
+    // Stop whatever is being transmitted or tuned, discarding anything queued, and release the PTT.
+    fn halt_transmission(&mut self);
 }
 
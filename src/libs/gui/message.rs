@@ -1,4 +1,8 @@
+use crate::libs::gui::gui_facades::StationDetails;
 use crate::libs::keyer_io::keyer_io::KeyerSpeed;
+use crate::libs::playback::receive_filter::ReceiveFilter;
+use crate::libs::transmitter::transmitter::{AudioFrequencyHz, RadioFrequencyHz};
+use crate::libs::transmitter::tune::TuneSignal;
 
 #[derive(Clone, Debug)]
 pub struct KeyingText {
@@ -16,4 +20,13 @@ pub enum Message {
     SetRxIndicator(bool),
     SetWaitIndicator(bool),
     SetTxIndicator(bool),
+    AddWaterfallSpectrum(Vec<f32>),
+    AddStation(StationDetails),
+    ClearStation(AudioFrequencyHz),
+    AddCostasArray(AudioFrequencyHz),
+    ClearCostasArray(AudioFrequencyHz),
+    SetReceiveFilter(ReceiveFilter),
+    SetDialFrequency(Option<RadioFrequencyHz>),
+    ToggleTuning(TuneSignal),
+    HaltTransmission,
 }
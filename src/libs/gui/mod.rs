@@ -2,3 +2,5 @@ pub mod gui;
 pub mod gui_driver;
 pub mod gui_facades;
 pub mod message;
+pub mod station_overlays;
+pub mod waterfall;
@@ -0,0 +1,137 @@
+/*
+ * The StationOverlays are the markers drawn over the waterfall, independently of FLTK, where the
+ * Decoder has detected a Costas Array, or decoded a station. Each is marked at the column of its
+ * audio offset; a station is labelled with its callsign, or its callsign hash if its callsign is
+ * not yet known, and its locator, if it has sent one. The Decoder clears a station's marker when it
+ * retires the station; in case it does not, markers also age out: a station's when it has not been
+ * heard for as long as the Decoder would retire it, and a Costas Array's when its block has been
+ * received. Clicking on a station's marker selects it for the receive filter.
+ */
+
+use crate::libs::channel_codec::channel_encoding::CHANNEL_ENCODER_BLOCK_SIZE;
+use crate::libs::channel_codec::costas::COSTAS_ARRAY_SYMBOLS;
+use crate::libs::gui::gui_facades::StationDetails;
+use crate::libs::gui::waterfall::WATERFALL_MAX_FREQUENCY_HZ;
+use crate::libs::receiver::decoder::STATION_SILENCE_SECONDS;
+use crate::libs::source_codec::source_encoding::UNKNOWN_CALLSIGN_HASH;
+use crate::libs::transmitter::modulate::SYMBOL_PERIOD_SECONDS;
+use crate::libs::transmitter::transmitter::AudioFrequencyHz;
+
+const STATION_MARKER_LIFETIME_MS: u128 = (STATION_SILENCE_SECONDS * 1000.0) as u128;
+const COSTAS_ARRAY_MARKER_LIFETIME_MS: u128 = ((COSTAS_ARRAY_SYMBOLS + CHANNEL_ENCODER_BLOCK_SIZE) as f32 * SYMBOL_PERIOD_SECONDS * 1000.0) as u128;
+
+// The label of a station: its callsign, or hash (or ? if it has not identified itself), and
+// locator.
+pub fn station_label(details: &StationDetails) -> String {
+    let identity = match &details.callsign {
+        Some(callsign) => callsign.clone(),
+        None if details.callsign_hash == UNKNOWN_CALLSIGN_HASH => "?".to_string(),
+        None => format!("#{:06X}", details.callsign_hash),
+    };
+    match &details.locator {
+        Some(locator) => format!("{} {}", identity, locator),
+        None => identity,
+    }
+}
+
+// The waterfall column of an audio offset, for a waterfall of the given width.
+pub fn audio_offset_column(audio_offset: AudioFrequencyHz, width: usize) -> usize {
+    ((audio_offset as f32 / WATERFALL_MAX_FREQUENCY_HZ * width as f32) as usize).min(width.saturating_sub(1))
+}
+
+// The audio offset of a waterfall column, for a waterfall of the given width; the inverse of
+// audio_offset_column.
+pub fn column_audio_offset(column: usize, width: usize) -> AudioFrequencyHz {
+    (column.min(width) as f32 * WATERFALL_MAX_FREQUENCY_HZ / width as f32).round() as AudioFrequencyHz
+}
+
+struct StationMarker {
+    details: StationDetails,
+    last_heard_ms: u128,
+}
+
+struct CostasArrayMarker {
+    audio_offset: AudioFrequencyHz,
+    detected_ms: u128,
+}
+
+#[derive(Default)]
+pub struct StationOverlays {
+    stations: Vec<StationMarker>,
+    costas_arrays: Vec<CostasArrayMarker>,
+}
+
+impl StationOverlays {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Add or update the marker of the station at the details' audio offset. A callsign or locator
+    // already known is kept if the update does not carry one, as the station only sends them in
+    // some of its blocks.
+    pub fn add_station(&mut self, details: StationDetails, now_ms: u128) {
+        match self.stations.iter_mut().find(|marker| marker.details.audio_offset == details.audio_offset) {
+            Some(marker) => {
+                let callsign = details.callsign.or_else(|| marker.details.callsign.take());
+                let locator = details.locator.or_else(|| marker.details.locator.take());
+                marker.details = StationDetails { callsign, locator, ..details };
+                marker.last_heard_ms = now_ms;
+            }
+            None => {
+                self.stations.push(StationMarker { details, last_heard_ms: now_ms });
+            }
+        }
+    }
+
+    pub fn clear_station(&mut self, audio_offset: AudioFrequencyHz) {
+        self.stations.retain(|marker| marker.details.audio_offset != audio_offset);
+    }
+
+    pub fn add_costas_array(&mut self, audio_offset: AudioFrequencyHz, now_ms: u128) {
+        self.clear_costas_array(audio_offset);
+        self.costas_arrays.push(CostasArrayMarker { audio_offset, detected_ms: now_ms });
+    }
+
+    pub fn clear_costas_array(&mut self, audio_offset: AudioFrequencyHz) {
+        self.costas_arrays.retain(|marker| marker.audio_offset != audio_offset);
+    }
+
+    // Remove the markers that have outlived their lifetimes, as of now.
+    pub fn age_out(&mut self, now_ms: u128) {
+        self.stations.retain(|marker| now_ms < marker.last_heard_ms + STATION_MARKER_LIFETIME_MS);
+        self.costas_arrays.retain(|marker| now_ms < marker.detected_ms + COSTAS_ARRAY_MARKER_LIFETIME_MS);
+    }
+
+    // The column and label of each station, on a waterfall of the given width, in order of audio
+    // offset.
+    pub fn station_markers(&self, width: usize) -> Vec<(usize, String)> {
+        let mut stations: Vec<&StationMarker> = self.stations.iter().collect();
+        stations.sort_by_key(|marker| marker.details.audio_offset);
+        stations.iter()
+            .map(|marker| (audio_offset_column(marker.details.audio_offset, width), station_label(&marker.details)))
+            .collect()
+    }
+
+    // The station whose marker is nearest the column, if it is within the given number of columns,
+    // on a waterfall of the given width.
+    pub fn station_near(&self, column: usize, width: usize, tolerance_columns: usize) -> Option<StationDetails> {
+        self.stations.iter()
+            .map(|marker| (audio_offset_column(marker.details.audio_offset, width).abs_diff(column), marker))
+            .filter(|(distance, _)| *distance <= tolerance_columns)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, marker)| marker.details.clone())
+    }
+
+    // The column of each Costas Array, on a waterfall of the given width, in order of audio offset.
+    pub fn costas_array_markers(&self, width: usize) -> Vec<usize> {
+        let mut columns: Vec<usize> = self.costas_arrays.iter()
+            .map(|marker| audio_offset_column(marker.audio_offset, width))
+            .collect();
+        columns.sort_unstable();
+        columns
+    }
+}
+
+#[cfg(test)]
+#[path = "./station_overlays_spec.rs"]
+mod station_overlays_spec;
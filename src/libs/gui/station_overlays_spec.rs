@@ -0,0 +1,130 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod station_overlays_spec {
+    use std::env;
+    use hamcrest2::prelude::*;
+    use rstest::*;
+    use crate::libs::channel_codec::channel_encoding::CHANNEL_ENCODER_BLOCK_SIZE;
+    use crate::libs::channel_codec::costas::COSTAS_ARRAY_SYMBOLS;
+    use crate::libs::gui::gui_facades::StationDetails;
+    use crate::libs::gui::station_overlays::{audio_offset_column, column_audio_offset, station_label, StationOverlays};
+    use crate::libs::source_codec::source_encoding::UNKNOWN_CALLSIGN_HASH;
+
+    #[ctor::ctor]
+    fn before_each() {
+        env::set_var("RUST_LOG", "debug");
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[ctor::dtor]
+    fn after_each() {}
+
+    fn details(audio_offset: u16, callsign: Option<&str>, locator: Option<&str>) -> StationDetails {
+        StationDetails {
+            audio_offset,
+            radio_frequency: None,
+            callsign_hash: 0x1ABCD,
+            callsign: callsign.map(str::to_string),
+            locator: locator.map(str::to_string),
+        }
+    }
+
+    #[rstest]
+    #[case(Some("M0CUV"), Some("IO91"), "M0CUV IO91")]
+    #[case(Some("M0CUV"), None, "M0CUV")]
+    #[case(None, Some("IO91"), "#01ABCD IO91")]
+    #[case(None, None, "#01ABCD")]
+    fn stations_are_labelled_with_callsign_or_hash_and_locator(#[case] callsign: Option<&str>, #[case] locator: Option<&str>, #[case] expected: &str) {
+        assert_that!(station_label(&details(1000, callsign, locator)), equal_to(expected.to_string()));
+    }
+
+    #[test]
+    fn unidentified_station_is_labelled_as_unknown() {
+        let details = StationDetails { callsign_hash: UNKNOWN_CALLSIGN_HASH, ..details(1000, None, None) };
+        assert_that!(station_label(&details), equal_to("?".to_string()));
+    }
+
+    #[test]
+    fn audio_offsets_are_columns_of_the_waterfall_up_to_2500hz() {
+        assert_that!(audio_offset_column(0, 1000), equal_to(0));
+        assert_that!(audio_offset_column(1000, 1000), equal_to(400));
+        assert_that!(audio_offset_column(3000, 1000), equal_to(999));
+        assert_that!(column_audio_offset(0, 1000), equal_to(0));
+        assert_that!(column_audio_offset(400, 1000), equal_to(1000));
+        assert_that!(column_audio_offset(1000, 1000), equal_to(2500));
+    }
+
+    #[test]
+    fn station_near_a_column_is_found() {
+        let mut overlays = StationOverlays::new();
+        overlays.add_station(details(700, Some("M0CUV"), None), 0);
+        overlays.add_station(details(720, Some("G4DPZ"), None), 0);
+        assert_that!(overlays.station_near(281, 1000, 4).map(|station| station.audio_offset), equal_to(Some(700)));
+        assert_that!(overlays.station_near(287, 1000, 4).map(|station| station.audio_offset), equal_to(Some(720)));
+        assert_that!(overlays.station_near(293, 1000, 4), none());
+    }
+
+    #[test]
+    fn stations_are_marked_in_order_of_audio_offset() {
+        let mut overlays = StationOverlays::new();
+        overlays.add_station(details(1500, Some("G4DPZ"), None), 0);
+        overlays.add_station(details(700, Some("M0CUV"), None), 0);
+        let expected = vec![(280, "M0CUV".to_string()), (600, "G4DPZ".to_string())];
+        assert_that!(overlays.station_markers(1000), equal_to(expected));
+    }
+
+    #[test]
+    fn updated_station_keeps_its_callsign_and_locator() {
+        let mut overlays = StationOverlays::new();
+        overlays.add_station(details(700, None, Some("IO91")), 0);
+        overlays.add_station(details(700, Some("M0CUV"), None), 1000);
+        overlays.add_station(details(700, None, None), 2000);
+        assert_that!(overlays.station_markers(1000), equal_to(vec![(280, "M0CUV IO91".to_string())]));
+    }
+
+    #[test]
+    fn stations_are_cleared() {
+        let mut overlays = StationOverlays::new();
+        overlays.add_station(details(700, Some("M0CUV"), None), 0);
+        overlays.add_station(details(1500, Some("G4DPZ"), None), 0);
+        overlays.clear_station(700);
+        assert_that!(overlays.station_markers(1000), equal_to(vec![(600, "G4DPZ".to_string())]));
+    }
+
+    #[test]
+    fn costas_arrays_are_marked_and_cleared() {
+        let mut overlays = StationOverlays::new();
+        overlays.add_costas_array(1500, 0);
+        overlays.add_costas_array(700, 0);
+        overlays.add_costas_array(700, 100);
+        assert_that!(overlays.costas_array_markers(1000), equal_to(vec![280, 600]));
+        overlays.clear_costas_array(1500);
+        assert_that!(overlays.costas_array_markers(1000), equal_to(vec![280]));
+    }
+
+    #[test]
+    fn markers_age_out_when_stations_go_quiet() {
+        let mut overlays = StationOverlays::new();
+        overlays.add_station(details(700, Some("M0CUV"), None), 0);
+        overlays.add_station(details(1500, Some("G4DPZ"), None), 0);
+        overlays.add_costas_array(1000, 0);
+        overlays.add_station(details(1500, None, None), 15000);
+
+        // A Costas Array's marker lasts as long as its block: the array and codeword, of 160ms
+        // symbols.
+        let block_ms = (COSTAS_ARRAY_SYMBOLS + CHANNEL_ENCODER_BLOCK_SIZE) as u128 * 160;
+        overlays.age_out(block_ms - 100);
+        assert_that!(overlays.costas_array_markers(1000), equal_to(vec![400]));
+        overlays.age_out(block_ms + 100);
+        assert_that!(overlays.costas_array_markers(1000).len(), equal_to(0));
+
+        // A station's marker lasts until it has been silent as long as the Decoder would retire it.
+        overlays.age_out(19000);
+        assert_that!(overlays.station_markers(1000).len(), equal_to(2));
+        overlays.age_out(21000);
+        assert_that!(overlays.station_markers(1000), equal_to(vec![(600, "G4DPZ".to_string())]));
+        overlays.age_out(36000);
+        assert_that!(overlays.station_markers(1000).len(), equal_to(0));
+    }
+}
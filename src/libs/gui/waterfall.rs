@@ -0,0 +1,141 @@
+/*
+ * The Waterfall renders the received spectrum over time, independently of FLTK, into an RGB buffer
+ * that the GUI draws on its waterfall canvas. Each spectrum is reduced to one value per column of
+ * the canvas, spanning 0 to 2500Hz, the peak magnitude of the FFT bins in each column. Its
+ * magnitudes in dB are scaled by the gain and contrast to a level between 0 and 1, which the
+ * colour map converts to a colour. The newest spectrum is the top row; older rows scroll down.
+ * The WaterfallObserver reduces the FFT observer's spectra, and sends them to the GUI.
+ */
+
+use std::sync::Arc;
+use std::sync::mpsc::SyncSender;
+use crate::libs::config_file::waterfall_settings::ColourMap;
+use crate::libs::gui::gui_facades::GUIInputMessage;
+use crate::libs::patterns::observer::Observer;
+use crate::libs::receiver::fft::ObservableFrequencySlice;
+use crate::libs::transmitter::transmitter::AudioFrequencyHz;
+
+// The waterfall spans the audio passband, from 0Hz to this.
+pub const WATERFALL_MAX_FREQUENCY_HZ: f32 = 2500.0;
+
+// The range of magnitudes, in dB, that map to levels 0 to 1 at unit contrast.
+const WATERFALL_RANGE_DB: f32 = 60.0;
+
+// Reduce a spectrum (the magnitudes of FFT bins from DC up to half the sample rate) to the peak
+// magnitude in each of the width columns from 0Hz to WATERFALL_MAX_FREQUENCY_HZ. Columns narrower
+// than a bin take the bin they start in.
+pub fn reduce_spectrum(magnitudes: &[f32], sample_rate: AudioFrequencyHz, width: usize) -> Vec<f32> {
+    if magnitudes.len() < 2 {
+        return vec![0.0; width];
+    }
+    let bin_hz = sample_rate as f32 / 2.0 / (magnitudes.len() - 1) as f32;
+    let column_hz = WATERFALL_MAX_FREQUENCY_HZ / width as f32;
+    (0..width)
+        .map(|column| {
+            let low = ((column as f32 * column_hz / bin_hz) as usize).min(magnitudes.len() - 1);
+            let high = (((column + 1) as f32 * column_hz / bin_hz).ceil() as usize).clamp(low + 1, magnitudes.len());
+            magnitudes[low..high].iter().fold(0.0, |peak, magnitude| f32::max(peak, *magnitude))
+        })
+        .collect()
+}
+
+pub struct Waterfall {
+    width: usize,
+    height: usize,
+    colour_map: ColourMap,
+    gain_db: f32,
+    contrast: f32,
+    // Three bytes per pixel, row by row from the top.
+    rgb: Vec<u8>,
+}
+
+impl Waterfall {
+    pub fn new(width: usize, height: usize, colour_map: ColourMap, gain_db: f32, contrast: f32) -> Self {
+        let background = colour_map.colour(0.0);
+        Self {
+            width,
+            height,
+            colour_map,
+            gain_db,
+            contrast,
+            rgb: background.repeat(width * height),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn rgb(&self) -> &[u8] {
+        &self.rgb
+    }
+
+    // Changes to the colour map, gain and contrast apply to spectra added afterwards.
+    pub fn set_colour_map(&mut self, colour_map: ColourMap) {
+        self.colour_map = colour_map;
+    }
+
+    pub fn set_gain(&mut self, gain_db: f32) {
+        self.gain_db = gain_db;
+    }
+
+    pub fn set_contrast(&mut self, contrast: f32) {
+        self.contrast = contrast;
+    }
+
+    // Scroll the waterfall down a row, and draw the spectrum, already reduced to the waterfall's
+    // width, as its top row.
+    pub fn add_spectrum(&mut self, columns: &[f32]) {
+        if columns.len() != self.width {
+            panic!("Expecting spectra of width {} not {}", self.width, columns.len());
+        }
+        if self.height == 0 {
+            return;
+        }
+        let row_bytes = self.width * 3;
+        self.rgb.copy_within(0..(self.height - 1) * row_bytes, row_bytes);
+        let colours: Vec<[u8; 3]> = columns.iter().map(|magnitude| self.colour_map.colour(self.level(*magnitude))).collect();
+        for (pixel, colour) in self.rgb[..row_bytes].chunks_mut(3).zip(colours) {
+            pixel.copy_from_slice(&colour);
+        }
+    }
+
+    fn level(&self, magnitude: f32) -> f32 {
+        let db = 20.0 * magnitude.max(1e-10).log10();
+        self.contrast * (db + self.gain_db) / WATERFALL_RANGE_DB
+    }
+}
+
+// Observes the spectra of audio at the sample rate, sending each to the GUI, reduced to the
+// waterfall's width. If the GUI has not kept up, spectra are dropped rather than holding up the
+// receiver.
+pub struct WaterfallObserver {
+    gui_input: Arc<SyncSender<GUIInputMessage>>,
+    sample_rate: AudioFrequencyHz,
+    width: usize,
+}
+
+impl WaterfallObserver {
+    pub fn new(gui_input: Arc<SyncSender<GUIInputMessage>>, sample_rate: AudioFrequencyHz, width: usize) -> Self {
+        Self {
+            gui_input,
+            sample_rate,
+            width,
+        }
+    }
+}
+
+impl Observer<ObservableFrequencySlice> for WaterfallObserver {
+    fn on_notify(&self, spectrum: &ObservableFrequencySlice) {
+        let columns = reduce_spectrum(spectrum.magnitudes(), self.sample_rate, self.width);
+        let _ = self.gui_input.try_send(GUIInputMessage::AddWaterfallSpectrum(columns));
+    }
+}
+
+#[cfg(test)]
+#[path = "./waterfall_spec.rs"]
+mod waterfall_spec;
@@ -0,0 +1,123 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod waterfall_spec {
+    use std::env;
+    use std::f32::consts::PI;
+    use std::sync::Arc;
+    use std::sync::mpsc::sync_channel;
+    use hamcrest2::prelude::*;
+    use rstest::*;
+    use crate::libs::buffer_pool::observable_buffer::{OBSERVABLE_BUFFER_SLICE_SIZE, ObservableBufferSlice};
+    use crate::libs::config_file::waterfall_settings::ColourMap;
+    use crate::libs::gui::gui_facades::GUIInputMessage;
+    use crate::libs::gui::waterfall::{reduce_spectrum, Waterfall, WaterfallObserver};
+    use crate::libs::patterns::observer::Observer;
+    use crate::libs::receiver::fft::{FFTingBufferObserver, ObservableFrequencySlice};
+
+    #[ctor::ctor]
+    fn before_each() {
+        env::set_var("RUST_LOG", "debug");
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[ctor::dtor]
+    fn after_each() {}
+
+    fn row(waterfall: &Waterfall, row: usize) -> Vec<[u8; 3]> {
+        let row_bytes = waterfall.width() * 3;
+        waterfall.rgb()[row * row_bytes..(row + 1) * row_bytes].chunks(3).map(|pixel| [pixel[0], pixel[1], pixel[2]]).collect()
+    }
+
+    #[rstest]
+    #[case(ColourMap::Greyscale, 0.0, [0, 0, 0])]
+    #[case(ColourMap::Greyscale, 0.5, [128, 128, 128])]
+    #[case(ColourMap::Greyscale, 1.0, [255, 255, 255])]
+    #[case(ColourMap::Greyscale, -1.0, [0, 0, 0])]
+    #[case(ColourMap::Greyscale, 2.0, [255, 255, 255])]
+    #[case(ColourMap::Blue, 0.25, [0, 0, 160])]
+    #[case(ColourMap::Blue, 0.375, [0, 100, 208])]
+    #[case(ColourMap::Heat, 1.0, [255, 255, 255])]
+    fn colour_maps_interpolate_between_their_stops(#[case] colour_map: ColourMap, #[case] level: f32, #[case] expected: [u8; 3]) {
+        assert_that!(colour_map.colour(level), equal_to(expected));
+    }
+
+    #[test]
+    fn new_waterfall_is_the_colour_of_silence() {
+        let waterfall = Waterfall::new(4, 3, ColourMap::Blue, 30.0, 1.0);
+        assert_that!(waterfall.rgb().len(), equal_to(4 * 3 * 3));
+        assert_that!(waterfall.rgb().iter().all(|component| *component == 0), equal_to(true));
+    }
+
+    #[test]
+    fn spectrum_is_reduced_to_the_peak_in_each_column_up_to_2500hz() {
+        // 500Hz bins, from 0 to 6000Hz; ten 250Hz columns take half a bin each.
+        let magnitudes: Vec<f32> = (0..13).map(|bin| bin as f32).collect();
+        let expected = vec![0.0, 0.0, 1.0, 1.0, 2.0, 2.0, 3.0, 3.0, 4.0, 4.0];
+        assert_that!(reduce_spectrum(&magnitudes, 12000, 10), equal_to(expected));
+
+        // Two 1250Hz columns take the peak of the three bins they overlap.
+        let mut magnitudes = vec![0.0; 13];
+        magnitudes[1] = 5.0;
+        magnitudes[3] = 7.0;
+        assert_that!(reduce_spectrum(&magnitudes, 12000, 2), equal_to(vec![5.0, 7.0]));
+    }
+
+    #[test]
+    fn spectra_are_added_at_the_top_and_scroll_down() {
+        let mut waterfall = Waterfall::new(2, 3, ColourMap::Greyscale, 0.0, 1.0);
+        // 60dB is full scale, 30dB is half.
+        waterfall.add_spectrum(&[1000.0, 1.0]);
+        waterfall.add_spectrum(&[31.6228, 1000.0]);
+        assert_that!(row(&waterfall, 0), equal_to(vec![[128, 128, 128], [255, 255, 255]]));
+        assert_that!(row(&waterfall, 1), equal_to(vec![[255, 255, 255], [0, 0, 0]]));
+        assert_that!(row(&waterfall, 2), equal_to(vec![[0, 0, 0], [0, 0, 0]]));
+
+        waterfall.add_spectrum(&[1.0, 1.0]);
+        waterfall.add_spectrum(&[1.0, 1.0]);
+        assert_that!(row(&waterfall, 2), equal_to(vec![[128, 128, 128], [255, 255, 255]]));
+    }
+
+    #[test]
+    fn gain_and_contrast_scale_the_level() {
+        let mut waterfall = Waterfall::new(1, 1, ColourMap::Greyscale, 0.0, 1.0);
+        waterfall.add_spectrum(&[1.0]);
+        assert_that!(row(&waterfall, 0), equal_to(vec![[0, 0, 0]]));
+        waterfall.set_gain(30.0);
+        waterfall.add_spectrum(&[1.0]);
+        assert_that!(row(&waterfall, 0), equal_to(vec![[128, 128, 128]]));
+        waterfall.set_contrast(2.0);
+        waterfall.add_spectrum(&[1.0]);
+        assert_that!(row(&waterfall, 0), equal_to(vec![[255, 255, 255]]));
+        waterfall.set_colour_map(ColourMap::Heat);
+        waterfall.set_contrast(0.5);
+        waterfall.add_spectrum(&[1.0]);
+        assert_that!(row(&waterfall, 0), equal_to(vec![[150, 0, 0]]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn spectrum_must_be_the_width_of_the_waterfall() {
+        let mut waterfall = Waterfall::new(4, 3, ColourMap::Blue, 30.0, 1.0);
+        waterfall.add_spectrum(&[1.0, 2.0]);
+    }
+
+    #[test]
+    fn observed_spectra_are_sent_to_the_gui_reduced_to_its_width() {
+        let (gui_input_tx, gui_input_rx) = sync_channel::<GUIInputMessage>(16);
+        let mut fft_observer = FFTingBufferObserver::new();
+        fft_observer.add_observer(Arc::new(WaterfallObserver::new(Arc::new(gui_input_tx), 12000, 1000)) as Arc<dyn Observer<ObservableFrequencySlice>>);
+        let slice: Vec<f32> = (0..OBSERVABLE_BUFFER_SLICE_SIZE).map(|n| (2.0 * PI * 1000.0 * n as f32 / 12000.0).sin()).collect();
+        fft_observer.on_notify(&ObservableBufferSlice { slice });
+
+        match gui_input_rx.try_recv().unwrap() {
+            GUIInputMessage::AddWaterfallSpectrum(spectrum) => {
+                assert_that!(spectrum.len(), equal_to(1000));
+                // 2.5Hz columns; 1000Hz is in column 400.
+                let peak_column = (0..spectrum.len()).fold(0, |peak, column| if spectrum[column] > spectrum[peak] { column } else { peak });
+                assert_that!(peak_column, equal_to(400));
+            }
+            _ => panic!("Expecting a waterfall spectrum"),
+        }
+    }
+}
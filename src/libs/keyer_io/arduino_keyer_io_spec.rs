@@ -69,6 +69,14 @@ impl SerialIO for FakeSerialIO {
     fn flush(&mut self) -> io::Result<()> {
         return Ok(())
     }
+
+    fn set_request_to_send(&mut self, _level: bool) -> io::Result<()> {
+        return Ok(())
+    }
+
+    fn set_data_terminal_ready(&mut self, _level: bool) -> io::Result<()> {
+        return Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -1,7 +1,9 @@
 pub mod application;
 pub mod audio;
 pub mod buffer_pool;
+pub mod cat;
 pub mod channel_codec;
+pub mod channel_sim;
 pub mod config_dir;
 pub mod config_file;
 pub mod conversion;
@@ -1 +1,4 @@
-pub mod playback;
\ No newline at end of file
+pub mod playback;
+pub mod receive_filter;
+#[cfg(test)]
+pub mod recording_player;
@@ -8,6 +8,7 @@ use syncbox::{ScheduledThreadPool, Task};
 use crate::libs::application::application::BusOutput;
 use crate::libs::audio::tone_generator::{KeyingEventToneChannel, ToneGenerator};
 use crate::libs::keyer_io::keyer_io::{KeyerEdgeDurationMs, KeyingEvent, KeyingTimedEvent};
+use crate::libs::playback::receive_filter::{ReceiveFilter, ReceiveFiltering};
 use crate::libs::source_codec::keying_timing::{DefaultKeyingTiming, KeyingTiming};
 use crate::libs::source_codec::source_encoding::{CallsignHash, Frame};
 use crate::libs::util::util::get_epoch_ms;
@@ -38,6 +39,7 @@ pub struct Playback {
     tone_generator: Arc<Mutex<ToneGenerator>>,
     scheduled_thread_pool: Arc<ScheduledThreadPool>,
     output_tx: Arc<Mutex<Option<Arc<Mutex<Bus<KeyingEventToneChannel>>>>>>,
+    receive_filter: ReceiveFilter,
 }
 
 const BODGE_HACK_FIRST_FRAME_PLAYBACK_DELAY_MS: u32 = 1000;
@@ -74,6 +76,7 @@ impl Playback {
             tone_generator: arc_tone_generator,
             scheduled_thread_pool: arc_scheduled_thread_pool,
             output_tx: output_tx_holder,
+            receive_filter: ReceiveFilter::default(),
         }
     }
 
@@ -95,13 +98,17 @@ impl Playback {
         let key = StationIdentifier { callsign_hash, audio_offset };
         if !self.playback_state.contains_key(&key) {
             debug!("New state for {:?}", key);
+            let mut tone_generator = self.tone_generator.lock().unwrap();
+            let tone_generator_channel = tone_generator.allocate_channel(audio_offset);
+            tone_generator.set_in_filter_bandpass(tone_generator_channel, self.receive_filter.passes(callsign_hash, audio_offset));
+            drop(tone_generator);
             let new_details = StationDetails {
                 // frames: vec![],
                 timing: None,
                 next_playback_schedule_time: 0,
                 last_playback_end_epoch_ms: start_time,
                 current_polarity: true,
-                tone_generator_channel: self.tone_generator.lock().unwrap().allocate_channel(audio_offset),
+                tone_generator_channel,
                 last_play_call_epoch_ms_for_channel_expiry: 0, // will be updated below...
                 send_start: true,
             };
@@ -135,6 +142,7 @@ impl Playback {
                                 Frame::CallsignMetadata { .. } => {}
                                 Frame::CallsignHashMetadata { .. } => {}
                                 Frame::LocatorMetadata { .. } => {}
+                                Frame::PowerMetadata { .. } => {}
                                 Frame::KeyingPerfectDit => {
                                     match &details.timing {
                                         None => { warn!("No KeyingTiming set before {:?}", frame) }
@@ -339,6 +347,32 @@ impl Playback {
     }
 }
 
+// Stations that do not pass the filter are still played, so that their timing is kept, but their
+// ToneGenerator channels are silent.
+impl ReceiveFiltering for Playback {
+    fn set_receive_filter(&mut self, receive_filter: ReceiveFilter) {
+        info!("Setting receive filter to {:?}", receive_filter);
+        let mut tone_generator = self.tone_generator.lock().unwrap();
+        for station in self.playback_state.iter() {
+            let in_bandpass = receive_filter.passes(station.key().callsign_hash, station.key().audio_offset);
+            tone_generator.set_in_filter_bandpass(station.value().tone_generator_channel, in_bandpass);
+        }
+        self.receive_filter = receive_filter;
+    }
+}
+
+// Plays the frames decoded from a station's blocks. The receiver's StationDecoders play through
+// this, so that they can be tested without a ToneGenerator.
+pub trait FramePlayer {
+    fn play(&mut self, decode: Result<Vec<Frame>, Box<dyn Error>>, callsign_hash: CallsignHash, audio_offset: u16);
+}
+
+impl FramePlayer for Playback {
+    fn play(&mut self, decode: Result<Vec<Frame>, Box<dyn Error>>, callsign_hash: CallsignHash, audio_offset: u16) {
+        Playback::play(self, decode, callsign_hash, audio_offset);
+    }
+}
+
 struct TimedPlayback {
     item: KeyingEventToneChannel,
     output_tx: Arc<Mutex<Bus<KeyingEventToneChannel>>>,
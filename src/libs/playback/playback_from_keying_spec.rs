@@ -23,7 +23,7 @@ mod playback_from_keying_spec {
     use crate::libs::playback::playback::Playback;
     use crate::libs::source_codec::source_decoder::SourceDecoder;
     use crate::libs::source_codec::source_encoder::SourceEncoder;
-    use crate::libs::source_codec::source_encoding::SourceEncoding;
+    use crate::libs::source_codec::source_encoding::{CallsignHash, SourceEncoding};
     use crate::libs::util::test_util;
 
     const TEST_SOURCE_ENCODER_BLOCK_SIZE_IN_BITS: usize = 64;
@@ -111,7 +111,7 @@ mod playback_from_keying_spec {
         }
     }
 
-    const CALLSIGN_HASH: u16 = 0x1234u16;
+    const CALLSIGN_HASH: CallsignHash = 0x1234;
     const AUDIO_OFFSET: u16 = 700;
 
     #[rstest]
@@ -18,7 +18,8 @@ mod playback_spec {
     use crate::libs::config_dir::config_dir;
     use crate::libs::config_file::config_file::ConfigurationStore;
     use crate::libs::playback::playback::Playback;
-    use crate::libs::source_codec::source_encoding::Frame;
+    use crate::libs::playback::receive_filter::{ReceiveFilter, ReceiveFiltering};
+    use crate::libs::source_codec::source_encoding::{CallsignHash, Frame};
     use crate::libs::util::test_util;
 
     #[ctor::ctor]
@@ -91,7 +92,7 @@ mod playback_spec {
         }
     }
 
-    const CALLSIGN_HASH: u16 = 0x1234u16;
+    const CALLSIGN_HASH: CallsignHash = 0x1234;
     const AUDIO_OFFSET: u16 = 700;
 
     #[rstest]
@@ -252,5 +253,30 @@ mod playback_spec {
         info!("End of test")
     }
 
-}
+    // Does not need the audio device, so not using the fixture.
+    #[test]
+    #[serial]
+    pub fn receive_filter_silences_the_channels_of_stations_it_does_not_pass() {
+        let terminate = Arc::new(AtomicBool::new(false));
+        let tone_generator = Arc::new(Mutex::new(ToneGenerator::new(600, terminate.clone())));
+        let scheduled_thread_pool = Arc::new(syncbox::ScheduledThreadPool::single_thread());
+        let mut playback = Playback::new(terminate.clone(), scheduled_thread_pool, tone_generator.clone());
+        let frame = vec![Frame::WPMPolarity { wpm: 20, polarity: true }, Frame::KeyingPerfectDit];
+
+        playback.play(Ok(frame.clone()), CALLSIGN_HASH, AUDIO_OFFSET);
+        playback.set_receive_filter(ReceiveFilter::bandpass(1000, 2000));
+        assert_eq!(tone_generator.lock().unwrap().test_get_in_filter_bandpass_states(), vec![true, false]);
 
+        // Newly-heard stations are filtered as they are allocated channels.
+        playback.play(Ok(frame.clone()), 0x5678, 1500);
+        playback.play(Ok(frame), 0x9ABC, 2100);
+        assert_eq!(tone_generator.lock().unwrap().test_get_in_filter_bandpass_states(), vec![true, false, true, false]);
+
+        playback.set_receive_filter(ReceiveFilter::Station { callsign_hash: CALLSIGN_HASH, audio_offset: AUDIO_OFFSET });
+        assert_eq!(tone_generator.lock().unwrap().test_get_in_filter_bandpass_states(), vec![true, true, false, false]);
+
+        playback.set_receive_filter(ReceiveFilter::Disabled);
+        assert_eq!(tone_generator.lock().unwrap().test_get_in_filter_bandpass_states(), vec![true, true, true, true]);
+        terminate.store(true, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,56 @@
+/*
+ * The ReceiveFilter decides which received stations are heard. It may be disabled, playing every
+ * station; or pass the stations within a range of audio offsets, as dragged over the waterfall; or
+ * pass a single station, as clicked on the waterfall. A selected station is known by its callsign
+ * hash if it has identified itself, wherever it drifts to; otherwise, by its audio offset.
+ * Playback applies the filter to the ToneGenerator channels of the stations it plays.
+ */
+
+use crate::libs::source_codec::source_encoding::{CallsignHash, UNKNOWN_CALLSIGN_HASH};
+use crate::libs::transmitter::modulate::TONE_SPACING_HZ;
+use crate::libs::transmitter::transmitter::AudioFrequencyHz;
+
+// A station not yet identified is selected if it is within this of the selected audio offset, as
+// the Decoder would consider it the same station.
+const STATION_AUDIO_OFFSET_TOLERANCE_HZ: f32 = 1.5 * TONE_SPACING_HZ;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum ReceiveFilter {
+    #[default]
+    Disabled,
+    Bandpass { low: AudioFrequencyHz, high: AudioFrequencyHz },
+    Station { callsign_hash: CallsignHash, audio_offset: AudioFrequencyHz },
+}
+
+impl ReceiveFilter {
+    // A bandpass between two audio offsets, in either order.
+    pub fn bandpass(from: AudioFrequencyHz, to: AudioFrequencyHz) -> Self {
+        ReceiveFilter::Bandpass { low: from.min(to), high: from.max(to) }
+    }
+
+    // Is the station with the given callsign hash (UNKNOWN_CALLSIGN_HASH if it has not identified
+    // itself) at the given audio offset to be heard?
+    pub fn passes(&self, callsign_hash: CallsignHash, audio_offset: AudioFrequencyHz) -> bool {
+        match self {
+            ReceiveFilter::Disabled => true,
+            ReceiveFilter::Bandpass { low, high } => (*low..=*high).contains(&audio_offset),
+            ReceiveFilter::Station { callsign_hash: selected_hash, audio_offset: selected_offset } => {
+                if *selected_hash != UNKNOWN_CALLSIGN_HASH {
+                    callsign_hash == *selected_hash
+                } else {
+                    (audio_offset as f32 - *selected_offset as f32).abs() < STATION_AUDIO_OFFSET_TOLERANCE_HZ
+                }
+            }
+        }
+    }
+}
+
+// Implemented by Playback, so that the ReceiveFilter can be set from the GUI without a
+// ToneGenerator.
+pub trait ReceiveFiltering {
+    fn set_receive_filter(&mut self, receive_filter: ReceiveFilter);
+}
+
+#[cfg(test)]
+#[path = "./receive_filter_spec.rs"]
+mod receive_filter_spec;
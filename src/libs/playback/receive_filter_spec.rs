@@ -0,0 +1,72 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod receive_filter_spec {
+    use std::env;
+    use hamcrest2::prelude::*;
+    use rstest::*;
+    use crate::libs::playback::receive_filter::ReceiveFilter;
+    use crate::libs::source_codec::source_encoding::UNKNOWN_CALLSIGN_HASH;
+
+    #[ctor::ctor]
+    fn before_each() {
+        env::set_var("RUST_LOG", "debug");
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[ctor::dtor]
+    fn after_each() {}
+
+    #[test]
+    fn default_filter_is_disabled_and_passes_everything() {
+        let filter = ReceiveFilter::default();
+        assert_that!(&filter, equal_to(&ReceiveFilter::Disabled));
+        assert_that!(filter.passes(0, 100), equal_to(true));
+        assert_that!(filter.passes(0x1ABCD, 2400), equal_to(true));
+    }
+
+    #[test]
+    fn bandpass_is_ordered() {
+        assert_that!(ReceiveFilter::bandpass(1500, 1000), equal_to(ReceiveFilter::Bandpass { low: 1000, high: 1500 }));
+        assert_that!(ReceiveFilter::bandpass(1000, 1500), equal_to(ReceiveFilter::Bandpass { low: 1000, high: 1500 }));
+    }
+
+    #[rstest]
+    #[case(999, false)]
+    #[case(1000, true)]
+    #[case(1250, true)]
+    #[case(1500, true)]
+    #[case(1501, false)]
+    fn bandpass_passes_stations_within_its_range(#[case] audio_offset: u16, #[case] passes: bool) {
+        assert_that!(ReceiveFilter::bandpass(1000, 1500).passes(0x1ABCD, audio_offset), equal_to(passes));
+    }
+
+    #[rstest]
+    #[case(0x1ABCD, 700, true)]
+    #[case(0x1ABCD, 1800, true)]
+    #[case(0x2BCDE, 700, false)]
+    #[case(UNKNOWN_CALLSIGN_HASH, 700, false)]
+    fn identified_station_is_selected_by_callsign_hash(#[case] callsign_hash: u32, #[case] audio_offset: u16, #[case] passes: bool) {
+        let filter = ReceiveFilter::Station { callsign_hash: 0x1ABCD, audio_offset: 700 };
+        assert_that!(filter.passes(callsign_hash, audio_offset), equal_to(passes));
+    }
+
+    #[rstest]
+    #[case(UNKNOWN_CALLSIGN_HASH, 700, true)]
+    #[case(UNKNOWN_CALLSIGN_HASH, 709, true)]
+    #[case(0x1ABCD, 691, true)]
+    #[case(UNKNOWN_CALLSIGN_HASH, 710, false)]
+    #[case(UNKNOWN_CALLSIGN_HASH, 1500, false)]
+    fn unidentified_station_is_selected_by_audio_offset(#[case] callsign_hash: u32, #[case] audio_offset: u16, #[case] passes: bool) {
+        let filter = ReceiveFilter::Station { callsign_hash: UNKNOWN_CALLSIGN_HASH, audio_offset: 700 };
+        assert_that!(filter.passes(callsign_hash, audio_offset), equal_to(passes));
+    }
+
+    #[rstest]
+    #[case(0, 1800, true)]
+    #[case(UNKNOWN_CALLSIGN_HASH, 700, false)]
+    fn station_whose_callsign_hashes_to_zero_is_selected_by_callsign_hash(#[case] callsign_hash: u32, #[case] audio_offset: u16, #[case] passes: bool) {
+        let filter = ReceiveFilter::Station { callsign_hash: 0, audio_offset: 700 };
+        assert_that!(filter.passes(callsign_hash, audio_offset), equal_to(passes));
+    }
+}
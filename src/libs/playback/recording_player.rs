@@ -0,0 +1,16 @@
+use std::error::Error;
+use crate::libs::playback::playback::FramePlayer;
+use crate::libs::source_codec::source_encoding::{CallsignHash, Frame};
+
+// A FramePlayer for tests, recording each decode it is given (None if it failed), with the
+// station's callsign hash and audio offset.
+#[derive(Default)]
+pub struct RecordingPlayer {
+    pub plays: Vec<(Option<Vec<Frame>>, CallsignHash, u16)>,
+}
+
+impl FramePlayer for RecordingPlayer {
+    fn play(&mut self, decode: Result<Vec<Frame>, Box<dyn Error>>, callsign_hash: CallsignHash, audio_offset: u16) {
+        self.plays.push((decode.ok(), callsign_hash, audio_offset));
+    }
+}
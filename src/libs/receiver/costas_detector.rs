@@ -0,0 +1,187 @@
+/*
+ * The CostasDetector finds transmissions in the spectra emitted by the FFTingBufferObserver, by
+ * correlating them against the Costas Array that starts every ChannelEncoding block.
+ * Each spectrum is of a symbol period (160ms) of downsampled audio; successive spectra may overlap,
+ * there being spectra_per_symbol of them in each symbol period. For every starting spectrum, and
+ * every FFT bin at which all the tones of a transmission would fit in the spectrum, the array is
+ * scored as the mean, over its symbols, of the share of the power of the 16 tones in that symbol's
+ * spectrum that is in the array's tone. A perfectly received array scores 1, and noise around
+ * 1/16. Since the array has the Costas property, shifted copies of it score poorly, and
+ * transmissions overlapping in time or frequency each give their own peak. Scoring each symbol
+ * relative to its own spectrum, rather than to the energy of the whole array, keeps a strong
+ * transmission from dominating the scores of windows that only partly overlap it. The data symbols
+ * of a strong transmission will still match two or three of the array's tones by chance, giving
+ * weaker candidates within it; these are rejected when they fail to decode.
+ * Scores that are the greatest of their neighbours (one spectrum earlier and later, one bin lower
+ * and higher), and reach the detection threshold, are emitted to observers as candidates.
+ */
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use log::debug;
+use crate::libs::buffer_pool::observable_buffer::OBSERVABLE_BUFFER_SLICE_SIZE;
+use crate::libs::channel_codec::channel_encoding::NUMBER_OF_TONES;
+use crate::libs::channel_codec::costas::{COSTAS_ARRAY, COSTAS_ARRAY_SYMBOLS};
+use crate::libs::patterns::observer::{ConcreteObserverList, Observable, Observer, ObserverList};
+use crate::libs::receiver::fft::ObservableFrequencySlice;
+use crate::libs::transmitter::modulate::TONE_SPACING_HZ;
+use crate::libs::transmitter::transmitter::AudioFrequencyHz;
+
+pub const DEFAULT_DETECTION_THRESHOLD: f32 = 0.25;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CostasCandidate {
+    // The audio offset of the lowest tone.
+    pub audio_offset: AudioFrequencyHz,
+    // The index of the spectrum (counting from the first received by the detector) that starts
+    // with the first symbol of the array.
+    pub time_offset: usize,
+    pub score: f32,
+}
+
+impl Observable for CostasCandidate {
+}
+
+struct DetectorState {
+    // Enough of the most recent spectra to cover the array.
+    spectra: VecDeque<Vec<f32>>,
+    spectra_received: usize,
+    // The scores of each bin, for the most recent starting spectra.
+    score_rows: VecDeque<Vec<f32>>,
+}
+
+pub struct CostasDetector {
+    bin_hz: f32,
+    bins_per_tone: usize,
+    spectra_per_symbol: usize,
+    threshold: f32,
+    // RefCell for interior mutability since the on_notify method does not have &mut self
+    state: RefCell<DetectorState>,
+    observers: ConcreteObserverList<CostasCandidate>,
+}
+
+impl CostasDetector {
+    pub fn new(sample_rate: AudioFrequencyHz, spectra_per_symbol: usize) -> Self {
+        if sample_rate == 0 || spectra_per_symbol == 0 {
+            panic!("No sample rate or spectra per symbol defined for CostasDetector");
+        }
+        // Sample rate is 12000Hz, so each bin is 6.25Hz, one tone.
+        let bin_hz = sample_rate as f32 / OBSERVABLE_BUFFER_SLICE_SIZE as f32;
+        Self {
+            bin_hz,
+            bins_per_tone: ((TONE_SPACING_HZ / bin_hz).round() as usize).max(1),
+            spectra_per_symbol,
+            threshold: DEFAULT_DETECTION_THRESHOLD,
+            state: RefCell::new(DetectorState {
+                spectra: VecDeque::new(),
+                spectra_received: 0,
+                score_rows: VecDeque::new(),
+            }),
+            observers: ConcreteObserverList::new(),
+        }
+    }
+
+    pub fn add_observer(&mut self, observer: Arc<dyn Observer<CostasCandidate>>) {
+        self.observers.register_observer(observer);
+    }
+
+    pub fn set_threshold(&mut self, threshold: f32) {
+        self.threshold = threshold;
+    }
+
+    // The number of spectra from the first symbol of the array to its last, inclusive.
+    fn spectra_per_array(&self) -> usize {
+        (COSTAS_ARRAY_SYMBOLS - 1) * self.spectra_per_symbol + 1
+    }
+
+    // Score the array starting at the first of the spectra, at each bin.
+    fn score_row(&self, spectra: &VecDeque<Vec<f32>>) -> Vec<f32> {
+        let bins = spectra[0].len();
+        let span = (NUMBER_OF_TONES - 1) * self.bins_per_tone;
+        if bins <= span {
+            return vec![];
+        }
+        (0..bins - span)
+            .map(|bin| {
+                let array_tones: f32 = COSTAS_ARRAY.iter().enumerate()
+                    .map(|(symbol, array_tone)| {
+                        let spectrum = &spectra[symbol * self.spectra_per_symbol];
+                        let power = |tone: usize| spectrum[bin + tone * self.bins_per_tone].powi(2);
+                        let all_tones: f32 = (0..NUMBER_OF_TONES).map(power).sum();
+                        if all_tones > 0.0 {
+                            power(*array_tone as usize) / all_tones
+                        } else {
+                            0.0
+                        }
+                    })
+                    .sum();
+                array_tones / COSTAS_ARRAY_SYMBOLS as f32
+            })
+            .collect()
+    }
+
+    // The bins of the row whose score reaches the threshold, and exceeds that of their
+    // neighbours. Equal neighbours earlier in time or lower in frequency take precedence.
+    fn local_maxima(&self, previous: Option<&Vec<f32>>, row: &[f32], next: &[f32]) -> Vec<usize> {
+        (0..row.len())
+            .filter(|bin| {
+                let score = row[*bin];
+                if score < self.threshold {
+                    return false;
+                }
+                let lower = bin.saturating_sub(1);
+                let higher = (bin + 1).min(row.len() - 1);
+                let previous_ok = previous.map_or(true, |previous| previous[lower..=higher].iter().all(|s| score > *s));
+                let next_ok = next[lower..=higher].iter().all(|s| score >= *s);
+                let lower_ok = *bin == lower || score > row[lower];
+                let higher_ok = *bin == higher || score >= row[higher];
+                previous_ok && next_ok && lower_ok && higher_ok
+            })
+            .collect()
+    }
+}
+
+impl Observer<ObservableFrequencySlice> for CostasDetector {
+    fn on_notify(&self, frequency_slice: &ObservableFrequencySlice) {
+        let candidates = {
+            let mut state = self.state.borrow_mut();
+            state.spectra.push_back(frequency_slice.magnitudes().to_vec());
+            state.spectra_received += 1;
+            if state.spectra.len() > self.spectra_per_array() {
+                state.spectra.pop_front();
+            }
+            if state.spectra.len() < self.spectra_per_array() {
+                return;
+            }
+            let row = self.score_row(&state.spectra);
+            state.score_rows.push_back(row);
+            if state.score_rows.len() > 3 {
+                state.score_rows.pop_front();
+            }
+            if state.score_rows.len() < 2 {
+                return;
+            }
+            // The row before the newest can now be compared with its neighbours.
+            let rows = state.score_rows.len();
+            let previous = if rows == 3 { state.score_rows.front() } else { None };
+            let row = &state.score_rows[rows - 2];
+            let time_offset = state.spectra_received - self.spectra_per_array() - 1;
+            self.local_maxima(previous, row, &state.score_rows[rows - 1]).iter()
+                .map(|bin| CostasCandidate {
+                    audio_offset: (*bin as f32 * self.bin_hz).round() as AudioFrequencyHz,
+                    time_offset,
+                    score: row[*bin],
+                })
+                .collect::<Vec<CostasCandidate>>()
+        };
+        for candidate in candidates {
+            debug!("Costas Array detected: {:?}", candidate);
+            self.observers.notify_observers(&candidate);
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./costas_detector_spec.rs"]
+mod costas_detector_spec;
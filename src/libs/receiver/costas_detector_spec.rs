@@ -0,0 +1,155 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod costas_detector_spec {
+    use std::cell::RefCell;
+    use std::env;
+    use std::sync::Arc;
+    use hamcrest2::prelude::*;
+    use log::info;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use crate::libs::buffer_pool::observable_buffer::{OBSERVABLE_BUFFER_SLICE_SIZE, ObservableBufferSlice};
+    use crate::libs::channel_codec::sample_channel_encoding::sample_channel_encoding;
+    use crate::libs::patterns::observer::Observer;
+    use crate::libs::receiver::costas_detector::{CostasCandidate, CostasDetector};
+    use crate::libs::receiver::fft::{FFTingBufferObserver, ObservableFrequencySlice};
+    use crate::libs::transmitter::modulate::GfskModulator;
+    use crate::libs::transmitter::transmitter::AudioFrequencyHz;
+    use crate::libs::util::test_util::{awgn, mix, temp_filename};
+    use crate::libs::wav::wav::{read_waveform_file, write_waveform_file};
+
+    const SAMPLE_RATE: AudioFrequencyHz = 48000;
+    const DOWNSAMPLED_SAMPLE_RATE: AudioFrequencyHz = 12000;
+    const DOWNSAMPLE_FACTOR: usize = 4;
+
+    #[ctor::ctor]
+    fn before_each() {
+        env::set_var("RUST_LOG", "info");
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[ctor::dtor]
+    fn after_each() {}
+
+    struct CandidateCollector {
+        candidates: RefCell<Vec<CostasCandidate>>,
+    }
+
+    impl Observer<CostasCandidate> for CandidateCollector {
+        fn on_notify(&self, candidate: &CostasCandidate) {
+            self.candidates.borrow_mut().push(candidate.clone());
+        }
+    }
+
+    // Pass the downsampled audio through the FFT observer to the detector, in 160ms slices, each
+    // starting a hop after the last. Returns the detector's candidates, strongest first.
+    fn detect(downsampled: &[f32], spectra_per_symbol: usize) -> Vec<CostasCandidate> {
+        let collector = Arc::new(CandidateCollector { candidates: RefCell::new(vec![]) });
+        let mut detector = CostasDetector::new(DOWNSAMPLED_SAMPLE_RATE, spectra_per_symbol);
+        detector.add_observer(collector.clone() as Arc<dyn Observer<CostasCandidate>>);
+        let mut fft_observer = FFTingBufferObserver::new();
+        fft_observer.add_observer(Arc::new(detector) as Arc<dyn Observer<ObservableFrequencySlice>>);
+
+        let hop = OBSERVABLE_BUFFER_SLICE_SIZE / spectra_per_symbol;
+        let mut start = 0;
+        while start + OBSERVABLE_BUFFER_SLICE_SIZE <= downsampled.len() {
+            let slice = ObservableBufferSlice { slice: downsampled[start..start + OBSERVABLE_BUFFER_SLICE_SIZE].to_vec() };
+            fft_observer.on_notify(&slice);
+            start += hop;
+        }
+        let mut candidates = collector.candidates.borrow().clone();
+        candidates.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        for candidate in &candidates {
+            info!("{:?}", candidate);
+        }
+        candidates
+    }
+
+    // The sample channel encoding, modulated to unit amplitude, ramped up and down. Returns the
+    // waveform, and the sample at which its first symbol starts.
+    fn modulated_sample_channel_encoding(sample_rate: AudioFrequencyHz, audio_offset: AudioFrequencyHz) -> (Vec<f32>, usize) {
+        let symbols = sample_channel_encoding().block;
+        let mut modulator = GfskModulator::new(sample_rate);
+        let mut waveform = vec![0.0; modulator.number_of_samples(symbols.len(), true, true)];
+        modulator.modulate(audio_offset, &symbols, &mut waveform, true, true);
+        (waveform, modulator.ramp_samples_per_symbol())
+    }
+
+    // Average each group of samples: a crude low-pass filter, adequate for the audio passband.
+    fn downsample(samples: &[f32]) -> Vec<f32> {
+        samples.chunks_exact(DOWNSAMPLE_FACTOR).map(|chunk| chunk.iter().sum::<f32>() / DOWNSAMPLE_FACTOR as f32).collect()
+    }
+
+    #[test]
+    #[should_panic]
+    fn spectra_per_symbol_must_be_given() {
+        CostasDetector::new(DOWNSAMPLED_SAMPLE_RATE, 0);
+    }
+
+    #[test]
+    fn noise_is_not_detected() {
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        let candidates = detect(&awgn(15 * DOWNSAMPLED_SAMPLE_RATE as usize, DOWNSAMPLED_SAMPLE_RATE, 1.0, &mut rng), 4);
+        assert_that!(candidates.len(), equal_to(0));
+    }
+
+    #[test]
+    fn strong_transmission_is_detected_at_its_offsets() {
+        let (waveform, first_symbol) = modulated_sample_channel_encoding(DOWNSAMPLED_SAMPLE_RATE, 1000);
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        let mut downsampled = awgn(15 * DOWNSAMPLED_SAMPLE_RATE as usize, DOWNSAMPLED_SAMPLE_RATE, 1.0, &mut rng);
+        // Align the first symbol with the fifth spectrum.
+        mix(&mut downsampled, &waveform, 5 * OBSERVABLE_BUFFER_SLICE_SIZE - first_symbol, 10.0);
+        let candidates = detect(&downsampled, 1);
+        assert_that!(candidates.len(), greater_than_or_equal_to(1));
+        assert_that!(candidates[0].audio_offset, equal_to(1000));
+        assert_that!(candidates[0].time_offset, equal_to(5));
+        assert_that!(candidates[0].score, greater_than(0.95));
+        // Chance matches with the data symbols are much weaker.
+        for candidate in &candidates[1..] {
+            assert_that!(candidate.score, less_than(0.5));
+        }
+    }
+
+    struct Station {
+        audio_offset: AudioFrequencyHz,
+        start_seconds: f32,
+        snr_db: f32,
+    }
+
+    #[test]
+    fn overlapping_stations_in_a_wav_file_are_detected() {
+        // The second overlaps the first in time, and (by half its bandwidth) in frequency.
+        let stations = [
+            Station { audio_offset: 600, start_seconds: 0.5, snr_db: -8.0 },
+            Station { audio_offset: 650, start_seconds: 1.3, snr_db: -12.0 },
+            Station { audio_offset: 1400, start_seconds: 0.7, snr_db: -14.0 },
+            Station { audio_offset: 2000, start_seconds: 3.05, snr_db: -10.0 },
+        ];
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        let mut mixture = awgn(16 * SAMPLE_RATE as usize, SAMPLE_RATE, 1.0, &mut rng);
+        let mut first_symbol_seconds = vec![];
+        for station in &stations {
+            let (waveform, first_symbol) = modulated_sample_channel_encoding(SAMPLE_RATE, station.audio_offset);
+            let start = (station.start_seconds * SAMPLE_RATE as f32) as usize;
+            mix(&mut mixture, &waveform, start, station.snr_db);
+            first_symbol_seconds.push((start + first_symbol) as f32 / SAMPLE_RATE as f32);
+        }
+        let filename = temp_filename("costas-detector-stations.wav");
+        write_waveform_file(mixture, &filename).unwrap();
+        let received = read_waveform_file(&filename).unwrap();
+
+        let spectra_per_symbol = 4;
+        let spectrum_seconds = (OBSERVABLE_BUFFER_SLICE_SIZE / spectra_per_symbol) as f32 / DOWNSAMPLED_SAMPLE_RATE as f32;
+        let candidates = detect(&downsample(&received), spectra_per_symbol);
+        assert_that!(candidates.len(), greater_than_or_equal_to(stations.len()));
+        // The strongest candidates are the stations, at their offsets, to a bin and a spectrum.
+        for (station, first_symbol_seconds) in stations.iter().zip(first_symbol_seconds) {
+            let found = candidates[0..stations.len()].iter().find(|candidate|
+                (candidate.audio_offset as f32 - station.audio_offset as f32).abs() <= 6.25 &&
+                    (candidate.time_offset as f32 * spectrum_seconds - first_symbol_seconds).abs() <= spectrum_seconds);
+            assert_that!(found.is_some(), equal_to(true));
+        }
+    }
+}
@@ -0,0 +1,84 @@
+/*
+ * The Decimator reduces the sample rate of the received audio by an integer factor, 48000Hz to
+ * 12000Hz in the Receiver. Before discarding samples, the audio is low-pass filtered so that
+ * frequencies above the new Nyquist frequency do not alias into the audio passband. The filter is
+ * a Blackman-windowed sinc, cut off at 80% of the new Nyquist frequency: flat across the audio
+ * passband (to 3kHz or so), and attenuating by more than 70dB the frequencies that would alias
+ * into it. It is only evaluated at the samples that are kept.
+ * Samples are added one at a time, as they arrive in the audio callback, so the Decimator does no
+ * allocation after construction.
+ */
+
+use std::f32::consts::PI;
+
+// The filter's length, per unit of the decimation factor.
+const TAPS_PER_FACTOR: usize = 32;
+
+// The cutoff frequency, as a fraction of the decimated Nyquist frequency.
+const CUTOFF: f32 = 0.8;
+
+pub struct Decimator {
+    factor: usize,
+    taps: Vec<f32>,
+    // The most recent samples, a ring buffer of the filter's length.
+    history: Vec<f32>,
+    next: usize,
+    // Samples added since the last output.
+    phase: usize,
+}
+
+impl Decimator {
+    pub fn new(factor: usize) -> Self {
+        if factor == 0 {
+            panic!("No decimation factor defined for Decimator");
+        }
+        let length = TAPS_PER_FACTOR * factor - 1;
+        let cutoff = CUTOFF * 0.5 / factor as f32; // cycles per input sample
+        let middle = (length - 1) as f32 / 2.0;
+        let mut taps: Vec<f32> = (0..length)
+            .map(|n| {
+                let t = n as f32 - middle;
+                let sinc = if t == 0.0 { 2.0 * cutoff } else { (2.0 * PI * cutoff * t).sin() / (PI * t) };
+                let x = 2.0 * PI * n as f32 / (length - 1) as f32;
+                let blackman = 0.42 - 0.5 * x.cos() + 0.08 * (2.0 * x).cos();
+                sinc * blackman
+            })
+            .collect();
+        // Unity gain at DC.
+        let sum: f32 = taps.iter().sum();
+        taps.iter_mut().for_each(|tap| *tap /= sum);
+        Self {
+            factor,
+            taps,
+            history: vec![0.0; length],
+            next: 0,
+            phase: 0,
+        }
+    }
+
+    pub fn factor(&self) -> usize {
+        self.factor
+    }
+
+    // Add a sample; every factor samples, returns the filtered, decimated sample.
+    pub fn add_sample(&mut self, sample: f32) -> Option<f32> {
+        self.history[self.next] = sample;
+        self.next = (self.next + 1) % self.history.len();
+        self.phase += 1;
+        if self.phase < self.factor {
+            return None;
+        }
+        self.phase = 0;
+        // The oldest sample is at next; the taps are symmetric, so can be applied in either order.
+        let (newest, oldest) = self.history.split_at(self.next);
+        let filtered = oldest.iter().chain(newest.iter())
+            .zip(&self.taps)
+            .map(|(sample, tap)| sample * tap)
+            .sum();
+        Some(filtered)
+    }
+}
+
+#[cfg(test)]
+#[path = "./decimator_spec.rs"]
+mod decimator_spec;
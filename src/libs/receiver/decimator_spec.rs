@@ -0,0 +1,82 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod decimator_spec {
+    use std::env;
+    use std::f64::consts::PI;
+    use hamcrest2::prelude::*;
+    use rstest::*;
+    use crate::libs::receiver::decimator::Decimator;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+    const FACTOR: usize = 4;
+
+    #[ctor::ctor]
+    fn before_each() {
+        env::set_var("RUST_LOG", "debug");
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[ctor::dtor]
+    fn after_each() {}
+
+    // The phase is computed in f64, since f32 loses precision over a second of samples.
+    fn sine(frequency: f32, samples: usize) -> Vec<f32> {
+        (0..samples).map(|n| (2.0 * PI * frequency as f64 * n as f64 / SAMPLE_RATE as f64).sin() as f32).collect()
+    }
+
+    fn decimate(decimator: &mut Decimator, samples: &[f32]) -> Vec<f32> {
+        samples.iter().filter_map(|sample| decimator.add_sample(*sample)).collect()
+    }
+
+    // The amplitude of the decimated output, once the filter has filled.
+    fn output_amplitude(frequency: f32) -> f32 {
+        let output = decimate(&mut Decimator::new(FACTOR), &sine(frequency, 48000));
+        output[100..].iter().fold(0.0, |max, sample| sample.abs().max(max))
+    }
+
+    #[test]
+    #[should_panic]
+    fn factor_must_be_given() {
+        Decimator::new(0);
+    }
+
+    #[test]
+    fn one_sample_is_output_per_factor_added() {
+        let mut decimator = Decimator::new(FACTOR);
+        assert_that!(decimator.factor(), equal_to(FACTOR));
+        let outputs: Vec<bool> = (0..8).map(|_| decimator.add_sample(1.0).is_some()).collect();
+        assert_that!(outputs, equal_to(vec![false, false, false, true, false, false, false, true]));
+        assert_that!(decimate(&mut decimator, &[0.0; 4000]).len(), equal_to(1000));
+    }
+
+    #[rstest]
+    #[case(300.0)]
+    #[case(1000.0)]
+    #[case(3000.0)]
+    fn passband_is_preserved(#[case] frequency: f32) {
+        let amplitude = output_amplitude(frequency);
+        assert_that!(amplitude, greater_than(0.98));
+        assert_that!(amplitude, less_than(1.02));
+    }
+
+    #[rstest]
+    // The first three would alias to 3000, 2000 and 500Hz.
+    #[case(9000.0)]
+    #[case(10000.0)]
+    #[case(11500.0)]
+    #[case(20000.0)]
+    fn frequencies_that_would_alias_into_the_passband_are_removed(#[case] frequency: f32) {
+        // 70dB down
+        assert_that!(output_amplitude(frequency), less_than(0.0003));
+    }
+
+    #[test]
+    fn decimation_is_continuous_across_chunks() {
+        let input = sine(1234.0, 10000);
+        let whole = decimate(&mut Decimator::new(FACTOR), &input);
+        let mut decimator = Decimator::new(FACTOR);
+        let chunked: Vec<f32> = input.chunks(64).flat_map(|chunk| decimate(&mut decimator, chunk)).collect();
+        assert_that!(chunked, equal_to(whole));
+    }
+}
@@ -0,0 +1,278 @@
+/*
+ * The Decoder receives downsampled audio, and finds and decodes the transmissions in it.
+ * Each symbol period of audio, starting every hop (a fraction of a symbol period), is passed
+ * through the FFTingBufferObserver to the CostasDetector. Each Costas Array detected starts a block
+ * of a station's transmission: it is queued with the StationDecoder at its audio offset, and if
+ * there is no such StationDecoder, one is added.
+ * Once all the audio of a station's next queued block has been received, the relevant subset of
+ * the audio - the block, and half a hop either side of its detected start - is given to its
+ * StationDecoder. The blocks of all stations that are ready are demodulated and channel-decoded in
+ * parallel, then source-decoded and played back in turn, since they share the Playback.
+ * StationDecoders that have retired are then removed. If the Decoder has been given the GUI's
+ * input, it tells the GUI of each Costas Array queued, each station after its blocks are played,
+ * and each station retired, so they can be marked on the waterfall; if it has been given the
+ * DialFrequency, each station is given its radio frequency. Audio is retained until no queued
+ * block or Costas Array yet to be detected could need it.
+ */
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::SyncSender;
+use std::thread;
+use log::{debug, info};
+use crate::libs::cat::dial_frequency::DialFrequency;
+use crate::libs::buffer_pool::observable_buffer::{OBSERVABLE_BUFFER_SLICE_SIZE, ObservableBufferSlice};
+use crate::libs::channel_codec::channel_decoder::ChannelDecodeError;
+use crate::libs::channel_codec::costas::COSTAS_ARRAY_SYMBOLS;
+use crate::libs::gui::gui_facades::{GUIInputMessage, StationDetails};
+use crate::libs::patterns::observer::Observer;
+use crate::libs::playback::playback::FramePlayer;
+use crate::libs::receiver::costas_detector::{CostasCandidate, CostasDetector};
+use crate::libs::receiver::demodulate::GfskDemodulator;
+use crate::libs::receiver::fft::{FFTingBufferObserver, ObservableFrequencySlice};
+use crate::libs::receiver::station_decoder::{QueuedBlock, StationDecoder};
+use crate::libs::source_codec::callsign_hash_cache::CallsignHashCache;
+use crate::libs::source_codec::source_encoding::{SourceEncoding, UNKNOWN_CALLSIGN_HASH};
+use crate::libs::transmitter::modulate::TONE_SPACING_HZ;
+use crate::libs::transmitter::transmitter::AudioFrequencyHz;
+use crate::libs::util::util::get_epoch_ms;
+
+// A station that has not been heard for this long is retired, as its Playback channel would be.
+pub const STATION_SILENCE_SECONDS: f32 = 20.0;
+
+// Candidates collected from the CostasDetector, for the Decoder to act on after each spectrum.
+struct CandidateQueue {
+    candidates: Mutex<VecDeque<CostasCandidate>>,
+}
+
+impl Observer<CostasCandidate> for CandidateQueue {
+    fn on_notify(&self, candidate: &CostasCandidate) {
+        self.candidates.lock().unwrap().push_back(candidate.clone());
+    }
+}
+
+pub struct Decoder {
+    hop: usize,
+    spectra_per_array: usize,
+    silence_samples: usize,
+    fft_observer: FFTingBufferObserver,
+    candidate_queue: Arc<CandidateQueue>,
+    demodulator: Arc<GfskDemodulator>,
+    // The audio retained, and the index (from the first sample received) of its first sample.
+    samples: Vec<f32>,
+    samples_start: usize,
+    samples_received: usize,
+    // The index of the first sample of the next spectrum.
+    next_spectrum_start: usize,
+    station_decoders: Vec<StationDecoder>,
+    callsign_hash_cache: CallsignHashCache,
+    player: Arc<Mutex<dyn FramePlayer>>,
+    gui_input: Option<Arc<SyncSender<GUIInputMessage>>>,
+    dial_frequency: Option<Arc<DialFrequency>>,
+}
+
+impl Decoder {
+    pub fn new(sample_rate: AudioFrequencyHz, spectra_per_symbol: usize, player: Arc<Mutex<dyn FramePlayer>>) -> Self {
+        let candidate_queue = Arc::new(CandidateQueue { candidates: Mutex::new(VecDeque::new()) });
+        let mut costas_detector = CostasDetector::new(sample_rate, spectra_per_symbol);
+        costas_detector.add_observer(candidate_queue.clone() as Arc<dyn Observer<CostasCandidate>>);
+        let mut fft_observer = FFTingBufferObserver::new();
+        // The detector is only notified on the Decoder's thread.
+        #[allow(clippy::arc_with_non_send_sync)]
+        let costas_detector = Arc::new(costas_detector);
+        fft_observer.add_observer(costas_detector as Arc<dyn Observer<ObservableFrequencySlice>>);
+        Self {
+            hop: OBSERVABLE_BUFFER_SLICE_SIZE / spectra_per_symbol,
+            spectra_per_array: (COSTAS_ARRAY_SYMBOLS - 1) * spectra_per_symbol + 1,
+            silence_samples: (STATION_SILENCE_SECONDS * sample_rate as f32) as usize,
+            fft_observer,
+            candidate_queue,
+            demodulator: Arc::new(GfskDemodulator::new(sample_rate)),
+            samples: vec![],
+            samples_start: 0,
+            samples_received: 0,
+            next_spectrum_start: 0,
+            station_decoders: vec![],
+            callsign_hash_cache: CallsignHashCache::default(),
+            player,
+            gui_input: None,
+            dial_frequency: None,
+        }
+    }
+
+    pub fn set_gui_input(&mut self, gui_input: Arc<SyncSender<GUIInputMessage>>) {
+        self.gui_input = Some(gui_input);
+    }
+
+    pub fn set_dial_frequency(&mut self, dial_frequency: Arc<DialFrequency>) {
+        self.dial_frequency = Some(dial_frequency);
+    }
+
+    // If the GUI has not kept up, markers are dropped rather than holding up the Decoder.
+    fn send_to_gui(&self, message: GUIInputMessage) {
+        if let Some(gui_input) = &self.gui_input {
+            let _ = gui_input.try_send(message);
+        }
+    }
+
+    // The spectra the Costas Arrays are detected in can also be observed, e.g. by the waterfall.
+    pub fn add_spectrum_observer(&mut self, observer: Arc<dyn Observer<ObservableFrequencySlice>>) {
+        self.fft_observer.add_observer(observer);
+    }
+
+    // The audio offsets of the stations currently being decoded.
+    pub fn station_audio_offsets(&self) -> Vec<AudioFrequencyHz> {
+        self.station_decoders.iter().map(StationDecoder::audio_offset).collect()
+    }
+
+    pub fn add_samples(&mut self, samples: &[f32]) {
+        self.samples.extend_from_slice(samples);
+        self.samples_received += samples.len();
+
+        while self.next_spectrum_start + OBSERVABLE_BUFFER_SLICE_SIZE <= self.samples_received {
+            let from = self.next_spectrum_start - self.samples_start;
+            let slice = ObservableBufferSlice { slice: self.samples[from..from + OBSERVABLE_BUFFER_SLICE_SIZE].to_vec() };
+            self.fft_observer.on_notify(&slice);
+            self.next_spectrum_start += self.hop;
+            self.queue_candidates();
+        }
+
+        self.decode_ready_blocks();
+        self.retire_silent_stations();
+        self.discard_unneeded_samples();
+    }
+
+    fn queue_candidates(&mut self) {
+        let candidates: Vec<CostasCandidate> = self.candidate_queue.candidates.lock().unwrap().drain(..).collect();
+        for candidate in candidates {
+            // The detector scores the whole spectrum; the demodulator needs room either side of
+            // the tones, so cannot demodulate the lowest and highest candidates.
+            if !self.demodulator.audio_offset_in_range(candidate.audio_offset) {
+                debug!("Ignoring Costas Array at {} Hz, out of the demodulator's range", candidate.audio_offset);
+                continue;
+            }
+            let block = QueuedBlock { start_sample: candidate.time_offset * self.hop, audio_offset: candidate.audio_offset };
+            let station = self.station_decoders.iter_mut()
+                .find(|station| (station.audio_offset() as f32 - candidate.audio_offset as f32).abs() < 1.5 * TONE_SPACING_HZ);
+            let queued_audio_offset = match station {
+                Some(station) => {
+                    station.queue_block(block).then(|| station.audio_offset())
+                }
+                None => {
+                    debug!("Costas Array detected at {} Hz, sample {}, score {}", candidate.audio_offset, block.start_sample, candidate.score);
+                    let mut station = StationDecoder::new(candidate.audio_offset, block.start_sample, self.demodulator.clone(), self.silence_samples);
+                    station.queue_block(block);
+                    self.station_decoders.push(station);
+                    Some(candidate.audio_offset)
+                }
+            };
+            if let Some(audio_offset) = queued_audio_offset {
+                self.send_to_gui(GUIInputMessage::AddCostasArray(audio_offset));
+            }
+        }
+    }
+
+    // The range of samples (from the first received) to be decoded for a block: the block, and
+    // half a hop either side of its detected start.
+    fn block_sample_range(&self, block: &QueuedBlock, block_samples: usize) -> (usize, usize) {
+        let margin = self.hop / 2;
+        (block.start_sample.saturating_sub(margin).max(self.samples_start), block.start_sample + block_samples + margin)
+    }
+
+    fn decode_ready_blocks(&mut self) {
+        loop {
+            let ready: Vec<(usize, QueuedBlock, (usize, usize))> = self.station_decoders.iter().enumerate()
+                .filter_map(|(index, station)| {
+                    station.next_queued_block()
+                        .map(|block| (index, block, self.block_sample_range(&block, station.block_samples())))
+                        .filter(|(_, _, (_, end))| *end <= self.samples_received)
+                })
+                .collect();
+            if ready.is_empty() {
+                return;
+            }
+
+            let samples = &self.samples;
+            let samples_start = self.samples_start;
+            let station_decoders = &self.station_decoders;
+            let decodings: Vec<Result<SourceEncoding, ChannelDecodeError>> = thread::scope(|scope| {
+                let handles: Vec<_> = ready.iter()
+                    .map(|(index, block, (start, end))| {
+                        let station = &station_decoders[*index];
+                        let audio = &samples[start - samples_start..end - samples_start];
+                        let audio_offset = block.audio_offset;
+                        scope.spawn(move || station.decode_block(audio, audio_offset))
+                    })
+                    .collect();
+                handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+            });
+
+            let mut player = self.player.lock().unwrap();
+            let mut played = vec![];
+            for ((index, block, _), decoding) in ready.into_iter().zip(decodings) {
+                let station = &mut self.station_decoders[index];
+                station.dequeue_block();
+                if decoding.is_ok() {
+                    played.push(index);
+                }
+                station.play_block(block, decoding, &mut self.callsign_hash_cache, &mut *player);
+            }
+            drop(player);
+            for index in played {
+                let details = self.station_details(&self.station_decoders[index]);
+                self.send_to_gui(GUIInputMessage::AddStation(details));
+            }
+        }
+    }
+
+    fn station_details(&self, station: &StationDecoder) -> StationDetails {
+        let callsign_hash = station.callsign_hash().unwrap_or(UNKNOWN_CALLSIGN_HASH);
+        StationDetails {
+            audio_offset: station.audio_offset(),
+            radio_frequency: self.dial_frequency.as_ref().and_then(|dial_frequency| dial_frequency.station_frequency(station.audio_offset())),
+            callsign_hash,
+            callsign: station.callsign_hash().and_then(|hash| self.callsign_hash_cache.lookup(hash, get_epoch_ms())),
+            locator: station.locator(),
+        }
+    }
+
+    fn retire_silent_stations(&mut self) {
+        let current_sample = self.samples_received;
+        let mut retired_audio_offsets = vec![];
+        self.station_decoders.retain(|station| {
+            let retired = station.is_retired(current_sample);
+            if retired {
+                if station.has_ended() {
+                    info!("Retiring StationDecoder at {} Hz after its transmission ended", station.audio_offset());
+                } else if station.blocks_decoded() > 0 {
+                    info!("Retiring StationDecoder at {} Hz after silence", station.audio_offset());
+                }
+                retired_audio_offsets.push(station.audio_offset());
+            }
+            !retired
+        });
+        for audio_offset in retired_audio_offsets {
+            self.send_to_gui(GUIInputMessage::ClearCostasArray(audio_offset));
+            self.send_to_gui(GUIInputMessage::ClearStation(audio_offset));
+        }
+    }
+
+    fn discard_unneeded_samples(&mut self) {
+        // Costas Arrays yet to be detected start no earlier than this.
+        let spectra_awaiting_detection = self.spectra_per_array + 2;
+        let mut keep_from = self.next_spectrum_start.saturating_sub(spectra_awaiting_detection * self.hop + self.hop / 2);
+        for station in &self.station_decoders {
+            if let Some(block) = station.next_queued_block() {
+                keep_from = keep_from.min(self.block_sample_range(&block, station.block_samples()).0);
+            }
+        }
+        if keep_from > self.samples_start {
+            self.samples.drain(0..keep_from - self.samples_start);
+            self.samples_start = keep_from;
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./decoder_spec.rs"]
+mod decoder_spec;
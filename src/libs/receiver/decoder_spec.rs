@@ -0,0 +1,167 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod decoder_spec {
+    use std::env;
+    use std::sync::{Arc, Mutex};
+    use std::sync::mpsc::sync_channel;
+    use hamcrest2::prelude::*;
+    use crate::libs::cat::dial_frequency::DialFrequency;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use crate::libs::channel_codec::channel_encoder::source_encoding_to_channel_encoding;
+    use crate::libs::gui::gui_facades::{GUIInputMessage, StationDetails};
+    use crate::libs::playback::recording_player::RecordingPlayer;
+    use crate::libs::buffer_pool::observable_buffer::OBSERVABLE_BUFFER_SLICE_SIZE;
+    use crate::libs::channel_codec::channel_encoding::NUMBER_OF_TONES;
+    use crate::libs::channel_codec::costas::COSTAS_ARRAY_SYMBOLS;
+    use crate::libs::receiver::costas_detector::CostasCandidate;
+    use crate::libs::receiver::decoder::{Decoder, STATION_SILENCE_SECONDS};
+    use crate::libs::source_codec::metadata_codec::hash_callsign;
+    use crate::libs::source_codec::source_decoder::SourceDecoder;
+    use crate::libs::source_codec::source_encoding::{CallsignHash, Frame, SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, SourceEncoding};
+    use crate::libs::source_codec::test_encoding_builder::encoded;
+    use crate::libs::transmitter::modulate::{GfskModulator, SYMBOL_PERIOD_SECONDS};
+    use crate::libs::transmitter::transmitter::AudioFrequencyHz;
+    use crate::libs::util::test_util::{awgn, mix};
+
+    const SAMPLE_RATE: AudioFrequencyHz = 12000;
+
+    #[ctor::ctor]
+    fn before_each() {
+        env::set_var("RUST_LOG", "info");
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[ctor::dtor]
+    fn after_each() {}
+
+    // A transmission of a station's blocks, the first carrying its callsign (or callsign hash),
+    // each carrying some keying, and the last ending the keying.
+    fn source_encodings(station_identification: Frame, blocks: usize) -> Vec<SourceEncoding> {
+        (0..blocks)
+            .map(|block| {
+                let mut frames = vec![];
+                if block == 0 {
+                    frames.push(station_identification.clone());
+                }
+                frames.extend(vec![Frame::WPMPolarity { wpm: 20, polarity: true }, Frame::KeyingPerfectDit, Frame::KeyingPerfectDah]);
+                let is_end = block == blocks - 1;
+                if is_end {
+                    frames.push(Frame::KeyingEnd);
+                }
+                SourceEncoding { block: encoded(SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, 20, &frames), is_end, is_metadata_only: false }
+            })
+            .collect()
+    }
+
+    // The blocks, channel-encoded and modulated as one transmission, ramped up and down.
+    fn modulated_transmission(audio_offset: AudioFrequencyHz, source_encodings: &[SourceEncoding]) -> Vec<f32> {
+        let mut modulator = GfskModulator::new(SAMPLE_RATE);
+        let mut waveform = vec![];
+        for (index, source_encoding) in source_encodings.iter().enumerate() {
+            let symbols = source_encoding_to_channel_encoding(source_encoding.clone()).block;
+            let first = index == 0;
+            let last = index == source_encodings.len() - 1;
+            let mut block = vec![0.0; modulator.number_of_samples(symbols.len(), first, last)];
+            modulator.modulate(audio_offset, &symbols, &mut block, first, last);
+            waveform.extend(block);
+        }
+        waveform
+    }
+
+    fn expected_plays(source_encodings: &[SourceEncoding], hash: CallsignHash, audio_offset: u16) -> Vec<(Option<Vec<Frame>>, CallsignHash, u16)> {
+        let source_decoder = SourceDecoder::new(SOURCE_ENCODER_BLOCK_SIZE_IN_BITS);
+        source_encodings.iter()
+            .map(|source_encoding| (source_decoder.source_decode(source_encoding.block.clone()).ok(), hash, audio_offset))
+            .collect()
+    }
+
+    #[test]
+    fn nothing_is_played_from_noise() {
+        let player = Arc::new(Mutex::new(RecordingPlayer::default()));
+        let mut decoder = Decoder::new(SAMPLE_RATE, 4, player.clone());
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        decoder.add_samples(&awgn(20 * SAMPLE_RATE as usize, SAMPLE_RATE, 1.0, &mut rng));
+        assert_that!(decoder.station_audio_offsets().len(), equal_to(0));
+        assert_that!(player.lock().unwrap().plays.len(), equal_to(0));
+    }
+
+    #[test]
+    fn candidates_out_of_the_demodulators_range_are_ignored() {
+        let player = Arc::new(Mutex::new(RecordingPlayer::default()));
+        let mut decoder = Decoder::new(SAMPLE_RATE, 4, player.clone());
+        // The lowest and highest bins the detector scores, with 6.25Hz bins.
+        let top_bin_audio_offset = ((OBSERVABLE_BUFFER_SLICE_SIZE / 2 - (NUMBER_OF_TONES - 1)) as f32 * 6.25) as AudioFrequencyHz;
+        for audio_offset in [0, top_bin_audio_offset, 1000] {
+            decoder.candidate_queue.candidates.lock().unwrap().push_back(CostasCandidate { audio_offset, time_offset: 0, score: 1.0 });
+        }
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        decoder.add_samples(&awgn(OBSERVABLE_BUFFER_SLICE_SIZE, SAMPLE_RATE, 1.0, &mut rng));
+        assert_that!(decoder.station_audio_offsets(), equal_to(vec![1000]));
+
+        // The candidate in range fails to decode, and is retired.
+        decoder.add_samples(&awgn(15 * SAMPLE_RATE as usize, SAMPLE_RATE, 1.0, &mut rng));
+        assert_that!(decoder.station_audio_offsets().len(), equal_to(0));
+        assert_that!(player.lock().unwrap().plays.len(), equal_to(0));
+    }
+
+    #[test]
+    fn overlapping_stations_are_decoded_played_and_retired() {
+        let first_callsign_hash = hash_callsign("M0CUV".to_string()).unwrap();
+        let first = source_encodings(Frame::CallsignMetadata { callsign: "M0CUV".to_string() }, 3);
+        let second_callsign_hash = hash_callsign("G4DPZ".to_string()).unwrap();
+        let second = source_encodings(Frame::CallsignHashMetadata { hash: second_callsign_hash }, 2);
+
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        let first_transmission = modulated_transmission(700, &first);
+        let seconds = first_transmission.len() as f32 / SAMPLE_RATE as f32 + STATION_SILENCE_SECONDS + 5.0;
+        let mut audio = awgn((seconds * SAMPLE_RATE as f32) as usize, SAMPLE_RATE, 1.0, &mut rng);
+        mix(&mut audio, &first_transmission, SAMPLE_RATE as usize / 2, -10.0);
+        let second_start = 23 * SAMPLE_RATE as usize / 10;
+        mix(&mut audio, &modulated_transmission(1500, &second), second_start, -12.0);
+
+        let player = Arc::new(Mutex::new(RecordingPlayer::default()));
+        let mut decoder = Decoder::new(SAMPLE_RATE, 4, player.clone());
+        let (gui_input_tx, gui_input_rx) = sync_channel::<GUIInputMessage>(1000);
+        decoder.set_gui_input(Arc::new(gui_input_tx));
+        let dial_frequency = Arc::new(DialFrequency::new());
+        dial_frequency.set(Some(14070000));
+        decoder.set_dial_frequency(dial_frequency);
+        // As the receiver would, in irregular chunks, until the second station's Costas Array (and
+        // a few symbols more) has been received.
+        let mut chunks = audio.chunks(4321);
+        let samples_per_symbol = (SYMBOL_PERIOD_SECONDS * SAMPLE_RATE as f32) as usize;
+        for chunk in chunks.by_ref().take((second_start + (COSTAS_ARRAY_SYMBOLS + 5) * samples_per_symbol) / 4321 + 1) {
+            decoder.add_samples(chunk);
+        }
+        // Chance matches of the Costas Array in the data of the stations may also have been detected,
+        // and will be retired when they fail to decode.
+        assert_that!(&decoder.station_audio_offsets(), contains(vec![700, 1500]));
+        for chunk in chunks {
+            decoder.add_samples(chunk);
+        }
+
+        let player = player.lock().unwrap();
+        let plays = &player.plays;
+        let plays_at = |audio_offset: u16| plays.iter().filter(|play| play.2 == audio_offset).cloned().collect::<Vec<_>>();
+        assert_that!(plays_at(700), equal_to(expected_plays(&first, first_callsign_hash, 700)));
+        assert_that!(plays_at(1500), equal_to(expected_plays(&second, second_callsign_hash, 1500)));
+        assert_that!(plays.len(), equal_to(first.len() + second.len()));
+        assert_that!(decoder.station_audio_offsets().len(), equal_to(0));
+
+        // The GUI is told of the stations as they are decoded, and when they are retired. The
+        // callsign of the second station, only sent as a hash, is not known. Their radio frequencies
+        // are the dial frequency plus their audio offsets.
+        let gui_inputs: Vec<GUIInputMessage> = gui_input_rx.try_iter().collect();
+        let first_details = StationDetails { audio_offset: 700, radio_frequency: Some(14070700), callsign_hash: first_callsign_hash, callsign: Some("M0CUV".to_string()), locator: None };
+        let second_details = StationDetails { audio_offset: 1500, radio_frequency: Some(14071500), callsign_hash: second_callsign_hash, callsign: None, locator: None };
+        let count = |message: GUIInputMessage| gui_inputs.iter().filter(|input| **input == message).count();
+        assert_that!(count(GUIInputMessage::AddStation(first_details)), equal_to(first.len()));
+        assert_that!(count(GUIInputMessage::AddStation(second_details)), equal_to(second.len()));
+        assert_that!(count(GUIInputMessage::AddCostasArray(700)), equal_to(first.len()));
+        assert_that!(count(GUIInputMessage::AddCostasArray(1500)), equal_to(second.len()));
+        assert_that!(count(GUIInputMessage::ClearStation(700)), equal_to(1));
+        assert_that!(count(GUIInputMessage::ClearStation(1500)), equal_to(1));
+    }
+}
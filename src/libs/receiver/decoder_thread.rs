@@ -0,0 +1,99 @@
+/*
+ * The Decoder is not Send (its FFT observer and Costas Array detector are only notified on the
+ * thread that owns them), so the DecoderThread constructs it on its own thread, and feeds it the
+ * Receiver's audio. The Receiver notifies its observers of overlapping slices: the first holds
+ * OBSERVABLE_BUFFER_SLICE_SIZE new samples, and each after it OBSERVABLE_BUFFER_HOP_SIZE new
+ * samples, at its end. The DecoderThread's observer queues just the new samples, so the Decoder
+ * receives the audio contiguously. If the Decoder falls behind, the queue fills, and slices are
+ * dropped rather than holding up the Receiver's notification thread.
+ */
+
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver as ChannelReceiver, RecvTimeoutError, sync_channel, SyncSender};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use log::{debug, info, warn};
+use crate::libs::buffer_pool::observable_buffer::{OBSERVABLE_BUFFER_HOP_SIZE, ObservableBufferSlice};
+use crate::libs::patterns::observer::Observer;
+use crate::libs::receiver::decoder::Decoder;
+use crate::libs::receiver::receiver::ReceiverObserver;
+
+// The hops that can be queued for the Decoder: 4s worth.
+const DECODER_QUEUE_SIZE: usize = 100;
+
+// Registered with the Receiver, to queue the new samples of each slice for the Decoder.
+struct DecoderQueue {
+    samples_tx: SyncSender<Vec<f32>>,
+    first_slice: Mutex<bool>,
+}
+
+impl Observer<ObservableBufferSlice<f32>> for DecoderQueue {
+    fn on_notify(&self, slice: &ObservableBufferSlice<f32>) {
+        let mut first_slice = self.first_slice.lock().unwrap();
+        let new_samples = if *first_slice {
+            &slice.slice[..]
+        } else {
+            &slice.slice[slice.slice.len().saturating_sub(OBSERVABLE_BUFFER_HOP_SIZE)..]
+        };
+        if self.samples_tx.try_send(new_samples.to_vec()).is_err() {
+            warn!("Decoder is behind; dropping {} samples", new_samples.len());
+            return;
+        }
+        *first_slice = false;
+    }
+}
+
+pub struct DecoderThread {
+    terminate: Arc<AtomicBool>,
+    thread_handle: Option<JoinHandle<()>>,
+}
+
+impl DecoderThread {
+    // The Decoder is made by make_decoder on the DecoderThread's thread. The returned observer is
+    // to be added to the Receiver.
+    pub fn new<F>(make_decoder: F, terminate: Arc<AtomicBool>) -> (Self, ReceiverObserver)
+        where F: FnOnce() -> Decoder + Send + 'static {
+        let (samples_tx, samples_rx) = sync_channel(DECODER_QUEUE_SIZE);
+        let observer = Arc::new(DecoderQueue { samples_tx, first_slice: Mutex::new(true) });
+        let move_clone_terminate = terminate.clone();
+        let decoder_thread = Self {
+            terminate,
+            thread_handle: Some(thread::spawn(move || {
+                decode_queued_samples(make_decoder(), samples_rx, move_clone_terminate);
+            })),
+        };
+        (decoder_thread, observer)
+    }
+}
+
+impl Drop for DecoderThread {
+    fn drop(&mut self) {
+        debug!("DecoderThread signalling termination to thread on drop");
+        self.terminate.store(true, Ordering::SeqCst);
+        self.thread_handle.take().map(JoinHandle::join);
+    }
+}
+
+fn decode_queued_samples(mut decoder: Decoder, samples_rx: ChannelReceiver<Vec<f32>>, terminate: Arc<AtomicBool>) {
+    info!("Decoder thread started");
+    loop {
+        if terminate.load(Ordering::SeqCst) {
+            info!("Terminating decoder thread");
+            break;
+        }
+        match samples_rx.recv_timeout(Duration::from_millis(50)) {
+            Ok(samples) => decoder.add_samples(&samples),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                info!("Decoder thread's input has gone");
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./decoder_thread_spec.rs"]
+mod decoder_thread_spec;
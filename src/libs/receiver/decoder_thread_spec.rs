@@ -0,0 +1,75 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod decoder_thread_spec {
+    use std::env;
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::AtomicBool;
+    use std::time::{Duration, Instant};
+    use hamcrest2::prelude::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use crate::libs::buffer_pool::observable_buffer::{OBSERVABLE_BUFFER_HOP_SIZE, OBSERVABLE_BUFFER_SLICE_SIZE, ObservableBuffer, ObservableBufferSlice};
+    use crate::libs::channel_codec::channel_encoder::source_encoding_to_channel_encoding;
+    use crate::libs::patterns::observer::Observer;
+    use crate::libs::playback::recording_player::RecordingPlayer;
+    use crate::libs::receiver::decoder::Decoder;
+    use crate::libs::receiver::decoder_thread::DecoderThread;
+    use crate::libs::receiver::receiver::DOWNSAMPLED_SAMPLE_RATE;
+    use crate::libs::source_codec::metadata_codec::hash_callsign;
+    use crate::libs::source_codec::source_decoder::SourceDecoder;
+    use crate::libs::source_codec::source_encoding::{Frame, SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, SourceEncoding};
+    use crate::libs::source_codec::test_encoding_builder::encoded;
+    use crate::libs::transmitter::modulate::GfskModulator;
+    use crate::libs::util::test_util::{awgn, mix, wait_n_ms};
+
+    #[ctor::ctor]
+    fn before_each() {
+        env::set_var("RUST_LOG", "info");
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[ctor::dtor]
+    fn after_each() {}
+
+    #[test]
+    fn slices_from_the_receiver_are_decoded() {
+        let frames = [Frame::CallsignMetadata { callsign: "M0CUV".to_string() },
+            Frame::WPMPolarity { wpm: 20, polarity: true }, Frame::KeyingPerfectDit, Frame::KeyingEnd];
+        let source_encoding = SourceEncoding { block: encoded(SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, 20, &frames), is_end: true, is_metadata_only: false };
+        let symbols = source_encoding_to_channel_encoding(source_encoding.clone()).block;
+        let mut modulator = GfskModulator::new(DOWNSAMPLED_SAMPLE_RATE);
+        let mut waveform = vec![0.0; modulator.number_of_samples(symbols.len(), true, true)];
+        modulator.modulate(1200, &symbols, &mut waveform, true, true);
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        let mut audio = awgn(waveform.len() + 2 * DOWNSAMPLED_SAMPLE_RATE as usize, DOWNSAMPLED_SAMPLE_RATE, 1.0, &mut rng);
+        mix(&mut audio, &waveform, DOWNSAMPLED_SAMPLE_RATE as usize / 2, -10.0);
+
+        let player = Arc::new(Mutex::new(RecordingPlayer::default()));
+        let decoder_player = player.clone();
+        let terminate = Arc::new(AtomicBool::new(false));
+        let (decoder_thread, observer) = DecoderThread::new(move || {
+            Decoder::new(DOWNSAMPLED_SAMPLE_RATE, OBSERVABLE_BUFFER_SLICE_SIZE / OBSERVABLE_BUFFER_HOP_SIZE, decoder_player)
+        }, terminate);
+        // As the Receiver's notification thread would, though at twice real time.
+        let mut observable_buffer = ObservableBuffer::new();
+        observable_buffer.add_observer(observer as Arc<dyn Observer<ObservableBufferSlice<f32>>>);
+        for hop in audio.chunks(OBSERVABLE_BUFFER_HOP_SIZE) {
+            for sample in hop {
+                observable_buffer.add_sample(*sample);
+            }
+            wait_n_ms(20);
+        }
+
+        let start = Instant::now();
+        while player.lock().unwrap().plays.is_empty() && start.elapsed() < Duration::from_secs(10) {
+            wait_n_ms(50);
+        }
+        drop(decoder_thread);
+
+        let expected = SourceDecoder::new(SOURCE_ENCODER_BLOCK_SIZE_IN_BITS).source_decode(source_encoding.block).ok();
+        let callsign_hash = hash_callsign("M0CUV".to_string()).unwrap();
+        let plays = player.lock().unwrap().plays.clone();
+        assert_that!(plays, equal_to(vec![(expected, callsign_hash, 1200)]));
+    }
+}
@@ -0,0 +1,146 @@
+/*
+ * The GfskDemodulator recovers the channel symbols of a block from downsampled audio, given the
+ * audio offset at which it was transmitted, and the sample at which its first symbol starts (as
+ * found by the Costas Array detector).
+ * Each symbol period of audio is transformed with zero-padding to OVERSAMPLING times its length,
+ * so that there are OVERSAMPLING FFT bins between each tone. The tones are 6.25Hz apart; the
+ * transmitting station's audio offset (and its dial frequency, and ours) will not be exactly that
+ * given, so the frequency error is estimated first: the bin offset, within half a tone spacing
+ * either way, at which the strongest tone of each symbol is strongest overall. Larger errors are
+ * indistinguishable from a shift of all the tones; it is the Costas Array detector that finds the
+ * offset to the nearest tone.
+ * The magnitude of each tone in each symbol is then read at that offset; the hard decision is the
+ * strongest tone, and the magnitudes are retained for soft-decision decoding.
+ */
+
+use std::sync::Arc;
+use log::debug;
+use num::Complex;
+use realfft::{RealFftPlanner, RealToComplex};
+use crate::libs::channel_codec::channel_encoding::{ChannelSymbol, NUMBER_OF_TONES, ToneMagnitudes};
+use crate::libs::channel_codec::soft_decision::tone_magnitudes_to_symbols;
+use crate::libs::transmitter::modulate::{SYMBOL_PERIOD_SECONDS, TONE_SPACING_HZ};
+use crate::libs::transmitter::transmitter::AudioFrequencyHz;
+
+// FFT bins per tone spacing.
+const OVERSAMPLING: usize = 8;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Demodulation {
+    pub symbols: Vec<ChannelSymbol>,
+    pub tone_magnitudes: Vec<ToneMagnitudes>,
+    // The estimated frequency error of the received tones, relative to the given audio offset.
+    pub frequency_error_hz: f32,
+}
+
+pub struct GfskDemodulator {
+    sample_rate: AudioFrequencyHz,
+    samples_per_symbol: usize,
+    bin_hz: f32,
+    r2c: Arc<dyn RealToComplex<f32>>,
+}
+
+impl GfskDemodulator {
+    pub fn new(sample_rate: AudioFrequencyHz) -> Self {
+        if sample_rate == 0 {
+            panic!("No sample rate defined for GfskDemodulator");
+        }
+        // Sample rate is 12000Hz, downsampled from 48000Hz.
+        let samples_per_symbol = (sample_rate as f32 * SYMBOL_PERIOD_SECONDS) as usize;
+        let nfft = samples_per_symbol * OVERSAMPLING;
+        let r2c = RealFftPlanner::<f32>::new().plan_fft_forward(nfft);
+        Self {
+            sample_rate,
+            samples_per_symbol,
+            bin_hz: sample_rate as f32 / nfft as f32,
+            r2c,
+        }
+    }
+
+    pub fn samples_per_symbol(&self) -> usize {
+        self.samples_per_symbol
+    }
+
+    // Demodulate number_of_symbols symbols, the first starting at the first of the given samples.
+    // Fails if there are too few samples, or the tones at the audio offset (allowing for the
+    // frequency error) do not all fit in the spectrum.
+    pub fn demodulate(&self, audio_offset: AudioFrequencyHz, samples: &[f32], number_of_symbols: usize) -> Result<Demodulation, String> {
+        if samples.len() < number_of_symbols * self.samples_per_symbol {
+            return Err(format!("Need {} samples to demodulate {} symbols, not {}",
+                               number_of_symbols * self.samples_per_symbol, number_of_symbols, samples.len()));
+        }
+        if !self.audio_offset_in_range(audio_offset) {
+            return Err(format!("Audio offset {}Hz is out of range at a sample rate of {}Hz", audio_offset, self.sample_rate));
+        }
+        let half_spacing = (OVERSAMPLING / 2) as isize;
+
+        let spectra = self.symbol_magnitude_spectra(samples, number_of_symbols);
+
+        // Find the bin offset within half a tone spacing at which the tones are strongest.
+        let mut best_error_bins = 0;
+        let mut best_strength = -1.0;
+        for error_bins in -half_spacing..=half_spacing {
+            let strength: f32 = spectra.iter()
+                .map(|spectrum| (0..NUMBER_OF_TONES)
+                    .map(|tone| spectrum[(self.tone_bin(audio_offset, tone) + error_bins) as usize])
+                    .fold(0.0, f32::max))
+                .sum();
+            if strength > best_strength {
+                best_strength = strength;
+                best_error_bins = error_bins;
+            }
+        }
+        let frequency_error_hz = best_error_bins as f32 * self.bin_hz;
+        debug!("Frequency error of {}Hz at audio offset {}Hz", frequency_error_hz, audio_offset);
+
+        let tone_magnitudes: Vec<ToneMagnitudes> = spectra.iter()
+            .map(|spectrum| {
+                let mut tone_magnitudes = [0.0; NUMBER_OF_TONES];
+                for (tone, magnitude) in tone_magnitudes.iter_mut().enumerate() {
+                    *magnitude = spectrum[(self.tone_bin(audio_offset, tone) + best_error_bins) as usize];
+                }
+                tone_magnitudes
+            })
+            .collect();
+        Ok(Demodulation {
+            symbols: tone_magnitudes_to_symbols(&tone_magnitudes),
+            tone_magnitudes,
+            frequency_error_hz,
+        })
+    }
+
+    // Can a transmission at this audio offset be demodulated? All its tones, and half a tone
+    // spacing either side of them, must be within the spectrum.
+    pub fn audio_offset_in_range(&self, audio_offset: AudioFrequencyHz) -> bool {
+        let half_spacing = (OVERSAMPLING / 2) as isize;
+        let lowest_bin = self.tone_bin(audio_offset, 0) - half_spacing;
+        let highest_bin = self.tone_bin(audio_offset, NUMBER_OF_TONES - 1) + half_spacing;
+        lowest_bin >= 0 && (highest_bin as usize) < self.r2c.len() / 2
+    }
+
+    // The FFT bin of a tone, without any frequency error.
+    fn tone_bin(&self, audio_offset: AudioFrequencyHz, tone: usize) -> isize {
+        ((audio_offset as f32 + tone as f32 * TONE_SPACING_HZ) / self.bin_hz).round() as isize
+    }
+
+    // The magnitude of each bin of each symbol period's spectrum, scaled so that a tone of unit
+    // amplitude centred in a bin has unit magnitude.
+    fn symbol_magnitude_spectra(&self, samples: &[f32], number_of_symbols: usize) -> Vec<Vec<f32>> {
+        let mut input = self.r2c.make_input_vec();
+        let mut spectrum: Vec<Complex<f32>> = self.r2c.make_output_vec();
+        let scale = 2.0 / self.samples_per_symbol as f32;
+        samples.chunks_exact(self.samples_per_symbol)
+            .take(number_of_symbols)
+            .map(|symbol_samples| {
+                input[0..self.samples_per_symbol].copy_from_slice(symbol_samples);
+                input[self.samples_per_symbol..].iter_mut().for_each(|s| *s = 0.0);
+                self.r2c.process(&mut input, &mut spectrum).unwrap();
+                spectrum.iter().map(|bin| bin.norm() * scale).collect()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+#[path = "./demodulate_spec.rs"]
+mod demodulate_spec;
@@ -0,0 +1,132 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod demodulate_spec {
+    use std::env;
+    use hamcrest2::prelude::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use rstest::*;
+    use crate::libs::channel_codec::channel_decoder::channel_tone_magnitudes_to_channel_decoding;
+    use crate::libs::channel_codec::costas::COSTAS_ARRAY_SYMBOLS;
+    use crate::libs::channel_codec::sample_channel_encoding::sample_channel_encoding;
+    use crate::libs::receiver::demodulate::GfskDemodulator;
+    use crate::libs::transmitter::modulate::GfskModulator;
+    use crate::libs::transmitter::transmitter::AudioFrequencyHz;
+    use crate::libs::util::test_util::awgn;
+
+    const SAMPLE_RATE: AudioFrequencyHz = 12000;
+    const AUDIO_FREQUENCY: AudioFrequencyHz = 600;
+    const SAMPLES_PER_SYMBOL: usize = 1920;
+
+    #[ctor::ctor]
+    fn before_each() {
+        env::set_var("RUST_LOG", "debug");
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[ctor::dtor]
+    fn after_each() {}
+
+    // The waveform of the sample channel encoding, ramped up and down, and the sample at which its
+    // first symbol starts.
+    fn modulated_sample_channel_encoding(audio_frequency: AudioFrequencyHz) -> (Vec<f32>, usize) {
+        let symbols = sample_channel_encoding().block;
+        let mut modulator = GfskModulator::new(SAMPLE_RATE);
+        let mut waveform = vec![0.0; modulator.number_of_samples(symbols.len(), true, true)];
+        modulator.modulate(audio_frequency, &symbols, &mut waveform, true, true);
+        (waveform, modulator.ramp_samples_per_symbol())
+    }
+
+    // Add white Gaussian noise at the given signal to noise ratio, measured (as for FT8) in a 2500Hz
+    // bandwidth, for the unit amplitude waveform, whose power is 1/2.
+    fn add_noise(waveform: &mut [f32], snr_db: f32, rng: &mut StdRng) {
+        let noise_power = 0.5 / 10f32.powf(snr_db / 10.0);
+        let noise = awgn(waveform.len(), SAMPLE_RATE, noise_power, rng);
+        for (sample, noise) in waveform.iter_mut().zip(noise) {
+            *sample += noise;
+        }
+    }
+
+    #[test]
+    fn symbol_period() {
+        assert_that!(GfskDemodulator::new(SAMPLE_RATE).samples_per_symbol(), equal_to(SAMPLES_PER_SYMBOL));
+    }
+
+    #[test]
+    #[should_panic]
+    fn sample_rate_must_be_given() {
+        GfskDemodulator::new(0);
+    }
+
+    #[test]
+    fn too_few_samples_is_an_error() {
+        let demodulation = GfskDemodulator::new(SAMPLE_RATE).demodulate(AUDIO_FREQUENCY, &[0.0; SAMPLES_PER_SYMBOL], 2);
+        assert_that!(demodulation.unwrap_err(), matches_regex("Need 3840 samples"));
+    }
+
+    #[rstest]
+    #[case(0)]
+    #[case(5950)]
+    fn audio_offset_out_of_range_is_an_error(#[case] audio_offset: AudioFrequencyHz) {
+        let demodulator = GfskDemodulator::new(SAMPLE_RATE);
+        assert_that!(demodulator.audio_offset_in_range(audio_offset), equal_to(false));
+        let demodulation = demodulator.demodulate(audio_offset, &[0.0; SAMPLES_PER_SYMBOL], 1);
+        assert_that!(demodulation.unwrap_err(), matches_regex("out of range"));
+    }
+
+    #[test]
+    fn audio_offsets_in_the_passband_are_in_range() {
+        let demodulator = GfskDemodulator::new(SAMPLE_RATE);
+        assert_that!(demodulator.audio_offset_in_range(100), equal_to(true));
+        assert_that!(demodulator.audio_offset_in_range(AUDIO_FREQUENCY), equal_to(true));
+        assert_that!(demodulator.audio_offset_in_range(2500), equal_to(true));
+    }
+
+    #[test]
+    fn clean_waveform_is_demodulated() {
+        let (waveform, start) = modulated_sample_channel_encoding(AUDIO_FREQUENCY);
+        let symbols = sample_channel_encoding().block;
+        let demodulation = GfskDemodulator::new(SAMPLE_RATE).demodulate(AUDIO_FREQUENCY, &waveform[start..], symbols.len()).unwrap();
+        assert_that!(&demodulation.symbols, equal_to(&symbols));
+        assert_that!(demodulation.tone_magnitudes.len(), equal_to(symbols.len()));
+        assert_that!(demodulation.frequency_error_hz, equal_to(0.0));
+        // The sent tone is near unit magnitude; the others are well below.
+        for (symbol, tone_magnitudes) in symbols.iter().zip(&demodulation.tone_magnitudes) {
+            assert_that!(tone_magnitudes[*symbol as usize], greater_than(0.75));
+            for (tone, magnitude) in tone_magnitudes.iter().enumerate() {
+                if tone != *symbol as usize {
+                    assert_that!(*magnitude, less_than(0.25));
+                }
+            }
+        }
+    }
+
+    #[rstest]
+    #[case(1)]
+    #[case(-2)]
+    #[case(3)]
+    fn frequency_error_is_estimated_and_corrected(#[case] error_hz: i16) {
+        let transmitted_frequency = (AUDIO_FREQUENCY as i16 + error_hz) as AudioFrequencyHz;
+        let (mut waveform, start) = modulated_sample_channel_encoding(transmitted_frequency);
+        add_noise(&mut waveform, -10.0, &mut StdRng::seed_from_u64(0x5eed));
+        let symbols = sample_channel_encoding().block;
+        let demodulation = GfskDemodulator::new(SAMPLE_RATE).demodulate(AUDIO_FREQUENCY, &waveform[start..], symbols.len()).unwrap();
+        assert_that!(demodulation.symbols, equal_to(symbols));
+        assert_that!((demodulation.frequency_error_hz - error_hz as f32).abs(), less_than(0.5));
+    }
+
+    #[test]
+    fn weak_noisy_off_frequency_signal_is_decoded_from_its_tone_magnitudes() {
+        let (mut waveform, start) = modulated_sample_channel_encoding(AUDIO_FREQUENCY + 2);
+        add_noise(&mut waveform, -18.0, &mut StdRng::seed_from_u64(0x5eed));
+        let channel_encoding = sample_channel_encoding();
+        let demodulation = GfskDemodulator::new(SAMPLE_RATE).demodulate(AUDIO_FREQUENCY, &waveform[start..], channel_encoding.block.len()).unwrap();
+        let symbol_errors = demodulation.symbols.iter().zip(&channel_encoding.block).filter(|(r, s)| r != s).count();
+        assert_that!(symbol_errors, greater_than(0));
+
+        let decoding = channel_tone_magnitudes_to_channel_decoding(&demodulation.tone_magnitudes[COSTAS_ARRAY_SYMBOLS..]);
+        let source_encoding = decoding.source_encoding.unwrap();
+        assert_that!(source_encoding.is_end, equal_to(channel_encoding.is_end));
+    }
+}
@@ -13,6 +13,13 @@ pub struct ObservableFrequencySlice {
 impl Observable for ObservableFrequencySlice {
 }
 
+impl ObservableFrequencySlice {
+    // The magnitude of each FFT bin, from DC up to half the sample rate.
+    pub fn magnitudes(&self) -> &[f32] {
+        &self.reals
+    }
+}
+
 pub struct FFTingBufferObserver {
     _fft: RealFftPlanner<f32>,
     r2c: Arc<dyn RealToComplex<f32>>,
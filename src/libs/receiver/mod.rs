@@ -1,2 +1,9 @@
+pub mod costas_detector;
+pub mod decimator;
+pub mod decoder;
+pub mod decoder_thread;
+pub mod demodulate;
 pub mod fft;
-pub mod receiver;
\ No newline at end of file
+pub mod receiver;
+pub mod station_decoder;
+pub mod waveform_file_decoder;
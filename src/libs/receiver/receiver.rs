@@ -1,6 +1,10 @@
 use std::error::Error;
 use std::sync::{Arc, Mutex, RwLock};
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver as ChannelReceiver, RecvTimeoutError, sync_channel, SyncSender};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
 use bus::Bus;
 use log::{debug, info, warn};
 use portaudio::{NonBlocking, Input, InputStreamSettings, PortAudio, Stream};
@@ -8,8 +12,18 @@ use portaudio as pa;
 use crate::libs::application::application::BusOutput;
 use crate::libs::buffer_pool::observable_buffer::{ObservableBuffer, ObservableBufferSlice};
 use crate::libs::patterns::observer::Observer;
+use crate::libs::receiver::decimator::Decimator;
 use crate::libs::transmitter::transmitter::{AmplitudeMax, AudioFrequencyHz};
 
+// The input is downsampled to this rate before being buffered and emitted to observers.
+pub const DOWNSAMPLED_SAMPLE_RATE: AudioFrequencyHz = 12000;
+
+// The downsampled samples that the callback can queue for the notification thread: 2s worth.
+const DOWNSAMPLED_QUEUE_SIZE: usize = 2 * DOWNSAMPLED_SAMPLE_RATE as usize;
+
+// Observers are notified on the Receiver's notification thread, not the thread that adds them.
+pub type ReceiverObserver = Arc<dyn Observer<ObservableBufferSlice<f32>> + Send + Sync>;
+
 pub struct Receiver {
     audio_offset: AudioFrequencyHz,
     amplitude_max: AmplitudeMax,
@@ -17,7 +31,9 @@ pub struct Receiver {
     stream: Option<Stream<NonBlocking, Input<f32>>>,
     callback_data: Arc<RwLock<CallbackData>>,
     terminate: Arc<AtomicBool>,
-    observable_buffer: ObservableBuffer<f32>,
+    observers: Arc<RwLock<Vec<ReceiverObserver>>>,
+    downsampled_tx: SyncSender<f32>,
+    thread_handle: Option<JoinHandle<()>>,
 }
 
 #[derive(Clone, PartialEq, Copy)]
@@ -36,11 +52,37 @@ struct InjectedWaveform {
     playback_index: usize,
 }
 
+impl InjectedWaveform {
+    fn next_sample(&mut self) -> Option<f32> {
+        let sample = self.waveform.get(self.playback_index).copied();
+        self.playback_index += 1;
+        sample
+    }
+}
+
+// Registered with the notification thread's ObservableBuffer, to pass its slices to the Receiver's
+// observers.
+struct ReceiverObservers {
+    observers: Arc<RwLock<Vec<ReceiverObserver>>>,
+}
+
+impl Observer<ObservableBufferSlice<f32>> for ReceiverObservers {
+    fn on_notify(&self, slice: &ObservableBufferSlice<f32>) {
+        for observer in self.observers.read().unwrap().iter() {
+            observer.on_notify(slice);
+        }
+    }
+}
+
 impl Receiver {
 
     pub fn new(audio_offset: AudioFrequencyHz, terminate: Arc<AtomicBool>,
                /* TODO CAT controller passed in here */) -> Self {
         let callback_data = CallbackData { injected_waveform: None, output_tx: None };
+        let observers = Arc::new(RwLock::new(vec![]));
+        let move_clone_observers = observers.clone();
+        let move_clone_terminate = terminate.clone();
+        let (downsampled_tx, downsampled_rx) = sync_channel(DOWNSAMPLED_QUEUE_SIZE);
         Self {
             audio_offset: audio_offset,
             amplitude_max: 1.0,
@@ -48,12 +90,16 @@ impl Receiver {
             stream: None,
             callback_data: Arc::new(RwLock::new(callback_data)),
             terminate,
-            observable_buffer: ObservableBuffer::new(),
+            observers,
+            downsampled_tx,
+            thread_handle: Some(thread::spawn(move || {
+                notify_downsampled_slices(downsampled_rx, move_clone_observers, move_clone_terminate);
+            })),
         }
     }
 
-    pub fn add_observer(&mut self, observer: Arc<dyn Observer<ObservableBufferSlice<f32>>>) {
-        self.observable_buffer.add_observer(observer);
+    pub fn add_observer(&mut self, observer: ReceiverObserver) {
+        self.observers.write().unwrap().push(observer);
     }
 
     pub fn set_amplitude_max(&mut self, amplitude_max: AmplitudeMax) {
@@ -70,23 +116,42 @@ impl Receiver {
     // The odd form of this callback setup (pass in the PortAudio and settings) rather than just
     // returning the callback to the caller to do stuff with... is because I can't work out what
     // the correct type signature of a callback-returning function should be.
-    pub fn start_callback(&mut self, pa: &PortAudio, mut input_settings: InputStreamSettings<f32>) -> Result<(), Box<dyn Error>> {
+    pub fn start_callback(&mut self, pa: &PortAudio, input_settings: InputStreamSettings<f32>) -> Result<(), Box<dyn Error>> {
         let sample_rate = input_settings.sample_rate as u32;
         self.sample_rate = sample_rate;
         debug!("in start_callback, sample rate is {}", sample_rate);
+        if sample_rate == 0 || sample_rate % DOWNSAMPLED_SAMPLE_RATE as u32 != 0 {
+            return Err(Box::<dyn Error + Send + Sync>::from(format!("Can't downsample input at {}Hz to {}Hz", sample_rate, DOWNSAMPLED_SAMPLE_RATE)));
+        }
+        let channels = (input_settings.params.channel_count as usize).max(1);
+        let mut decimator = Decimator::new((sample_rate / DOWNSAMPLED_SAMPLE_RATE as u32) as usize);
+        let downsampled_tx = self.downsampled_tx.clone();
+        let move_clone_callback_data = self.callback_data.clone();
 
         let callback = move |pa::InputStreamCallbackArgs::<f32> { buffer, frames, .. }| {
-            // info!("buffer length is {}, frames is {}", buffer.len(), frames);
-            // buffer length is 64, frames is 64
-
-            // TODO is there a waveform to inject?
-            // Downsampled audio is collected into a circular buffer. Every 40ms, the last 160ms
-            // of audio is emitted to observers (after we've received the first 160ms, of course).
-            // The FFT observer zero-pads this 160ms audio to 320ms, and transforms, and emits that
-            // to its observers.
             // The input rate is 48000Hz. Each ms there are 48 samples. We're downsampling by 4, so
-            // each ms has 12 downsamples. 160ms therefore contains 12 samples * 160 ms = 1920 samples.
-            // The circular buffer needs to hold twice as much as this to prevent collisions.
+            // each ms has 12 downsamples. Only the first channel of the input is used.
+            // The downsampled audio is queued for the notification thread, which collects it into
+            // a circular buffer, and emits the last 160ms every 40ms to the observers (the FFT
+            // observer, decoder, etc.) - so none of their work is done here. If the notification
+            // thread falls behind, the queue fills, and samples are dropped rather than blocking.
+            // An injected waveform replaces the input until it has all been received. The callback
+            // data is only tried for, so that the callback never waits on the lock.
+            let mut maybe_locked_callback_data = move_clone_callback_data.try_write().ok();
+            for frame in 0..frames {
+                let mut sample = buffer[frame * channels];
+                if let Some(locked_callback_data) = maybe_locked_callback_data.as_mut() {
+                    if let Some(injected_waveform) = locked_callback_data.injected_waveform.as_mut() {
+                        match injected_waveform.next_sample() {
+                            Some(injected_sample) => sample = injected_sample,
+                            None => locked_callback_data.injected_waveform = None,
+                        }
+                    }
+                }
+                if let Some(downsampled) = decimator.add_sample(sample) {
+                    let _ = downsampled_tx.try_send(downsampled);
+                }
+            }
             pa::Continue
         };
 
@@ -111,9 +176,9 @@ impl Receiver {
     pub fn terminate(&mut self) {
         debug!("Terminating Receiver");
         self.terminate.store(true, Ordering::SeqCst);
-        // debug!("Receiver joining read thread handle...");
-        // self.thread_handle.take().map(JoinHandle::join);
-        // debug!("Receiver ...joined thread handle");
+        debug!("Receiver joining notification thread handle...");
+        self.thread_handle.take().map(JoinHandle::join);
+        debug!("Receiver ...joined thread handle");
     }
 
     // Has the thread finished (ie has it been joined)?
@@ -166,9 +231,33 @@ impl Drop for Receiver {
         self.terminate();
         debug!("Receiver stopping stream...");
         self.stream.take().map(|mut r| r.stop());
-        // debug!("Receiver joining thread handle...");
-        // self.thread_handle.take().map(JoinHandle::join);
-        // debug!("Receiver ...joined thread handle");
+    }
+}
+
+// Runs on the Receiver's notification thread: collects the downsampled audio queued by the
+// callback into the ObservableBuffer, which notifies the Receiver's observers.
+fn notify_downsampled_slices(downsampled_rx: ChannelReceiver<f32>, observers: Arc<RwLock<Vec<ReceiverObserver>>>, terminate: Arc<AtomicBool>) {
+    info!("Receiver notification thread started");
+    let mut observable_buffer = ObservableBuffer::new();
+    observable_buffer.add_observer(Arc::new(ReceiverObservers { observers }));
+    loop {
+        if terminate.load(Ordering::SeqCst) {
+            info!("Terminating receiver notification thread");
+            break;
+        }
+        match downsampled_rx.recv_timeout(Duration::from_millis(50)) {
+            Ok(sample) => {
+                observable_buffer.add_sample(sample);
+                for sample in downsampled_rx.try_iter() {
+                    observable_buffer.add_sample(sample);
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                info!("Receiver notification thread's input has gone");
+                break;
+            }
+        }
     }
 }
 
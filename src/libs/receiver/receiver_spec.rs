@@ -4,7 +4,7 @@ extern crate hamcrest2;
 mod receiver_spec {
     use std::env;
     use std::sync::{Arc, Mutex};
-    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
     use bus::{Bus, BusReader};
 
     use log::{debug, info};
@@ -12,12 +12,14 @@ mod receiver_spec {
     use rstest::*;
     use hamcrest2::prelude::*;
     use crate::libs::application::application::{BusInput, BusOutput};
+    use crate::libs::buffer_pool::observable_buffer::{OBSERVABLE_BUFFER_HOP_SIZE, OBSERVABLE_BUFFER_SLICE_SIZE, ObservableBufferSlice};
+    use crate::libs::patterns::observer::Observer;
 
     use crate::libs::audio::audio_devices::open_input_audio_device;
     use crate::libs::channel_codec::sample_channel_encoding::sample_channel_encoding;
-    use crate::libs::receiver::receiver::{Receiver, ReceiverEvent};
+    use crate::libs::receiver::receiver::{DOWNSAMPLED_SAMPLE_RATE, Receiver, ReceiverEvent};
     use crate::libs::test::test_hardware;
-    use crate::libs::transmitter::modulate::gfsk_modulate;
+    use crate::libs::transmitter::modulate::GfskModulator;
     use crate::libs::transmitter::transmitter::{AmplitudeMax, AudioFrequencyHz};
     use crate::libs::util::test_util;
     use crate::libs::wav::wav::{read_waveform_file, write_waveform_file};
@@ -98,6 +100,17 @@ mod receiver_spec {
         }
     }
 
+    struct SliceCounter {
+        slices: AtomicUsize,
+    }
+
+    impl Observer<ObservableBufferSlice<f32>> for SliceCounter {
+        fn on_notify(&self, slice: &ObservableBufferSlice<f32>) {
+            assert_that!(slice.slice.len(), equal_to(OBSERVABLE_BUFFER_SLICE_SIZE));
+            self.slices.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
     #[rstest]
     #[serial]
     pub fn receive_sample_waveform(mut fixture: ReceiverFixture) {
@@ -112,10 +125,15 @@ mod receiver_spec {
         let sample_waveform = read_waveform_file(filename);
         let waveform_vec = sample_waveform.unwrap();
         assert_that!(waveform_vec.len(), equal_to(MODULATED_SAMPLE_ENCODING_WAVEFORM_LENGTH));
+        let counter = Arc::new(SliceCounter { slices: AtomicUsize::new(0) });
+        fixture.receiver.add_observer(counter.clone());
         fixture.receiver.inject_waveform(&waveform_vec);
-        // TODO WOZERE - how to sense this?
-        panic!("can't sense this");
-        test_util::wait_n_ms(1000);
+        let waveform_ms = waveform_vec.len() as u64 * 1000 / SAMPLE_RATE as u64;
+        test_util::wait_n_ms(waveform_ms + 500);
+        // Each 40ms hop of the downsampled waveform, after the first 160ms, emits a slice.
+        let downsampled = waveform_vec.len() * DOWNSAMPLED_SAMPLE_RATE as usize / SAMPLE_RATE as usize;
+        let expected_slices = (downsampled - OBSERVABLE_BUFFER_SLICE_SIZE) / OBSERVABLE_BUFFER_HOP_SIZE;
+        assert_that!(counter.slices.load(Ordering::SeqCst), greater_than_or_equal_to(expected_slices));
         info!("End of test");
     }
 
@@ -129,14 +147,14 @@ mod receiver_spec {
     }
 
     // length found by running it and seeing what's needed...
-    const MODULATED_SAMPLE_ENCODING_WAVEFORM_LENGTH: usize = 493440;
+    const MODULATED_SAMPLE_ENCODING_WAVEFORM_LENGTH: usize = 547200;
     fn sample_waveform() -> Vec<f32> {
         let channel_encoding = sample_channel_encoding();
         let channel_symbols = &channel_encoding.block;
         let mut waveform_buffer: Vec<f32> = Vec::with_capacity(MODULATED_SAMPLE_ENCODING_WAVEFORM_LENGTH);
         waveform_buffer.resize(MODULATED_SAMPLE_ENCODING_WAVEFORM_LENGTH, 0_f32);
 
-        let _ = gfsk_modulate(AUDIO_FREQUENCY, SAMPLE_RATE, channel_symbols, &mut waveform_buffer.as_mut_slice(), true, true);
+        let _ = GfskModulator::new(SAMPLE_RATE).modulate(AUDIO_FREQUENCY, channel_symbols, &mut waveform_buffer.as_mut_slice(), true, true);
         waveform_buffer
     }
 }
@@ -0,0 +1,208 @@
+/*
+ * A StationDecoder follows the transmission of one station, at one audio offset. The Decoder
+ * queues the start of each of the station's blocks with it as their Costas Arrays are detected,
+ * and, when all of a block's audio has been received, has it decoded.
+ * Decoding is in two parts. First, the block's audio is demodulated and channel-decoded; this
+ * needs no state other than the audio, so the blocks of several stations can be decoded in
+ * parallel. The detector only finds the start of the block to within a fraction of a symbol, so
+ * the block is demodulated at the start (within the audio given) at which the Costas Array's tones
+ * are strongest. Second, the decoded block is source-decoded into Frames, the station is
+ * identified from its callsign or callsign hash, its locator noted if it sends one, and the Frames
+ * are played back.
+ * A station that has not been heard for a period of silence is retired, as is one whose first
+ * block cannot be decoded: that was not a station, but noise, or a chance match of the Costas
+ * Array in another station's data. A station is also retired once the block marking the end of
+ * its transmission has been played, without waiting for the silence.
+ */
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use log::{debug, info, warn};
+use crate::libs::channel_codec::channel_decoder::{channel_tone_magnitudes_to_channel_decoding, ChannelDecodeError};
+use crate::libs::channel_codec::channel_encoding::{CHANNEL_ENCODER_BLOCK_SIZE, ToneMagnitudes};
+use crate::libs::channel_codec::costas::{COSTAS_ARRAY, COSTAS_ARRAY_SYMBOLS};
+use crate::libs::playback::playback::FramePlayer;
+use crate::libs::receiver::demodulate::GfskDemodulator;
+use crate::libs::source_codec::callsign_hash_cache::CallsignHashCache;
+use crate::libs::source_codec::source_decoder::SourceDecoder;
+use crate::libs::source_codec::source_encoding::{CallsignHash, Frame, Locator, SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, SourceEncoding, UNKNOWN_CALLSIGN_HASH};
+use crate::libs::transmitter::transmitter::AudioFrequencyHz;
+use crate::libs::util::util::get_epoch_ms;
+
+// The start of a block is searched for in steps of this fraction of a symbol.
+const START_SEARCH_STEPS_PER_SYMBOL: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QueuedBlock {
+    // The sample at which the block's Costas Array was detected to start.
+    pub start_sample: usize,
+    // The audio offset at which it was detected; within a tone of the station's.
+    pub audio_offset: AudioFrequencyHz,
+}
+
+pub struct StationDecoder {
+    audio_offset: AudioFrequencyHz,
+    demodulator: Arc<GfskDemodulator>,
+    source_decoder: SourceDecoder,
+    callsign_hash: Option<CallsignHash>,
+    locator: Option<Locator>,
+    queued_blocks: VecDeque<QueuedBlock>,
+    // Blocks starting before this sample overlap the last block queued.
+    next_block_earliest_sample: usize,
+    blocks_decoded: usize,
+    // The sample at which the last block decoded ended (or, until one is decoded, at which the
+    // station was detected).
+    last_heard_sample: usize,
+    silence_samples: usize,
+    ended: bool,
+    retired: bool,
+}
+
+impl StationDecoder {
+    pub fn new(audio_offset: AudioFrequencyHz, detected_sample: usize, demodulator: Arc<GfskDemodulator>, silence_samples: usize) -> Self {
+        info!("New StationDecoder at {} Hz", audio_offset);
+        Self {
+            audio_offset,
+            demodulator,
+            source_decoder: SourceDecoder::new(SOURCE_ENCODER_BLOCK_SIZE_IN_BITS),
+            callsign_hash: None,
+            locator: None,
+            queued_blocks: VecDeque::new(),
+            next_block_earliest_sample: 0,
+            blocks_decoded: 0,
+            last_heard_sample: detected_sample,
+            silence_samples,
+            ended: false,
+            retired: false,
+        }
+    }
+
+    pub fn audio_offset(&self) -> AudioFrequencyHz {
+        self.audio_offset
+    }
+
+    pub fn callsign_hash(&self) -> Option<CallsignHash> {
+        self.callsign_hash
+    }
+
+    pub fn locator(&self) -> Option<Locator> {
+        self.locator.clone()
+    }
+
+    pub fn blocks_decoded(&self) -> usize {
+        self.blocks_decoded
+    }
+
+    // Has the block marking the end of the station's transmission been played?
+    pub fn has_ended(&self) -> bool {
+        self.ended
+    }
+
+    // The number of samples in a block: its Costas Array and the codeword.
+    pub fn block_samples(&self) -> usize {
+        (COSTAS_ARRAY_SYMBOLS + CHANNEL_ENCODER_BLOCK_SIZE) * self.demodulator.samples_per_symbol()
+    }
+
+    // Queue a block detected at the given start sample. Returns false (ignoring the block) if it
+    // overlaps the previous block queued: that is a chance match of the Costas Array within it.
+    pub fn queue_block(&mut self, block: QueuedBlock) -> bool {
+        if block.start_sample < self.next_block_earliest_sample {
+            debug!("Ignoring block at {} Hz starting at sample {}, within the previous block", block.audio_offset, block.start_sample);
+            return false;
+        }
+        // Allow the next block's start to be detected up to a symbol early.
+        self.next_block_earliest_sample = block.start_sample + self.block_samples() - self.demodulator.samples_per_symbol();
+        self.queued_blocks.push_back(block);
+        true
+    }
+
+    pub fn next_queued_block(&self) -> Option<QueuedBlock> {
+        self.queued_blocks.front().copied()
+    }
+
+    pub fn dequeue_block(&mut self) -> Option<QueuedBlock> {
+        self.queued_blocks.pop_front()
+    }
+
+    // Demodulate and channel-decode the block whose audio starts somewhere in the first
+    // audio.len() - block_samples() samples of the given audio. Audio that cannot be demodulated
+    // (too short, or at an audio offset out of the demodulator's range) is a failed block.
+    pub fn decode_block(&self, audio: &[f32], audio_offset: AudioFrequencyHz) -> Result<SourceEncoding, ChannelDecodeError> {
+        let block_samples = self.block_samples();
+        if audio.len() < block_samples {
+            return Err(ChannelDecodeError::DemodulationFailure(format!("Need {} samples to decode a block, not {}", block_samples, audio.len())));
+        }
+        let step = (self.demodulator.samples_per_symbol() / START_SEARCH_STEPS_PER_SYMBOL).max(1);
+        let mut best_start = 0;
+        let mut best_strength = -1.0;
+        for start in (0..=audio.len() - block_samples).step_by(step) {
+            let costas = self.demodulator.demodulate(audio_offset, &audio[start..], COSTAS_ARRAY_SYMBOLS)
+                .map_err(ChannelDecodeError::DemodulationFailure)?;
+            let strength = costas_array_strength(&costas.tone_magnitudes);
+            if strength > best_strength {
+                best_strength = strength;
+                best_start = start;
+            }
+        }
+        let demodulation = self.demodulator.demodulate(audio_offset, &audio[best_start..], COSTAS_ARRAY_SYMBOLS + CHANNEL_ENCODER_BLOCK_SIZE)
+            .map_err(ChannelDecodeError::DemodulationFailure)?;
+        debug!("Decoding block at {} Hz (frequency error {} Hz) from sample {} of {}", audio_offset,
+               demodulation.frequency_error_hz, best_start, audio.len() - block_samples);
+        channel_tone_magnitudes_to_channel_decoding(&demodulation.tone_magnitudes[COSTAS_ARRAY_SYMBOLS..]).source_encoding
+    }
+
+    // Source-decode and play back a block that has been decoded (or not) by decode_block.
+    pub fn play_block(&mut self, block: QueuedBlock, decoding: Result<SourceEncoding, ChannelDecodeError>,
+                      callsign_hash_cache: &mut CallsignHashCache, player: &mut dyn FramePlayer) {
+        match decoding {
+            Ok(source_encoding) => {
+                self.blocks_decoded += 1;
+                self.last_heard_sample = block.start_sample + self.block_samples();
+                let decode = self.source_decoder.source_decode(source_encoding.block.clone());
+                if let Ok(frames) = &decode {
+                    if let Some(hash) = callsign_hash_cache.observe(frames, get_epoch_ms()) {
+                        self.callsign_hash = Some(hash);
+                    }
+                    for frame in frames {
+                        if let Frame::LocatorMetadata { locator } = frame {
+                            self.locator = Some(locator.clone());
+                        }
+                    }
+                }
+                player.play(decode, self.callsign_hash.unwrap_or(UNKNOWN_CALLSIGN_HASH), self.audio_offset);
+                if source_encoding.is_end {
+                    debug!("Retiring StationDecoder at {} Hz; its transmission has ended", self.audio_offset);
+                    self.ended = true;
+                }
+            }
+            Err(e) => {
+                if self.blocks_decoded == 0 {
+                    debug!("Retiring StationDecoder at {} Hz; first block cannot be decoded: {:?}", self.audio_offset, e);
+                    self.retired = true;
+                } else {
+                    warn!("Cannot decode block at {} Hz: {:?}", self.audio_offset, e);
+                }
+            }
+        }
+    }
+
+    // Has the station ended its transmission, or been silent for too long, as of the given sample?
+    pub fn is_retired(&self, current_sample: usize) -> bool {
+        self.retired || (self.queued_blocks.is_empty() && (self.ended || current_sample > self.last_heard_sample + self.silence_samples))
+    }
+}
+
+// The share of the power of the tones of the Costas Array's symbols that is in the array's tones.
+fn costas_array_strength(tone_magnitudes: &[ToneMagnitudes]) -> f32 {
+    let mut array_power = 0.0;
+    let mut all_power = 0.0;
+    for (magnitudes, array_tone) in tone_magnitudes.iter().zip(COSTAS_ARRAY) {
+        array_power += magnitudes[*array_tone as usize].powi(2);
+        all_power += magnitudes.iter().map(|m| m.powi(2)).sum::<f32>();
+    }
+    if all_power > 0.0 { array_power / all_power } else { 0.0 }
+}
+
+#[cfg(test)]
+#[path = "./station_decoder_spec.rs"]
+mod station_decoder_spec;
@@ -0,0 +1,198 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod station_decoder_spec {
+    use std::env;
+    use std::sync::Arc;
+    use hamcrest2::prelude::*;
+    use crate::libs::channel_codec::channel_encoder::source_encoding_to_channel_encoding;
+    use crate::libs::channel_codec::channel_decoder::ChannelDecodeError;
+    use crate::libs::channel_codec::channel_encoding::CHANNEL_ENCODER_BLOCK_SIZE;
+    use crate::libs::channel_codec::costas::COSTAS_ARRAY_SYMBOLS;
+    use crate::libs::playback::recording_player::RecordingPlayer;
+    use crate::libs::receiver::demodulate::GfskDemodulator;
+    use crate::libs::receiver::station_decoder::{QueuedBlock, StationDecoder};
+    use crate::libs::source_codec::callsign_hash_cache::CallsignHashCache;
+    use crate::libs::source_codec::metadata_codec::hash_callsign;
+    use crate::libs::source_codec::source_decoder::SourceDecoder;
+    use crate::libs::source_codec::source_encoding::{Frame, SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, SourceEncoding, UNKNOWN_CALLSIGN_HASH};
+    use crate::libs::source_codec::test_encoding_builder::encoded;
+    use crate::libs::transmitter::modulate::GfskModulator;
+    use crate::libs::transmitter::transmitter::AudioFrequencyHz;
+    use crate::libs::util::util::get_epoch_ms;
+
+    const SAMPLE_RATE: AudioFrequencyHz = 12000;
+    const AUDIO_OFFSET: AudioFrequencyHz = 800;
+    const SAMPLES_PER_SYMBOL: usize = 1920;
+    const BLOCK_SAMPLES: usize = (COSTAS_ARRAY_SYMBOLS + CHANNEL_ENCODER_BLOCK_SIZE) * SAMPLES_PER_SYMBOL;
+    const SILENCE_SAMPLES: usize = 20 * SAMPLE_RATE as usize;
+
+    #[ctor::ctor]
+    fn before_each() {
+        env::set_var("RUST_LOG", "debug");
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[ctor::dtor]
+    fn after_each() {}
+
+    fn station_decoder() -> StationDecoder {
+        StationDecoder::new(AUDIO_OFFSET, 0, Arc::new(GfskDemodulator::new(SAMPLE_RATE)), SILENCE_SAMPLES)
+    }
+
+    fn source_encoding(frames: &[Frame]) -> SourceEncoding {
+        SourceEncoding { block: encoded(SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, 20, frames), is_end: false, is_metadata_only: false }
+    }
+
+    fn keying_frames() -> Vec<Frame> {
+        vec![Frame::WPMPolarity { wpm: 20, polarity: true }, Frame::KeyingPerfectDah, Frame::KeyingPerfectDit, Frame::KeyingPerfectDah]
+    }
+
+    fn source_decoded(source_encoding: &SourceEncoding) -> Vec<Frame> {
+        SourceDecoder::new(SOURCE_ENCODER_BLOCK_SIZE_IN_BITS).source_decode(source_encoding.block.clone()).unwrap()
+    }
+
+    // The block's waveform, ramped down, with the given number of samples of silence before it,
+    // and a symbol's silence after it.
+    fn modulated_block(source_encoding: &SourceEncoding, lead_in: usize) -> Vec<f32> {
+        let symbols = source_encoding_to_channel_encoding(source_encoding.clone()).block;
+        let mut modulator = GfskModulator::new(SAMPLE_RATE);
+        let samples = modulator.number_of_samples(symbols.len(), false, true);
+        let mut audio = vec![0.0; lead_in + samples + SAMPLES_PER_SYMBOL];
+        modulator.modulate(AUDIO_OFFSET, &symbols, &mut audio[lead_in..lead_in + samples], false, true);
+        audio
+    }
+
+    #[test]
+    fn block_is_the_costas_array_and_codeword() {
+        assert_that!(station_decoder().block_samples(), equal_to(BLOCK_SAMPLES));
+    }
+
+    #[test]
+    fn blocks_overlapping_the_previous_block_are_ignored() {
+        let mut decoder = station_decoder();
+        assert_that!(decoder.queue_block(QueuedBlock { start_sample: 1000, audio_offset: AUDIO_OFFSET }), equal_to(true));
+        // A chance match within the block.
+        assert_that!(decoder.queue_block(QueuedBlock { start_sample: 1000 + 30 * SAMPLES_PER_SYMBOL, audio_offset: AUDIO_OFFSET }), equal_to(false));
+        // The next block, detected a little early.
+        let next = QueuedBlock { start_sample: 1000 + BLOCK_SAMPLES - SAMPLES_PER_SYMBOL / 2, audio_offset: AUDIO_OFFSET + 6 };
+        assert_that!(decoder.queue_block(next), equal_to(true));
+
+        assert_that!(decoder.dequeue_block().unwrap().start_sample, equal_to(1000));
+        assert_that!(decoder.next_queued_block(), equal_to(Some(next)));
+        assert_that!(decoder.dequeue_block(), equal_to(Some(next)));
+        assert_that!(decoder.next_queued_block(), equal_to(None));
+    }
+
+    #[test]
+    fn decoding_needs_a_whole_block() {
+        let decoding = station_decoder().decode_block(&[0.0; BLOCK_SAMPLES - 1], AUDIO_OFFSET);
+        assert_that!(matches!(decoding, Err(ChannelDecodeError::DemodulationFailure(_))), equal_to(true));
+    }
+
+    #[test]
+    fn block_out_of_the_demodulators_range_is_not_decoded() {
+        let decoding = station_decoder().decode_block(&[0.0; BLOCK_SAMPLES + SAMPLES_PER_SYMBOL], 5950);
+        assert_that!(matches!(decoding, Err(ChannelDecodeError::DemodulationFailure(_))), equal_to(true));
+    }
+
+    #[test]
+    fn block_is_decoded_from_within_the_audio_given() {
+        let source_encoding = source_encoding(&keying_frames());
+        // The block starts between two of the start search steps.
+        let audio = modulated_block(&source_encoding, 317);
+        let decoding = station_decoder().decode_block(&audio, AUDIO_OFFSET);
+        assert_that!(decoding, equal_to(Ok(source_encoding)));
+    }
+
+    #[test]
+    fn silence_is_not_decoded() {
+        let decoding = station_decoder().decode_block(&[0.0; BLOCK_SAMPLES + SAMPLES_PER_SYMBOL], AUDIO_OFFSET);
+        assert_that!(decoding.is_err(), equal_to(true));
+    }
+
+    #[test]
+    fn decoded_blocks_are_played_with_the_stations_callsign_hash() {
+        let mut decoder = station_decoder();
+        let mut cache = CallsignHashCache::default();
+        let mut player = RecordingPlayer::default();
+        let hash = hash_callsign("M0CUV".to_string()).unwrap();
+
+        // Before the station identifies itself, its frames are played with the unknown hash.
+        let anonymous = source_encoding(&keying_frames());
+        decoder.play_block(QueuedBlock { start_sample: 0, audio_offset: AUDIO_OFFSET }, Ok(anonymous.clone()), &mut cache, &mut player);
+        assert_that!(decoder.callsign_hash(), equal_to(None));
+
+        let mut identified_frames = vec![Frame::CallsignMetadata { callsign: "M0CUV".to_string() }];
+        identified_frames.extend(keying_frames());
+        let identified = source_encoding(&identified_frames);
+        decoder.play_block(QueuedBlock { start_sample: BLOCK_SAMPLES, audio_offset: AUDIO_OFFSET + 1 }, Ok(identified.clone()), &mut cache, &mut player);
+        assert_that!(decoder.callsign_hash(), equal_to(Some(hash)));
+
+        // Subsequent blocks are played with its hash, even if they don't carry it.
+        decoder.play_block(QueuedBlock { start_sample: 2 * BLOCK_SAMPLES, audio_offset: AUDIO_OFFSET }, Ok(anonymous.clone()), &mut cache, &mut player);
+
+        assert_that!(decoder.blocks_decoded(), equal_to(3));
+        let expected_plays = vec![
+            (Some(source_decoded(&anonymous)), UNKNOWN_CALLSIGN_HASH, AUDIO_OFFSET),
+            (Some(source_decoded(&identified)), hash, AUDIO_OFFSET),
+            (Some(source_decoded(&anonymous)), hash, AUDIO_OFFSET),
+        ];
+        assert_that!(&player.plays, equal_to(&expected_plays));
+        assert_that!(cache.lookup(hash, get_epoch_ms()).is_some(), equal_to(true));
+    }
+
+    #[test]
+    fn undecodable_first_block_retires_the_station() {
+        let mut decoder = station_decoder();
+        let mut player = RecordingPlayer::default();
+        decoder.play_block(QueuedBlock { start_sample: 0, audio_offset: AUDIO_OFFSET }, Err(ChannelDecodeError::LDPCDecodeFailure),
+                           &mut CallsignHashCache::default(), &mut player);
+        assert_that!(decoder.is_retired(BLOCK_SAMPLES), equal_to(true));
+        assert_that!(player.plays.len(), equal_to(0));
+    }
+
+    #[test]
+    fn undecodable_later_block_does_not_retire_the_station() {
+        let mut decoder = station_decoder();
+        let mut cache = CallsignHashCache::default();
+        let mut player = RecordingPlayer::default();
+        decoder.play_block(QueuedBlock { start_sample: 0, audio_offset: AUDIO_OFFSET }, Ok(source_encoding(&keying_frames())), &mut cache, &mut player);
+        decoder.play_block(QueuedBlock { start_sample: BLOCK_SAMPLES, audio_offset: AUDIO_OFFSET }, Err(ChannelDecodeError::LDPCDecodeFailure), &mut cache, &mut player);
+        assert_that!(decoder.is_retired(2 * BLOCK_SAMPLES), equal_to(false));
+        assert_that!(player.plays.len(), equal_to(1));
+    }
+
+    #[test]
+    fn station_retires_after_silence() {
+        let mut decoder = station_decoder();
+        let mut player = RecordingPlayer::default();
+        decoder.play_block(QueuedBlock { start_sample: 0, audio_offset: AUDIO_OFFSET }, Ok(source_encoding(&keying_frames())),
+                           &mut CallsignHashCache::default(), &mut player);
+        assert_that!(decoder.is_retired(BLOCK_SAMPLES + SILENCE_SAMPLES), equal_to(false));
+        assert_that!(decoder.is_retired(BLOCK_SAMPLES + SILENCE_SAMPLES + 1), equal_to(true));
+
+        // Not while a block is queued, though.
+        decoder.queue_block(QueuedBlock { start_sample: 2 * BLOCK_SAMPLES + SILENCE_SAMPLES, audio_offset: AUDIO_OFFSET });
+        assert_that!(decoder.is_retired(BLOCK_SAMPLES + SILENCE_SAMPLES + 1), equal_to(false));
+    }
+
+    #[test]
+    fn station_retires_once_the_end_of_its_transmission_is_played() {
+        let mut decoder = station_decoder();
+        let mut player = RecordingPlayer::default();
+        let mut cache = CallsignHashCache::default();
+        decoder.play_block(QueuedBlock { start_sample: 0, audio_offset: AUDIO_OFFSET }, Ok(source_encoding(&keying_frames())), &mut cache, &mut player);
+        assert_that!(decoder.is_retired(BLOCK_SAMPLES), equal_to(false));
+
+        let end = SourceEncoding { is_end: true, ..source_encoding(&keying_frames()) };
+        decoder.play_block(QueuedBlock { start_sample: BLOCK_SAMPLES, audio_offset: AUDIO_OFFSET }, Ok(end), &mut cache, &mut player);
+        assert_that!(player.plays.len(), equal_to(2));
+        assert_that!(decoder.has_ended(), equal_to(true));
+        assert_that!(decoder.is_retired(2 * BLOCK_SAMPLES), equal_to(true));
+
+        // Not while a block is queued, though: the station has started another transmission.
+        decoder.queue_block(QueuedBlock { start_sample: 2 * BLOCK_SAMPLES, audio_offset: AUDIO_OFFSET });
+        assert_that!(decoder.is_retired(2 * BLOCK_SAMPLES), equal_to(false));
+    }
+}
@@ -0,0 +1,98 @@
+/*
+ * Decodes a recorded waveform file (such as a capture of the band) from start to finish, offline,
+ * as fast as it can be processed, rather than in real time. The audio takes the same path as that
+ * of the Receiver: its first channel is downsampled to 12000Hz by the Decimator, and given to the
+ * Decoder (which FFTs it, detects Costas Arrays, and decodes the stations found) one second at a
+ * time. The decoded frames are given to a FramePlayer; the FramePrinter logs them, and the
+ * stations heard.
+ */
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use log::info;
+use crate::libs::buffer_pool::observable_buffer::{OBSERVABLE_BUFFER_HOP_SIZE, OBSERVABLE_BUFFER_SLICE_SIZE};
+use crate::libs::playback::playback::FramePlayer;
+use crate::libs::receiver::decimator::Decimator;
+use crate::libs::receiver::decoder::Decoder;
+use crate::libs::receiver::receiver::DOWNSAMPLED_SAMPLE_RATE;
+use crate::libs::source_codec::callsign_hash_cache::CallsignHashCache;
+use crate::libs::source_codec::source_encoding::{CallsignHash, Frame, UNKNOWN_CALLSIGN_HASH};
+use crate::libs::util::util::get_epoch_ms;
+use crate::libs::wav::wav::read_waveform_file_with_sample_rate;
+
+// Decode the waveform file, giving the decoded frames to the player. Returns the duration of the
+// waveform, in seconds.
+pub fn decode_waveform_file(filename: &str, player: Arc<Mutex<dyn FramePlayer>>) -> Result<f32, Box<dyn Error>> {
+    let (samples, sample_rate) = read_waveform_file_with_sample_rate(filename)?;
+    if sample_rate == 0 || sample_rate % DOWNSAMPLED_SAMPLE_RATE as u32 != 0 {
+        return Err(Box::<dyn Error + Send + Sync>::from(format!("Can't downsample waveform file {} at {}Hz to {}Hz", filename, sample_rate, DOWNSAMPLED_SAMPLE_RATE)));
+    }
+    info!("Decoding {} samples at {}Hz from {}", samples.len(), sample_rate, filename);
+    let mut decimator = Decimator::new((sample_rate / DOWNSAMPLED_SAMPLE_RATE as u32) as usize);
+    // As many spectra per symbol as the Receiver emits.
+    let mut decoder = Decoder::new(DOWNSAMPLED_SAMPLE_RATE, OBSERVABLE_BUFFER_SLICE_SIZE / OBSERVABLE_BUFFER_HOP_SIZE, player);
+    for second in samples.chunks(sample_rate as usize) {
+        let downsampled: Vec<f32> = second.iter().filter_map(|sample| decimator.add_sample(*sample)).collect();
+        decoder.add_samples(&downsampled);
+    }
+    // A symbol of silence, so that a block that ends with the waveform can be decoded.
+    decoder.add_samples(&[0.0; OBSERVABLE_BUFFER_SLICE_SIZE]);
+    Ok(samples.len() as f32 / sample_rate as f32)
+}
+
+// Logs the frames decoded from each block, with the station that sent them, and keeps a count of
+// the blocks decoded from each station.
+#[derive(Default)]
+pub struct FramePrinter {
+    callsign_hash_cache: CallsignHashCache,
+    blocks_by_station: BTreeMap<(u16, CallsignHash), usize>,
+}
+
+impl FramePrinter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The stations heard: their audio offset, callsign hash, and the number of blocks decoded.
+    pub fn stations(&self) -> Vec<(u16, CallsignHash, usize)> {
+        self.blocks_by_station.iter().map(|((audio_offset, hash), blocks)| (*audio_offset, *hash, *blocks)).collect()
+    }
+
+    pub fn log_stations(&self) {
+        info!("{} station(s) heard", self.blocks_by_station.len());
+        for (audio_offset, hash, blocks) in self.stations() {
+            info!("{} Hz {}: {} block(s)", audio_offset, self.station_name(hash), blocks);
+        }
+    }
+
+    // The station's callsign if it has been decoded, otherwise its hash.
+    fn station_name(&self, hash: CallsignHash) -> String {
+        if hash == UNKNOWN_CALLSIGN_HASH {
+            return "(unidentified)".to_string();
+        }
+        match self.callsign_hash_cache.lookup(hash, get_epoch_ms()) {
+            Some(callsign) => callsign,
+            None => format!("#{:06X}", hash),
+        }
+    }
+}
+
+impl FramePlayer for FramePrinter {
+    fn play(&mut self, decode: Result<Vec<Frame>, Box<dyn Error>>, callsign_hash: CallsignHash, audio_offset: u16) {
+        match decode {
+            Ok(frames) => {
+                self.callsign_hash_cache.observe(&frames, get_epoch_ms());
+                *self.blocks_by_station.entry((audio_offset, callsign_hash)).or_insert(0) += 1;
+                info!("{} Hz {}: {:?}", audio_offset, self.station_name(callsign_hash), frames);
+            }
+            Err(e) => {
+                info!("{} Hz {}: cannot decode frames: {}", audio_offset, self.station_name(callsign_hash), e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./waveform_file_decoder_spec.rs"]
+mod waveform_file_decoder_spec;
@@ -0,0 +1,108 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod waveform_file_decoder_spec {
+    use std::env;
+    use std::fs::File;
+    use std::sync::{Arc, Mutex};
+    use hamcrest2::prelude::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use wav::{BitDepth, Header, WAV_FORMAT_IEEE_FLOAT};
+    use crate::libs::channel_codec::channel_encoder::source_encoding_to_channel_encoding;
+    use crate::libs::playback::recording_player::RecordingPlayer;
+    use crate::libs::receiver::waveform_file_decoder::{decode_waveform_file, FramePrinter};
+    use crate::libs::source_codec::metadata_codec::hash_callsign;
+    use crate::libs::source_codec::source_decoder::SourceDecoder;
+    use crate::libs::source_codec::source_encoding::{Frame, SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, SourceEncoding};
+    use crate::libs::source_codec::test_encoding_builder::encoded;
+    use crate::libs::transmitter::modulate::GfskModulator;
+    use crate::libs::transmitter::transmitter::AudioFrequencyHz;
+    use crate::libs::util::test_util::{awgn, mix, temp_filename};
+    use crate::libs::wav::wav::write_waveform_file;
+
+    const SAMPLE_RATE: AudioFrequencyHz = 48000;
+
+    #[ctor::ctor]
+    fn before_each() {
+        env::set_var("RUST_LOG", "info");
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[ctor::dtor]
+    fn after_each() {}
+
+    // The blocks, each identifying the station, channel-encoded and modulated as one transmission.
+    fn transmission(audio_offset: AudioFrequencyHz, callsign: &str, blocks: usize) -> (Vec<SourceEncoding>, Vec<f32>) {
+        let source_encodings: Vec<SourceEncoding> = (0..blocks)
+            .map(|block| {
+                let frames = [Frame::CallsignMetadata { callsign: callsign.to_string() },
+                    Frame::WPMPolarity { wpm: 20, polarity: true }, Frame::KeyingPerfectDah, Frame::KeyingPerfectDit];
+                SourceEncoding { block: encoded(SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, 20, &frames), is_end: block == blocks - 1, is_metadata_only: false }
+            })
+            .collect();
+        let mut modulator = GfskModulator::new(SAMPLE_RATE);
+        let mut waveform = vec![];
+        for (index, source_encoding) in source_encodings.iter().enumerate() {
+            let symbols = source_encoding_to_channel_encoding(source_encoding.clone()).block;
+            let (first, last) = (index == 0, index == blocks - 1);
+            let mut block = vec![0.0; modulator.number_of_samples(symbols.len(), first, last)];
+            modulator.modulate(audio_offset, &symbols, &mut block, first, last);
+            waveform.extend(block);
+        }
+        (source_encodings, waveform)
+    }
+
+    #[test]
+    fn missing_file_is_an_error() {
+        let player = Arc::new(Mutex::new(FramePrinter::new()));
+        assert_that!(decode_waveform_file(&temp_filename("no-such-capture.wav"), player).is_err(), equal_to(true));
+    }
+
+    #[test]
+    fn sample_rate_that_cannot_be_downsampled_is_an_error() {
+        let filename = temp_filename("capture-at-44100hz.wav");
+        let mut out_file = File::create(&filename).unwrap();
+        wav::write(Header::new(WAV_FORMAT_IEEE_FLOAT, 1, 44100, 32), &BitDepth::ThirtyTwoFloat(vec![0.0; 44100]), &mut out_file).unwrap();
+        let player = Arc::new(Mutex::new(FramePrinter::new()));
+        assert_that!(decode_waveform_file(&filename, player).is_err(), equal_to(true));
+    }
+
+    #[test]
+    fn stations_in_a_recorded_capture_are_decoded() {
+        // The stations at -8 and -12dB, starting 0.7 and 5.3s in. Scaled down to be quiet, as a band
+        // capture might be: the noise then has power 0.0001 in 2500Hz.
+        let (first, first_waveform) = transmission(1100, "M0CUV", 2);
+        let (second, second_waveform) = transmission(1900, "G4DPZ", 1);
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        let mut capture = awgn(first_waveform.len() + 2 * SAMPLE_RATE as usize, SAMPLE_RATE, 1.0, &mut rng);
+        mix(&mut capture, &first_waveform, (0.7 * SAMPLE_RATE as f32) as usize, -8.0);
+        mix(&mut capture, &second_waveform, (5.3 * SAMPLE_RATE as f32) as usize, -12.0);
+        capture.iter_mut().for_each(|sample| *sample *= 0.01);
+        let filename = temp_filename("waveform-file-decoder-capture.wav");
+        write_waveform_file(capture, &filename).unwrap();
+
+        let player = Arc::new(Mutex::new(RecordingPlayer::default()));
+        let seconds = decode_waveform_file(&filename, player.clone()).unwrap();
+        assert_that!(seconds, greater_than(24.0));
+
+        let player = player.lock().unwrap();
+        let source_decoder = SourceDecoder::new(SOURCE_ENCODER_BLOCK_SIZE_IN_BITS);
+        let decoded = |source_encodings: &[SourceEncoding]| source_encodings.iter()
+            .map(|source_encoding| source_decoder.source_decode(source_encoding.block.clone()).ok())
+            .collect::<Vec<_>>();
+        let played_at = |audio_offset: u16| player.plays.iter()
+            .filter(|play| play.2 == audio_offset)
+            .map(|play| play.0.clone())
+            .collect::<Vec<_>>();
+        assert_that!(played_at(1100), equal_to(decoded(&first)));
+        assert_that!(played_at(1900), equal_to(decoded(&second)));
+        assert_that!(player.plays.len(), equal_to(3));
+
+        let first_hash = hash_callsign("M0CUV".to_string()).unwrap();
+        let second_hash = hash_callsign("G4DPZ".to_string()).unwrap();
+        let mut stations = player.plays.iter().map(|play| (play.2, play.1)).collect::<Vec<_>>();
+        stations.sort();
+        assert_that!(stations, equal_to(vec![(1100, first_hash), (1100, first_hash), (1900, second_hash)]));
+    }
+}
@@ -4,11 +4,13 @@ use std::time::Duration;
 use std::io;
 
 // This trait is an abstraction over serialport, so that it can be mocked. Read and write are
-// blocking.
+// blocking. The modem control lines (RTS, DTR) can be set, e.g. to key a rig's PTT.
 pub trait SerialIO : Send {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize>;
     fn write(&mut self, buf: &[u8]) -> io::Result<usize>;
     fn flush(&mut self) -> io::Result<()>;
+    fn set_request_to_send(&mut self, level: bool) -> io::Result<()>;
+    fn set_data_terminal_ready(&mut self, level: bool) -> io::Result<()>;
 }
 
 // A SerialIO that uses serialport.
@@ -57,6 +59,14 @@ impl SerialIO for DefaultSerialIO {
     fn flush(&mut self) -> io::Result<()> {
         self.serial_port.flush()
     }
+
+    fn set_request_to_send(&mut self, level: bool) -> io::Result<()> {
+        Ok(self.serial_port.write_request_to_send(level)?)
+    }
+
+    fn set_data_terminal_ready(&mut self, level: bool) -> io::Result<()> {
+        Ok(self.serial_port.write_data_terminal_ready(level)?)
+    }
 }
 
 pub struct NullSerialIO {
@@ -80,6 +90,14 @@ impl SerialIO for NullSerialIO {
     fn flush(&mut self) -> io::Result<()> {
         Ok(())
     }
+
+    fn set_request_to_send(&mut self, _level: bool) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn set_data_terminal_ready(&mut self, _level: bool) -> io::Result<()> {
+        Ok(())
+    }
 }
 
 
@@ -5,6 +5,7 @@ use crate::libs::source_codec::source_encoding::{SourceEncodingBuilder, SourceEn
 pub struct BitvecSourceEncodingBuilder {
     bits: BitVec::<Msb0, u8>,
     end: bool,
+    metadata_only: bool,
     block_size_in_bits: usize,
 }
 
@@ -19,6 +20,7 @@ impl BitvecSourceEncodingBuilder {
         Self {
             bits: bit_vec,
             end: false,
+            metadata_only: false,
             block_size_in_bits
         }
     }
@@ -83,6 +85,10 @@ impl SourceEncodingBuilder for BitvecSourceEncodingBuilder {
         self.end = true;
     }
 
+    fn set_metadata_only(&mut self) {
+        self.metadata_only = true;
+    }
+
     fn build(&mut self) -> SourceEncoding {
         // Extend the bitvec to its capacity
         unsafe {
@@ -91,10 +97,12 @@ impl SourceEncodingBuilder for BitvecSourceEncodingBuilder {
         let out = SourceEncoding {
             block: self.bits.as_raw_slice().to_vec(),
             is_end: self.end,
+            is_metadata_only: self.metadata_only,
         };
         self.bits.clear();
         self.bits.set_uninitialized(false);
         self.end = false;
+        self.metadata_only = false;
         out
     }
 }
@@ -119,6 +119,27 @@ mod bitvec_source_encoding_builder_spec {
         assert_eq!(encoding.is_end, false);
     }
 
+    #[rstest]
+    pub fn blocks_are_not_metadata_only_by_default(mut fixture: BitvecSourceEncodingBuilderFixture) {
+        let encoding = fixture.storage.build();
+        assert_eq!(encoding.is_metadata_only, false);
+    }
+
+    #[rstest]
+    pub fn blocks_can_be_set_as_metadata_only(mut fixture: BitvecSourceEncodingBuilderFixture) {
+        fixture.storage.set_metadata_only();
+        let encoding = fixture.storage.build();
+        assert_eq!(encoding.is_metadata_only, true);
+    }
+
+    #[rstest]
+    pub fn blocks_metadata_only_state_is_reset_on_build(mut fixture: BitvecSourceEncodingBuilderFixture) {
+        fixture.storage.set_metadata_only();
+        fixture.storage.build();
+        let encoding = fixture.storage.build();
+        assert_eq!(encoding.is_metadata_only, false);
+    }
+
     #[rstest]
     pub fn build_clears_for_new_block(mut fixture: BitvecSourceEncodingBuilderFixture) {
         fixture.storage.add_bool(true);
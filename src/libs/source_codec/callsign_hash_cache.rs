@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+
+use log::{debug, warn};
+
+use crate::libs::source_codec::metadata_codec::{hash_callsign, MetadataCodecError};
+use crate::libs::source_codec::source_encoding::{Callsign, CallsignHash, Frame};
+
+/*
+ * Most blocks carry only a station's CallsignHash, not its full Callsign. The full Callsign is
+ * sent at the start of each transmission, so a receiver that has decoded it can remember which
+ * callsign a hash belongs to, and show the full callsign for subsequent blocks that only carry the
+ * hash. Seeing either the callsign or its hash extends the entry's expiry, so a station's callsign
+ * is remembered for as long as it keeps transmitting, however long its transmissions are.
+ *
+ * Hashes are lossy: two callsigns may have the same hash. If a callsign is decoded whose hash is
+ * already held for a different callsign, this is reported as a collision, and the newer callsign
+ * replaces the older. Entries that have not been seen for the expiry period are forgotten, and if
+ * the cache is full, the least recently seen entry is evicted to make room for a new one.
+ */
+
+// A station that has not been heard for this period is forgotten; its callsign will be learnt
+// again at the start of its next transmission.
+pub const CALLSIGN_HASH_CACHE_EXPIRY_MS: u128 = 20 * 60 * 1000;
+pub const CALLSIGN_HASH_CACHE_CAPACITY: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CallsignHashCacheInsertion {
+    // The callsign was not previously held.
+    Added(CallsignHash),
+    // The callsign was already held; its expiry has been extended.
+    Refreshed(CallsignHash),
+    // A different callsign was held with the same hash; it has been replaced by this callsign.
+    Collision(CallsignHash, Callsign),
+}
+
+#[derive(Debug, Clone)]
+struct CallsignHashCacheEntry {
+    callsign: Callsign,
+    last_seen_epoch_ms: u128,
+}
+
+pub struct CallsignHashCache {
+    capacity: usize,
+    expiry_ms: u128,
+    entries: HashMap<CallsignHash, CallsignHashCacheEntry>,
+}
+
+impl Default for CallsignHashCache {
+    fn default() -> Self {
+        Self::new(CALLSIGN_HASH_CACHE_CAPACITY, CALLSIGN_HASH_CACHE_EXPIRY_MS)
+    }
+}
+
+impl CallsignHashCache {
+    pub fn new(capacity: usize, expiry_ms: u128) -> Self {
+        Self {
+            capacity,
+            expiry_ms,
+            entries: HashMap::with_capacity(capacity),
+        }
+    }
+
+    // Callsigns are held in upper case, as they would be decoded.
+    pub fn insert(&mut self, callsign: Callsign, now_epoch_ms: u128) -> Result<CallsignHashCacheInsertion, MetadataCodecError> {
+        let callsign = callsign.to_ascii_uppercase();
+        let hash = hash_callsign(callsign.clone())?;
+        self.expire(now_epoch_ms);
+        let insertion = match self.entries.get(&hash) {
+            Some(entry) if entry.callsign == callsign => CallsignHashCacheInsertion::Refreshed(hash),
+            Some(entry) => {
+                warn!("Callsign hash {:#08x} collision: {} replaces {}", hash, callsign, entry.callsign);
+                CallsignHashCacheInsertion::Collision(hash, entry.callsign.clone())
+            }
+            None => {
+                if self.entries.len() >= self.capacity {
+                    self.evict_least_recently_seen();
+                }
+                debug!("Callsign hash {:#08x} is {}", hash, callsign);
+                CallsignHashCacheInsertion::Added(hash)
+            }
+        };
+        self.entries.insert(hash, CallsignHashCacheEntry { callsign, last_seen_epoch_ms: now_epoch_ms });
+        Ok(insertion)
+    }
+
+    // The callsign last seen with this hash, if it has not expired.
+    pub fn lookup(&self, hash: CallsignHash, now_epoch_ms: u128) -> Option<Callsign> {
+        self.entries.get(&hash)
+            .filter(|entry| !self.is_expired(entry, now_epoch_ms))
+            .map(|entry| entry.callsign.clone())
+    }
+
+    // Examine the frames decoded from a block, caching any Callsign, and refreshing the entry for
+    // any CallsignHash, and return the hash of the station that sent the block, from its Callsign
+    // or CallsignHash, if either is present.
+    pub fn observe(&mut self, frames: &[Frame], now_epoch_ms: u128) -> Option<CallsignHash> {
+        let mut station_hash = None;
+        for frame in frames {
+            match frame {
+                Frame::CallsignMetadata { callsign } => {
+                    match self.insert(callsign.clone(), now_epoch_ms) {
+                        Ok(CallsignHashCacheInsertion::Added(hash)) |
+                        Ok(CallsignHashCacheInsertion::Refreshed(hash)) |
+                        Ok(CallsignHashCacheInsertion::Collision(hash, _)) => {
+                            station_hash = Some(hash);
+                        }
+                        Err(e) => {
+                            warn!("Cannot cache callsign: {}", e);
+                        }
+                    }
+                }
+                Frame::CallsignHashMetadata { hash } => {
+                    self.refresh(*hash, now_epoch_ms);
+                    station_hash = Some(*hash);
+                }
+                _ => {}
+            }
+        }
+        station_hash
+    }
+
+    // Extend the expiry of the callsign held with this hash, if it has not expired.
+    fn refresh(&mut self, hash: CallsignHash, now_epoch_ms: u128) {
+        let expiry_ms = self.expiry_ms;
+        if let Some(entry) = self.entries.get_mut(&hash) {
+            if now_epoch_ms.saturating_sub(entry.last_seen_epoch_ms) < expiry_ms {
+                entry.last_seen_epoch_ms = now_epoch_ms;
+            }
+        }
+    }
+
+    pub fn expire(&mut self, now_epoch_ms: u128) {
+        let expiry_ms = self.expiry_ms;
+        self.entries.retain(|hash, entry| {
+            let retain = now_epoch_ms.saturating_sub(entry.last_seen_epoch_ms) < expiry_ms;
+            if !retain {
+                debug!("Callsign hash {:#08x} for {} has expired", hash, entry.callsign);
+            }
+            retain
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn is_expired(&self, entry: &CallsignHashCacheEntry, now_epoch_ms: u128) -> bool {
+        now_epoch_ms.saturating_sub(entry.last_seen_epoch_ms) >= self.expiry_ms
+    }
+
+    fn evict_least_recently_seen(&mut self) {
+        let oldest = self.entries.iter()
+            .min_by_key(|(_, entry)| entry.last_seen_epoch_ms)
+            .map(|(hash, _)| *hash);
+        if let Some(hash) = oldest {
+            debug!("Callsign hash cache is full; evicting {:#08x}", hash);
+            self.entries.remove(&hash);
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./callsign_hash_cache_spec.rs"]
+mod callsign_hash_cache_spec;
@@ -0,0 +1,160 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod callsign_hash_cache_spec {
+    use std::env;
+    use hamcrest2::prelude::*;
+    use rstest::*;
+    use crate::libs::source_codec::callsign_hash_cache::{CallsignHashCache, CallsignHashCacheInsertion};
+    use crate::libs::source_codec::metadata_codec::{hash_callsign, MetadataCodecError};
+    use crate::libs::source_codec::source_encoding::Frame;
+
+    const EXPIRY_MS: u128 = 1000;
+    const M0CUV_HASH: u32 = 0x397c48;
+
+    #[ctor::ctor]
+    fn before_each() {
+        env::set_var("RUST_LOG", "debug");
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[ctor::dtor]
+    fn after_each() {}
+
+    #[fixture]
+    fn cache() -> CallsignHashCache {
+        CallsignHashCache::new(3, EXPIRY_MS)
+    }
+
+    #[test]
+    fn hash_is_deterministic() {
+        assert_that!(hash_callsign("M0CUV".to_owned()), eq(Ok(M0CUV_HASH)));
+        assert_that!(hash_callsign("m0cuv".to_owned()), eq(Ok(M0CUV_HASH)));
+        assert_that!(hash_callsign("M0CUV/P".to_owned()), eq(Ok(0x20f587)));
+        assert_that!(hash_callsign("F/M0CUV".to_owned()), eq(Ok(0x109c27)));
+    }
+
+    #[rstest(callsign,
+        case(""),
+        case("M0CUV/ABCDEF"),
+        case("M0-UV"),
+    )]
+    fn invalid_callsigns_cannot_be_hashed(callsign: &str) {
+        assert_that!(hash_callsign(callsign.to_owned()), eq(Err(MetadataCodecError::InvalidCallsign(callsign.to_owned()))));
+    }
+
+    #[rstest]
+    fn empty_cache_has_no_callsigns(cache: CallsignHashCache) {
+        assert_that!(cache.is_empty(), eq(true));
+        assert_that!(&cache.lookup(M0CUV_HASH, 0), none());
+    }
+
+    #[rstest]
+    fn inserted_callsign_can_be_looked_up_by_hash(mut cache: CallsignHashCache) {
+        assert_that!(cache.insert("m0cuv".to_owned(), 0), eq(Ok(CallsignHashCacheInsertion::Added(M0CUV_HASH))));
+        assert_that!(cache.lookup(M0CUV_HASH, 10), eq(Some("M0CUV".to_owned())));
+        assert_that!(cache.len(), eq(1));
+    }
+
+    #[rstest]
+    fn reinserted_callsign_is_refreshed(mut cache: CallsignHashCache) {
+        cache.insert("M0CUV".to_owned(), 0).unwrap();
+        assert_that!(cache.insert("M0CUV".to_owned(), 900), eq(Ok(CallsignHashCacheInsertion::Refreshed(M0CUV_HASH))));
+        // Would have expired at 1000 had it not been refreshed.
+        assert_that!(cache.lookup(M0CUV_HASH, 1500), eq(Some("M0CUV".to_owned())));
+        assert_that!(cache.len(), eq(1));
+    }
+
+    #[rstest]
+    fn colliding_callsign_replaces_existing(mut cache: CallsignHashCache) {
+        cache.insert("M0CUV".to_owned(), 0).unwrap();
+        assert_that!(cache.insert("AD6FBR".to_owned(), 10), eq(Ok(CallsignHashCacheInsertion::Collision(M0CUV_HASH, "M0CUV".to_owned()))));
+        assert_that!(cache.lookup(M0CUV_HASH, 20), eq(Some("AD6FBR".to_owned())));
+        assert_that!(cache.len(), eq(1));
+    }
+
+    #[rstest]
+    fn callsigns_expire(mut cache: CallsignHashCache) {
+        cache.insert("M0CUV".to_owned(), 0).unwrap();
+        assert_that!(cache.lookup(M0CUV_HASH, EXPIRY_MS - 1), eq(Some("M0CUV".to_owned())));
+        assert_that!(&cache.lookup(M0CUV_HASH, EXPIRY_MS), none());
+        cache.expire(EXPIRY_MS);
+        assert_that!(cache.is_empty(), eq(true));
+    }
+
+    #[rstest]
+    fn expired_colliding_callsign_is_not_a_collision(mut cache: CallsignHashCache) {
+        cache.insert("M0CUV".to_owned(), 0).unwrap();
+        assert_that!(cache.insert("AD6FBR".to_owned(), EXPIRY_MS), eq(Ok(CallsignHashCacheInsertion::Added(M0CUV_HASH))));
+    }
+
+    #[rstest]
+    fn least_recently_seen_is_evicted_when_full(mut cache: CallsignHashCache) {
+        cache.insert("M0CUV".to_owned(), 0).unwrap();
+        cache.insert("G4ABC".to_owned(), 10).unwrap();
+        cache.insert("K1A".to_owned(), 20).unwrap();
+        cache.insert("M0CUV".to_owned(), 30).unwrap();
+        cache.insert("W1AW".to_owned(), 40).unwrap();
+        assert_that!(cache.len(), eq(3));
+        assert_that!(&cache.lookup(hash_callsign("G4ABC".to_owned()).unwrap(), 50), none());
+        assert_that!(cache.lookup(M0CUV_HASH, 50), eq(Some("M0CUV".to_owned())));
+        assert_that!(cache.lookup(hash_callsign("K1A".to_owned()).unwrap(), 50), eq(Some("K1A".to_owned())));
+        assert_that!(cache.lookup(hash_callsign("W1AW".to_owned()).unwrap(), 50), eq(Some("W1AW".to_owned())));
+    }
+
+    #[rstest]
+    fn invalid_callsign_cannot_be_inserted(mut cache: CallsignHashCache) {
+        assert_that!(cache.insert("M0-UV".to_owned(), 0), eq(Err(MetadataCodecError::InvalidCallsign("M0-UV".to_owned()))));
+        assert_that!(cache.is_empty(), eq(true));
+    }
+
+    #[rstest]
+    fn observing_a_callsign_frame_caches_it(mut cache: CallsignHashCache) {
+        let frames = vec![
+            Frame::CallsignMetadata { callsign: "M0CUV".to_owned() },
+            Frame::WPMPolarity { wpm: 20, polarity: true },
+            Frame::KeyingPerfectDit,
+        ];
+        assert_that!(cache.observe(&frames, 0), eq(Some(M0CUV_HASH)));
+        assert_that!(cache.lookup(M0CUV_HASH, 10), eq(Some("M0CUV".to_owned())));
+    }
+
+    #[rstest]
+    fn observing_a_hash_frame_gives_its_hash_without_caching(mut cache: CallsignHashCache) {
+        let frames = vec![
+            Frame::CallsignHashMetadata { hash: M0CUV_HASH },
+            Frame::WPMPolarity { wpm: 20, polarity: true },
+        ];
+        assert_that!(cache.observe(&frames, 0), eq(Some(M0CUV_HASH)));
+        assert_that!(cache.is_empty(), eq(true));
+    }
+
+    #[rstest]
+    fn observing_a_hash_frame_refreshes_its_callsign(mut cache: CallsignHashCache) {
+        cache.insert("M0CUV".to_owned(), 0).unwrap();
+        let frames = vec![
+            Frame::CallsignHashMetadata { hash: M0CUV_HASH },
+            Frame::KeyingPerfectDit,
+        ];
+        assert_that!(cache.observe(&frames, 900), eq(Some(M0CUV_HASH)));
+        // Would have expired at 1000 had it not been refreshed.
+        assert_that!(cache.lookup(M0CUV_HASH, 1500), eq(Some("M0CUV".to_owned())));
+    }
+
+    #[rstest]
+    fn observing_a_hash_frame_does_not_revive_an_expired_callsign(mut cache: CallsignHashCache) {
+        cache.insert("M0CUV".to_owned(), 0).unwrap();
+        let frames = vec![Frame::CallsignHashMetadata { hash: M0CUV_HASH }];
+        cache.observe(&frames, EXPIRY_MS);
+        assert_that!(&cache.lookup(M0CUV_HASH, EXPIRY_MS + 1), none());
+    }
+
+    #[rstest]
+    fn observing_frames_without_metadata_gives_no_hash(mut cache: CallsignHashCache) {
+        let frames = vec![
+            Frame::WPMPolarity { wpm: 20, polarity: true },
+            Frame::KeyingPerfectDah,
+        ];
+        assert_that!(&cache.observe(&frames, 0), none());
+    }
+}
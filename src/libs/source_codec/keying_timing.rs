@@ -8,8 +8,9 @@ pub type KeyerRangeDelta = i16;
 
 /// All calculated durations, ranges of deltas from perfect timing, bounds of keying durations for
 /// the three elements, numbers of bits required to encode deltas - all related to, and calculated
-/// from the current keyer speed.
-pub trait KeyingTiming {
+/// from the current keyer speed. Send, so that the Playback holding each station's timing can
+/// play from the decoder's thread.
+pub trait KeyingTiming: Send {
     // The KeyingEncoder needs to know the keyer speed to build keying frames into their most
     // compact form; a minimal delta from the three timing elements.
     fn set_keyer_speed(&mut self, speed: KeyerSpeed);
@@ -1,25 +1,376 @@
-use crate::libs::source_codec::source_encoding::{Callsign, Locator, Power};
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
 
-pub fn encode_callsign(_callsign: Callsign) -> u32 {
-    0 // TODO
+use crate::libs::source_codec::source_encoding::{Callsign, CallsignHash, Locator, Power};
+
+// Callsigns are packed much as in FT8 (see the WSJT-X 'pack28' routine). A 28-bit base value holds
+// either a special token (DE, QRZ, CQ, CQ nnn, CQ ABCD), a reserved range for 22-bit hashed
+// callsigns, or a standard callsign of up to six characters. A further two bits to the left of
+// these hold a portable suffix, since all the standard callsigns leave no room in 28 bits for it.
+pub const CALLSIGN_BASE_BITS: usize = 28;
+pub const CALLSIGN_SUFFIX_BITS: usize = 2;
+pub const CALLSIGN_ENCODING_BITS: usize = CALLSIGN_BASE_BITS + CALLSIGN_SUFFIX_BITS;
+
+const CALLSIGN_BASE_MASK: u32 = (1 << CALLSIGN_BASE_BITS) - 1;
+
+// Special tokens occupy [0, NTOKENS); the hashed callsign range occupies the next MAX22 values;
+// standard callsigns follow, filling the 28 bits exactly.
+const NTOKENS: u32 = 2063592;
+const MAX22: u32 = 4194304;
+const TOKEN_DE: u32 = 0;
+const TOKEN_QRZ: u32 = 1;
+const TOKEN_CQ: u32 = 2;
+const TOKEN_CQ_NUMERIC: u32 = 3; // CQ 000 .. CQ 999
+const TOKEN_CQ_ALPHABETIC: u32 = 1003; // CQ A .. CQ ZZZZ
+
+// The character sets of each position in a six-character standard callsign.
+const A1: &str = " 0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const A2: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const A3: &str = "0123456789";
+const A4: &str = " ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+// Portable suffixes, indexed by their 2-bit encoding; 0 is no suffix.
+const SUFFIXES: [&str; 4] = ["", "/P", "/M", "/MM"];
+
+// Callsign hashes are computed as in FT8 (see the WSJT-X 'ihashcall' routine), over any callsign
+// of up to eleven characters from this alphabet, including compound callsigns such as F/M0CUV.
+pub const CALLSIGN_HASH_ENCODING_BITS: usize = 22;
+const CALLSIGN_HASH_ALPHABET: &str = " 0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ/";
+const CALLSIGN_HASH_LENGTH: usize = 11;
+const CALLSIGN_HASH_MULTIPLIER: u64 = 47055833459;
+
+// Maidenhead locators: 18x18 fields, 10x10 squares in each field, 24x24 subsquares in each square.
+pub const LOCATOR_ENCODING_BITS: usize = 15;
+pub const LOCATOR_SUBSQUARE_ENCODING_BITS: usize = 10;
+const LOCATOR_FIELDS: u16 = 18;
+const LOCATOR_SQUARES: u16 = 10;
+const LOCATOR_SUBSQUARES: u16 = 24;
+
+// Power is in dBm, 0 (1mW) to 63 (2kW).
+pub const POWER_ENCODING_BITS: usize = 6;
+const MAX_POWER_DBM: Power = 63;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetadataCodecError {
+    // The callsign cannot be represented in the callsign encoding.
+    InvalidCallsign(Callsign),
+    // The encoded value does not correspond to a callsign.
+    InvalidCallsignEncoding(u32),
+    // The locator is not a 4- or 6-character Maidenhead locator.
+    InvalidLocator(Locator),
+    // The encoded value does not correspond to a locator or subsquare.
+    InvalidLocatorEncoding(u16),
+    // The power cannot be represented in the power encoding.
+    InvalidPower(Power),
+    // The encoded value does not correspond to a power.
+    InvalidPowerEncoding(u8),
+}
+
+impl Display for MetadataCodecError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MetadataCodecError::InvalidCallsign(callsign) => {
+                write!(f, "Cannot encode callsign '{}'", callsign)
+            }
+            MetadataCodecError::InvalidCallsignEncoding(encoded) => {
+                write!(f, "Cannot decode callsign from {:#010x}", encoded)
+            }
+            MetadataCodecError::InvalidLocator(locator) => {
+                write!(f, "Cannot encode locator '{}'", locator)
+            }
+            MetadataCodecError::InvalidLocatorEncoding(encoded) => {
+                write!(f, "Cannot decode locator from {:#06x}", encoded)
+            }
+            MetadataCodecError::InvalidPower(power) => {
+                write!(f, "Cannot encode power of {} dBm", power)
+            }
+            MetadataCodecError::InvalidPowerEncoding(encoded) => {
+                write!(f, "Cannot decode power from {:#04x}", encoded)
+            }
+        }
+    }
+}
+
+impl Error for MetadataCodecError {}
+
+// Returns the callsign in the rightmost CALLSIGN_ENCODING_BITS of the u32.
+pub fn encode_callsign(callsign: Callsign) -> Result<u32, MetadataCodecError> {
+    let upper = callsign.to_ascii_uppercase();
+    let (base, suffix) = split_suffix(upper.as_str());
+    let invalid = || MetadataCodecError::InvalidCallsign(callsign.clone());
+    let encoded_base = encode_callsign_base(base).ok_or_else(invalid)?;
+    if suffix != 0 && encoded_base < NTOKENS {
+        return Err(invalid());
+    }
+    // Not every string that packs will unpack to the same string, e.g. the workarounds for 3DA0
+    // and 3X prefixes; only accept those that round-trip exactly.
+    if decode_callsign_base(encoded_base).as_deref() != Some(base) {
+        return Err(invalid());
+    }
+    Ok((suffix << CALLSIGN_BASE_BITS) | encoded_base)
+}
+
+pub fn decode_callsign(last_30_bits_of_encoded_callsign: u32) -> Result<Callsign, MetadataCodecError> {
+    let invalid = || MetadataCodecError::InvalidCallsignEncoding(last_30_bits_of_encoded_callsign);
+    if last_30_bits_of_encoded_callsign >> CALLSIGN_ENCODING_BITS != 0 {
+        return Err(invalid());
+    }
+    let suffix = last_30_bits_of_encoded_callsign >> CALLSIGN_BASE_BITS;
+    let encoded_base = last_30_bits_of_encoded_callsign & CALLSIGN_BASE_MASK;
+    if suffix != 0 && encoded_base < NTOKENS {
+        return Err(invalid());
+    }
+    let base = decode_callsign_base(encoded_base).ok_or_else(invalid)?;
+    // Some values unpack to strings that would pack differently (e.g. embedded spaces); these are
+    // not valid encodings.
+    if encode_callsign_base(base.as_str()) != Some(encoded_base) {
+        return Err(invalid());
+    }
+    Ok(base + SUFFIXES[suffix as usize])
+}
+
+fn split_suffix(callsign: &str) -> (&str, u32) {
+    // Longest suffix first, so /MM isn't taken as /M.
+    for suffix in [3, 2, 1] {
+        if let Some(base) = callsign.strip_suffix(SUFFIXES[suffix]) {
+            return (base, suffix as u32);
+        }
+    }
+    (callsign, 0)
+}
+
+fn encode_callsign_base(callsign: &str) -> Option<u32> {
+    match callsign {
+        "DE" => Some(TOKEN_DE),
+        "QRZ" => Some(TOKEN_QRZ),
+        "CQ" => Some(TOKEN_CQ),
+        _ => {
+            match callsign.strip_prefix("CQ ") {
+                Some(qualifier) => encode_cq_qualifier(qualifier),
+                None => encode_standard_callsign(callsign).map(|n| NTOKENS + MAX22 + n),
+            }
+        }
+    }
+}
+
+fn decode_callsign_base(encoded_base: u32) -> Option<Callsign> {
+    match encoded_base {
+        TOKEN_DE => Some("DE".to_owned()),
+        TOKEN_QRZ => Some("QRZ".to_owned()),
+        TOKEN_CQ => Some("CQ".to_owned()),
+        n if n < TOKEN_CQ_ALPHABETIC => Some(format!("CQ {:03}", n - TOKEN_CQ_NUMERIC)),
+        n if n < NTOKENS => {
+            let mut m = n - TOKEN_CQ_ALPHABETIC;
+            let mut qualifier = [' '; 4];
+            for i in (0..4).rev() {
+                qualifier[i] = char_at(A4, m % 27);
+                m /= 27;
+            }
+            if m != 0 {
+                return None;
+            }
+            Some(format!("CQ {}", qualifier.iter().collect::<String>().trim_start()))
+        }
+        n if n < NTOKENS + MAX22 => None, // Hashed callsigns are not decoded here.
+        n => decode_standard_callsign(n - NTOKENS - MAX22),
+    }
+}
+
+// CQ nnn (three digits) or CQ followed by one to four letters, e.g. CQ DX, CQ TEST.
+fn encode_cq_qualifier(qualifier: &str) -> Option<u32> {
+    if qualifier.len() == 3 && qualifier.bytes().all(|b| b.is_ascii_digit()) {
+        return qualifier.parse::<u32>().ok().map(|n| TOKEN_CQ_NUMERIC + n);
+    }
+    if qualifier.is_empty() || qualifier.len() > 4 || !qualifier.bytes().all(|b| b.is_ascii_uppercase()) {
+        return None;
+    }
+    let mut m = 0;
+    for ch in qualifier.chars() {
+        m = m * 27 + index_of(A4, ch)?;
+    }
+    Some(TOKEN_CQ_ALPHABETIC + m)
+}
+
+fn encode_standard_callsign(callsign: &str) -> Option<u32> {
+    // Workarounds for prefixes that don't fit the pattern: Swaziland 3DA0 becomes 3D0; Guinea 3X
+    // becomes Q.
+    let callsign = if callsign.starts_with("3DA0") && callsign.len() > 4 {
+        format!("3D0{}", &callsign[4..])
+    } else if callsign.starts_with("3X") && callsign[2..].starts_with(|c: char| c.is_ascii_uppercase()) {
+        format!("Q{}", &callsign[2..])
+    } else {
+        callsign.to_owned()
+    };
+    if !callsign.bytes().all(|b| b.is_ascii_uppercase() || b.is_ascii_digit()) {
+        return None;
+    }
+
+    // Align so that the call area digit is always the third character.
+    let bytes = callsign.as_bytes();
+    let c6 = if bytes.len() >= 3 && bytes.len() <= 6 && bytes[2].is_ascii_digit() {
+        format!("{:<6}", callsign)
+    } else if bytes.len() >= 2 && bytes.len() <= 5 && bytes[1].is_ascii_digit() {
+        format!(" {:<5}", callsign)
+    } else {
+        return None;
+    };
+    let chars: Vec<char> = c6.chars().collect();
+    // There must be at least one letter after the call area digit.
+    if chars[3] == ' ' {
+        return None;
+    }
+    let mut n = index_of(A1, chars[0])?;
+    n = n * 36 + index_of(A2, chars[1])?;
+    n = n * 10 + index_of(A3, chars[2])?;
+    n = n * 27 + index_of(A4, chars[3])?;
+    n = n * 27 + index_of(A4, chars[4])?;
+    n = n * 27 + index_of(A4, chars[5])?;
+    Some(n)
+}
+
+fn decode_standard_callsign(mut n: u32) -> Option<Callsign> {
+    let mut c6 = [' '; 6];
+    c6[5] = char_at(A4, n % 27);
+    n /= 27;
+    c6[4] = char_at(A4, n % 27);
+    n /= 27;
+    c6[3] = char_at(A4, n % 27);
+    n /= 27;
+    c6[2] = char_at(A3, n % 10);
+    n /= 10;
+    c6[1] = char_at(A2, n % 36);
+    n /= 36;
+    if n >= 37 {
+        return None;
+    }
+    c6[0] = char_at(A1, n);
+    let callsign = c6.iter().collect::<String>().trim().to_owned();
+    if callsign.starts_with("3D0") && callsign.len() > 3 {
+        Some(format!("3DA0{}", &callsign[3..]))
+    } else if callsign.starts_with('Q') && callsign[1..].starts_with(|c: char| c.is_ascii_uppercase()) {
+        Some(format!("3X{}", &callsign[1..]))
+    } else {
+        Some(callsign)
+    }
+}
+
+fn index_of(alphabet: &str, ch: char) -> Option<u32> {
+    alphabet.find(ch).map(|i| i as u32)
+}
+
+fn char_at(alphabet: &str, index: u32) -> char {
+    alphabet.as_bytes()[index as usize] as char
+}
+
+// Returns the hash of the callsign in the rightmost CALLSIGN_HASH_ENCODING_BITS of the hash. The
+// same callsign always hashes to the same value, irrespective of case.
+pub fn hash_callsign(callsign: Callsign) -> Result<CallsignHash, MetadataCodecError> {
+    let upper = callsign.to_ascii_uppercase();
+    if upper.is_empty() || upper.len() > CALLSIGN_HASH_LENGTH {
+        return Err(MetadataCodecError::InvalidCallsign(callsign));
+    }
+    let mut n: u64 = 0;
+    for ch in format!("{:<width$}", upper, width = CALLSIGN_HASH_LENGTH).chars() {
+        match index_of(CALLSIGN_HASH_ALPHABET, ch) {
+            Some(index) => n = 38 * n + index as u64,
+            None => return Err(MetadataCodecError::InvalidCallsign(callsign)),
+        }
+    }
+    // 38^11 fits in 64 bits; the multiplication is deliberately modulo 2^64.
+    Ok((CALLSIGN_HASH_MULTIPLIER.wrapping_mul(n) >> (64 - CALLSIGN_HASH_ENCODING_BITS)) as CallsignHash)
+}
+
+// Returns the 4-character grid square of a 4- or 6-character locator in the rightmost
+// LOCATOR_ENCODING_BITS of the u16.
+pub fn encode_locator(locator: Locator) -> Result<u16, MetadataCodecError> {
+    let chars = validate_locator(&locator)?;
+    let field_longitude = chars[0] as u16 - 'A' as u16;
+    let field_latitude = chars[1] as u16 - 'A' as u16;
+    let square_longitude = chars[2] as u16 - '0' as u16;
+    let square_latitude = chars[3] as u16 - '0' as u16;
+    Ok(((field_longitude * LOCATOR_FIELDS + field_latitude) * LOCATOR_SQUARES + square_longitude) * LOCATOR_SQUARES + square_latitude)
+}
+
+pub fn decode_locator(last_15_bits_of_encoded_locator: u16) -> Result<Locator, MetadataCodecError> {
+    if last_15_bits_of_encoded_locator >= LOCATOR_FIELDS * LOCATOR_FIELDS * LOCATOR_SQUARES * LOCATOR_SQUARES {
+        return Err(MetadataCodecError::InvalidLocatorEncoding(last_15_bits_of_encoded_locator));
+    }
+    let mut n = last_15_bits_of_encoded_locator;
+    let square_latitude = n % LOCATOR_SQUARES;
+    n /= LOCATOR_SQUARES;
+    let square_longitude = n % LOCATOR_SQUARES;
+    n /= LOCATOR_SQUARES;
+    let field_latitude = n % LOCATOR_FIELDS;
+    let field_longitude = n / LOCATOR_FIELDS;
+    Ok(format!("{}{}{}{}",
+               (b'A' + field_longitude as u8) as char, (b'A' + field_latitude as u8) as char,
+               (b'0' + square_longitude as u8) as char, (b'0' + square_latitude as u8) as char))
+}
+
+// Returns the subsquare of a 6-character locator in the rightmost LOCATOR_SUBSQUARE_ENCODING_BITS
+// of the u16, or None for a 4-character locator. The subsquare is sent in an Extension frame that
+// follows the LocatorMetadata frame.
+pub fn encode_locator_subsquare(locator: Locator) -> Result<Option<u16>, MetadataCodecError> {
+    let chars = validate_locator(&locator)?;
+    if chars.len() == 4 {
+        return Ok(None);
+    }
+    let subsquare_longitude = chars[4] as u16 - 'a' as u16;
+    let subsquare_latitude = chars[5] as u16 - 'a' as u16;
+    Ok(Some(subsquare_longitude * LOCATOR_SUBSQUARES + subsquare_latitude))
 }
 
-pub fn decode_callsign(_last_28_bits_of_encoded_callsign: u32) -> Callsign {
-    "M0CUV".to_string() as Callsign // TODO
+// Extends a decoded 4-character locator with its subsquare.
+pub fn decode_locator_subsquare(locator: Locator, last_10_bits_of_encoded_subsquare: u16) -> Result<Locator, MetadataCodecError> {
+    if last_10_bits_of_encoded_subsquare >= LOCATOR_SUBSQUARES * LOCATOR_SUBSQUARES {
+        return Err(MetadataCodecError::InvalidLocatorEncoding(last_10_bits_of_encoded_subsquare));
+    }
+    let chars = validate_locator(&locator)?;
+    if chars.len() != 4 {
+        return Err(MetadataCodecError::InvalidLocator(locator));
+    }
+    let subsquare_longitude = last_10_bits_of_encoded_subsquare / LOCATOR_SUBSQUARES;
+    let subsquare_latitude = last_10_bits_of_encoded_subsquare % LOCATOR_SUBSQUARES;
+    Ok(format!("{}{}{}", chars.iter().collect::<String>(),
+               (b'a' + subsquare_longitude as u8) as char, (b'a' + subsquare_latitude as u8) as char))
 }
 
-pub fn encode_locator(_locator: Locator) -> u16 {
-    0
+// Fields are A-R, squares 0-9, subsquares a-x (either case is accepted).
+fn validate_locator(locator: &Locator) -> Result<Vec<char>, MetadataCodecError> {
+    let invalid = || MetadataCodecError::InvalidLocator(locator.clone());
+    let chars: Vec<char> = locator.chars().collect();
+    if chars.len() != 4 && chars.len() != 6 {
+        return Err(invalid());
+    }
+    let is_field = |c: char| ('A'..='R').contains(&c.to_ascii_uppercase());
+    let is_square = |c: char| c.is_ascii_digit();
+    let is_subsquare = |c: char| ('a'..='x').contains(&c.to_ascii_lowercase());
+    if !is_field(chars[0]) || !is_field(chars[1]) || !is_square(chars[2]) || !is_square(chars[3]) {
+        return Err(invalid());
+    }
+    if chars.len() == 6 && (!is_subsquare(chars[4]) || !is_subsquare(chars[5])) {
+        return Err(invalid());
+    }
+    // Normalised as e.g. IO91wm
+    Ok(chars.iter().enumerate().map(|(i, c)| if i < 4 { c.to_ascii_uppercase() } else { c.to_ascii_lowercase() }).collect())
 }
 
-pub fn decode_locator(_last_15_bits_of_encoded_locator: u16) -> Locator {
-    "JO01".to_string() as Locator // TODO
+// Returns the power in dBm in the rightmost POWER_ENCODING_BITS of the u8.
+pub fn encode_power(power: Power) -> Result<u8, MetadataCodecError> {
+    if power > MAX_POWER_DBM {
+        return Err(MetadataCodecError::InvalidPower(power));
+    }
+    Ok(power)
 }
 
-pub fn encode_power(_power: Power) -> u8 {
-    0
+pub fn decode_power(last_6_bits_of_encoded_power: u8) -> Result<Power, MetadataCodecError> {
+    if last_6_bits_of_encoded_power > MAX_POWER_DBM {
+        return Err(MetadataCodecError::InvalidPowerEncoding(last_6_bits_of_encoded_power));
+    }
+    Ok(last_6_bits_of_encoded_power as Power)
 }
 
-pub fn decode_power(_last_n_bits_of_u8: u8) -> Power {
-    0 as Power
-}
\ No newline at end of file
+#[cfg(test)]
+#[path = "./metadata_codec_spec.rs"]
+mod metadata_codec_spec;
@@ -0,0 +1,281 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod metadata_codec_spec {
+    use std::env;
+    use hamcrest2::prelude::*;
+    use rstest::*;
+    use crate::libs::source_codec::metadata_codec::{CALLSIGN_ENCODING_BITS, decode_callsign, decode_locator, decode_locator_subsquare, decode_power, encode_callsign, encode_locator, encode_locator_subsquare, encode_power, LOCATOR_ENCODING_BITS, LOCATOR_SUBSQUARE_ENCODING_BITS, MetadataCodecError, POWER_ENCODING_BITS};
+
+    #[ctor::ctor]
+    fn before_each() {
+        env::set_var("RUST_LOG", "debug");
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[ctor::dtor]
+    fn after_each() {}
+
+    fn round_trip(callsign: &str) -> String {
+        let encoded = encode_callsign(callsign.to_owned()).unwrap();
+        assert_that!(encoded >> CALLSIGN_ENCODING_BITS, eq(0));
+        decode_callsign(encoded).unwrap()
+    }
+
+    #[rstest(callsign,
+        case("M0CUV"),
+        case("G4ABC"),
+        case("K1A"),
+        case("KA1ABC"),
+        case("2E0ABC"),
+        case("W1AW"),
+        case("VK2XYZ"),
+        case("9A1A"),
+        case("3DA0RS"),
+        case("3XA1ZZ"),
+        case("M0CUV/P"),
+        case("M0CUV/M"),
+        case("M0CUV/MM"),
+        case("DE"),
+        case("QRZ"),
+        case("CQ"),
+        case("CQ 000"),
+        case("CQ 145"),
+        case("CQ 999"),
+        case("CQ A"),
+        case("CQ DX"),
+        case("CQ TEST"),
+        case("CQ ZZZZ"),
+    )]
+    fn callsigns_round_trip(callsign: &str) {
+        assert_that!(round_trip(callsign), eq(callsign));
+    }
+
+    #[test]
+    fn lower_case_callsigns_are_encoded_as_upper_case() {
+        assert_that!(round_trip("m0cuv/p"), eq("M0CUV/P"));
+    }
+
+    #[test]
+    fn distinct_callsigns_have_distinct_encodings() {
+        assert_that!(encode_callsign("M0CUV".to_owned()).unwrap(), not(eq(encode_callsign("M0CUV/P".to_owned()).unwrap())));
+        assert_that!(encode_callsign("K1A".to_owned()).unwrap(), not(eq(encode_callsign("K1AA".to_owned()).unwrap())));
+        assert_that!(encode_callsign("CQ".to_owned()).unwrap(), not(eq(encode_callsign("CQ 000".to_owned()).unwrap())));
+    }
+
+    #[rstest(callsign,
+        case(""),
+        case("M0"),
+        case("MCUV"),
+        case("M0CUVXY"),
+        case("M0C1"),
+        case("AB1CDEF"),
+        case("M0 CUV"),
+        case(" M0CUV"),
+        case("M0CUV/"),
+        case("M0CUV/QRP"),
+        case("F/M0CUV"),
+        case("M0-UV"),
+        case("M0ÇUV"),
+        case("3D0ABC"), // would decode as 3DA0ABC
+        case("QA1ABC"), // would decode as 3XA1ABC
+        case("CQ/P"),
+        case("DE/M"),
+        case("CQ "),
+        case("CQ 1"),
+        case("CQ 1000"),
+        case("CQ ABCDE"),
+        case("CQ A1"),
+    )]
+    fn invalid_callsigns_cannot_be_encoded(callsign: &str) {
+        assert_that!(encode_callsign(callsign.to_owned()), eq(Err(MetadataCodecError::InvalidCallsign(callsign.to_owned()))));
+    }
+
+    #[test]
+    fn values_wider_than_the_encoding_cannot_be_decoded() {
+        let encoded = encode_callsign("M0CUV".to_owned()).unwrap() | (1 << CALLSIGN_ENCODING_BITS);
+        assert_that!(decode_callsign(encoded), eq(Err(MetadataCodecError::InvalidCallsignEncoding(encoded))));
+    }
+
+    #[test]
+    fn hashed_callsign_range_cannot_be_decoded() {
+        // Tokens end at 2063592; the next 2^22 values are reserved for hashed callsigns.
+        for encoded in [2063592, 2063592 + 1234567, 2063592 + 4194303] {
+            assert_that!(decode_callsign(encoded), eq(Err(MetadataCodecError::InvalidCallsignEncoding(encoded))));
+        }
+    }
+
+    #[test]
+    fn tokens_cannot_have_suffixes() {
+        let encoded = encode_callsign("CQ DX".to_owned()).unwrap() | (1 << 28);
+        assert_that!(decode_callsign(encoded), eq(Err(MetadataCodecError::InvalidCallsignEncoding(encoded))));
+    }
+
+    // Every value either decodes to a callsign that encodes back to the same value, or is an
+    // error; never a panic.
+    fn assert_decode_encode_round_trip(encoded: u32) -> bool {
+        match decode_callsign(encoded) {
+            Ok(callsign) => {
+                assert_that!(encode_callsign(callsign), eq(Ok(encoded)));
+                true
+            }
+            Err(e) => {
+                assert_that!(e, eq(MetadataCodecError::InvalidCallsignEncoding(encoded)));
+                false
+            }
+        }
+    }
+
+    #[test]
+    fn all_tokens_round_trip() {
+        let decodable = (0..2063592).filter(|encoded| assert_decode_encode_round_trip(*encoded)).count();
+        // DE, QRZ, CQ, CQ 000..999, CQ A..ZZZZ
+        assert_that!(decodable, eq(3 + 1000 + 26 + 26 * 26 + 26 * 26 * 26 + 26 * 26 * 26 * 26));
+    }
+
+    #[test]
+    fn standard_callsign_range_round_trips() {
+        // All 2^28 with each suffix would take too long; sample it with a prime stride.
+        let first_standard = 2063592 + 4194304;
+        let mut decodable = 0;
+        for suffix in 0..4 {
+            for base in (first_standard..(1 << 28)).step_by(1009) {
+                if assert_decode_encode_round_trip((suffix << 28) | base) {
+                    decodable += 1;
+                }
+            }
+        }
+        assert_that!(decodable, greater_than(0));
+    }
+
+    #[test]
+    fn all_single_letter_suffixes_of_all_prefixes_round_trip() {
+        let alphanumerics: Vec<char> = ('0'..='9').chain('A'..='Z').collect();
+        for first in alphanumerics.iter() {
+            for digit in '0'..='9' {
+                for suffix in 'A'..='Z' {
+                    let callsign = format!("{}{}{}", first, digit, suffix);
+                    // A leading Q followed by a digit is not subject to the Guinea 3X workaround.
+                    assert_that!(round_trip(&callsign), eq(callsign.clone()));
+                }
+            }
+        }
+    }
+
+    fn locator_round_trip(locator: &str) -> String {
+        let encoded = encode_locator(locator.to_owned()).unwrap();
+        assert_that!(encoded >> LOCATOR_ENCODING_BITS, eq(0));
+        let decoded = decode_locator(encoded).unwrap();
+        match encode_locator_subsquare(locator.to_owned()).unwrap() {
+            None => decoded,
+            Some(subsquare) => {
+                assert_that!(subsquare >> LOCATOR_SUBSQUARE_ENCODING_BITS, eq(0));
+                decode_locator_subsquare(decoded, subsquare).unwrap()
+            }
+        }
+    }
+
+    #[rstest(locator,
+        case("JO01"),
+        case("IO91"),
+        case("AA00"),
+        case("RR99"),
+        case("IO91wm"),
+        case("AA00aa"),
+        case("RR99xx"),
+    )]
+    fn locators_round_trip(locator: &str) {
+        assert_that!(locator_round_trip(locator), eq(locator));
+    }
+
+    #[test]
+    fn locators_are_normalised() {
+        assert_that!(locator_round_trip("io91WM"), eq("IO91wm"));
+    }
+
+    #[test]
+    fn locator_extremes() {
+        assert_that!(encode_locator("AA00".to_owned()), eq(Ok(0)));
+        assert_that!(encode_locator("RR99".to_owned()), eq(Ok(32399)));
+        assert_that!(encode_locator_subsquare("AA00aa".to_owned()), eq(Ok(Some(0))));
+        assert_that!(encode_locator_subsquare("AA00xx".to_owned()), eq(Ok(Some(575))));
+        assert_that!(encode_locator_subsquare("AA00".to_owned()), eq(Ok(None)));
+    }
+
+    #[test]
+    fn all_locators_round_trip() {
+        for encoded in 0..32400 {
+            let locator = decode_locator(encoded).unwrap();
+            assert_that!(encode_locator(locator), eq(Ok(encoded)));
+        }
+    }
+
+    #[test]
+    fn all_subsquares_round_trip() {
+        for locator in ["AA00", "JO01", "RR99"] {
+            let encoded = encode_locator(locator.to_owned()).unwrap();
+            for encoded_subsquare in 0..576 {
+                let subsquare_locator = decode_locator_subsquare(locator.to_owned(), encoded_subsquare).unwrap();
+                assert_that!(encode_locator(subsquare_locator.clone()), eq(Ok(encoded)));
+                assert_that!(encode_locator_subsquare(subsquare_locator), eq(Ok(Some(encoded_subsquare))));
+            }
+        }
+    }
+
+    #[rstest(locator,
+        case(""),
+        case("JO0"),
+        case("JO011"),
+        case("JO01abc"),
+        case("SO01"),
+        case("JS01"),
+        case("JOA1"),
+        case("JO0A"),
+        case("JO01ya"),
+        case("JO01a1"),
+        case("JO01 a"),
+        case("ÇO01"),
+    )]
+    fn invalid_locators_cannot_be_encoded(locator: &str) {
+        assert_that!(encode_locator(locator.to_owned()), eq(Err(MetadataCodecError::InvalidLocator(locator.to_owned()))));
+        assert_that!(encode_locator_subsquare(locator.to_owned()), eq(Err(MetadataCodecError::InvalidLocator(locator.to_owned()))));
+    }
+
+    #[test]
+    fn out_of_range_locators_cannot_be_decoded() {
+        for encoded in [32400, 32767, 65535] {
+            assert_that!(decode_locator(encoded), eq(Err(MetadataCodecError::InvalidLocatorEncoding(encoded))));
+        }
+    }
+
+    #[test]
+    fn out_of_range_subsquares_cannot_be_decoded() {
+        for encoded in [576, 1023] {
+            assert_that!(decode_locator_subsquare("JO01".to_owned(), encoded), eq(Err(MetadataCodecError::InvalidLocatorEncoding(encoded))));
+        }
+    }
+
+    #[test]
+    fn subsquares_only_extend_4_character_locators() {
+        assert_that!(decode_locator_subsquare("JO01ab".to_owned(), 0), eq(Err(MetadataCodecError::InvalidLocator("JO01ab".to_owned()))));
+    }
+
+    #[test]
+    fn all_powers_round_trip() {
+        for power in 0..=63 {
+            let encoded = encode_power(power).unwrap();
+            assert_that!(encoded >> POWER_ENCODING_BITS, eq(0));
+            assert_that!(decode_power(encoded), eq(Ok(power)));
+        }
+    }
+
+    #[test]
+    fn excessive_power_cannot_be_encoded() {
+        assert_that!(encode_power(64), eq(Err(MetadataCodecError::InvalidPower(64))));
+    }
+
+    #[test]
+    fn out_of_range_power_cannot_be_decoded() {
+        assert_that!(decode_power(64), eq(Err(MetadataCodecError::InvalidPowerEncoding(64))));
+    }
+}
@@ -1,5 +1,6 @@
 pub mod keying_encoder;
 pub mod keying_timing;
+pub mod callsign_hash_cache;
 pub mod metadata_codec;
 pub mod source_decoder;
 pub mod source_encoder;
@@ -4,6 +4,7 @@ use crate::enum_primitive::FromPrimitive;
 use crate::libs::source_codec::bitvec_source_encoding_extractor::BitvecSourceEncodingExtractor;
 use crate::libs::source_codec::keying_encoder::decode_from_binary_with_known_sign;
 use crate::libs::source_codec::keying_timing::{DefaultKeyingTiming, KeyingTiming};
+use crate::libs::source_codec::metadata_codec::{CALLSIGN_ENCODING_BITS, CALLSIGN_HASH_ENCODING_BITS, decode_callsign, decode_locator, decode_locator_subsquare, decode_power, LOCATOR_ENCODING_BITS, LOCATOR_SUBSQUARE_ENCODING_BITS, POWER_ENCODING_BITS};
 use crate::libs::source_codec::source_encoding::{EncoderFrameType, Frame, SourceEncodingExtractor};
 use crate::libs::util::util::dump_byte_vec;
 
@@ -69,16 +70,20 @@ impl SourceDecoder {
                             frames.push(Frame::WPMPolarity { wpm: keying_speed, polarity: mark });
                         }
                         EncoderFrameType::CallsignMetadata => {
-                            todo!();
+                            let callsign = decode_callsign(extractor.extract_32_bits(CALLSIGN_ENCODING_BITS))?;
+                            frames.push(Frame::CallsignMetadata { callsign });
                         }
                         EncoderFrameType::CallsignHashMetadata => {
-                            todo!();
+                            let hash = extractor.extract_32_bits(CALLSIGN_HASH_ENCODING_BITS);
+                            frames.push(Frame::CallsignHashMetadata { hash });
                         }
                         EncoderFrameType::LocatorMetadata => {
-                            todo!();
+                            let locator = decode_locator(extractor.extract_16_bits(LOCATOR_ENCODING_BITS))?;
+                            frames.push(Frame::LocatorMetadata { locator });
                         }
                         EncoderFrameType::PowerMetadata => {
-                            todo!();
+                            let power = decode_power(extractor.extract_8_bits(POWER_ENCODING_BITS))?;
+                            frames.push(Frame::PowerMetadata { power });
                         }
                         EncoderFrameType::KeyingPerfectDit => {
                             if !seen_wpm_polarity {
@@ -136,7 +141,17 @@ impl SourceDecoder {
                             frames.push(Frame::Unused);
                         }
                         EncoderFrameType::Extension => {
-                            frames.push(Frame::Extension);
+                            // An Extension immediately after a 4-character LocatorMetadata carries
+                            // its subsquare.
+                            match frames.last_mut() {
+                                Some(Frame::LocatorMetadata { locator }) if locator.len() == 4 => {
+                                    let subsquare = extractor.extract_16_bits(LOCATOR_SUBSQUARE_ENCODING_BITS);
+                                    *locator = decode_locator_subsquare(locator.clone(), subsquare)?;
+                                }
+                                _ => {
+                                    frames.push(Frame::Extension);
+                                }
+                            }
                         }
                     }
                 }
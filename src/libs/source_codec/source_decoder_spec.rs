@@ -157,6 +157,58 @@ mod source_decoder_spec {
         assert_decoded_frame(&fixture, Frame::KeyingNaive { duration: 80 });
     }
 
+    #[rstest]
+    pub fn decode_callsign(fixture: SourceDecoderFixture) {
+        assert_decoded_frame(&fixture, Frame::CallsignMetadata { callsign: "M0CUV/P".to_owned() });
+    }
+
+    #[rstest]
+    pub fn decode_callsign_hash(fixture: SourceDecoderFixture) {
+        assert_decoded_frame(&fixture, Frame::CallsignHashMetadata { hash: 0x1234 });
+    }
+
+    #[rstest]
+    pub fn decode_power(fixture: SourceDecoderFixture) {
+        assert_decoded_frame(&fixture, Frame::PowerMetadata { power: 37 });
+    }
+
+    #[rstest]
+    pub fn invalid_callsign_cannot_be_decoded(fixture: SourceDecoderFixture) {
+        // F:CA then 30 bits of 0b11 followed by the first hashed callsign value, 2063592.
+        should_decode_with_error(&fixture, vec![0b00101100, 0b00000111, 0b11011111, 0b00111010, 0, 0, 0, 0],
+                                 "Cannot decode callsign from 0x301f7ce8");
+    }
+
+    #[rstest]
+    pub fn decode_locator(fixture: SourceDecoderFixture) {
+        assert_decoded_frame(&fixture, Frame::LocatorMetadata { locator: "JO01".to_owned() });
+    }
+
+    #[rstest]
+    pub fn decode_locator_with_subsquare(fixture: SourceDecoderFixture) {
+        // The subsquare is carried in an Extension frame after the LocatorMetadata frame.
+        assert_decoded_frame(&fixture, Frame::LocatorMetadata { locator: "IO91wm".to_owned() });
+    }
+
+    #[rstest]
+    pub fn extension_not_after_a_locator_is_not_a_subsquare(fixture: SourceDecoderFixture) {
+        let frames = &[
+            Frame::WPMPolarity { wpm: 20, polarity: true },
+            Frame::LocatorMetadata { locator: "IO91wm".to_owned() },
+            Frame::Extension,
+            Frame::Padding
+        ];
+        let block = encoded(TEST_SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, 20, frames);
+        assert_decoded_eq(&fixture, block, frames.to_vec());
+    }
+
+    #[rstest]
+    pub fn out_of_range_locator_cannot_be_decoded(fixture: SourceDecoderFixture) {
+        // F:LO then 15 bits of 111111111111111
+        should_decode_with_error(&fixture, vec![0b01001111, 0b11111111, 0b11100000, 0, 0, 0, 0, 0],
+                                 "Cannot decode locator from 0x7fff");
+    }
+
     #[rstest]
     pub fn decode_unused(fixture: SourceDecoderFixture) {
         assert_decoded_frame(&fixture, Frame::Unused);
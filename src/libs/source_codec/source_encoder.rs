@@ -1,3 +1,4 @@
+use std::collections::VecDeque;
 use std::sync::{Arc, Mutex, RwLock};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
@@ -9,7 +10,8 @@ use crate::libs::application::application::{BusInput, BusOutput};
 use crate::libs::keyer_io::keyer_io::{KeyingEvent, KeyerSpeed};
 use crate::libs::source_codec::bitvec_source_encoding_builder::BitvecSourceEncodingBuilder;
 use crate::libs::source_codec::keying_encoder::{DefaultKeyingEncoder, KeyingEncoder};
-use crate::libs::source_codec::source_encoding::{EncoderFrameType, SourceEncoding, SourceEncodingBuilder};
+use crate::libs::source_codec::metadata_codec::{CALLSIGN_ENCODING_BITS, CALLSIGN_HASH_ENCODING_BITS, encode_callsign, encode_locator, encode_locator_subsquare, encode_power, hash_callsign, LOCATOR_ENCODING_BITS, LOCATOR_SUBSQUARE_ENCODING_BITS, MetadataCodecError, POWER_ENCODING_BITS};
+use crate::libs::source_codec::source_encoding::{Callsign, CallsignHash, EncoderFrameType, Frame, Locator, Power, SourceEncoding, SourceEncodingBuilder};
 
 /*
  * The source encoder transforms keying information into a number of frames.
@@ -118,6 +120,13 @@ impl SourceEncoder {
             is_mark: true,
             sent_wpm_polarity: false,
             keying_speed: 0,
+            callsign: None,
+            callsign_hash: None,
+            locator: None,
+            power: None,
+            pending_metadata: VecDeque::new(),
+            sent_metadata: false,
+            keying_in_block: false,
         });
         let arc_shared = Arc::new(shared);
         let arc_shared_cloned = arc_shared.clone();
@@ -172,6 +181,30 @@ impl SourceEncoder {
         self.keyer_speed
     }
 
+    // The operator's callsign, locator and power are sent as metadata frames at the start of each
+    // transmission, one per block, if they have been set. Each is checked to be encodable when set.
+    // Once the callsign is set, every block that doesn't contain it contains its hash.
+    pub fn set_callsign(&mut self, callsign: Callsign) -> Result<(), MetadataCodecError> {
+        encode_callsign(callsign.clone())?;
+        let hash = hash_callsign(callsign.clone())?;
+        let mut shared = self.shared.lock().unwrap();
+        shared.callsign = Some(callsign);
+        shared.callsign_hash = Some(hash);
+        Ok(())
+    }
+
+    pub fn set_locator(&mut self, locator: Locator) -> Result<(), MetadataCodecError> {
+        encode_locator(locator.clone())?;
+        self.shared.lock().unwrap().locator = Some(locator);
+        Ok(())
+    }
+
+    pub fn set_power(&mut self, power: Power) -> Result<(), MetadataCodecError> {
+        encode_power(power)?;
+        self.shared.lock().unwrap().power = Some(power);
+        Ok(())
+    }
+
     // Irrespective of how full the current frame is, pad it to SOURCE_ENCODER_BLOCK_SIZE and emit
     // it on the output Bus<SourceEncoding>.
     pub fn emit(&mut self) {
@@ -197,6 +230,16 @@ struct SourceEncoderShared {
     sent_wpm_polarity: bool,
     is_mark: bool,
     keying_speed: KeyerSpeed,
+    callsign: Option<Callsign>,
+    callsign_hash: Option<CallsignHash>,
+    locator: Option<Locator>,
+    power: Option<Power>,
+    // Metadata frames yet to be sent in this transmission; a block only contains one.
+    pending_metadata: VecDeque<Frame>,
+    sent_metadata: bool,
+    // Whether any WPM|Polarity or keying frames are in the current block; if not, it is marked as
+    // metadata only.
+    keying_in_block: bool,
 }
 
 impl SourceEncoderShared {
@@ -206,7 +249,14 @@ impl SourceEncoderShared {
             return;
         }
         self.sent_wpm_polarity = false;
-        let encoding = self.storage.write().unwrap().build();
+        self.sent_metadata = false;
+        let mut storage = self.storage.write().unwrap();
+        if !self.keying_in_block {
+            storage.set_metadata_only();
+        }
+        self.keying_in_block = false;
+        let encoding = storage.build();
+        drop(storage);
         info!("Emitting {}", encoding);
         match self.source_encoder_tx.lock().unwrap().as_deref() {
             None => {}
@@ -223,6 +273,46 @@ impl SourceEncoderShared {
         self.sent_wpm_polarity = false;
     }
 
+    // At the start of a block, identify the station, if its callsign has been set: the block
+    // contains either the next pending metadata frame if that is the callsign, or the callsign
+    // hash followed by the next pending metadata frame, if any. If these won't fit, emit the
+    // current block, and add them to the next.
+    fn add_pending_metadata(&mut self) {
+        if self.sent_metadata {
+            return;
+        }
+        let mut frames = vec![];
+        let pending = self.pending_metadata.pop_front();
+        if !matches!(pending, Some(Frame::CallsignMetadata { .. })) {
+            if let Some(hash) = self.callsign_hash {
+                frames.push(Frame::CallsignHashMetadata { hash });
+            }
+        }
+        frames.extend(pending);
+        if frames.is_empty() {
+            return;
+        }
+        self.sent_metadata = true;
+        let size: usize = frames.iter().map(metadata_frame_size).sum();
+        loop {
+            let mut storage = self.storage.write().unwrap();
+            let remaining = storage.remaining();
+            if remaining < size {
+                drop(storage);
+                debug!("Insufficient space ({}) to encode {:?}", remaining, frames);
+                self.emit();
+                self.sent_metadata = true;
+            } else {
+                for frame in &frames {
+                    debug!("Timed: Adding {:?}", frame);
+                    add_metadata_frame(&mut storage, frame);
+                }
+                drop(storage);
+                break;
+            }
+        }
+    }
+
     fn keying_event(&mut self, keying_event: KeyingEvent) {
         debug!("Encoding keying event {}", keying_event);
         match keying_event {
@@ -230,9 +320,22 @@ impl SourceEncoderShared {
                 // Don't add anything to storage, but reset the polarity to Mark
                 self.is_mark = true;
                 debug!("Start: Polarity is now MARK (true)");
+                // Send any metadata in the first blocks of the transmission.
+                self.sent_metadata = false;
+                self.pending_metadata.clear();
+                if let Some(callsign) = &self.callsign {
+                    self.pending_metadata.push_back(Frame::CallsignMetadata { callsign: callsign.clone() });
+                }
+                if let Some(locator) = &self.locator {
+                    self.pending_metadata.push_back(Frame::LocatorMetadata { locator: locator.clone() });
+                }
+                if let Some(power) = self.power {
+                    self.pending_metadata.push_back(Frame::PowerMetadata { power });
+                }
             }
             KeyingEvent::Timed(timed) => {
                 loop {
+                    self.add_pending_metadata();
                     if !self.sent_wpm_polarity {
                         // Encode the WPM|Polarity.
                         self.sent_wpm_polarity = true;
@@ -251,6 +354,7 @@ impl SourceEncoderShared {
                                 storage.add_8_bits(self.keying_speed, 6);
                                 storage.add_bool(self.is_mark);
                                 drop(storage);
+                                self.keying_in_block = true;
                                 break;
                             }
                         }
@@ -261,6 +365,7 @@ impl SourceEncoderShared {
                     // will be emitted first since emit clears that flag, then we'll succeed in
                     // encoding this keying.
                     if self.keying_encoder.encode_keying(&timed) {
+                        self.keying_in_block = true;
                         self.is_mark = !timed.up; // up == false => MARK, up == true => SPACE.
                         debug!("Polarity after encoding is {} ({})", if self.is_mark { "MARK" } else { "SPACE"}, self.is_mark);
                         break;
@@ -276,6 +381,8 @@ impl SourceEncoderShared {
                 }
             }
             KeyingEvent::End() => {
+                // Any metadata not yet sent will be sent at the start of the next transmission.
+                self.pending_metadata.clear();
                 loop {
                     let mut storage = self.storage.write().unwrap();
                     let remaining = storage.remaining();
@@ -291,6 +398,7 @@ impl SourceEncoderShared {
                         // Set the end of the storage
                         storage.set_end();
                         drop(storage);
+                        self.keying_in_block = true;
                         self.emit();
                         break;
                     }
@@ -300,6 +408,45 @@ impl SourceEncoderShared {
     }
 }
 
+fn metadata_frame_size(frame: &Frame) -> usize {
+    4 + match frame {
+        Frame::CallsignMetadata { .. } => CALLSIGN_ENCODING_BITS,
+        Frame::CallsignHashMetadata { .. } => CALLSIGN_HASH_ENCODING_BITS,
+        Frame::LocatorMetadata { locator } => {
+            if locator.len() == 6 { LOCATOR_ENCODING_BITS + 4 + LOCATOR_SUBSQUARE_ENCODING_BITS } else { LOCATOR_ENCODING_BITS }
+        }
+        Frame::PowerMetadata { .. } => POWER_ENCODING_BITS,
+        _ => panic!("{:?} is not a metadata frame", frame),
+    }
+}
+
+// The metadata has been validated when set on the SourceEncoder, so it will encode.
+fn add_metadata_frame(storage: &mut Box<dyn SourceEncodingBuilder + Send + Sync>, frame: &Frame) {
+    match frame {
+        Frame::CallsignMetadata { callsign } => {
+            storage.add_8_bits(EncoderFrameType::CallsignMetadata as u8, 4);
+            storage.add_32_bits(encode_callsign(callsign.clone()).unwrap(), CALLSIGN_ENCODING_BITS);
+        }
+        Frame::CallsignHashMetadata { hash } => {
+            storage.add_8_bits(EncoderFrameType::CallsignHashMetadata as u8, 4);
+            storage.add_32_bits(*hash, CALLSIGN_HASH_ENCODING_BITS);
+        }
+        Frame::LocatorMetadata { locator } => {
+            storage.add_8_bits(EncoderFrameType::LocatorMetadata as u8, 4);
+            storage.add_16_bits(encode_locator(locator.clone()).unwrap(), LOCATOR_ENCODING_BITS);
+            // A 6-character locator's subsquare follows in an Extension frame.
+            if let Some(subsquare) = encode_locator_subsquare(locator.clone()).unwrap() {
+                storage.add_8_bits(EncoderFrameType::Extension as u8, 4);
+                storage.add_16_bits(subsquare, LOCATOR_SUBSQUARE_ENCODING_BITS);
+            }
+        }
+        Frame::PowerMetadata { power } => {
+            storage.add_8_bits(EncoderFrameType::PowerMetadata as u8, 4);
+            storage.add_8_bits(encode_power(*power).unwrap(), POWER_ENCODING_BITS);
+        }
+        _ => panic!("{:?} is not a metadata frame", frame),
+    }
+}
 
 struct SourceEncoderKeyerThread {
     // Terminate flag
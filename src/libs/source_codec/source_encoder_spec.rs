@@ -14,12 +14,14 @@ mod source_encoder_spec {
     use crate::libs::application::application::{BusInput, BusOutput};
 
     use crate::libs::keyer_io::keyer_io::{KeyerSpeed, KeyingEvent, KeyingTimedEvent};
+    use crate::libs::source_codec::metadata_codec::MetadataCodecError;
     use crate::libs::source_codec::source_encoder::{SourceEncoder, SourceEncoding};
     use crate::libs::source_codec::source_encoding::Frame;
     use crate::libs::source_codec::test_encoding_builder::encoded;
     use crate::libs::util::test_util;
 
     const TEST_SOURCE_ENCODER_BLOCK_SIZE_IN_BITS: usize = 64;
+    const M0CUV_HASH: u32 = 0x397c48;
 
     #[ctor::ctor]
     fn before_each() {
@@ -49,7 +51,9 @@ mod source_encoder_spec {
         source_encoder.set_output_tx(Arc::new(Mutex::new(source_encoder_tx)));
 
         info!("Fixture setup sleeping");
-        test_util::wait_5_ms(); // give things time to start
+        // give things time to start - if the thread started before its input was set, it sleeps
+        // for 100ms before reading any keying.
+        test_util::wait_n_ms(110);
         info!("Fixture setup out of sleep");
 
         SourceEncoderFixture {
@@ -659,4 +663,218 @@ mod source_encoder_spec {
             }
         }
     }
+
+    fn send_perfect_dits(fixture: &mut SourceEncoderFixture, count: usize) {
+        // A precise dit at 20WPM is 60ms long; alternate mark and space, starting with mark.
+        for n in 0..count {
+            fixture.keying_event_tx.broadcast(KeyingEvent::Timed(KeyingTimedEvent { up: n % 2 == 0, duration: 60 }));
+        }
+    }
+
+    #[rstest]
+    fn metadata_is_sent_one_frame_per_block_at_the_start_of_a_transmission(mut fixture: SourceEncoderFixture) {
+        test_util::panic_after(Duration::from_secs(2), move || {
+            fixture.source_encoder.set_keyer_speed(20);
+            fixture.source_encoder.set_callsign("M0CUV".to_owned()).unwrap();
+            fixture.source_encoder.set_locator("JO01".to_owned()).unwrap();
+            fixture.source_encoder.set_power(37).unwrap();
+            test_util::wait_5_ms();
+
+            fixture.keying_event_tx.broadcast(KeyingEvent::Start());
+            // The callsign takes 34 bits, WPM|Polarity 11, leaving room for four perfect dits.
+            send_perfect_dits(&mut fixture, 4);
+            // The hash takes 26 bits, the locator 19, WPM|Polarity 11, leaving room for two perfect dits.
+            send_perfect_dits(&mut fixture, 2);
+            // The hash takes 26 bits, the power 10.
+            send_perfect_dits(&mut fixture, 1);
+            fixture.keying_event_tx.broadcast(KeyingEvent::End());
+            test_util::wait_5_ms();
+
+            // Block 1
+            expect_encoded_block(&mut fixture, encoded(TEST_SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, 20, &[
+                Frame::CallsignMetadata { callsign: "M0CUV".to_owned() },
+                Frame::WPMPolarity { wpm: 20, polarity: true },
+                Frame::KeyingPerfectDit,
+                Frame::KeyingPerfectDit,
+                Frame::KeyingPerfectDit,
+                Frame::KeyingPerfectDit,
+            ]));
+            // Block 2
+            expect_encoded_block(&mut fixture, encoded(TEST_SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, 20, &[
+                Frame::CallsignHashMetadata { hash: M0CUV_HASH },
+                Frame::LocatorMetadata { locator: "JO01".to_owned() },
+                Frame::WPMPolarity { wpm: 20, polarity: true },
+                Frame::KeyingPerfectDit,
+                Frame::KeyingPerfectDit,
+            ]));
+            // Block 3
+            expect_encoded_block(&mut fixture, encoded(TEST_SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, 20, &[
+                Frame::CallsignHashMetadata { hash: M0CUV_HASH },
+                Frame::PowerMetadata { power: 37 },
+                Frame::WPMPolarity { wpm: 20, polarity: true },
+                Frame::KeyingPerfectDit,
+                Frame::KeyingEnd,
+            ]));
+        });
+    }
+
+    #[rstest]
+    fn callsign_hash_is_sent_in_blocks_without_the_callsign(mut fixture: SourceEncoderFixture) {
+        test_util::panic_after(Duration::from_secs(2), move || {
+            fixture.source_encoder.set_keyer_speed(20);
+            fixture.source_encoder.set_callsign("M0CUV".to_owned()).unwrap();
+            test_util::wait_5_ms();
+
+            fixture.keying_event_tx.broadcast(KeyingEvent::Start());
+            send_perfect_dits(&mut fixture, 4);
+            // The hash takes 26 bits, WPM|Polarity 11, leaving room for six perfect dits.
+            send_perfect_dits(&mut fixture, 6);
+            send_perfect_dits(&mut fixture, 1);
+            fixture.keying_event_tx.broadcast(KeyingEvent::End());
+            test_util::wait_5_ms();
+
+            // Block 1
+            expect_encoded_block(&mut fixture, encoded(TEST_SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, 20, &[
+                Frame::CallsignMetadata { callsign: "M0CUV".to_owned() },
+                Frame::WPMPolarity { wpm: 20, polarity: true },
+                Frame::KeyingPerfectDit,
+                Frame::KeyingPerfectDit,
+                Frame::KeyingPerfectDit,
+                Frame::KeyingPerfectDit,
+            ]));
+            // Block 2
+            expect_encoded_block(&mut fixture, encoded(TEST_SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, 20, &[
+                Frame::CallsignHashMetadata { hash: M0CUV_HASH },
+                Frame::WPMPolarity { wpm: 20, polarity: true },
+                Frame::KeyingPerfectDit,
+                Frame::KeyingPerfectDit,
+                Frame::KeyingPerfectDit,
+                Frame::KeyingPerfectDit,
+                Frame::KeyingPerfectDit,
+                Frame::KeyingPerfectDit,
+            ]));
+            // Block 3
+            expect_encoded_block(&mut fixture, encoded(TEST_SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, 20, &[
+                Frame::CallsignHashMetadata { hash: M0CUV_HASH },
+                Frame::WPMPolarity { wpm: 20, polarity: true },
+                Frame::KeyingPerfectDit,
+                Frame::KeyingEnd,
+            ]));
+        });
+    }
+
+    fn expect_block_with_expected_metadata_only(fixture: &mut SourceEncoderFixture, expected_metadata_only: bool) -> Vec<u8> {
+        match fixture.source_encoder_rx.recv_timeout(Duration::from_secs(1)) {
+            Ok(encoding) => {
+                info!("Received SourceEncoding of {}", encoding);
+                assert_eq!(encoding.is_metadata_only, expected_metadata_only);
+                encoding.block
+            }
+            Err(e) => {
+                panic!("Should have received a SourceEncoding, not an error of {}", e);
+            }
+        }
+    }
+
+    #[rstest]
+    fn block_without_keying_is_marked_as_metadata_only(mut fixture: SourceEncoderFixture) {
+        test_util::panic_after(Duration::from_secs(2), move || {
+            fixture.source_encoder.set_keyer_speed(20);
+            fixture.source_encoder.set_callsign("M0CUV".to_owned()).unwrap();
+            fixture.source_encoder.set_locator("IO91wm".to_owned()).unwrap();
+            test_util::wait_5_ms();
+
+            fixture.keying_event_tx.broadcast(KeyingEvent::Start());
+            send_perfect_dits(&mut fixture, 4);
+            // The hash takes 26 bits, the locator and its subsquare 33, leaving no room for
+            // WPM|Polarity, so the block is emitted with only metadata.
+            send_perfect_dits(&mut fixture, 1);
+            fixture.keying_event_tx.broadcast(KeyingEvent::End());
+            test_util::wait_5_ms();
+
+            // Block 1
+            expect_block_with_expected_metadata_only(&mut fixture, false);
+            // Block 2
+            let block = expect_block_with_expected_metadata_only(&mut fixture, true);
+            assert_eq!(block, encoded(TEST_SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, 20, &[
+                Frame::CallsignHashMetadata { hash: M0CUV_HASH },
+                Frame::LocatorMetadata { locator: "IO91wm".to_owned() },
+            ]));
+            // Block 3
+            expect_block_with_expected_metadata_only(&mut fixture, false);
+        });
+    }
+
+    #[rstest]
+    fn metadata_is_resent_at_the_start_of_the_next_transmission(mut fixture: SourceEncoderFixture) {
+        test_util::panic_after(Duration::from_secs(2), move || {
+            fixture.source_encoder.set_keyer_speed(20);
+            fixture.source_encoder.set_locator("IO91wm".to_owned()).unwrap();
+            test_util::wait_5_ms();
+
+            for _transmission in 0..2 {
+                fixture.keying_event_tx.broadcast(KeyingEvent::Start());
+                send_perfect_dits(&mut fixture, 1);
+                fixture.keying_event_tx.broadcast(KeyingEvent::End());
+                test_util::wait_5_ms();
+
+                expect_encoded_block(&mut fixture, encoded(TEST_SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, 20, &[
+                    Frame::LocatorMetadata { locator: "IO91wm".to_owned() },
+                    Frame::WPMPolarity { wpm: 20, polarity: true },
+                    Frame::KeyingPerfectDit,
+                    Frame::KeyingEnd,
+                ]));
+            }
+        });
+    }
+
+    #[rstest]
+    fn metadata_wont_fit_in_first_block_so_goes_in_next_block(mut fixture: SourceEncoderFixture) {
+        test_util::panic_after(Duration::from_secs(2), move || {
+            fixture.source_encoder.set_keyer_speed(20);
+            test_util::wait_5_ms();
+
+            // A transmission without metadata fills most of the block...
+            fixture.keying_event_tx.broadcast(KeyingEvent::Start());
+            send_perfect_dits(&mut fixture, 12);
+            test_util::wait_5_ms();
+            // ... then the callsign is set, and a new transmission started without emitting.
+            fixture.source_encoder.set_callsign("M0CUV".to_owned()).unwrap();
+            fixture.keying_event_tx.broadcast(KeyingEvent::Start());
+            send_perfect_dits(&mut fixture, 1);
+            test_util::wait_5_ms();
+            fixture.source_encoder.emit();
+            test_util::wait_5_ms();
+
+            // Block 1
+            expect_encoded_block(&mut fixture, encoded(TEST_SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, 20, &[
+                Frame::WPMPolarity { wpm: 20, polarity: true },
+                Frame::KeyingPerfectDit,
+                Frame::KeyingPerfectDit,
+                Frame::KeyingPerfectDit,
+                Frame::KeyingPerfectDit,
+                Frame::KeyingPerfectDit,
+                Frame::KeyingPerfectDit,
+                Frame::KeyingPerfectDit,
+                Frame::KeyingPerfectDit,
+                Frame::KeyingPerfectDit,
+                Frame::KeyingPerfectDit,
+                Frame::KeyingPerfectDit,
+                Frame::KeyingPerfectDit,
+            ]));
+            // Block 2
+            expect_encoded_block(&mut fixture, encoded(TEST_SOURCE_ENCODER_BLOCK_SIZE_IN_BITS, 20, &[
+                Frame::CallsignMetadata { callsign: "M0CUV".to_owned() },
+                Frame::WPMPolarity { wpm: 20, polarity: true },
+                Frame::KeyingPerfectDit,
+            ]));
+        });
+    }
+
+    #[rstest]
+    fn invalid_metadata_cannot_be_set(mut fixture: SourceEncoderFixture) {
+        assert_that!(fixture.source_encoder.set_callsign("F/M0CUV".to_owned()), eq(Err(MetadataCodecError::InvalidCallsign("F/M0CUV".to_owned()))));
+        assert_that!(fixture.source_encoder.set_locator("ZZ99".to_owned()), eq(Err(MetadataCodecError::InvalidLocator("ZZ99".to_owned()))));
+        assert_that!(fixture.source_encoder.set_power(64), eq(Err(MetadataCodecError::InvalidPower(64))));
+    }
 }
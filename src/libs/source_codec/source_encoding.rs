@@ -15,12 +15,15 @@ pub struct SourceEncoding {
     pub block: Vec<u8>,
     // Is this encoding block the last in the sequence?
     pub is_end: bool,
+    // Does this encoding block contain only metadata frames, and no keying?
+    pub is_metadata_only: bool,
 }
 
 impl Display for SourceEncoding {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         let c = if self.is_end { 'Y' } else { 'N' };
-        write!(f, "End? {} Data [", c)?;
+        let m = if self.is_metadata_only { 'Y' } else { 'N' };
+        write!(f, "End? {} Metadata only? {} Data [", c, m)?;
         for b in &self.block {
             write!(f, "{:02X?} ", b)?;
         }
@@ -47,6 +50,8 @@ pub trait SourceEncodingBuilder {
     fn add_bool(&mut self, data: bool);
     /// Set the 'end' state.
     fn set_end(&mut self);
+    /// Set the 'metadata only' state.
+    fn set_metadata_only(&mut self);
     /// Build the SourceEncoding by padding it out to the block size, and reset the storage.
     fn build(&mut self) -> SourceEncoding;
 }
@@ -70,9 +75,12 @@ pub trait SourceEncodingExtractor {
 }
 
 pub type Callsign = String;
-pub type CallsignHash = u16; // MAYBE?
+pub type CallsignHash = u32; // 22 bits
+// The hash played back for a station that has not (yet) identified itself. Outside the 22 bits
+// of a real hash, so no station can be mistaken for it.
+pub const UNKNOWN_CALLSIGN_HASH: CallsignHash = 1 << 22;
 pub type Locator = String;
-pub type Power = u8; // MAYBE?
+pub type Power = u8; // dBm
 pub type KeyingDelta = i16;
 pub type KeyingNaive = u16;
 
@@ -106,6 +114,7 @@ pub enum Frame {
     CallsignMetadata { callsign: Callsign },
     CallsignHashMetadata { hash: CallsignHash },
     LocatorMetadata { locator: Locator },
+    PowerMetadata { power: Power },
     KeyingPerfectDit,
     KeyingPerfectDah,
     KeyingPerfectWordgap,
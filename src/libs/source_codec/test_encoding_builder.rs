@@ -6,7 +6,7 @@ use std::sync::{Arc, RwLock};
 use crate::libs::keyer_io::keyer_io::KeyerSpeed;
 use crate::libs::source_codec::bitvec_source_encoding_builder::BitvecSourceEncodingBuilder;
 use crate::libs::source_codec::keying_encoder::{DefaultKeyingEncoder, KeyingEncoder};
-use crate::libs::source_codec::metadata_codec::{encode_callsign, encode_locator};
+use crate::libs::source_codec::metadata_codec::{CALLSIGN_ENCODING_BITS, CALLSIGN_HASH_ENCODING_BITS, encode_callsign, encode_locator, encode_locator_subsquare, encode_power, LOCATOR_ENCODING_BITS, LOCATOR_SUBSQUARE_ENCODING_BITS, POWER_ENCODING_BITS};
 use crate::libs::source_codec::source_encoding::{EncoderFrameType, Frame, SourceEncodingBuilder};
 
 /// Build a block of encoded data, not caring about overstuffing it since this
@@ -41,17 +41,27 @@ pub fn encoded(block_size_in_bits: usize, wpm: KeyerSpeed, frames: &[Frame]) ->
             Frame::CallsignMetadata { callsign } => {
                 let mut b = builder.write().unwrap();
                 b.add_8_bits(EncoderFrameType::CallsignMetadata as u8, 4);
-                b.add_32_bits(encode_callsign(callsign.clone()), 28);
+                b.add_32_bits(encode_callsign(callsign.clone()).unwrap(), CALLSIGN_ENCODING_BITS);
             }
             Frame::CallsignHashMetadata { hash } => {
                 let mut b = builder.write().unwrap();
                 b.add_8_bits(EncoderFrameType::CallsignHashMetadata as u8, 4);
-                b.add_16_bits(*hash as u16, 16);
+                b.add_32_bits(*hash, CALLSIGN_HASH_ENCODING_BITS);
             }
             Frame::LocatorMetadata { locator } => {
                 let mut b = builder.write().unwrap();
                 b.add_8_bits(EncoderFrameType::LocatorMetadata as u8, 4);
-                b.add_16_bits(encode_locator(locator.clone()), 15);
+                b.add_16_bits(encode_locator(locator.clone()).unwrap(), LOCATOR_ENCODING_BITS);
+                // A 6-character locator's subsquare follows in an Extension frame.
+                if let Some(subsquare) = encode_locator_subsquare(locator.clone()).unwrap() {
+                    b.add_8_bits(EncoderFrameType::Extension as u8, 4);
+                    b.add_16_bits(subsquare, LOCATOR_SUBSQUARE_ENCODING_BITS);
+                }
+            }
+            Frame::PowerMetadata { power } => {
+                let mut b = builder.write().unwrap();
+                b.add_8_bits(EncoderFrameType::PowerMetadata as u8, 4);
+                b.add_8_bits(encode_power(*power).unwrap(), POWER_ENCODING_BITS);
             }
             Frame::KeyingPerfectDit => {
                 keying_encoder.encode_perfect_dit();
@@ -1,2 +1,4 @@
 pub mod modulate;
-pub mod transmitter;
\ No newline at end of file
+pub mod transmitter;
+pub mod tune;
+pub mod waveform_file_encoder;
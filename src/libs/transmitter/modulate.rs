@@ -11,6 +11,8 @@ use crate::libs::transmitter::transmitter::AudioFrequencyHz;
 pub const SYMBOL_SMOOTHING_FILTER_BANDWIDTH: f32 = 2.0f32; // TODO FT8 uses 2; FT4 uses 1; unsure what to use here
 pub const SYMBOL_PERIOD_SECONDS: f32 = 0.160_f32;
 pub const RAMP_SYMBOL_PERIOD_SECONDS: f32 = 0.020_f32;
+// Adjacent tones are one cycle per symbol period apart, so the tones are orthogonal.
+pub const TONE_SPACING_HZ: f32 = 1.0 / SYMBOL_PERIOD_SECONDS;
 const PI: f32 = std::f32::consts::PI;
 
 const GFSK_CONST_K: f32 = 5.336446f32; // PI * sqrt(2 / log(2))
@@ -32,159 +34,227 @@ pub fn gfsk_pulse(samples_per_symbol: usize, pulse: &mut [f32]) {
 const SYMBOL_WIDTH_IN_SPSYM: usize = 3;
 const RAMP_SYMBOL_WIDTH_IN_SPSYM: usize = 2;  // WHY 2 * n_spsym (when the above channel symbol modulation uses 3 * n_spsym)?
 
-/// Synthesize a waveform of tones, based on the channel_symbols, at the sample_rate, with a base
-/// audio frequency given by the audio_offset. Shape the tones using Gaussian Frequency Shift Keying
-/// phase shaping. Store the waveform in the supplied array of signal waveform samples.
-/// There should be sample_rate * channel_symbols.len() * SYMBOL_PERIOD_SECONDS samples.
-/// If channel_symbols starts or ends with RampUp/RampDown symbols, the amplitude of the start/end
-/// of the waveform will be ramped accordingly.
-/// Note: It is the caller's responsibility to decide whether to ramp up / down, and to provide
-/// a waveform_store large enough to store the whole modulated channel_symbols.
-/// @param[in] audio_offset is the base audio frequency of the synthesized waveform
-/// @param[in] sample_rate is the sample rate of the output device used to emit the synthesized
-/// waveform
-/// @param[in] channel_symbols a vector of channel symbols; if emitting the ramp up/down waveforms
-/// then the first and last channel symbol is used for these
-/// @param[in] waveform_store will be mutated to contain the emitted synthesized waveform
-/// @param[in] need_ramp_up indicates the start of a transmitted sequence of waveforms, and that
-/// the first symbol should be repeated with a ramped-up waveform
-/// @param[in] need_ramp_up indicates the final waveform in a transmission, and that the last
-/// symbol should be repeated with a ramped-down waveform
-/// @param[out] The return is the number of samples stored in the waveform_store, based on whether
-/// the ramp up/down samples are present. The waveform has maximum amplitude; this is scaled by
-/// the playback code.
-pub fn gfsk_modulate(audio_offset: AudioFrequencyHz, sample_rate: AudioFrequencyHz,
-                     channel_symbols: &Vec<ChannelSymbol>, waveform_store: &mut [f32],
-                     need_ramp_up: bool, need_ramp_down: bool) -> usize {
-    if sample_rate == 0 {
-        panic!("No sample rate defined for gfsk_modulate");
+/*
+ * The GfskModulator synthesizes the waveform of a transmission, one ChannelEncoding block at a
+ * time. It is built once for a sample rate, computing the GFSK pulse, and reuses its working
+ * storage for each block.
+ * Each symbol's pulse is three symbols long: it smooths the frequency into the symbol from the
+ * previous one, and out of it into the next. So the last symbol of a block cannot be completely
+ * synthesized until the first symbol of the next block is known. Unless the block ends the
+ * transmission (is ramped down), its last symbol is withheld, and emitted at the start of the
+ * next block's waveform, along with the frequency and phase at which the previous block's waveform
+ * ended. Successive blocks therefore join without a break in frequency or phase, and only the first
+ * block of a transmission is ramped up.
+ * There is therefore one more symbol in the waveform of a transmission's last block than there
+ * are channel symbols in it (and one fewer in its first, if that is not also the last).
+ */
+pub struct GfskModulator {
+    sample_rate: AudioFrequencyHz,
+    samples_per_symbol: usize,
+    ramp_samples_per_symbol: usize,
+    pulse: Vec<f32>,
+    dphi_peak: f32,
+    // Working storage for the smoothed frequency of each sample, without the audio offset.
+    dphi: Vec<f32>,
+    // Between the blocks of a transmission: the frequency contributions of the symbols already
+    // received, to the samples yet to be emitted - starting with the withheld last symbol.
+    carried_dphi: Vec<f32>,
+    last_channel_symbol: ChannelSymbol,
+    phase: f32,
+    in_transmission: bool,
+}
+
+impl GfskModulator {
+    pub fn new(sample_rate: AudioFrequencyHz) -> Self {
+        if sample_rate == 0 {
+            panic!("No sample rate defined for GfskModulator");
+        }
+        // Sample rate is 48000Hz.
+        let samples_per_symbol = (sample_rate as f32 * SYMBOL_PERIOD_SECONDS) as usize;
+        let ramp_samples_per_symbol = (sample_rate as f32 * RAMP_SYMBOL_PERIOD_SECONDS) as usize;
+        let mut pulse = vec![0.0; SYMBOL_WIDTH_IN_SPSYM * samples_per_symbol];
+        debug!("Creating GFSK pulse");
+        gfsk_pulse(samples_per_symbol, &mut pulse);
+        Self {
+            sample_rate,
+            samples_per_symbol,
+            ramp_samples_per_symbol,
+            pulse,
+            dphi_peak: 2.0 * PI / samples_per_symbol as f32,
+            dphi: Vec::new(),
+            carried_dphi: vec![0.0; 2 * samples_per_symbol],
+            last_channel_symbol: 0,
+            phase: 0.0,
+            in_transmission: false,
+        }
     }
 
-    // Sample rate is 48000Hz.
-    let samples_per_symbol = (sample_rate as f32 * SYMBOL_PERIOD_SECONDS) as usize;
-    let ramp_samples_per_symbol = (sample_rate as f32 * RAMP_SYMBOL_PERIOD_SECONDS) as usize;
-
-    let any_channel_symbols = channel_symbols.len() > 0;
-    // Ramping symbols can only be generated iff there are any channel symbols to base them on.
-    let ramp_up = need_ramp_up && any_channel_symbols;
-    let ramp_down = need_ramp_down && any_channel_symbols;
-
-    let total_number_of_samples = channel_symbols.len() * samples_per_symbol +
-        (if ramp_up { ramp_samples_per_symbol } else { 0 }) +
-        (if ramp_down { ramp_samples_per_symbol } else { 0 });
-    debug!("sample_rate {} # channel_symbols {} samples_per_symbol {} ramp_symbols_per_symbol {} total_number_of_samples {}",
-        sample_rate, channel_symbols.len(), samples_per_symbol, ramp_samples_per_symbol, total_number_of_samples);
-    if waveform_store.len() < total_number_of_samples {
-        panic!("Cannot store gfsk_modulate waveform in {} f32s, expecting {}", waveform_store.len(), total_number_of_samples);
+    pub fn samples_per_symbol(&self) -> usize {
+        self.samples_per_symbol
     }
 
-    // Compute the smoothed frequency waveform.
-    // Length = (n_sym+2)*samples_per_symbol samples, first and last symbols extended
-    let dphi_peak = 2.0 * PI / samples_per_symbol as f32;
-    let mut dphi = Vec::new();
+    pub fn ramp_samples_per_symbol(&self) -> usize {
+        self.ramp_samples_per_symbol
+    }
 
-    // Shift frequency up by audio_offset Hz
-    let audio_offset_dphi = 2.0 * PI * audio_offset as f32 / sample_rate as f32;
-    for _ in 0..(total_number_of_samples + 2 * samples_per_symbol) { // WHY: + 2 * samples_per_symbol?
-        dphi.push(audio_offset_dphi);
+    // Is a transmission under way; ie has a block been modulated, without ramping down?
+    pub fn in_transmission(&self) -> bool {
+        self.in_transmission
     }
 
-    let mut pulse = vec![0.0; SYMBOL_WIDTH_IN_SPSYM * samples_per_symbol];
+    // Abandon any transmission under way, discarding its withheld last symbol. The next block will
+    // start a new transmission.
+    pub fn reset(&mut self) {
+        self.in_transmission = false;
+        self.phase = 0.0;
+    }
 
-    debug!("Creating GFSK pulse");
-    gfsk_pulse(samples_per_symbol, &mut pulse);
-/*
-    plot_graph(
-        "./gauss-envelope.png",
-        "GFSK Phase Envelope",
-        &pulse,
-        0,
-        pulse.len(),
-        0.0,
-        1.0,
-    );
-*/
-    let mut symbol_offset = 0;
-
-    // Add dummy symbol at beginning with tone value equal to 1st symbol if necessary/possible.
-    if ramp_up {
-        let first_channel_symbol = channel_symbols[0] as f32;
-        debug!("Adding ramp up symbol of #{}", first_channel_symbol);
-        for j in 0..(RAMP_SYMBOL_WIDTH_IN_SPSYM * ramp_samples_per_symbol) {
-            dphi[j] += dphi_peak * pulse[j + ramp_samples_per_symbol] * first_channel_symbol;
+    /// The number of samples that modulate() will store, given the same arguments.
+    pub fn number_of_samples(&self, number_of_channel_symbols: usize, need_ramp_up: bool, need_ramp_down: bool) -> usize {
+        if number_of_channel_symbols == 0 && !(need_ramp_down && self.in_transmission) {
+            return 0;
         }
-        symbol_offset = ramp_samples_per_symbol;
+        let lead = self.lead_samples(number_of_channel_symbols, need_ramp_up);
+        let symbols = if need_ramp_down || number_of_channel_symbols == 0 {
+            number_of_channel_symbols
+        } else {
+            number_of_channel_symbols - 1
+        };
+        let ramp_down = if need_ramp_down { self.ramp_samples_per_symbol } else { 0 };
+        lead + symbols * self.samples_per_symbol + ramp_down
     }
-    let mut symbol_index = 0;
-
-    // Modulate the channel symbols...
-    debug!("Modulating channel symbols");
-    for sym in channel_symbols.as_slice().iter() {
-        let ib = symbol_index * samples_per_symbol;
-        debug!("channel symbol #{} at offset {}", sym, ib);
 
-        for j in 0..(SYMBOL_WIDTH_IN_SPSYM * samples_per_symbol) { // WHY 3 * n_spsym? (same length as the gfsk pulse)
-            dphi[symbol_offset + j + ib] += dphi_peak * pulse[j] * (*sym as f32);
-            //debug!("  #{}={}", j+ib, dphi[j+ib]);
+    // The samples before the first of the channel symbols: the withheld last symbol of the previous
+    // block, or the ramp up.
+    fn lead_samples(&self, number_of_channel_symbols: usize, need_ramp_up: bool) -> usize {
+        if self.in_transmission {
+            self.samples_per_symbol
+        } else if need_ramp_up && number_of_channel_symbols > 0 {
+            self.ramp_samples_per_symbol
+        } else {
+            0
         }
-        symbol_index += 1;
     }
 
-    // Add dummy symbol at end with tone value equal to last symbol if necessary/possible.
-    if ramp_down {
-        let ib = symbol_index * samples_per_symbol;
-        let last_channel_symbol = channel_symbols[channel_symbols.len() - 1] as f32;
-        debug!("Adding ramp down symbol of #{} at offset {}", last_channel_symbol, ib);
-        for j in 0..(RAMP_SYMBOL_WIDTH_IN_SPSYM * ramp_samples_per_symbol) {
-            dphi[symbol_offset + j + ib] += dphi_peak * pulse[j] * last_channel_symbol
+    /// Synthesize the waveform of a block of tones, based on the channel_symbols, with a base
+    /// audio frequency given by the audio_offset. Shape the tones using Gaussian Frequency Shift
+    /// Keying phase shaping. Store the waveform in the supplied array of signal waveform samples,
+    /// which must be at least number_of_samples() long.
+    /// @param[in] audio_offset is the base audio frequency of the synthesized waveform
+    /// @param[in] channel_symbols a block of channel symbols; if emitting the ramp up/down
+    /// waveforms then the first and last channel symbol is used for these
+    /// @param[in] waveform_store will be mutated to contain the emitted synthesized waveform
+    /// @param[in] need_ramp_up indicates the start of a transmitted sequence of waveforms, and that
+    /// the first symbol should be repeated with a ramped-up waveform. This is ignored if a
+    /// transmission is already under way, since that continues from its withheld symbol.
+    /// @param[in] need_ramp_down indicates the final waveform in a transmission, and that the last
+    /// symbol should be repeated with a ramped-down waveform
+    /// @param[out] The return is the number of samples stored in the waveform_store. The waveform
+    /// has maximum amplitude; this is scaled by the playback code.
+    pub fn modulate(&mut self, audio_offset: AudioFrequencyHz, channel_symbols: &[ChannelSymbol],
+                    waveform_store: &mut [f32], need_ramp_up: bool, need_ramp_down: bool) -> usize {
+        let samples_per_symbol = self.samples_per_symbol;
+        let ramp_samples_per_symbol = self.ramp_samples_per_symbol;
+        let continuing = self.in_transmission;
+        let any_channel_symbols = !channel_symbols.is_empty();
+        // Ramping symbols can only be generated iff there are any channel symbols to base them on.
+        let ramp_up = need_ramp_up && any_channel_symbols && !continuing;
+        let ramp_down = need_ramp_down && (any_channel_symbols || continuing);
+        let total_number_of_samples = self.number_of_samples(channel_symbols.len(), need_ramp_up, need_ramp_down);
+        if total_number_of_samples == 0 {
+            return 0;
+        }
+        let lead = self.lead_samples(channel_symbols.len(), need_ramp_up);
+        debug!("sample_rate {} # channel_symbols {} samples_per_symbol {} ramp_samples_per_symbol {} continuing {} total_number_of_samples {}",
+            self.sample_rate, channel_symbols.len(), samples_per_symbol, ramp_samples_per_symbol, continuing, total_number_of_samples);
+        if waveform_store.len() < total_number_of_samples {
+            panic!("Cannot store GFSK waveform in {} f32s, expecting {}", waveform_store.len(), total_number_of_samples);
         }
-    }
 
-    /*
-    if any_channel_symbols {
-        debug!("plotting tones.png with {} tones", dphi.len());
-        plot_graph("./tones.png", "GFSK Tones", &dphi, 0, dphi.len(), 0.07, 0.1);
-    }
-    */
-
-    debug!("Calculating waveform");
-    // Calculate and insert the audio waveform
-    let mut phi = 0.0f32;
-    for k in 0..total_number_of_samples {
-        // Don't include dummy symbols
-        waveform_store[k] = phi.sin();
-        phi = libm::fmodf(phi + dphi[k + samples_per_symbol], 2.0 * PI);
-    }
+        // Compute the smoothed frequency waveform. Sample k of the waveform is at dphi[k +
+        // samples_per_symbol], since the first symbol's pulse starts a symbol before it does.
+        let dphi_len = lead + (channel_symbols.len() + 2) * samples_per_symbol + ramp_samples_per_symbol;
+        self.dphi.clear();
+        self.dphi.resize(dphi_len, 0.0);
+        let dphi = &mut self.dphi;
+        let pulse = &self.pulse;
+        let dphi_peak = self.dphi_peak;
 
-    // Apply envelope shaping to the first and last symbols if necessary.
-    if ramp_up || ramp_down {
-        debug!("Shaping envelope");
-        let n_ramp = samples_per_symbol / 8;
-        for i in 0..n_ramp {
-            let env = (1.0 - (2.0 * PI * i as f32 / (2.0 * n_ramp as f32)).cos()) / 2.0;
-            if ramp_up {
-                waveform_store[i] *= env;
+        if continuing {
+            // The rest of the previous block, from its withheld last symbol.
+            for (j, carried) in self.carried_dphi.iter().enumerate() {
+                dphi[samples_per_symbol + j] += carried;
             }
-            if ramp_down {
-                waveform_store[total_number_of_samples - 1 - i] *= env;
+        } else if ramp_up {
+            // Add dummy symbol at beginning with tone value equal to 1st symbol.
+            let first_channel_symbol = channel_symbols[0] as f32;
+            debug!("Adding ramp up symbol of #{}", first_channel_symbol);
+            for j in 0..(RAMP_SYMBOL_WIDTH_IN_SPSYM * ramp_samples_per_symbol) {
+                dphi[j] += dphi_peak * pulse[j + ramp_samples_per_symbol] * first_channel_symbol;
             }
         }
 
-        /*
-        if ramp_up {
-            debug!("Plotting ramp up");
-            plot_graph("./ramp-up.png", "Modulated waveform", &waveform_store, 0, samples_per_symbol * 3, -1.1, 1.1);
+        // Modulate the channel symbols...
+        debug!("Modulating channel symbols");
+        for (symbol_index, sym) in channel_symbols.iter().enumerate() {
+            let ib = lead + symbol_index * samples_per_symbol;
+            let tone = *sym as f32;
+            for (j, p) in pulse.iter().enumerate() {
+                dphi[ib + j] += dphi_peak * p * tone;
+            }
+        }
+        if any_channel_symbols {
+            self.last_channel_symbol = channel_symbols[channel_symbols.len() - 1];
         }
+
+        // Add dummy symbol at end with tone value equal to last symbol if necessary/possible.
         if ramp_down {
-            debug!("Plotting ramp down");
-            plot_graph("./ramp-down.png", "Modulated waveform", &waveform_store, total_number_of_samples - (samples_per_symbol * 3), total_number_of_samples, -1.1, 1.1);
+            let ib = total_number_of_samples - ramp_samples_per_symbol;
+            let last_channel_symbol = self.last_channel_symbol as f32;
+            debug!("Adding ramp down symbol of #{} at offset {}", last_channel_symbol, ib);
+            for j in 0..(RAMP_SYMBOL_WIDTH_IN_SPSYM * ramp_samples_per_symbol) {
+                dphi[ib + j] += dphi_peak * pulse[j] * last_channel_symbol;
+            }
         }
 
-         */
-    }
+        debug!("Calculating waveform");
+        // Calculate and insert the audio waveform, continuing from the phase of the previous block.
+        let audio_offset_dphi = 2.0 * PI * audio_offset as f32 / self.sample_rate as f32;
+        let mut phi = if continuing { self.phase } else { 0.0f32 };
+        for k in 0..total_number_of_samples {
+            // Don't include dummy symbols
+            waveform_store[k] = phi.sin();
+            phi = libm::fmodf(phi + audio_offset_dphi + dphi[k + samples_per_symbol], 2.0 * PI);
+        }
 
-    debug!("Finished modulation");
-    total_number_of_samples
+        // Apply envelope shaping to the first and last symbols if necessary.
+        if ramp_up || ramp_down {
+            debug!("Shaping envelope");
+            let n_ramp = samples_per_symbol / 8;
+            for i in 0..n_ramp {
+                let env = (1.0 - (2.0 * PI * i as f32 / (2.0 * n_ramp as f32)).cos()) / 2.0;
+                if ramp_up {
+                    waveform_store[i] *= env;
+                }
+                if ramp_down {
+                    waveform_store[total_number_of_samples - 1 - i] *= env;
+                }
+            }
+        }
+
+        if ramp_down {
+            self.reset();
+        } else {
+            // Carry the withheld last symbol, and the tail of the previous one, to the next block.
+            let carried_start = total_number_of_samples + samples_per_symbol;
+            self.carried_dphi.copy_from_slice(&dphi[carried_start..carried_start + 2 * samples_per_symbol]);
+            self.phase = phi;
+            self.in_transmission = true;
+        }
+
+        debug!("Finished modulation");
+        total_number_of_samples
+    }
 }
 
 #[cfg(test)]
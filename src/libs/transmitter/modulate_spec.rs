@@ -1,11 +1,18 @@
+extern crate hamcrest2;
+
 #[cfg(test)]
 mod modulate_spec {
+    use hamcrest2::prelude::*;
+    use log::info;
+    use crate::libs::channel_codec::channel_encoding::ChannelSymbol;
     use crate::libs::channel_codec::sample_channel_encoding::sample_channel_encoding;
-    use crate::libs::transmitter::modulate::gfsk_modulate;
+    use crate::libs::transmitter::modulate::GfskModulator;
     use crate::libs::transmitter::transmitter::AudioFrequencyHz;
 
     const SAMPLE_RATE: AudioFrequencyHz = 48000;
     const AUDIO_FREQUENCY: AudioFrequencyHz = 600;
+    const SAMPLES_PER_SYMBOL: usize = 7680;
+    const RAMP_SAMPLES_PER_SYMBOL: usize = 960;
 
     #[ctor::ctor]
     fn before_each() {
@@ -20,7 +27,135 @@ mod modulate_spec {
     fn empty_panic() {
         let channel_symbols = &sample_channel_encoding().block;
         let mut empty_f32: [f32; 0] = [];
-        gfsk_modulate(AUDIO_FREQUENCY, SAMPLE_RATE, channel_symbols, &mut empty_f32, true, true);
+        GfskModulator::new(SAMPLE_RATE).modulate(AUDIO_FREQUENCY, channel_symbols, &mut empty_f32, true, true);
+    }
+
+    #[test]
+    #[should_panic]
+    fn sample_rate_must_be_given() {
+        GfskModulator::new(0);
     }
 
+    #[test]
+    fn symbol_periods() {
+        let modulator = GfskModulator::new(SAMPLE_RATE);
+        assert_that!(modulator.samples_per_symbol(), equal_to(SAMPLES_PER_SYMBOL));
+        assert_that!(modulator.ramp_samples_per_symbol(), equal_to(RAMP_SAMPLES_PER_SYMBOL));
+    }
+
+    // Modulate each block in turn, ramping up the first, and down the last, returning the
+    // concatenated waveform, and the number of samples of each block.
+    fn modulate_blocks(modulator: &mut GfskModulator, blocks: &[&[ChannelSymbol]]) -> (Vec<f32>, Vec<usize>) {
+        let mut waveform = vec![];
+        let mut block_samples = vec![];
+        for (index, block) in blocks.iter().enumerate() {
+            let need_ramp_up = index == 0;
+            let need_ramp_down = index == blocks.len() - 1;
+            let mut store = vec![0.0; (block.len() + 1) * SAMPLES_PER_SYMBOL + 2 * RAMP_SAMPLES_PER_SYMBOL];
+            let expected_samples = modulator.number_of_samples(block.len(), need_ramp_up, need_ramp_down);
+            let samples = modulator.modulate(AUDIO_FREQUENCY, block, &mut store, need_ramp_up, need_ramp_down);
+            assert_that!(samples, equal_to(expected_samples));
+            waveform.extend_from_slice(&store[0..samples]);
+            block_samples.push(samples);
+        }
+        (waveform, block_samples)
+    }
+
+    fn max_difference(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| (x - y).abs()).fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn single_block_is_ramped_up_and_down() {
+        let symbols = sample_channel_encoding().block;
+        let mut modulator = GfskModulator::new(SAMPLE_RATE);
+        let (waveform, block_samples) = modulate_blocks(&mut modulator, &[&symbols]);
+        assert_that!(block_samples, equal_to(vec![symbols.len() * SAMPLES_PER_SYMBOL + 2 * RAMP_SAMPLES_PER_SYMBOL]));
+        assert_that!(waveform[0], equal_to(0.0));
+        assert_that!(waveform[waveform.len() - 1].abs(), less_than(0.0001));
+        assert_that!(modulator.in_transmission(), equal_to(false));
+    }
+
+    #[test]
+    fn last_symbol_of_each_block_is_withheld_until_the_next() {
+        let symbols = sample_channel_encoding().block;
+        let mut modulator = GfskModulator::new(SAMPLE_RATE);
+        let (_, block_samples) = modulate_blocks(&mut modulator, &[&symbols, &symbols, &symbols]);
+        assert_that!(block_samples, equal_to(vec![
+            RAMP_SAMPLES_PER_SYMBOL + (symbols.len() - 1) * SAMPLES_PER_SYMBOL,
+            symbols.len() * SAMPLES_PER_SYMBOL,
+            (symbols.len() + 1) * SAMPLES_PER_SYMBOL + RAMP_SAMPLES_PER_SYMBOL,
+        ]));
+        assert_that!(modulator.in_transmission(), equal_to(false));
+    }
+
+    #[test]
+    fn blocks_are_modulated_as_one_continuous_waveform() {
+        // Splitting the symbols into blocks must not change the waveform at all.
+        let symbols = sample_channel_encoding().block;
+        let mut whole_modulator = GfskModulator::new(SAMPLE_RATE);
+        let (whole, _) = modulate_blocks(&mut whole_modulator, &[&symbols]);
+
+        let mut split_modulator = GfskModulator::new(SAMPLE_RATE);
+        let (split, block_samples) = modulate_blocks(&mut split_modulator, &[&symbols[0..30], &symbols[30..31], &symbols[31..]]);
+        info!("Block samples {:?}", block_samples);
+        assert_that!(split.len(), equal_to(whole.len()));
+        assert_that!(max_difference(&split, &whole), less_than(0.0001));
+    }
+
+    #[test]
+    fn phase_and_frequency_are_continuous_at_block_boundaries() {
+        let symbols = sample_channel_encoding().block;
+        let mut modulator = GfskModulator::new(SAMPLE_RATE);
+        let (waveform, block_samples) = modulate_blocks(&mut modulator, &[&symbols, &symbols]);
+        // The change between successive samples is at most the phase change per sample of the
+        // highest tone.
+        let highest_tone_hz = AUDIO_FREQUENCY as f32 + 15.0 * 6.25;
+        let max_step = 2.0 * std::f32::consts::PI * highest_tone_hz / SAMPLE_RATE as f32;
+        let boundary = block_samples[0];
+        for n in (boundary - 100)..(boundary + 100) {
+            assert_that!((waveform[n + 1] - waveform[n]).abs(), less_than_or_equal_to(max_step));
+        }
+        // Not ramped at the boundary.
+        let peak = waveform[boundary..(boundary + SAMPLES_PER_SYMBOL)].iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        assert_that!(peak, greater_than(0.99));
+    }
+
+    #[test]
+    fn ramp_up_is_ignored_during_a_transmission() {
+        let symbols = sample_channel_encoding().block;
+        let mut modulator = GfskModulator::new(SAMPLE_RATE);
+        let mut store = vec![0.0; (symbols.len() + 2) * SAMPLES_PER_SYMBOL];
+        modulator.modulate(AUDIO_FREQUENCY, &symbols, &mut store, true, false);
+        assert_that!(modulator.in_transmission(), equal_to(true));
+        let samples = modulator.modulate(AUDIO_FREQUENCY, &symbols, &mut store, true, false);
+        assert_that!(samples, equal_to(symbols.len() * SAMPLES_PER_SYMBOL));
+        assert_that!(store[0].abs(), greater_than(0.0));
+    }
+
+    #[test]
+    fn withheld_symbol_is_emitted_by_ramping_down_an_empty_block() {
+        let symbols = sample_channel_encoding().block;
+        let mut modulator = GfskModulator::new(SAMPLE_RATE);
+        let mut store = vec![0.0; (symbols.len() + 2) * SAMPLES_PER_SYMBOL];
+        modulator.modulate(AUDIO_FREQUENCY, &symbols, &mut store, true, false);
+        assert_that!(modulator.number_of_samples(0, false, false), equal_to(0));
+        assert_that!(modulator.modulate(AUDIO_FREQUENCY, &[], &mut store, false, false), equal_to(0));
+        assert_that!(modulator.modulate(AUDIO_FREQUENCY, &[], &mut store, false, true), equal_to(SAMPLES_PER_SYMBOL + RAMP_SAMPLES_PER_SYMBOL));
+        assert_that!(modulator.in_transmission(), equal_to(false));
+        assert_that!(modulator.modulate(AUDIO_FREQUENCY, &[], &mut store, false, true), equal_to(0));
+    }
+
+    #[test]
+    fn reset_abandons_the_transmission() {
+        let symbols = sample_channel_encoding().block;
+        let mut modulator = GfskModulator::new(SAMPLE_RATE);
+        let mut store = vec![0.0; (symbols.len() + 2) * SAMPLES_PER_SYMBOL];
+        modulator.modulate(AUDIO_FREQUENCY, &symbols, &mut store, true, false);
+        modulator.reset();
+        assert_that!(modulator.in_transmission(), equal_to(false));
+        let samples = modulator.modulate(AUDIO_FREQUENCY, &symbols, &mut store, true, true);
+        assert_that!(samples, equal_to(symbols.len() * SAMPLES_PER_SYMBOL + 2 * RAMP_SAMPLES_PER_SYMBOL));
+        assert_that!(store[0], equal_to(0.0));
+    }
 }
@@ -6,7 +6,7 @@ use std::sync::{Arc, Mutex, RwLock, RwLockWriteGuard};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
 use std::thread::JoinHandle;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use bus::BusReader;
 use fp_rust::sync::CountDownLatch;
 use log::{debug, error, info, warn};
@@ -14,36 +14,46 @@ use portaudio::{NonBlocking, Output, OutputStreamSettings, PortAudio, Stream};
 use portaudio as pa;
 use crate::libs::application::application::BusInput;
 use crate::libs::buffer_pool::buffer_pool::BufferPool;
+use crate::libs::cat::cat::CatController;
 use crate::libs::channel_codec::channel_encoding::ChannelEncoding;
+use crate::libs::channel_codec::costas::COSTAS_ARRAY_SYMBOLS;
 use crate::libs::gui::gui_facades::GUIInputMessage;
 use crate::libs::source_codec::source_encoding::SOURCE_ENCODER_BLOCK_SIZE_IN_BITS;
-use crate::libs::transmitter::modulate::{gfsk_modulate, RAMP_SYMBOL_PERIOD_SECONDS, SYMBOL_PERIOD_SECONDS};
+use crate::libs::transmitter::modulate::{GfskModulator, RAMP_SYMBOL_PERIOD_SECONDS, SYMBOL_PERIOD_SECONDS};
+use crate::libs::transmitter::tune::{TUNE_TIMEOUT, TuneOscillator, Tuner, TuneSignal};
 
-pub type RadioFrequencyMHz = u32;
+pub type RadioFrequencyHz = u64;
 pub type AudioFrequencyHz = u16;
 pub type AmplitudeMax = f32; // 0.0 to 1.0 to scale the output power
 
-pub const COSTAS_ARRAY_SYMBOLS: usize = 7; // TODO: no Costas array yet
-
 /*
  * The Transmitter receives ChannelEncodings (block of symbols and end flag) on its input bus.
  * It decides to add RampUp/RampDown symbols to these, based on whether it is currently silent (not
  * transmitting tones), and whether the end flag is set. These are then converted to a GFSK
  * waveform, in a pool-allocated buffer of samples, and passed to the audio output callback that
  * PortAudio will be calling. When that callback has finished with the sample buffer it is released
- * to the pool.
+ * to the pool. The rig's PTT is keyed via the CatController before the first buffer of a
+ * transmission, and released once the callback has output the last.
+ * When tuning is requested, and no transmission is in progress, the thread keys the PTT and gives
+ * the callback a TuneOscillator to output instead; channel encodings wait until tuning has been
+ * stopped, or has timed out, and the oscillator has ramped down.
+ * Setting the transmit halt flag stops whatever is being transmitted: the callback ramps its output
+ * down, then discards the queued buffers, and the thread releases the PTT, discarding the rest of
+ * the halted transmission as it arrives. The callback sets the flag itself if the output has lasted
+ * longer than the maximum transmit duration, e.g. if a keyer or bus is stuck.
  */
 pub struct Transmitter {
-    _radio_frequency_mhz: RadioFrequencyMHz, // TODO CAT controller will need this?
     _audio_offset: AudioFrequencyHz,
     amplitude_max: AmplitudeMax,
     sample_rate: u32,
     dt: f32, // Reciprocal of the sample rate
     terminate: Arc<AtomicBool>,
+    transmit_halt: Arc<AtomicBool>,
     thread_handle: Option<JoinHandle<()>>,
     stream: Option<Stream<NonBlocking, Output<f32>>>,
     callback_data: Arc<RwLock<CallbackData>>,
     silent: Arc<AtomicBool>,
+    tune_control: Arc<Mutex<TuneControl>>,
 
     // Shared between thread and Transmitter
     input_rx: Arc<Mutex<Option<Arc<Mutex<BusReader<ChannelEncoding>>>>>>,
@@ -58,10 +68,48 @@ struct CallbackData {
     sample_rate: u32, // Hz
     samples: Vec<f32>, // contains the GFSK modulated waveform to emit, allocated as a Vec, used as a slice
     buffer_pool: Arc<Mutex<Option<BufferPool>>>, // allocated when sample rate known
+    modulator: Option<GfskModulator>, // built when sample rate known
     callback_messages: VecDeque<CallbackMessage>, // buffers to emit, or latches to sync on
+    tune: Option<TuneOscillator>, // emitted instead of the buffers while tuning
+    halt_gain: f32, // ramped down from 1.0 when halting, before the buffers are discarded
+    max_transmit_duration: Duration,
+    transmitted_samples: usize, // since the output was last silent
     gui_input: Arc<Mutex<Option<Arc<SyncSender<GUIInputMessage>>>>>,
 }
 
+impl CallbackData {
+    fn new(audio_frequency: AudioFrequencyHz) -> Self {
+        Self {
+            _amplitude: 0.0,
+            audio_frequency,
+            amplitude_max: 1.0,
+            delta_phase: 0.0,
+            _phase: 0.0,
+            sample_rate: 0,
+            samples: vec![],
+            buffer_pool: Arc::new(Mutex::new(None)),
+            modulator: None,
+            callback_messages: VecDeque::new(),
+            tune: None,
+            halt_gain: 1.0,
+            max_transmit_duration: DEFAULT_MAX_TRANSMIT_DURATION,
+            transmitted_samples: 0,
+            gui_input: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+// Tuning requested of the Transmitter's thread, and how long it may last.
+struct TuneControl {
+    signal: Option<TuneSignal>,
+    timeout: Duration,
+}
+
+// How often the thread checks for the end of tuning, or of a halt.
+const TUNE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+pub const DEFAULT_MAX_TRANSMIT_DURATION: Duration = Duration::from_secs(180);
+
 const NUMBER_OF_BUFFERS: usize = 32;
 
 struct BufferIndex {
@@ -82,7 +130,6 @@ pub fn maximum_number_of_symbols() -> usize {
     // This is then LDPC-encoded to yield 256 bits of codeword. Each byte of that (32 bytes)
     // yield 2 symbols. The maximum number of symbols transmitted is therefore:
     // Costas array and a frame of 64 symbols.
-    // TODO Costas Array might not be 7 symbols ..
     let channel_encoded_bits = (SOURCE_ENCODER_BLOCK_SIZE_IN_BITS + 2 + 14) * 2;
     let channel_encoded_symbols = (channel_encoded_bits / 8) * 2;
     // Note: ramp up/down are shorter than a full symbol and aren't counted here.
@@ -90,43 +137,37 @@ pub fn maximum_number_of_symbols() -> usize {
 }
 
 impl Transmitter {
-    pub fn new(audio_offset: AudioFrequencyHz, terminate: Arc<AtomicBool>,
-               /* TODO transmit halt AtomicBool */ /* TODO CAT controller passed in here */) -> Self {
+    pub fn new(audio_offset: AudioFrequencyHz, terminate: Arc<AtomicBool>, transmit_halt: Arc<AtomicBool>,
+               cat_controller: Arc<Mutex<dyn CatController>>) -> Self {
         // Share this holder between the Transmitter and its thread
         let input_rx_holder: Arc<Mutex<Option<Arc<Mutex<BusReader<ChannelEncoding>>>>>> = Arc::new(Mutex::new(None));
         let move_clone_input_rx_holder = input_rx_holder.clone();
 
         info!("Initialising Transmitter");
         let silent = Arc::new(AtomicBool::new(true));
-        let no_buffer_pool = Arc::new(Mutex::new(None));
-        let gui_input_holder = Arc::new(Mutex::new(None));
-        let modulation_callback_data = CallbackData {
-            _amplitude: 0.0,
-            audio_frequency: audio_offset,
-            amplitude_max: 1.0,
-            delta_phase: 0.0,
-            _phase: 0.0,
-            sample_rate: 0,
-            samples: vec![],
-            buffer_pool: no_buffer_pool,
-            callback_messages: VecDeque::new(),
-            gui_input: gui_input_holder,
-        };
+        let modulation_callback_data = CallbackData::new(audio_offset);
         // TODO replace this Mutex with atomics to reduce contention in the callback.
         let arc_lock_modulation_callback_data = Arc::new(RwLock::new(modulation_callback_data));
         let move_clone_modulation_callback_data = arc_lock_modulation_callback_data.clone();
         let move_clone_modulation_silent = silent.clone();
-
+        let tune_control = Arc::new(Mutex::new(TuneControl { signal: None, timeout: TUNE_TIMEOUT }));
+        let move_clone_tune_control = tune_control.clone();
+        let mut tune_started: Option<Instant> = None;
+        let move_clone_transmit_halt = transmit_halt.clone();
+        // Is the PTT keyed for a transmission, and is the rest of a halted one to be discarded?
+        let mut transmitting = false;
+        let mut discarding_halted_transmission = false;
 
         Self {
-            _radio_frequency_mhz: 0,
             _audio_offset: 0,
             amplitude_max: 1.0,
             sample_rate: 0, // will be initialised when the callback is initialised
             dt: 0.0,        // will be initialised when the callback is initialised
             terminate: terminate.clone(),
+            transmit_halt,
             input_rx: input_rx_holder,    // Modified by BusInput
             silent: silent.clone(),
+            tune_control,
             thread_handle: Some(thread::spawn(move || {
                 info!("Transmitter channel-encoding listener thread started");
                 loop {
@@ -135,6 +176,35 @@ impl Transmitter {
                         break;
                     }
 
+                    if service_tuning(&move_clone_tune_control, &mut tune_started, &move_clone_modulation_callback_data,
+                                      &move_clone_modulation_silent, &move_clone_transmit_halt, &cat_controller) {
+                        thread::sleep(TUNE_POLL_INTERVAL);
+                        continue;
+                    }
+
+                    if move_clone_transmit_halt.load(Ordering::SeqCst) {
+                        // Once the callback has ramped down and discarded the queued buffers...
+                        if move_clone_modulation_callback_data.read().unwrap().callback_messages.is_empty() {
+                            if transmitting {
+                                if let Err(e) = cat_controller.lock().unwrap().set_ptt(false) {
+                                    error!("Could not release PTT: {}", e);
+                                }
+                                if let Some(gui_input) = move_clone_modulation_callback_data.write().unwrap().gui_input.lock().unwrap().as_ref() {
+                                    gui_input.send(GUIInputMessage::SetTxIndicator(false)).expect("Could not turn off TX indicator");
+                                    gui_input.send(GUIInputMessage::SetWaitIndicator(false)).expect("Could not turn off Wait indicator");
+                                    gui_input.send(GUIInputMessage::SetRxIndicator(true)).expect("Could not turn on RX indicator");
+                                }
+                                transmitting = false;
+                                discarding_halted_transmission = true;
+                            }
+                            info!("Transmission halted");
+                            move_clone_transmit_halt.store(false, Ordering::SeqCst);
+                        } else {
+                            thread::sleep(TUNE_POLL_INTERVAL);
+                        }
+                        continue;
+                    }
+
                     // If silent when a channel encoding arrives, this indicates that we are
                     // starting a transmission, and that we should PTT via CAT, and use a ramp up
                     // symbol at the start of the modulation.
@@ -160,12 +230,21 @@ impl Transmitter {
                         Some(input_rx) => {
                             if let Ok(channel_encoding) = input_rx.lock().unwrap().recv_timeout(Duration::from_millis(50)) {
                                 info!("Transmitter got {:?}", channel_encoding);
+                                if discarding_halted_transmission {
+                                    info!("Discarding channel encoding of halted transmission");
+                                    discarding_halted_transmission = !channel_encoding.is_end;
+                                    continue;
+                                }
                                 let mut maybe_countdown_latch: Option<Arc<CountDownLatch>> = None;
                                 let need_ramp_up = move_clone_modulation_silent.load(Ordering::SeqCst);
                                 let need_ramp_down = channel_encoding.is_end;
                                 info!("Ramp up {} down {}", need_ramp_up, need_ramp_down);
                                 if need_ramp_up {
-                                    // TODO CAT transmit enable.
+                                    // If the PTT can't be keyed, the rig may still transmit by VOX.
+                                    if let Err(e) = cat_controller.lock().unwrap().set_ptt(true) {
+                                        warn!("Could not key PTT: {}", e);
+                                    }
+                                    transmitting = true;
                                 }
                                 let mut locked_callback_data = move_clone_modulation_callback_data.write().unwrap();
                                 if need_ramp_up {
@@ -175,9 +254,8 @@ impl Transmitter {
                                         gui_input.send(GUIInputMessage::SetRxIndicator(false)).expect("Could not turn off RX indicator");
                                     }
                                 }
-                                let maybe_allocated_modulated_buffer: Option<(usize, Arc<RwLock<Vec<f32>>>, usize)> = 
-                                    allocate_buffer_and_write_modulation(&locked_callback_data, &channel_encoding, need_ramp_up, need_ramp_down, 
-                                                                         locked_callback_data.audio_frequency, locked_callback_data.sample_rate as AudioFrequencyHz);
+                                let maybe_allocated_modulated_buffer: Option<(usize, Arc<RwLock<Vec<f32>>>, usize)> =
+                                    allocate_buffer_and_write_modulation(&mut locked_callback_data, &channel_encoding, need_ramp_up, need_ramp_down);
                                 match maybe_allocated_modulated_buffer {
                                     None => {}
                                     Some((index, buffer, buffer_max)) => {
@@ -201,7 +279,10 @@ impl Transmitter {
                                     info!("Waiting for end of modulation");
                                     latch.wait();
                                     info!("End of modulation signalled");
-                                    // TODO CAT transmit disable
+                                    if let Err(e) = cat_controller.lock().unwrap().set_ptt(false) {
+                                        error!("Could not release PTT: {}", e);
+                                    }
+                                    transmitting = false;
                                     if let Some(gui_input) = move_clone_modulation_callback_data.write().unwrap().gui_input.lock().unwrap().as_ref() {
                                         gui_input.send(GUIInputMessage::SetTxIndicator(false)).expect("Could not turn off TX indicator");
                                         gui_input.send(GUIInputMessage::SetWaitIndicator(true)).expect("Could not turn on Wait indicator");
@@ -234,6 +315,8 @@ impl Transmitter {
 
         let move_clone_callback_data = self.callback_data.clone();
         let move_clone_callback_silent = self.silent.clone();
+        let move_clone_callback_halt = self.transmit_halt.clone();
+        let halt_gain_step = 1.0 / (RAMP_SYMBOL_PERIOD_SECONDS * sample_rate as f32);
         let callback = move |pa::OutputStreamCallbackArgs::<f32> { buffer, frames, .. }| {
 
             let set_silent = |silent: bool| {
@@ -253,8 +336,21 @@ impl Transmitter {
 
             let mut locked_callback_data = move_clone_callback_data.write().unwrap();
             let amplitude_max = locked_callback_data.amplitude_max;
-            // When we start a callback and there's no data, set the silence flag true.
-            if locked_callback_data.callback_messages.is_empty() {
+            let halting = move_clone_callback_halt.load(Ordering::SeqCst);
+            if let Some(tune) = locked_callback_data.tune.as_mut() {
+                set_silent(false);
+                if halting && !tune.is_stopping() {
+                    tune.stop();
+                }
+                let mut idx = 0;
+                for _ in 0..frames {
+                    let tune_val = tune.next_sample() * amplitude_max;
+                    buffer[idx] = tune_val;
+                    buffer[idx + 1] = tune_val;
+                    idx += 2;
+                }
+            } else if locked_callback_data.callback_messages.is_empty() {
+                // When we start a callback and there's no data, set the silence flag true.
                 debug!("Silence: true (no callback_messages)");
                 set_silent(true);
                 let mut idx = 0;
@@ -266,45 +362,18 @@ impl Transmitter {
             } else {
                 debug!("Silence: false (some callback_messages)");
                 set_silent(false);
-                let first = locked_callback_data.callback_messages.front_mut().unwrap();
-                let mut maybe_buffer_free_index: Option<usize> = None;
-                match first {
-                    CallbackMessage::BufferIndex(bi) => {
-                        debug!("Sample index at callback start: {}, samples written {}", bi.buffer_index, bi.buffer_max);
-                        let mut idx = 0;
-                        let locked_samples = bi.buffer.read().unwrap();
-                        for _ in 0..frames {
-                            let sine_val = if bi.buffer_index < bi.buffer_max {
-                                let this_sample = locked_samples[bi.buffer_index];
-                                bi.buffer_index += 1;
-                                this_sample * amplitude_max
-                            } else {
-                                0.0
-                            };
-
-                            // TODO MONO - if opening the stream with a single channel causes the same values to
-                            // be written to both left and right outputs, this could be optimised..
-                            buffer[idx] = sine_val;
-                            buffer[idx + 1] = sine_val;
-                            idx += 2;
-                        }
-                        drop(locked_samples);
-                        if bi.buffer_index == bi.buffer_max {
-                            // Free the index outside the current borrow of locked_callback_data...
-                            debug!("Want to free buffer {}", bi.index);
-                            maybe_buffer_free_index = Some(bi.index);
-                            locked_callback_data.callback_messages.pop_front();
-                        }
-                    }
-                    CallbackMessage::Wait(arc_latch) => {
-                        info!("Notifying end of modulation");
-                        arc_latch.countdown();
-                        info!("Notified end of modulation");
-                        locked_callback_data.callback_messages.pop_front();
-                    }
-                }
-                if let Some(to_free_index) = maybe_buffer_free_index {
-                    free_buffer(&locked_callback_data, to_free_index);
+                emit_callback_messages(&mut locked_callback_data, buffer, frames, halting, halt_gain_step);
+            }
+
+            // The maximum transmit duration trips the halt, as if it had been requested.
+            if move_clone_callback_silent.load(Ordering::SeqCst) {
+                locked_callback_data.transmitted_samples = 0;
+            } else {
+                locked_callback_data.transmitted_samples += frames;
+                let max_transmit_samples = locked_callback_data.max_transmit_duration.as_secs_f32() * sample_rate as f32;
+                if !halting && locked_callback_data.transmitted_samples as f32 > max_transmit_samples {
+                    warn!("Transmitting for longer than {:?}; halting", locked_callback_data.max_transmit_duration);
+                    move_clone_callback_halt.store(true, Ordering::SeqCst);
                 }
             }
             drop(locked_callback_data);
@@ -364,12 +433,15 @@ impl Transmitter {
             debug!("maximum_number_of_symbols {}", n_sym);
             let n_spsym = (self.sample_rate as f32 * SYMBOL_PERIOD_SECONDS) as usize;
             let n_rspsym = (self.sample_rate as f32 * RAMP_SYMBOL_PERIOD_SECONDS) as usize;
-            let new_sample_buffer_size = (n_sym * n_spsym) + (2 * n_rspsym); // Number of output samples, with max 2 ramping symbols
+            // Number of output samples, with max 2 ramping symbols, and the last symbol of the
+            // previous block, which the modulator withholds until it knows the next symbol.
+            let new_sample_buffer_size = ((n_sym + 1) * n_spsym) + (2 * n_rspsym);
             locked_callback_data.samples = Vec::with_capacity(new_sample_buffer_size);
             locked_callback_data.samples.resize(new_sample_buffer_size, 0_f32);
             if let Ok(mut locked) = locked_callback_data.buffer_pool.lock() {
                 *locked = Some(BufferPool::new(new_sample_buffer_size, NUMBER_OF_BUFFERS))
             }
+            locked_callback_data.modulator = Some(GfskModulator::new(self.sample_rate as AudioFrequencyHz));
 
             debug!("Setting transmitter frequency to {}, sample_rate {}, buffer size {}", locked_callback_data.audio_frequency, self.sample_rate, new_sample_buffer_size);
         }
@@ -390,6 +462,21 @@ impl Transmitter {
         self.silent.load(Ordering::SeqCst)
     }
 
+    pub fn set_tune_timeout(&mut self, timeout: Duration) {
+        self.tune_control.lock().unwrap().timeout = timeout;
+    }
+
+    pub fn set_max_transmit_duration(&mut self, max_transmit_duration: Duration) {
+        debug!("Setting maximum transmit duration to {:?}", max_transmit_duration);
+        self.callback_data.write().unwrap().max_transmit_duration = max_transmit_duration;
+    }
+
+    // Stop any transmission or tuning; equivalent to setting the transmit halt flag.
+    pub fn halt(&mut self) {
+        info!("Halting transmission");
+        self.transmit_halt.store(true, Ordering::SeqCst);
+    }
+
     pub fn set_gui_input(&mut self, gui_input: Arc<SyncSender<GUIInputMessage>>) {
         let locked_callback_data = self.callback_data.write().unwrap();
         let silent = self.is_silent();
@@ -401,6 +488,177 @@ impl Transmitter {
 
 }
 
+// Starts tuning if it has been requested and nothing is being transmitted; stops it when the request
+// is withdrawn, times out or is halted, releasing the PTT once the output has ramped down. Returns
+// whether tuning is in progress, so channel encodings are not to be transmitted.
+fn service_tuning(tune_control: &Arc<Mutex<TuneControl>>, tune_started: &mut Option<Instant>,
+                  callback_data: &Arc<RwLock<CallbackData>>, silent: &Arc<AtomicBool>,
+                  transmit_halt: &Arc<AtomicBool>, cat_controller: &Arc<Mutex<dyn CatController>>) -> bool {
+    let (requested_signal, timeout) = {
+        let locked_tune_control = tune_control.lock().unwrap();
+        (locked_tune_control.signal, locked_tune_control.timeout)
+    };
+    let halted = transmit_halt.load(Ordering::SeqCst);
+    match *tune_started {
+        None => {
+            let tune_signal = match requested_signal {
+                None => return false,
+                Some(tune_signal) => tune_signal,
+            };
+            if halted {
+                info!("Tuning halted before it started");
+                tune_control.lock().unwrap().signal = None;
+                return false;
+            }
+            if !silent.load(Ordering::SeqCst) {
+                debug!("Tuning will start when the transmission has been sent");
+                return false;
+            }
+            if callback_data.read().unwrap().sample_rate == 0 {
+                warn!("Cannot tune until the audio output has been started");
+                tune_control.lock().unwrap().signal = None;
+                return false;
+            }
+            info!("Tuning with {:?} for at most {:?}", tune_signal, timeout);
+            if let Err(e) = cat_controller.lock().unwrap().set_ptt(true) {
+                warn!("Could not key PTT: {}", e);
+            }
+            let mut locked_callback_data = callback_data.write().unwrap();
+            let audio_frequency = locked_callback_data.audio_frequency;
+            let sample_rate = locked_callback_data.sample_rate;
+            locked_callback_data.tune = Some(TuneOscillator::new(tune_signal, audio_frequency, sample_rate));
+            if let Some(gui_input) = locked_callback_data.gui_input.lock().unwrap().as_ref() {
+                gui_input.send(GUIInputMessage::SetTxIndicator(true)).expect("Could not turn on TX indicator");
+                gui_input.send(GUIInputMessage::SetWaitIndicator(false)).expect("Could not turn off Wait indicator");
+                gui_input.send(GUIInputMessage::SetRxIndicator(false)).expect("Could not turn off RX indicator");
+            }
+            *tune_started = Some(Instant::now());
+            true
+        }
+        Some(started) => {
+            let mut locked_callback_data = callback_data.write().unwrap();
+            let timed_out = started.elapsed() >= timeout;
+            if (timed_out || halted) && requested_signal.is_some() {
+                if timed_out {
+                    warn!("Tuning timed out after {:?}", timeout);
+                }
+                tune_control.lock().unwrap().signal = None;
+            }
+            if requested_signal.is_none() || timed_out || halted {
+                if let Some(tune) = locked_callback_data.tune.as_mut() {
+                    if !tune.is_stopping() {
+                        info!("Stopping tuning");
+                        tune.stop();
+                    }
+                }
+            }
+            if !locked_callback_data.tune.as_ref().map_or(true, TuneOscillator::is_finished) {
+                return true;
+            }
+            locked_callback_data.tune = None;
+            info!("Tuning stopped");
+            if let Err(e) = cat_controller.lock().unwrap().set_ptt(false) {
+                error!("Could not release PTT: {}", e);
+            }
+            if let Some(gui_input) = locked_callback_data.gui_input.lock().unwrap().as_ref() {
+                gui_input.send(GUIInputMessage::SetTxIndicator(false)).expect("Could not turn off TX indicator");
+                gui_input.send(GUIInputMessage::SetWaitIndicator(false)).expect("Could not turn off Wait indicator");
+                gui_input.send(GUIInputMessage::SetRxIndicator(true)).expect("Could not turn on RX indicator");
+            }
+            *tune_started = None;
+            false
+        }
+    }
+}
+
+// Emit the front buffer of samples into the output buffer, or release the thread waiting on the
+// front latch. While halting, the output is ramped down (over silence, if a latch is at the front),
+// then the queued buffers are discarded. The halt gain is restored once the queue has drained, or
+// the halt has been cleared.
+fn emit_callback_messages(locked_callback_data: &mut RwLockWriteGuard<CallbackData>, buffer: &mut [f32], frames: usize, halting: bool, halt_gain_step: f32) {
+    let amplitude_max = locked_callback_data.amplitude_max;
+    let mut halt_gain = if halting { locked_callback_data.halt_gain } else { 1.0 };
+    let first = locked_callback_data.callback_messages.front_mut().unwrap();
+    let mut maybe_buffer_free_index: Option<usize> = None;
+    match first {
+        CallbackMessage::BufferIndex(bi) => {
+            debug!("Sample index at callback start: {}, samples written {}", bi.buffer_index, bi.buffer_max);
+            let mut idx = 0;
+            let locked_samples = bi.buffer.read().unwrap();
+            for _ in 0..frames {
+                let sine_val = if bi.buffer_index < bi.buffer_max {
+                    let this_sample = locked_samples[bi.buffer_index];
+                    bi.buffer_index += 1;
+                    this_sample * amplitude_max * halt_gain
+                } else {
+                    0.0
+                };
+                if halting {
+                    halt_gain = (halt_gain - halt_gain_step).max(0.0);
+                }
+
+                // TODO MONO - if opening the stream with a single channel causes the same values to
+                // be written to both left and right outputs, this could be optimised..
+                buffer[idx] = sine_val;
+                buffer[idx + 1] = sine_val;
+                idx += 2;
+            }
+            drop(locked_samples);
+            if bi.buffer_index == bi.buffer_max {
+                // Free the index outside the current borrow of locked_callback_data...
+                debug!("Want to free buffer {}", bi.index);
+                maybe_buffer_free_index = Some(bi.index);
+                locked_callback_data.callback_messages.pop_front();
+            }
+        }
+        CallbackMessage::Wait(arc_latch) => {
+            let mut idx = 0;
+            for _ in 0..frames {
+                if halting {
+                    halt_gain = (halt_gain - halt_gain_step).max(0.0);
+                }
+                buffer[idx] = 0.0;
+                buffer[idx + 1] = 0.0;
+                idx += 2;
+            }
+            // When halting, the latch is released as the queue is discarded, below; popping it
+            // would let the buffers queued behind it play.
+            if !halting {
+                info!("Notifying end of modulation");
+                arc_latch.countdown();
+                info!("Notified end of modulation");
+                locked_callback_data.callback_messages.pop_front();
+            }
+        }
+    }
+    if let Some(to_free_index) = maybe_buffer_free_index {
+        free_buffer(locked_callback_data, to_free_index);
+    }
+    locked_callback_data.halt_gain = halt_gain;
+    if halting && halt_gain == 0.0 {
+        info!("Output ramped down; discarding queued buffers");
+        discard_callback_messages(locked_callback_data);
+    }
+    if locked_callback_data.callback_messages.is_empty() {
+        locked_callback_data.halt_gain = 1.0;
+    }
+}
+
+// Discard the queued buffers, releasing the thread if it is waiting for the end of the transmission,
+// and abandon the modulator's transmission, so the next block starts a new one.
+fn discard_callback_messages(locked_callback_data: &mut RwLockWriteGuard<CallbackData>) {
+    while let Some(callback_message) = locked_callback_data.callback_messages.pop_front() {
+        match callback_message {
+            CallbackMessage::BufferIndex(bi) => free_buffer(locked_callback_data, bi.index),
+            CallbackMessage::Wait(arc_latch) => arc_latch.countdown(),
+        }
+    }
+    if let Some(modulator) = locked_callback_data.modulator.as_mut() {
+        modulator.reset();
+    }
+    locked_callback_data.halt_gain = 1.0;
+}
+
 fn free_buffer(locked_callback_data: &RwLockWriteGuard<CallbackData>, to_free_index: usize) {
     match locked_callback_data.buffer_pool.lock().unwrap().as_mut() {
         None => {
@@ -413,8 +671,17 @@ fn free_buffer(locked_callback_data: &RwLockWriteGuard<CallbackData>, to_free_in
     }
 }
 
-fn allocate_buffer_and_write_modulation(locked_callback_data: &RwLockWriteGuard<CallbackData>, channel_encoding: &ChannelEncoding, need_ramp_up: bool, need_ramp_down: bool, offset_frequency: AudioFrequencyHz, sample_rate: AudioFrequencyHz) -> Option<(usize, Arc<RwLock<Vec<f32>>>, usize)> {
-    match locked_callback_data.buffer_pool.lock().unwrap().as_mut() {
+fn allocate_buffer_and_write_modulation(locked_callback_data: &mut RwLockWriteGuard<CallbackData>, channel_encoding: &ChannelEncoding, need_ramp_up: bool, need_ramp_down: bool) -> Option<(usize, Arc<RwLock<Vec<f32>>>, usize)> {
+    let callback_data = &mut **locked_callback_data;
+    let offset_frequency = callback_data.audio_frequency;
+    let modulator = match callback_data.modulator.as_mut() {
+        None => {
+            error!("No modulator present when channel encodings received");
+            return None;
+        }
+        Some(modulator) => modulator,
+    };
+    match callback_data.buffer_pool.lock().unwrap().as_mut() {
         None => {
             error!("No buffer pool present when channel encodings received");
             None
@@ -429,9 +696,8 @@ fn allocate_buffer_and_write_modulation(locked_callback_data: &RwLockWriteGuard<
                     let mut locked_samples = arc_samples.write().unwrap();
                     debug!("waveform store has {} space", locked_samples.capacity());
                     let arc_samples_slice = locked_samples.as_mut_slice();
-                    let samples_written = gfsk_modulate(
+                    let samples_written = modulator.modulate(
                         offset_frequency,
-                        sample_rate,
                         &channel_encoding.block,
                         arc_samples_slice,
                         need_ramp_up, need_ramp_down);
@@ -463,6 +729,23 @@ impl BusInput<ChannelEncoding> for Transmitter {
     }
 }
 
+impl Tuner for Transmitter {
+    fn start_tuning(&mut self, tune_signal: TuneSignal) {
+        info!("Requesting tuning with {:?}", tune_signal);
+        self.tune_control.lock().unwrap().signal = Some(tune_signal);
+    }
+
+    fn stop_tuning(&mut self) {
+        info!("Requesting end of tuning");
+        self.tune_control.lock().unwrap().signal = None;
+    }
+
+    // Tuning has been requested, or has yet to finish ramping down.
+    fn is_tuning(&self) -> bool {
+        self.tune_control.lock().unwrap().signal.is_some() || self.callback_data.read().unwrap().tune.is_some()
+    }
+}
+
 impl Drop for Transmitter {
     fn drop(&mut self) {
         debug!("Transmitter signalling termination to thread on drop");
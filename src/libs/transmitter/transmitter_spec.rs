@@ -1,24 +1,30 @@
 extern crate hamcrest2;
 
-// These are all manually run (and asserted correct aurally and with Audio Hijack / spectrum analyser).
+// Those using PortAudio are all manually run (and asserted correct aurally and with Audio Hijack / spectrum analyser).
 #[cfg(test)]
 mod transmitter_spec {
     use std::env;
-    use std::sync::{Arc, Mutex};
+    use std::sync::{Arc, Mutex, RwLock};
     use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
 
     use bus::Bus;
+    use fp_rust::sync::CountDownLatch;
     use hamcrest2::prelude::*;
     use log::{debug, info};
     use portaudio::PortAudio;
     use rstest::*;
 
     use crate::libs::application::application::BusInput;
+    use crate::libs::buffer_pool::buffer_pool::BufferPool;
+    use crate::libs::cat::mock_cat::MockCatController;
     use crate::libs::audio::audio_devices::open_output_audio_device;
     use crate::libs::channel_codec::channel_encoding::ChannelEncoding;
+    use crate::libs::channel_codec::costas::COSTAS_ARRAY_SYMBOLS;
     use crate::libs::channel_codec::sample_channel_encoding::sample_channel_encoding;
     use crate::libs::test::test_hardware;
-    use crate::libs::transmitter::transmitter::{AmplitudeMax, AudioFrequencyHz, maximum_number_of_symbols, Transmitter};
+    use crate::libs::transmitter::transmitter::{AmplitudeMax, AudioFrequencyHz, BufferIndex, CallbackData, CallbackMessage, emit_callback_messages, maximum_number_of_symbols, Transmitter};
+    use crate::libs::transmitter::tune::{Tuner, TuneSignal};
     use crate::libs::util::test_util;
 
     #[ctor::ctor]
@@ -34,13 +40,15 @@ mod transmitter_spec {
         terminate: Arc<AtomicBool>,
         channel_encoding_tx: Arc<Mutex<Bus<ChannelEncoding>>>,
         transmitter: Transmitter,
+        cat_controller: Arc<Mutex<MockCatController>>,
         pa: Arc<PortAudio>,
     }
 
     #[test]
     fn test_maximum_number_of_symbols() {
         // If the size of the source encoder's output changes, this will need to be recalculated.
-        assert_eq!(maximum_number_of_symbols(), 71);
+        // The Costas Array, then the 64 symbols of the codeword.
+        assert_eq!(maximum_number_of_symbols(), COSTAS_ARRAY_SYMBOLS + 64);
         // Note this does not count ramp up/down, they're not full symbols.
     }
 
@@ -54,14 +62,16 @@ mod transmitter_spec {
         let audio_frequency = 600 as AudioFrequencyHz;
         info!("Instantiating transmitter...");
         let transmitter_channel_encoding_rx = Arc::new(Mutex::new(channel_encoding_rx));
+        let cat_controller = Arc::new(Mutex::new(MockCatController::new()));
         let mut transmitter = Transmitter::new(audio_frequency,
-                                                 terminate.clone());
+                                                 terminate.clone(), Arc::new(AtomicBool::new(false)), cat_controller.clone());
         transmitter.set_input_rx(transmitter_channel_encoding_rx);
 
         let mut fixture = TransmitterFixture {
             terminate,
             channel_encoding_tx: fixture_channel_encoding_tx,
             transmitter,
+            cat_controller,
             pa: Arc::new(PortAudio::new().unwrap()),
         };
         let speaker = test_hardware::get_current_system_speaker_name();
@@ -203,6 +213,163 @@ mod transmitter_spec {
         debug!("Transmitter is silent; done!");
     }
 
+    #[rstest]
+    #[serial]
+    #[ignore]
+    pub fn ptt_is_keyed_for_the_transmission(fixture: TransmitterFixture) {
+        let cat_controller = fixture.cat_controller.clone();
+        assert_that!(cat_controller.lock().unwrap().ptt_history().is_empty(), equal_to(true));
+        play_encoding(fixture, sample_channel_encoding());
+        // The PTT is released once the end of the transmission has been output.
+        test_util::wait_n_ms(250);
+        assert_that!(cat_controller.lock().unwrap().ptt_history(), equal_to(vec![true, false]));
+    }
+
+    #[rstest]
+    #[serial]
+    #[ignore]
+    pub fn tune_carrier_until_stopped(mut fixture: TransmitterFixture) {
+        fixture.transmitter.start_tuning(TuneSignal::Carrier);
+        assert_that!(fixture.transmitter.is_tuning(), equal_to(true));
+        test_util::wait_n_ms(2000);
+        assert_that!(fixture.transmitter.is_silent(), equal_to(false));
+        assert_that!(fixture.cat_controller.lock().unwrap().ptt_history(), equal_to(vec![true]));
+        fixture.transmitter.stop_tuning();
+        test_util::wait_n_ms(250);
+        assert_that!(fixture.transmitter.is_tuning(), equal_to(false));
+        assert_that!(fixture.cat_controller.lock().unwrap().ptt_history(), equal_to(vec![true, false]));
+    }
+
+    #[rstest]
+    #[serial]
+    #[ignore]
+    pub fn tune_two_tone_until_timeout(mut fixture: TransmitterFixture) {
+        fixture.transmitter.set_tune_timeout(Duration::from_secs(2));
+        fixture.transmitter.start_tuning(TuneSignal::TwoTone);
+        test_util::wait_n_ms(1000);
+        assert_that!(fixture.transmitter.is_tuning(), equal_to(true));
+        test_util::wait_n_ms(1500);
+        assert_that!(fixture.transmitter.is_tuning(), equal_to(false));
+        assert_that!(fixture.cat_controller.lock().unwrap().ptt_history(), equal_to(vec![true, false]));
+    }
+
+    #[rstest]
+    #[serial]
+    #[ignore]
+    pub fn halt_stops_transmission_and_releases_ptt(mut fixture: TransmitterFixture) {
+        fixture.channel_encoding_tx.lock().unwrap().broadcast(sample_channel_encoding());
+        while fixture.transmitter.is_silent() {
+            test_util::wait_n_ms(50);
+        }
+        test_util::wait_n_ms(500);
+        fixture.transmitter.halt();
+        // The output ramps down over one symbol period, then the PTT is released.
+        test_util::wait_n_ms(250);
+        assert_that!(fixture.transmitter.is_silent(), equal_to(true));
+        assert_that!(fixture.cat_controller.lock().unwrap().ptt_history(), equal_to(vec![true, false]));
+    }
+
+    // The output callback's handling of its queue can be exercised without PortAudio.
+    const CALLBACK_FRAMES: usize = 64;
+    const CALLBACK_BUFFER_SAMPLES: usize = 1000;
+
+    // Queue buffers of full-scale samples (of the given lengths), with a latch before the buffer at
+    // latch_position, if given.
+    fn callback_data_with_queued_buffers(buffer_lengths: Vec<usize>, latch_position: Option<usize>) -> (RwLock<CallbackData>, Arc<CountDownLatch>) {
+        let mut callback_data = CallbackData::new(600);
+        let mut buffer_pool = BufferPool::new(CALLBACK_BUFFER_SAMPLES, buffer_lengths.len());
+        let latch = Arc::new(CountDownLatch::new(1));
+        for (n, buffer_max) in buffer_lengths.into_iter().enumerate() {
+            if latch_position == Some(n) {
+                callback_data.callback_messages.push_back(CallbackMessage::Wait(latch.clone()));
+            }
+            let (index, buffer) = buffer_pool.allocate().unwrap();
+            buffer.write().unwrap().iter_mut().for_each(|sample| *sample = 1.0);
+            callback_data.callback_messages.push_back(CallbackMessage::BufferIndex(BufferIndex { index, buffer, buffer_index: 0, buffer_max }));
+        }
+        callback_data.buffer_pool = Arc::new(Mutex::new(Some(buffer_pool)));
+        (RwLock::new(callback_data), latch)
+    }
+
+    fn emit(callback_data: &RwLock<CallbackData>, halting: bool, halt_gain_step: f32) -> Vec<f32> {
+        let mut buffer = vec![-1.0; CALLBACK_FRAMES * 2];
+        emit_callback_messages(&mut callback_data.write().unwrap(), &mut buffer, CALLBACK_FRAMES, halting, halt_gain_step);
+        buffer
+    }
+
+    fn all_buffers_free(callback_data: &RwLock<CallbackData>, number_of_buffers: usize) -> bool {
+        let locked_callback_data = callback_data.read().unwrap();
+        let mut locked_buffer_pool = locked_callback_data.buffer_pool.lock().unwrap();
+        let buffer_pool = locked_buffer_pool.as_mut().unwrap();
+        (0..number_of_buffers).all(|_| buffer_pool.allocate().is_some())
+    }
+
+    #[test]
+    fn halting_ramps_the_output_down_then_discards_the_queued_buffers() {
+        let (callback_data, latch) = callback_data_with_queued_buffers(vec![CALLBACK_BUFFER_SAMPLES, CALLBACK_BUFFER_SAMPLES], Some(1));
+        let halt_gain_step = 0.01;
+
+        let buffer = emit(&callback_data, true, halt_gain_step);
+        assert_that!(buffer[0], equal_to(1.0));
+        assert_that!(buffer[1], equal_to(1.0));
+        assert_that!(buffer[2] < buffer[0], equal_to(true));
+        assert_that!(buffer[126] > 0.0, equal_to(true));
+        assert_that!(callback_data.read().unwrap().callback_messages.len(), equal_to(3));
+
+        // The gain reaches zero during the next callback.
+        let buffer = emit(&callback_data, true, halt_gain_step);
+        assert_that!(buffer[126], equal_to(0.0));
+        assert_that!(callback_data.read().unwrap().callback_messages.is_empty(), equal_to(true));
+        assert_that!(callback_data.read().unwrap().halt_gain, equal_to(1.0));
+        latch.wait();
+        assert_that!(all_buffers_free(&callback_data, 2), equal_to(true));
+    }
+
+    #[test]
+    fn halting_with_a_latch_at_the_front_discards_the_buffers_behind_it() {
+        let (callback_data, latch) = callback_data_with_queued_buffers(vec![CALLBACK_BUFFER_SAMPLES], Some(0));
+        let buffer = emit(&callback_data, true, 1.0 / CALLBACK_FRAMES as f32);
+        assert_that!(buffer.iter().all(|sample| *sample == 0.0), equal_to(true));
+        assert_that!(callback_data.read().unwrap().callback_messages.is_empty(), equal_to(true));
+        assert_that!(callback_data.read().unwrap().halt_gain, equal_to(1.0));
+        latch.wait();
+        assert_that!(all_buffers_free(&callback_data, 1), equal_to(true));
+    }
+
+    #[test]
+    fn halt_gain_is_restored_when_the_queue_drains() {
+        // The buffer ends before the output has been ramped down to silence.
+        let (callback_data, _latch) = callback_data_with_queued_buffers(vec![CALLBACK_FRAMES / 2], None);
+        let buffer = emit(&callback_data, true, 0.01);
+        assert_that!(buffer[0], equal_to(1.0));
+        assert_that!(buffer[62] < 1.0, equal_to(true));
+        assert_that!(callback_data.read().unwrap().callback_messages.is_empty(), equal_to(true));
+        assert_that!(callback_data.read().unwrap().halt_gain, equal_to(1.0));
+    }
+
+    #[test]
+    fn halt_gain_is_restored_when_the_halt_is_cleared() {
+        let (callback_data, _latch) = callback_data_with_queued_buffers(vec![CALLBACK_BUFFER_SAMPLES], None);
+        let buffer = emit(&callback_data, true, 0.01);
+        assert_that!(buffer[126] < 1.0, equal_to(true));
+
+        let buffer = emit(&callback_data, false, 0.01);
+        assert_that!(buffer.iter().all(|sample| *sample == 1.0), equal_to(true));
+        assert_that!(callback_data.read().unwrap().halt_gain, equal_to(1.0));
+    }
+
+    #[rstest]
+    #[serial]
+    #[ignore]
+    pub fn maximum_transmit_duration_halts_tuning(mut fixture: TransmitterFixture) {
+        fixture.transmitter.set_max_transmit_duration(Duration::from_secs(1));
+        fixture.transmitter.start_tuning(TuneSignal::Carrier);
+        test_util::wait_n_ms(1500);
+        assert_that!(fixture.transmitter.is_tuning(), equal_to(false));
+        assert_that!(fixture.transmitter.is_silent(), equal_to(true));
+        assert_that!(fixture.cat_controller.lock().unwrap().ptt_history(), equal_to(vec![true, false]));
+    }
+
     #[rstest]
     #[serial]
     #[ignore]
@@ -0,0 +1,86 @@
+/*
+ * Tuning output, so that the rig's ALC and ATU can be set: a steady carrier at the transmit audio
+ * offset, or a two-tone test signal centred on it, whose peak is that of the carrier. The output
+ * is ramped up at the start and down at the end, as a transmission is, to avoid key clicks.
+ * The Transmitter keys the PTT for as long as it is tuning, and stops tuning after a timeout.
+ */
+
+use std::f32::consts::PI;
+use std::time::Duration;
+use crate::libs::transmitter::modulate::RAMP_SYMBOL_PERIOD_SECONDS;
+use crate::libs::transmitter::transmitter::AudioFrequencyHz;
+
+// Tuning is stopped after this long, in case it is forgotten.
+pub const TUNE_TIMEOUT: Duration = Duration::from_secs(30);
+
+// The tones of the two-tone test signal are this far apart.
+pub const TWO_TONE_SEPARATION_HZ: f32 = 400.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TuneSignal {
+    Carrier, TwoTone
+}
+
+// Implemented by the Transmitter, so that tuning can be started and stopped from the GUI.
+pub trait Tuner {
+    fn start_tuning(&mut self, tune_signal: TuneSignal);
+    fn stop_tuning(&mut self);
+    fn is_tuning(&self) -> bool;
+}
+
+pub struct TuneOscillator {
+    phases: Vec<f32>,
+    delta_phases: Vec<f32>,
+    gain: f32,
+    gain_step: f32,
+    stopping: bool,
+}
+
+impl TuneOscillator {
+    pub fn new(tune_signal: TuneSignal, audio_frequency: AudioFrequencyHz, sample_rate: u32) -> Self {
+        let frequencies = match tune_signal {
+            TuneSignal::Carrier => vec![audio_frequency as f32],
+            TuneSignal::TwoTone => vec![audio_frequency as f32 - TWO_TONE_SEPARATION_HZ / 2.0,
+                                        audio_frequency as f32 + TWO_TONE_SEPARATION_HZ / 2.0],
+        };
+        Self {
+            phases: vec![0.0; frequencies.len()],
+            delta_phases: frequencies.iter().map(|frequency| 2.0 * PI * frequency / sample_rate as f32).collect(),
+            gain: 0.0,
+            gain_step: 1.0 / (RAMP_SYMBOL_PERIOD_SECONDS * sample_rate as f32),
+            stopping: false,
+        }
+    }
+
+    pub fn next_sample(&mut self) -> f32 {
+        self.gain = if self.stopping {
+            (self.gain - self.gain_step).max(0.0)
+        } else {
+            (self.gain + self.gain_step).min(1.0)
+        };
+        let mut sample = 0.0;
+        for (phase, delta_phase) in self.phases.iter_mut().zip(self.delta_phases.iter()) {
+            sample += phase.sin();
+            *phase = (*phase + delta_phase) % (2.0 * PI);
+        }
+        sample * self.gain / self.phases.len() as f32
+    }
+
+    // Start ramping the output down.
+    pub fn stop(&mut self) {
+        self.stopping = true;
+    }
+
+    pub fn is_stopping(&self) -> bool {
+        self.stopping
+    }
+
+    // Has the output been ramped down to silence?
+    pub fn is_finished(&self) -> bool {
+        self.stopping && self.gain == 0.0
+    }
+}
+
+#[cfg(test)]
+#[path = "./tune_spec.rs"]
+mod tune_spec;
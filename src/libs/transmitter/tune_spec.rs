@@ -0,0 +1,77 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod tune_spec {
+    use std::env;
+    use hamcrest2::prelude::*;
+    use crate::libs::transmitter::modulate::RAMP_SYMBOL_PERIOD_SECONDS;
+    use crate::libs::transmitter::tune::{TuneOscillator, TuneSignal};
+
+    const SAMPLE_RATE: u32 = 48000;
+
+    #[ctor::ctor]
+    fn before_each() {
+        env::set_var("RUST_LOG", "debug");
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[ctor::dtor]
+    fn after_each() {}
+
+    fn samples(oscillator: &mut TuneOscillator, count: usize) -> Vec<f32> {
+        (0..count).map(|_| oscillator.next_sample()).collect()
+    }
+
+    fn ramp_samples() -> usize {
+        (RAMP_SYMBOL_PERIOD_SECONDS * SAMPLE_RATE as f32).ceil() as usize
+    }
+
+    fn peak(samples: &[f32]) -> f32 {
+        samples.iter().fold(0.0, |peak, sample| sample.abs().max(peak))
+    }
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    fn rising_zero_crossings(samples: &[f32]) -> usize {
+        samples.windows(2).filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0).count()
+    }
+
+    #[test]
+    fn carrier_is_at_the_audio_offset_after_ramping_up() {
+        let mut oscillator = TuneOscillator::new(TuneSignal::Carrier, 1000, SAMPLE_RATE);
+        let ramp = samples(&mut oscillator, ramp_samples());
+        assert_that!(ramp[0].abs() < 0.01, equal_to(true));
+        // One second of the carrier.
+        let carrier = samples(&mut oscillator, SAMPLE_RATE as usize);
+        assert_that!(rising_zero_crossings(&carrier) as f32, close_to(1000.0, 1.0));
+        assert_that!(peak(&carrier), close_to(1.0, 0.001));
+        assert_that!(rms(&carrier), close_to(0.707, 0.01));
+    }
+
+    #[test]
+    fn two_tone_has_the_carriers_peak() {
+        let mut oscillator = TuneOscillator::new(TuneSignal::TwoTone, 1000, SAMPLE_RATE);
+        samples(&mut oscillator, ramp_samples());
+        let two_tone = samples(&mut oscillator, SAMPLE_RATE as usize);
+        // The samples miss the tones' coincident peaks by a little.
+        assert_that!(peak(&two_tone), close_to(1.0, 0.05));
+        // Two equal tones have half the average power of a carrier with the same peak.
+        assert_that!(rms(&two_tone), close_to(0.5, 0.01));
+    }
+
+    #[test]
+    fn stopping_ramps_down_to_silence() {
+        let mut oscillator = TuneOscillator::new(TuneSignal::Carrier, 1000, SAMPLE_RATE);
+        samples(&mut oscillator, SAMPLE_RATE as usize / 10);
+        assert_that!(oscillator.is_finished(), equal_to(false));
+        oscillator.stop();
+        assert_that!(oscillator.is_stopping(), equal_to(true));
+        // Allowing a sample for rounding of the gain.
+        let ramp = samples(&mut oscillator, ramp_samples() + 1);
+        assert_that!(peak(&ramp[..100]) > 0.9, equal_to(true));
+        assert_that!(oscillator.is_finished(), equal_to(true));
+        assert_that!(peak(&samples(&mut oscillator, 100)), equal_to(0.0));
+    }
+}
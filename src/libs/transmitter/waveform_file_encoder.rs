@@ -0,0 +1,125 @@
+/*
+ * Encodes keying (from text, or recorded from a keyer as a CSV file of MARK/SPACE durations) into
+ * a waveform file, offline, rather than transmitting it: the keying takes the same path as it
+ * would through the transmit chain. The SourceEncoder encodes it into blocks, each of which is
+ * channel-encoded (as the ChannelEncoder does), and the GFSK modulator modulates them at the chosen
+ * audio offset, as a single transmission, ramping up at its start and down at its end. The
+ * waveform is written at 48000Hz, with a little silence either side of the transmission, giving
+ * deterministic test vectors for the receiver.
+ */
+
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use bus::Bus;
+use csv::{ReaderBuilder, StringRecord};
+use log::{debug, info};
+use crate::libs::application::application::{BusInput, BusOutput};
+use crate::libs::audio::audio_devices::SAMPLE_RATE;
+use crate::libs::channel_codec::channel_encoder::source_encoding_to_channel_encoding;
+use crate::libs::keyer_io::keyer_io::{KeyerEdgeDurationMs, KeyingEvent, KeyingTimedEvent};
+use crate::libs::source_codec::source_encoder::SourceEncoder;
+use crate::libs::source_codec::source_encoding::SourceEncoding;
+use crate::libs::transmitter::modulate::GfskModulator;
+use crate::libs::transmitter::transmitter::AudioFrequencyHz;
+use crate::libs::wav::wav::write_waveform_file;
+
+// The silence written before and after the transmission.
+const SILENCE_SECONDS: f32 = 0.5;
+
+// How long to wait for the SourceEncoder to emit each block.
+const SOURCE_ENCODER_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Reads a keying file, as recorded from a keyer: each row is MARK or SPACE, and its duration in ms.
+// The keying is returned as a single transmission, between Start and End events.
+pub fn read_keying_file(filename: &str) -> Result<Vec<KeyingEvent>, Box<dyn Error>> {
+    let mut reader = ReaderBuilder::default().has_headers(false).from_path(filename)?;
+    let mut keying = vec![KeyingEvent::Start()];
+    let mut row = StringRecord::new();
+    while reader.read_record(&mut row)? {
+        let up = match row.get(0) {
+            Some("MARK") => true,
+            Some("SPACE") => false,
+            other => {
+                return Err(format!("Keying file {} has '{}' where MARK or SPACE is expected", filename, other.unwrap_or("")).into());
+            }
+        };
+        let duration_str = row.get(1).unwrap_or("");
+        let duration = match duration_str.parse::<KeyerEdgeDurationMs>() {
+            Ok(duration) => duration,
+            Err(_) => {
+                return Err(format!("Keying file {} has '{}' where a duration in ms is expected", filename, duration_str).into());
+            }
+        };
+        keying.push(KeyingEvent::Timed(KeyingTimedEvent { up, duration }));
+    }
+    keying.push(KeyingEvent::End());
+    Ok(keying)
+}
+
+// Passes the keying through the SourceEncoder (whose keyer speed and station details have been
+// set), returning the blocks it emits, up to and including the end of the transmission.
+pub fn source_encode_keying(source_encoder: &mut SourceEncoder, keying: &[KeyingEvent]) -> Result<Vec<SourceEncoding>, Box<dyn Error>> {
+    // Large enough that the keying can be broadcast before any of the blocks are received.
+    let mut keying_event_tx = Bus::new(keying.len() + 1);
+    let keying_event_rx = keying_event_tx.add_rx();
+    let mut source_encoder_tx = Bus::new(16);
+    let mut source_encoder_rx = source_encoder_tx.add_rx();
+    source_encoder.set_input_rx(Arc::new(Mutex::new(keying_event_rx)));
+    source_encoder.set_output_tx(Arc::new(Mutex::new(source_encoder_tx)));
+    for keying_event in keying {
+        keying_event_tx.broadcast(*keying_event);
+    }
+    let mut source_encodings = vec![];
+    let result = loop {
+        match source_encoder_rx.recv_timeout(SOURCE_ENCODER_TIMEOUT) {
+            Ok(source_encoding) => {
+                debug!("Received {}", source_encoding);
+                let is_end = source_encoding.is_end;
+                source_encodings.push(source_encoding);
+                if is_end {
+                    break Ok(source_encodings);
+                }
+            }
+            Err(_) => {
+                break Err("The source encoder did not emit the end of the transmission; does the keying end?".into());
+            }
+        }
+    };
+    source_encoder.clear_input_rx();
+    source_encoder.clear_output_tx();
+    result
+}
+
+// Channel-encodes the blocks, and modulates them at the audio offset as a single transmission.
+pub fn modulate_source_encodings(source_encodings: &[SourceEncoding], audio_offset: AudioFrequencyHz, sample_rate: AudioFrequencyHz) -> Vec<f32> {
+    let mut modulator = GfskModulator::new(sample_rate);
+    let mut waveform = vec![];
+    for (index, source_encoding) in source_encodings.iter().enumerate() {
+        let symbols = source_encoding_to_channel_encoding(source_encoding.clone()).block;
+        let (first, last) = (index == 0, index == source_encodings.len() - 1);
+        let mut block = vec![0.0; modulator.number_of_samples(symbols.len(), first, last)];
+        modulator.modulate(audio_offset, &symbols, &mut block, first, last);
+        waveform.extend(block);
+    }
+    waveform
+}
+
+// Encode the keying, and write it as a waveform file. Returns the duration of the waveform, in
+// seconds.
+pub fn encode_waveform_file(source_encoder: &mut SourceEncoder, keying: &[KeyingEvent], audio_offset: AudioFrequencyHz, filename: &str) -> Result<f32, Box<dyn Error>> {
+    let source_encodings = source_encode_keying(source_encoder, keying)?;
+    let sample_rate = SAMPLE_RATE as AudioFrequencyHz;
+    let silence = vec![0.0; (SILENCE_SECONDS * sample_rate as f32) as usize];
+    let mut waveform = silence.clone();
+    waveform.extend(modulate_source_encodings(&source_encodings, audio_offset, sample_rate));
+    waveform.extend(silence);
+    info!("Writing {} block(s) at {}Hz, {} samples at {}Hz, to {}", source_encodings.len(), audio_offset, waveform.len(), sample_rate, filename);
+    let seconds = waveform.len() as f32 / sample_rate as f32;
+    write_waveform_file(waveform, filename)?;
+    Ok(seconds)
+}
+
+#[cfg(test)]
+#[path = "./waveform_file_encoder_spec.rs"]
+mod waveform_file_encoder_spec;
@@ -0,0 +1,94 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod waveform_file_encoder_spec {
+    use std::env;
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::AtomicBool;
+    use hamcrest2::prelude::*;
+    use crate::libs::conversion::conversion::text_to_keying;
+    use crate::libs::keyer_io::keyer_io::{KeyingEvent, KeyingTimedEvent};
+    use crate::libs::playback::recording_player::RecordingPlayer;
+    use crate::libs::receiver::waveform_file_decoder::decode_waveform_file;
+    use crate::libs::source_codec::metadata_codec::hash_callsign;
+    use crate::libs::source_codec::source_decoder::SourceDecoder;
+    use crate::libs::source_codec::source_encoder::SourceEncoder;
+    use crate::libs::source_codec::source_encoding::{CallsignHash, Frame, SOURCE_ENCODER_BLOCK_SIZE_IN_BITS};
+    use crate::libs::transmitter::waveform_file_encoder::{encode_waveform_file, read_keying_file, source_encode_keying};
+    use crate::libs::util::test_util::temp_filename;
+
+    #[ctor::ctor]
+    fn before_each() {
+        env::set_var("RUST_LOG", "info");
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[ctor::dtor]
+    fn after_each() {}
+
+    fn source_encoder(callsign: &str) -> SourceEncoder {
+        let mut source_encoder = SourceEncoder::new(Arc::new(AtomicBool::new(false)), SOURCE_ENCODER_BLOCK_SIZE_IN_BITS);
+        source_encoder.set_keyer_speed(20);
+        source_encoder.set_callsign(callsign.to_string()).unwrap();
+        source_encoder
+    }
+
+    #[test]
+    fn keying_file_is_read_as_a_transmission() {
+        let filename = temp_filename("keying.csv");
+        std::fs::write(&filename, "MARK,196\nSPACE,65\nMARK,71\n").unwrap();
+        let expected = vec![
+            KeyingEvent::Start(),
+            KeyingEvent::Timed(KeyingTimedEvent { up: true, duration: 196 }),
+            KeyingEvent::Timed(KeyingTimedEvent { up: false, duration: 65 }),
+            KeyingEvent::Timed(KeyingTimedEvent { up: true, duration: 71 }),
+            KeyingEvent::End(),
+        ];
+        assert_that!(read_keying_file(&filename).unwrap(), equal_to(expected));
+    }
+
+    #[test]
+    fn keying_file_without_mark_or_space_is_an_error() {
+        let filename = temp_filename("bad-keying.csv");
+        std::fs::write(&filename, "MARK,196\nDOWN,65\n").unwrap();
+        assert_that!(read_keying_file(&filename).is_err(), equal_to(true));
+    }
+
+    #[test]
+    fn keying_file_without_duration_is_an_error() {
+        let filename = temp_filename("bad-duration-keying.csv");
+        std::fs::write(&filename, "MARK,196\nSPACE,long\n").unwrap();
+        assert_that!(read_keying_file(&filename).is_err(), equal_to(true));
+    }
+
+    #[test]
+    fn recorded_keying_is_source_encoded_to_the_end_of_the_transmission() {
+        let keying = read_keying_file("cq-cq-keying.csv").unwrap();
+        let source_encodings = source_encode_keying(&mut source_encoder("M0CUV"), &keying).unwrap();
+        assert_that!(source_encodings.len(), greater_than(1));
+        let ends: Vec<bool> = source_encodings.iter().map(|source_encoding| source_encoding.is_end).collect();
+        let mut expected_ends = vec![false; source_encodings.len() - 1];
+        expected_ends.push(true);
+        assert_that!(ends, equal_to(expected_ends));
+    }
+
+    #[test]
+    fn encoded_waveform_file_is_decoded_by_the_receiver() {
+        let keying = text_to_keying(20, "CQ CQ DE M0CUV K");
+        let source_encodings = source_encode_keying(&mut source_encoder("M0CUV"), &keying).unwrap();
+        let filename = temp_filename("waveform-file-encoder-cq.wav");
+        let seconds = encode_waveform_file(&mut source_encoder("M0CUV"), &keying, 1200, &filename).unwrap();
+        assert_that!(seconds, greater_than(1.0));
+
+        let player = Arc::new(Mutex::new(RecordingPlayer::default()));
+        decode_waveform_file(&filename, player.clone()).unwrap();
+
+        // The same keying is encoded identically each time, so the blocks played are those encoded.
+        let source_decoder = SourceDecoder::new(SOURCE_ENCODER_BLOCK_SIZE_IN_BITS);
+        let hash = hash_callsign("M0CUV".to_string()).unwrap();
+        let expected: Vec<(Option<Vec<Frame>>, CallsignHash, u16)> = source_encodings.iter()
+            .map(|source_encoding| (source_decoder.source_decode(source_encoding.block.clone()).ok(), hash, 1200))
+            .collect();
+        assert_that!(&player.lock().unwrap().plays, equal_to(&expected));
+    }
+}
@@ -1,6 +1,11 @@
+use std::env;
+use std::f32::consts::PI;
 use std::sync::mpsc;
 use std::thread;
 use std::time::Duration;
+use rand::Rng;
+use rand::rngs::StdRng;
+use crate::libs::transmitter::transmitter::AudioFrequencyHz;
 
 // Thanks to Shepmaster, https://github.com/rust-lang/rfcs/issues/2798
 pub fn panic_after<T, F>(d: Duration, f: F) -> T
@@ -30,4 +35,28 @@ pub fn wait_n_ms(n: u64) {
     thread::sleep(Duration::from_millis(n));
 }
 
+// Box-Muller; a standard normal deviate.
+pub fn gaussian(rng: &mut StdRng) -> f32 {
+    let u1: f32 = rng.gen_range(f32::EPSILON..1.0);
+    let u2: f32 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
+
+// White Gaussian noise of the given power in a 2500Hz bandwidth, so spread over the whole band.
+pub fn awgn(samples: usize, sample_rate: AudioFrequencyHz, power: f32, rng: &mut StdRng) -> Vec<f32> {
+    let sigma = (power * sample_rate as f32 / 2.0 / 2500.0).sqrt();
+    (0..samples).map(|_| sigma * gaussian(rng)).collect()
+}
 
+// Add the waveform at the given SNR (in 2500Hz, against unit noise), starting at the sample.
+pub fn mix(mixture: &mut [f32], waveform: &[f32], start: usize, snr_db: f32) {
+    // A unit amplitude waveform has power 1/2.
+    let amplitude = (2.0 * 10f32.powf(snr_db / 10.0)).sqrt();
+    for (mixed, sample) in mixture[start..].iter_mut().zip(waveform) {
+        *mixed += amplitude * sample;
+    }
+}
+
+pub fn temp_filename(name: &str) -> String {
+    env::temp_dir().join(name).to_str().unwrap().to_string()
+}
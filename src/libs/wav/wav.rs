@@ -1,10 +1,11 @@
 use std::fs::File;
+use std::io::{Error, ErrorKind};
 use std::path::Path;
 use wav::{BitDepth, Header, WAV_FORMAT_IEEE_FLOAT};
 use crate::libs::audio::audio_devices::SAMPLE_RATE;
 
 pub fn write_waveform_file(sample_waveform: Vec<f32>, filename: &str) -> std::io::Result<()> {
-    let mut out_file = File::create(Path::new(filename)).unwrap();
+    let mut out_file = File::create(Path::new(filename))?;
     let header = Header::new(WAV_FORMAT_IEEE_FLOAT, 1, SAMPLE_RATE as u32, 32);
     let data = BitDepth::ThirtyTwoFloat(sample_waveform);
     wav::write(header, &data, &mut out_file)
@@ -12,8 +13,32 @@ pub fn write_waveform_file(sample_waveform: Vec<f32>, filename: &str) -> std::io
 
 
 pub fn read_waveform_file(filename: &str) -> std::io::Result<Vec<f32>> {
-    let mut in_file = File::open(Path::new(filename)).unwrap();
-    let (_header, data) = wav::read(&mut in_file)?;
-    Ok(data.as_thirty_two_float().unwrap().to_vec())
+    read_waveform_file_with_sample_rate(filename).map(|(samples, _sample_rate)| samples)
 }
 
+// Reads a file of any of the sample formats the wav crate supports - such as a recording of the
+// band made by other software - returning its first channel, scaled to [-1.0 .. 1.0], and its
+// sample rate.
+pub fn read_waveform_file_with_sample_rate(filename: &str) -> std::io::Result<(Vec<f32>, u32)> {
+    let mut in_file = File::open(Path::new(filename))?;
+    let (header, data) = wav::read(&mut in_file)?;
+    let channels = (header.channel_count as usize).max(1);
+    let samples: Vec<f32> = match data {
+        BitDepth::Eight(samples) => first_channel(&samples, channels, |s| (s as f32 - 128.0) / 128.0),
+        BitDepth::Sixteen(samples) => first_channel(&samples, channels, |s| s as f32 / 32768.0),
+        BitDepth::TwentyFour(samples) => first_channel(&samples, channels, |s| s as f32 / 8388608.0),
+        BitDepth::ThirtyTwoFloat(samples) => first_channel(&samples, channels, |s| s),
+        BitDepth::Empty => {
+            return Err(Error::new(ErrorKind::InvalidData, format!("No samples in waveform file {}", filename)));
+        }
+    };
+    Ok((samples, header.sampling_rate))
+}
+
+fn first_channel<T: Copy>(interleaved: &[T], channels: usize, to_f32: fn(T) -> f32) -> Vec<f32> {
+    interleaved.iter().step_by(channels).map(|sample| to_f32(*sample)).collect()
+}
+
+#[cfg(test)]
+#[path = "./wav_spec.rs"]
+mod wav_spec;
@@ -0,0 +1,56 @@
+extern crate hamcrest2;
+
+#[cfg(test)]
+mod wav_spec {
+    use std::env;
+    use std::fs::File;
+    use hamcrest2::prelude::*;
+    use wav::{BitDepth, Header, WAV_FORMAT_PCM};
+    use crate::libs::util::test_util::temp_filename;
+    use crate::libs::wav::wav::{read_waveform_file, read_waveform_file_with_sample_rate, write_waveform_file};
+
+    #[ctor::ctor]
+    fn before_each() {
+        env::set_var("RUST_LOG", "debug");
+        let _ = env_logger::builder().is_test(true).try_init();
+    }
+
+    #[ctor::dtor]
+    fn after_each() {}
+
+    #[test]
+    fn missing_file_is_an_error() {
+        let result = read_waveform_file(&temp_filename("no-such-waveform.wav"));
+        assert_that!(result.is_err(), equal_to(true));
+    }
+
+    #[test]
+    fn file_that_is_not_a_waveform_is_an_error() {
+        let filename = temp_filename("not-a-waveform.wav");
+        std::fs::write(&filename, "this is not a WAV file").unwrap();
+        assert_that!(read_waveform_file(&filename).is_err(), equal_to(true));
+    }
+
+    #[test]
+    fn unwritable_file_is_an_error() {
+        let filename = temp_filename("no-such-directory/waveform.wav");
+        assert_that!(write_waveform_file(vec![0.0], &filename).is_err(), equal_to(true));
+    }
+
+    #[test]
+    fn written_waveform_is_read_at_48khz() {
+        let filename = temp_filename("written-waveform.wav");
+        let waveform = vec![0.0, 0.5, -0.25, 1.0];
+        write_waveform_file(waveform.clone(), &filename).unwrap();
+        assert_that!(read_waveform_file_with_sample_rate(&filename).unwrap(), equal_to((waveform, 48000)));
+    }
+
+    #[test]
+    fn first_channel_of_16_bit_stereo_is_read_and_scaled() {
+        let filename = temp_filename("stereo-16-bit-waveform.wav");
+        let mut out_file = File::create(&filename).unwrap();
+        let header = Header::new(WAV_FORMAT_PCM, 2, 12000, 16);
+        wav::write(header, &BitDepth::Sixteen(vec![16384, 1, -32768, 2, 0, 3]), &mut out_file).unwrap();
+        assert_that!(read_waveform_file_with_sample_rate(&filename).unwrap(), equal_to((vec![0.5, -1.0, 0.0], 12000)));
+    }
+}
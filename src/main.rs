@@ -6,7 +6,7 @@ use std::error::Error;
 use std::sync::{Arc, Mutex};
 use std::sync::atomic::AtomicBool;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use clap::{App, Arg, ArgMatches};
 use clap::arg_enum;
@@ -17,6 +17,10 @@ use fltk::app::*;
 use portaudio::PortAudio;
 use syncbox::ScheduledThreadPool;
 
+use digimorse::libs::cat::cat::{CatController, CatType, NullCatController};
+use digimorse::libs::cat::dial_frequency::{DIAL_FREQUENCY_POLL_INTERVAL, DialFrequency, DialFrequencyPoller};
+use digimorse::libs::cat::rigctld_cat::{DEFAULT_RIGCTLD_ADDRESS, RigctldCatController};
+use digimorse::libs::cat::serial_ptt_cat::{PttLine, SerialPttCatController};
 use digimorse::libs::config_dir::config_dir;
 use digimorse::libs::keyer_io::arduino_keyer_io::ArduinoKeyer;
 use digimorse::libs::keyer_io::keyer_io::{MAX_KEYER_SPEED, MIN_KEYER_SPEED, Keyer};
@@ -27,13 +31,23 @@ use digimorse::libs::application::application::{Application, ApplicationMode};
 use digimorse::libs::config_file::config_file::ConfigurationStore;
 use digimorse::libs::audio::audio_devices::{list_audio_devices, output_audio_device_exists, input_audio_device_exists, list_audio_input_devices, list_audio_output_devices};
 use digimorse::libs::audio::tone_generator::ToneGenerator;
+use digimorse::libs::buffer_pool::observable_buffer::{OBSERVABLE_BUFFER_HOP_SIZE, OBSERVABLE_BUFFER_SLICE_SIZE};
 use digimorse::libs::channel_codec::channel_encoder::{ChannelEncoder, source_encoding_to_channel_encoding};
 use digimorse::libs::channel_codec::ldpc::init_ldpc;
-use digimorse::libs::gui::gui::Gui;
+use digimorse::libs::conversion::conversion::text_to_keying;
+use digimorse::libs::gui::gui::{Gui, WATERFALL_WIDTH};
 use digimorse::libs::gui::gui_facades::GUIOutput;
+use digimorse::libs::gui::waterfall::WaterfallObserver;
+use digimorse::libs::playback::playback::Playback;
+use digimorse::libs::receiver::decoder::Decoder;
+use digimorse::libs::receiver::decoder_thread::DecoderThread;
+use digimorse::libs::receiver::receiver::{DOWNSAMPLED_SAMPLE_RATE, Receiver};
+use digimorse::libs::receiver::waveform_file_decoder::{decode_waveform_file, FramePrinter};
+use digimorse::libs::source_codec::metadata_codec::{encode_callsign, encode_locator, encode_power};
 use digimorse::libs::source_codec::source_encoder::SourceEncoder;
 use digimorse::libs::source_codec::source_encoding::{SOURCE_ENCODER_BLOCK_SIZE_IN_BITS};
-use digimorse::libs::transmitter::transmitter::{AmplitudeMax, Transmitter};
+use digimorse::libs::transmitter::transmitter::{AmplitudeMax, AudioFrequencyHz, Transmitter};
+use digimorse::libs::transmitter::waveform_file_encoder::{encode_waveform_file, read_keying_file};
 use digimorse::libs::util::logging::initialise_logging;
 use digimorse::libs::util::version::VERSION;
 
@@ -53,6 +67,17 @@ const AUDIO_OUT_DEVICE: &'static str = "audio-out-device";
 const RIG_OUT_DEVICE: &'static str = "rig-out-device";
 const RIG_IN_DEVICE: &'static str = "rig-in-device";
 const KEYER_SPEED_WPM: &'static str = "keyer-speed-wpm";
+const CALLSIGN: &'static str = "callsign";
+const LOCATOR: &'static str = "locator";
+const POWER_DBM: &'static str = "power-dbm";
+const WAV_FILE: &'static str = "wav-file";
+const TEXT: &'static str = "text";
+const KEYING_FILE: &'static str = "keying-file";
+const AUDIO_OFFSET: &'static str = "audio-offset";
+const RIGCTLD_ADDRESS: &'static str = "rigctld-address";
+const NO_CAT: &'static str = "no-cat";
+const PTT_PORT_DEVICE: &'static str = "ptt-port-device";
+const PTT_LINE: &'static str = "ptt-line";
 
 arg_enum! {
     #[derive(Debug, Clone, Copy, PartialEq)]
@@ -63,6 +88,8 @@ arg_enum! {
         ListOutputDevices,
         ListInputDevices,
         SerialDiag,
+        ReceiveWav,
+        TransmitWav,
         SourceEncoderDiag // TODO remove when moved to diag_application_spec
     }
 }
@@ -103,6 +130,49 @@ fn parse_command_line<'a>() -> (ArgMatches<'a>, Mode) {
             .short("w").long("keyerwpm").help("Sets the typical keying speed in words per minute")
             .value_name("keyer speed in WPM").takes_value(true))
 
+        .arg(Arg::with_name(CALLSIGN)
+            .short("c").long("callsign").help("Sets your callsign, sent at the start of each transmission")
+            .value_name("callsign").takes_value(true))
+
+        .arg(Arg::with_name(LOCATOR)
+            .short("l").long("locator").help("Sets your 4- or 6-character Maidenhead locator, sent at the start of each transmission")
+            .value_name("locator").takes_value(true))
+
+        .arg(Arg::with_name(POWER_DBM)
+            .short("p").long("power").help("Sets your transmit power in dBm, sent at the start of each transmission")
+            .value_name("power in dBm").takes_value(true))
+
+        .arg(Arg::with_name(WAV_FILE)
+            .short("f").long("wavfile").help("Sets the WAV file to decode in the ReceiveWav mode, or to write in the TransmitWav mode")
+            .value_name("WAV file").takes_value(true))
+
+        .arg(Arg::with_name(TEXT)
+            .short("x").long("text").help("Sets the text to send in the TransmitWav mode")
+            .value_name("text").takes_value(true))
+
+        .arg(Arg::with_name(KEYING_FILE)
+            .short("y").long("keyingfile").help("Sets the keying CSV file (of MARK/SPACE durations in ms) to send in the TransmitWav mode")
+            .value_name("keying CSV file").takes_value(true))
+
+        .arg(Arg::with_name(AUDIO_OFFSET)
+            .short("o").long("offset").help("Sets the audio offset frequency in Hz to transmit at in the TransmitWav mode; the configured transmit offset if not set")
+            .value_name("audio offset in Hz").takes_value(true))
+
+        .arg(Arg::with_name(RIGCTLD_ADDRESS)
+            .long("rigctld").help("Sets the host:port of Hamlib's rigctld, to control the transceiver's PTT, e.g. localhost:4532")
+            .value_name("rigctld address").takes_value(true))
+
+        .arg(Arg::with_name(NO_CAT)
+            .long("nocat").help("Clears the rig control; the transceiver would be keyed by VOX"))
+
+        .arg(Arg::with_name(PTT_PORT_DEVICE)
+            .long("pttport").help("Sets the serial port whose RTS or DTR line keys the transceiver's PTT")
+            .value_name(KEYER_VALUE_NAME).takes_value(true))
+
+        .arg(Arg::with_name(PTT_LINE)
+            .long("pttline").help("Sets the serial port line that keys the transceiver's PTT")
+            .possible_values(&["RTS", "DTR"]).value_name("line").takes_value(true))
+
         .get_matches();
 
     let mode = value_t!(result.value_of("mode"), Mode).unwrap_or(Mode::GUI);
@@ -121,6 +191,15 @@ fn run(arguments: ArgMatches, mode: Mode) -> Result<i32, Box<dyn Error>> {
         info!("Configuration file is [{:?}]", config_file_path);
         return Ok(0)
     }
+    if mode == Mode::ReceiveWav {
+        receive_wav(&arguments)?;
+        return Ok(0)
+    }
+    if mode == Mode::TransmitWav {
+        configure_station(&arguments, &mut config)?;
+        transmit_wav(&arguments, &config)?;
+        return Ok(0)
+    }
     let pa = PortAudio::new()?;
 
     match mode {
@@ -144,6 +223,8 @@ fn run(arguments: ArgMatches, mode: Mode) -> Result<i32, Box<dyn Error>> {
     // pick the values from config to initialise the system, after checking that these configured
     // values are still valid.
     configure_audio_and_keyer_devices(&arguments, &mut config, &pa)?;
+    configure_station(&arguments, &mut config)?;
+    configure_cat(&arguments, &mut config)?;
 
     // Examine configured audio and keyer devices (may be repeating checks just made if they're
     // being set, or checking what was previously configured).
@@ -226,20 +307,28 @@ fn run(arguments: ArgMatches, mode: Mode) -> Result<i32, Box<dyn Error>> {
                                                 application.terminate_flag());
     tone_generator.start_callback(application.pa_ref(), output_settings)?; // also initialises DDS for sidetone.
     let application_tone_generator = Arc::new(Mutex::new(tone_generator));
-    application.set_tone_generator(application_tone_generator);
+    application.set_tone_generator(application_tone_generator.clone());
+
+    info!("Initialising playback...");
+    let playback = Arc::new(Mutex::new(Playback::new(application.terminate_flag(), scheduled_thread_pool.clone(),
+                                                     application_tone_generator)));
+    application.set_playback(playback.clone());
+    // The receive filter set on the waterfall is applied to the stations played.
+    application.set_receive_filtering(playback.clone());
 
     info!("Initialising source encoder...");
     let mut source_encoder = SourceEncoder::new(application.terminate_flag(),
                                                 SOURCE_ENCODER_BLOCK_SIZE_IN_BITS);
     // TODO the application should set the source encoder's speed.
     source_encoder.set_keyer_speed(config.get_wpm() as KeyerSpeed);
+    configure_source_encoder(&config, &mut source_encoder)?;
     application.set_source_encoder(Arc::new(Mutex::new(source_encoder)));
 
     // These devices have been previously checked for existence..
     info!("Initialising rig input (from the rig, ie its speaker) device...");
     let rig_in_dev_string = config.get_rig_in_device();
     let rig_in_dev_str = rig_in_dev_string.as_str();
-    let _rig_input_settings = application.open_input_audio_device(rig_in_dev_str).unwrap();
+    let rig_input_settings = application.open_input_audio_device(rig_in_dev_str).unwrap();
 
     info!("Initialising rig output (to the rig, ie its mic) device...");
     let rig_out_dev_string = config.get_rig_out_device();
@@ -253,20 +342,69 @@ fn run(arguments: ArgMatches, mode: Mode) -> Result<i32, Box<dyn Error>> {
     let channel_encoder = Arc::new(Mutex::new(ChannelEncoder::new(source_encoding_to_channel_encoding, application.terminate_flag())));
     application.set_channel_encoder(channel_encoder);
 
+    info!("Initialising rig control...");
+    let mut rigctld_address = config.get_rigctld_address();
+    if rigctld_address.is_empty() {
+        rigctld_address = DEFAULT_RIGCTLD_ADDRESS.to_string();
+    }
+    let cat_controller: Arc<Mutex<dyn CatController>> = match config.get_cat_type() {
+        CatType::Rigctld => {
+            // If rigctld isn't running yet, each use of the rig tries to connect again.
+            let mut rigctld_cat_controller = RigctldCatController::new(rigctld_address.clone());
+            if let Err(e) = rigctld_cat_controller.connect() {
+                warn!("{}; will try again when the rig is next used", e);
+            }
+            Arc::new(Mutex::new(rigctld_cat_controller))
+        }
+        CatType::SerialPtt => {
+            let ptt_serial_io = construct_default_serial_io(config.get_serial_ptt_port().as_str())?;
+            Arc::new(Mutex::new(SerialPttCatController::new(ptt_serial_io, config.get_serial_ptt_line(),
+                Duration::from_millis(config.get_serial_ptt_lead_delay_ms() as u64),
+                Duration::from_millis(config.get_serial_ptt_tail_delay_ms() as u64))?))
+        }
+        CatType::Null => {
+            Arc::new(Mutex::new(NullCatController::new()))
+        }
+    };
+
+    // The decoded stations' radio frequencies are given from this. Only rigctld can read the dial
+    // frequency; the poller has its own connection to it, so that a slow reply never holds up the
+    // transmitter's PTT.
+    let dial_frequency = Arc::new(DialFrequency::new());
+    let mut dial_frequency_poller = if config.get_cat_type() == CatType::Rigctld {
+        let poller_cat_controller = Arc::new(Mutex::new(RigctldCatController::new(rigctld_address)));
+        Some(DialFrequencyPoller::new(poller_cat_controller, dial_frequency.clone(),
+                                      DIAL_FREQUENCY_POLL_INTERVAL, application.terminate_flag()))
+    } else {
+        None
+    };
+
+    let max_transmit_seconds = config.get_max_transmit_seconds();
+    if max_transmit_seconds == 0 {
+        return Err(format!("The max_transmit_seconds setting in {:?} must be greater than zero", config.get_config_file_path()).into());
+    }
+
     info!("Initialising transmitter...");
-    let transmitter = Arc::new(Mutex::new(Transmitter::new(config.get_transmit_offset_frequency(), application.terminate_flag())));
+    let transmitter = Arc::new(Mutex::new(Transmitter::new(config.get_transmit_offset_frequency(), application.terminate_flag(), application.transmit_halt_flag(), cat_controller)));
     application.set_transmitter(transmitter.clone());
+    application.set_tuner(transmitter.clone());
 
     {
         let mut locked_transmitter = transmitter.lock().unwrap();
         info!("Setting amplitude max");
         locked_transmitter.set_amplitude_max(config.get_transmit_amplitude() as AmplitudeMax);
+        locked_transmitter.set_max_transmit_duration(Duration::from_secs(max_transmit_seconds as u64));
         info!("Initialising transmitter audio callback...");
         locked_transmitter.start_callback(application.pa_ref(), rig_output_settings)?;
         info!("Setting transmitter offset audio frequency...");
         locked_transmitter.set_audio_frequency_allocate_buffer(config.get_transmit_offset_frequency());
     }
 
+    info!("Initialising receiver...");
+    let mut receiver = Receiver::new(config.get_transmit_offset_frequency(), application.terminate_flag());
+    receiver.start_callback(application.pa_ref(), rig_input_settings)?;
+    let decoder_terminate = application.terminate_flag();
+
     info!("Initialising GUI...");
     let gui_config = Arc::new(Mutex::new(config));
     let gui_terminate = application.terminate_flag();
@@ -282,6 +420,23 @@ fn run(arguments: ArgMatches, mode: Mode) -> Result<i32, Box<dyn Error>> {
         let transmitter_gui_input = gui_input.clone();
         locked_transmitter.set_gui_input(transmitter_gui_input);
     }
+    if let Some(dial_frequency_poller) = dial_frequency_poller.as_mut() {
+        dial_frequency_poller.set_gui_input(gui_input.clone());
+    }
+
+    info!("Initialising decoder...");
+    let decoder_gui_input = gui_input.clone();
+    // The decoder is made on the thread that decodes the receiver's audio; it plays what it
+    // decodes, sends its spectra to the waterfall, and marks Costas Arrays and stations on it, with
+    // their radio frequencies if the dial frequency is known.
+    let (_decoder_thread, decoder_observer) = DecoderThread::new(move || {
+        let mut decoder = Decoder::new(DOWNSAMPLED_SAMPLE_RATE, OBSERVABLE_BUFFER_SLICE_SIZE / OBSERVABLE_BUFFER_HOP_SIZE, playback);
+        decoder.add_spectrum_observer(Arc::new(WaterfallObserver::new(decoder_gui_input.clone(), DOWNSAMPLED_SAMPLE_RATE, WATERFALL_WIDTH as usize)));
+        decoder.set_gui_input(decoder_gui_input);
+        decoder.set_dial_frequency(dial_frequency);
+        decoder
+    }, decoder_terminate);
+    receiver.add_observer(decoder_observer);
 
     while app.wait() {
         arc_mutex_gui.lock().unwrap().message_handle();
@@ -387,6 +542,99 @@ fn configure_audio_and_keyer_devices(arguments: &ArgMatches, config: &mut Config
     Ok(())
 }
 
+fn configure_station(arguments: &ArgMatches, config: &mut ConfigurationStore) -> Result<(), Box<dyn Error>> {
+    let mut station_ok = true;
+
+    // Set the station details in the configuration file, if present, and if they can be encoded.
+    if arguments.is_present(CALLSIGN) {
+        let callsign = arguments.value_of(CALLSIGN).unwrap();
+        match encode_callsign(callsign.to_string()) {
+            Ok(_) => {
+                info!("Setting callsign to '{}'", callsign);
+                config.set_callsign(callsign.to_string())?;
+            }
+            Err(e) => {
+                warn!("Setting {}: {}", CALLSIGN, e);
+                station_ok = false;
+            }
+        }
+    }
+    if arguments.is_present(LOCATOR) {
+        let locator = arguments.value_of(LOCATOR).unwrap();
+        match encode_locator(locator.to_string()) {
+            Ok(_) => {
+                info!("Setting locator to '{}'", locator);
+                config.set_locator(locator.to_string())?;
+            }
+            Err(e) => {
+                warn!("Setting {}: {}", LOCATOR, e);
+                station_ok = false;
+            }
+        }
+    }
+    if arguments.is_present(POWER_DBM) {
+        let power_str = arguments.value_of(POWER_DBM).unwrap();
+        match power_str.parse::<u8>() {
+            Ok(power) => {
+                match encode_power(power) {
+                    Ok(_) => {
+                        info!("Setting power to {} dBm", power);
+                        config.set_power(Some(power))?;
+                    }
+                    Err(e) => {
+                        warn!("Setting {}: {}", POWER_DBM, e);
+                        station_ok = false;
+                    }
+                }
+            }
+            Err(_) => {
+                warn!("Setting {}: Could not set power in dBm to '{}' - not an integer", POWER_DBM, power_str);
+                station_ok = false;
+            }
+        }
+    }
+
+    if !station_ok {
+        return Err("Configuration error in station details.".into());
+    }
+
+    Ok(())
+}
+
+fn configure_cat(arguments: &ArgMatches, config: &mut ConfigurationStore) -> Result<(), Box<dyn Error>> {
+    let cat_options = [RIGCTLD_ADDRESS, PTT_PORT_DEVICE, NO_CAT].iter().filter(|option| arguments.is_present(option)).count();
+    if cat_options > 1 {
+        return Err(format!("Cannot use more than one of the {}, {} and {} options", RIGCTLD_ADDRESS, PTT_PORT_DEVICE, NO_CAT).into());
+    }
+    if arguments.is_present(RIGCTLD_ADDRESS) {
+        let address = arguments.value_of(RIGCTLD_ADDRESS).unwrap();
+        info!("Setting rig control to rigctld at '{}'", address);
+        config.set_rigctld_address(address.to_string())?;
+        config.set_cat_type(CatType::Rigctld)?;
+    }
+    if arguments.is_present(PTT_PORT_DEVICE) {
+        let dev = arguments.value_of(PTT_PORT_DEVICE).unwrap();
+        if !port_exists(dev)? {
+            return Err(format!("Setting {}: No serial port device named '{}' is present in your system.", PTT_PORT_DEVICE, dev).into());
+        }
+        info!("Setting rig control to serial port '{}'", dev);
+        config.set_serial_ptt_port(dev.to_string())?;
+        config.set_cat_type(CatType::SerialPtt)?;
+    }
+    if arguments.is_present(PTT_LINE) {
+        let line = if arguments.value_of(PTT_LINE).unwrap() == "DTR" { PttLine::Dtr } else { PttLine::Rts };
+        info!("Setting PTT serial port line to {:?}", line);
+        config.set_serial_ptt_line(line)?;
+    }
+    if arguments.is_present(NO_CAT) {
+        info!("Clearing any rig control");
+        config.set_rigctld_address("".to_string())?;
+        config.set_serial_ptt_port("".to_string())?;
+        config.set_cat_type(CatType::Null)?;
+    }
+    Ok(())
+}
+
 fn check_audio_devices(config: &mut ConfigurationStore, pa: &PortAudio) -> Result<(), Box<dyn Error>> {
     let mut audio_devices_ok = true;
     {
@@ -483,6 +731,76 @@ fn port_exists(dev_name: &str) -> Result<bool, Box<dyn Error>> {
     Ok(std::path::Path::new(dev_name).exists())
 }
 
+// Decodes a recorded WAV file offline, faster than real time, logging the frames decoded and the
+// stations heard. Needs no audio devices or keyer.
+fn receive_wav(arguments: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let filename = match arguments.value_of(WAV_FILE) {
+        Some(filename) => filename,
+        None => return Err(format!("The ReceiveWav mode needs a WAV file, set with the {} option", WAV_FILE).into()),
+    };
+    init_ldpc();
+    let frame_printer = Arc::new(Mutex::new(FramePrinter::new()));
+    let start = Instant::now();
+    let seconds = decode_waveform_file(filename, frame_printer.clone())?;
+    info!("Decoded {:.1}s of {} in {}ms", seconds, filename, start.elapsed().as_millis());
+    frame_printer.lock().unwrap().log_stations();
+    Ok(())
+}
+
+// Encodes text, or a keying file, as a single transmission through the source encoder, channel
+// encoder and modulator, writing it to a WAV file rather than to the rig. Needs no audio devices or
+// keyer.
+fn transmit_wav(arguments: &ArgMatches, config: &ConfigurationStore) -> Result<(), Box<dyn Error>> {
+    let filename = match arguments.value_of(WAV_FILE) {
+        Some(filename) => filename,
+        None => return Err(format!("The TransmitWav mode needs a WAV file, set with the {} option", WAV_FILE).into()),
+    };
+    let keyer_speed: KeyerSpeed = match arguments.value_of(KEYER_SPEED_WPM) {
+        Some(wpm_str) => match wpm_str.parse::<KeyerSpeed>() {
+            Ok(wpm) if (MIN_KEYER_SPEED..=MAX_KEYER_SPEED).contains(&wpm) => wpm,
+            _ => return Err(format!("Keyer speed of '{}' is not in the range [{}..{}] WPM", wpm_str, MIN_KEYER_SPEED, MAX_KEYER_SPEED).into()),
+        },
+        None => config.get_wpm() as KeyerSpeed,
+    };
+    let keying = match (arguments.value_of(TEXT), arguments.value_of(KEYING_FILE)) {
+        (Some(text), None) => text_to_keying(keyer_speed as u32, text),
+        (None, Some(keying_file)) => read_keying_file(keying_file)?,
+        _ => return Err(format!("The TransmitWav mode needs either the {} or the {} option", TEXT, KEYING_FILE).into()),
+    };
+    let audio_offset: AudioFrequencyHz = match arguments.value_of(AUDIO_OFFSET) {
+        Some(offset_str) => match offset_str.parse::<AudioFrequencyHz>() {
+            Ok(offset) => offset,
+            Err(_) => return Err(format!("Could not set the audio offset in Hz to '{}' - not an integer", offset_str).into()),
+        },
+        None => config.get_transmit_offset_frequency(),
+    };
+
+    init_ldpc();
+    let terminate = Arc::new(AtomicBool::new(false));
+    let mut source_encoder = SourceEncoder::new(terminate, SOURCE_ENCODER_BLOCK_SIZE_IN_BITS);
+    source_encoder.set_keyer_speed(keyer_speed);
+    configure_source_encoder(config, &mut source_encoder)?;
+    let seconds = encode_waveform_file(&mut source_encoder, &keying, audio_offset, filename)?;
+    info!("Wrote {:.1}s at {}Hz to {}", seconds, audio_offset, filename);
+    Ok(())
+}
+
+// Give the source encoder the station's callsign, locator and power, if they are configured.
+fn configure_source_encoder(config: &ConfigurationStore, source_encoder: &mut SourceEncoder) -> Result<(), Box<dyn Error>> {
+    let callsign = config.get_callsign();
+    if !callsign.is_empty() {
+        source_encoder.set_callsign(callsign)?;
+    }
+    let locator = config.get_locator();
+    if !locator.is_empty() {
+        source_encoder.set_locator(locator)?;
+    }
+    if let Some(power) = config.get_power() {
+        source_encoder.set_power(power)?;
+    }
+    Ok(())
+}
+
 fn serial_diag(serial_io: &mut Box<dyn SerialIO>) -> Result<(), Box<dyn Error>> {
     loop {
         let mut read_buf: [u8; 1] = [0];